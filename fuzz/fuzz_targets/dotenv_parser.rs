@@ -0,0 +1,18 @@
+//! Fuzz target: the built-in dotenv parser
+//! (`config_lib::parsers::dotenv_parser::parse_with`).
+//!
+//! Looking for panics, infinite loops, OOMs on arbitrary byte input.
+//! Dotenv is interesting to fuzz because of multi-line quoted values
+//! and `${VAR:-default}` expansion, where chained references could
+//! otherwise grow without bound. Variables not defined in the input
+//! resolve to nothing, so runs do not depend on the environment.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(s) = std::str::from_utf8(data) {
+        let _ = config_lib::parsers::dotenv_parser::parse_with(s, &|_| None);
+    }
+});
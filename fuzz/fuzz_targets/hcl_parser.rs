@@ -5,6 +5,8 @@
 //! built-in parsers, and HCL itself has rich block / nested-object
 //! semantics that have historically attracted parsing bugs. This
 //! target is the highest-yield of the seven for adversarial finds.
+//! Parsed documents are written back with the serializer and must
+//! parse again, which exercises the template escaping rules.
 
 #![no_main]
 
@@ -12,6 +14,11 @@ use libfuzzer_sys::fuzz_target;
 
 fuzz_target!(|data: &[u8]| {
     if let Ok(s) = std::str::from_utf8(data) {
-        let _ = config_lib::parsers::hcl_parser::parse(s);
+        if let Ok(document) = config_lib::parsers::hcl_parser::parse_document(s) {
+            if let Ok(hcl) = config_lib::parsers::hcl_parser::serialize(document.value()) {
+                config_lib::parsers::hcl_parser::parse(&hcl).expect("serialized HCL parses again");
+            }
+            let _ = document.render();
+        }
     }
 });
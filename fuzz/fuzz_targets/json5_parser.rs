@@ -0,0 +1,25 @@
+//! Fuzz target: the built-in JSON5 / JSONC parser
+//! (`config_lib::parsers::json5_parser::parse_document`).
+//!
+//! Unlike `json_parser`, this is a hand-written recursive-descent parser
+//! with its own string escapes and number grammar, so it gets the same
+//! adversarial treatment as the other built-in parsers. Parsed documents
+//! are re-synced with their value after a nested edit, which exercises
+//! the in-place splicing; the result must parse again.
+
+#![no_main]
+
+use config_lib::parsers::json5_parser::{parse_document, Dialect};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(s) = std::str::from_utf8(data) {
+        if let Ok(mut document) = parse_document(s, Dialect::Json5) {
+            if document.value().is_table() {
+                let _ = document.set("fuzz.edit", config_lib::Value::integer(1));
+                parse_document(&document.to_string(), Dialect::Json5)
+                    .expect("edited JSON5 parses again");
+            }
+        }
+    }
+});
@@ -0,0 +1,23 @@
+//! Fuzz target: the built-in TOML parser
+//! (`config_lib::parsers::toml_parser::parse`) and its serializer.
+//!
+//! The parser is hand-written recursive descent with the spec's table
+//! redefinition rules, four string forms and the full number and
+//! datetime grammar, so it gets the same adversarial treatment as the
+//! other built-in parsers. Anything that parses is serialized again and
+//! must read back, which exercises key quoting and string escaping.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(s) = std::str::from_utf8(data) {
+        if let Ok(value) = config_lib::parsers::toml_parser::parse(s) {
+            if let Ok(toml) = config_lib::parsers::toml_parser::serialize(&value) {
+                config_lib::parsers::toml_parser::parse(&toml)
+                    .expect("serialized TOML parses again");
+            }
+        }
+    }
+});
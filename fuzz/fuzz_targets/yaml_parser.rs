@@ -0,0 +1,23 @@
+//! Fuzz target: the YAML parser wrapper
+//! (`config_lib::parsers::yaml_parser::parse`) and its serializer.
+//!
+//! The wrapper sits on top of `yaml-rust2`'s event parser. The
+//! interesting failure modes are at the wrapper layer: alias
+//! expansion budgets, merge keys, nesting depth, tag and core-schema
+//! scalar resolution. Anything that parses is serialized again and
+//! must read back, which exercises the quoting rules.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(s) = std::str::from_utf8(data) {
+        if let Ok(value) = config_lib::parsers::yaml_parser::parse(s) {
+            let yaml = config_lib::parsers::yaml_parser::serialize(&value)
+                .expect("serializing a parsed value never fails");
+            config_lib::parsers::yaml_parser::parse(&yaml)
+                .expect("serialized YAML parses again");
+        }
+    }
+});
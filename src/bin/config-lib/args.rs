@@ -0,0 +1,183 @@
+//! Command-line parsing for the `config-lib` tool.
+//!
+//! Options may appear anywhere after the command, as `--name value` or
+//! `--name=value`; `--` ends option parsing so paths starting with `-` can
+//! still be given.
+
+use config_lib::formatter::FormatOptions;
+use config_lib::lint::LintOptions;
+use std::fmt;
+
+/// How results are written to stdout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Output {
+    /// Human-readable text.
+    Plain,
+    /// A JSON document.
+    Json,
+    /// `export NAME='value'` lines for `eval` in a POSIX shell.
+    Export,
+    /// A SARIF 2.1.0 log (`lint` only).
+    Sarif,
+}
+
+/// A parsed invocation.
+#[derive(Debug)]
+pub enum Invocation {
+    /// `--help`, or no arguments at all.
+    Help,
+    /// `--version`.
+    Version,
+    /// A subcommand to run.
+    Run(Args),
+}
+
+/// Arguments for a subcommand.
+#[derive(Debug)]
+pub struct Args {
+    /// The subcommand name (`get`, `set`, ...).
+    pub command: String,
+    /// Positional arguments after the command.
+    pub positional: Vec<String>,
+    /// `--output` (or `--json` / `--export`).
+    pub output: Output,
+    /// `--format`: read input as this format instead of detecting it.
+    pub format: Option<String>,
+    /// `--to`: target format of `convert`.
+    pub to: Option<String>,
+    /// `--schema`: schema file for `validate`.
+    pub schema: Option<String>,
+    /// `--prefix`: prepended to variable names in export output.
+    pub prefix: String,
+    /// `--string`: store the value given to `set` as a string.
+    pub string: bool,
+    /// `--strict`: fail `convert` if the conversion is lossy, and `detect`
+    /// if the format is ambiguous.
+    pub strict: bool,
+    /// `--check`: make `fmt` report files instead of rewriting them.
+    pub check: bool,
+    /// `--sort` / `--no-align`: layout options for `fmt`.
+    pub style: FormatOptions,
+    /// `--disable`: rules `lint` skips.
+    pub lint: LintOptions,
+}
+
+/// A command line that cannot be run; reported with the usage text.
+#[derive(Debug)]
+pub struct UsageError(pub String);
+
+impl fmt::Display for UsageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Parse the arguments after the program name.
+pub fn parse(arguments: impl IntoIterator<Item = String>) -> Result<Invocation, UsageError> {
+    let mut arguments = arguments.into_iter();
+    let mut command = None;
+    let mut args = Args {
+        command: String::new(),
+        positional: Vec::new(),
+        output: Output::Plain,
+        format: None,
+        to: None,
+        schema: None,
+        prefix: String::new(),
+        string: false,
+        strict: false,
+        check: false,
+        style: FormatOptions::new(),
+        lint: LintOptions::new(),
+    };
+
+    let mut options_done = false;
+    while let Some(argument) = arguments.next() {
+        if options_done || argument == "-" || !argument.starts_with('-') {
+            if command.is_none() {
+                command = Some(argument);
+            } else {
+                args.positional.push(argument);
+            }
+            continue;
+        }
+
+        let (name, inline) = match argument.split_once('=') {
+            Some((name, value)) if name.starts_with("--") => (name, Some(value.to_string())),
+            _ => (argument.as_str(), None),
+        };
+        let mut value = |option: &str| {
+            inline
+                .clone()
+                .or_else(|| arguments.next())
+                .ok_or_else(|| UsageError(format!("`{option}` needs a value")))
+        };
+        match name {
+            "--" => options_done = true,
+            "-h" | "--help" => return Ok(Invocation::Help),
+            "-V" | "--version" => return Ok(Invocation::Version),
+            "--json" => args.output = Output::Json,
+            "--export" => args.output = Output::Export,
+            "--plain" => args.output = Output::Plain,
+            "--sarif" => args.output = Output::Sarif,
+            "-o" | "--output" => {
+                args.output = match value(name)?.as_str() {
+                    "plain" => Output::Plain,
+                    "json" => Output::Json,
+                    "export" => Output::Export,
+                    "sarif" => Output::Sarif,
+                    other => {
+                        return Err(UsageError(format!(
+                            "unknown output mode `{other}` (expected plain, json, export or sarif)"
+                        )))
+                    }
+                }
+            }
+            "-f" | "--format" => args.format = Some(value(name)?),
+            "--to" => args.to = Some(value(name)?),
+            "--schema" => args.schema = Some(value(name)?),
+            "--prefix" => args.prefix = value(name)?,
+            "--string" => args.string = true,
+            "--strict" => args.strict = true,
+            "--check" => args.check = true,
+            "--sort" => args.style.sort_keys = true,
+            "--no-align" => args.style.align = false,
+            "--disable" => {
+                let rules = value(name)?;
+                args.lint
+                    .disabled
+                    .extend(rules.split(',').map(|rule| rule.trim().to_string()));
+            }
+            _ => return Err(UsageError(format!("unknown option `{argument}`"))),
+        }
+    }
+
+    match command {
+        None => Ok(Invocation::Help),
+        Some(command) => {
+            args.command = command;
+            Ok(Invocation::Run(args))
+        }
+    }
+}
+
+impl Args {
+    /// The positional argument at `index`, named `name` in the error.
+    pub fn required(&self, index: usize, name: &str) -> Result<&str, UsageError> {
+        self.positional
+            .get(index)
+            .map(String::as_str)
+            .ok_or_else(|| UsageError(format!("`{}` needs a {name}", self.command)))
+    }
+
+    /// Fail if more than `count` positional arguments were given.
+    pub fn at_most(&self, count: usize) -> Result<(), UsageError> {
+        match self.positional.get(count) {
+            Some(extra) => Err(UsageError(format!(
+                "unexpected argument `{extra}` for `{}`",
+                self.command
+            ))),
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,482 @@
+//! `config-lib` — inspect, edit, convert, validate, format and lint
+//! configuration files from the command line.
+//!
+//! Built with the `cli` feature; run `config-lib --help` for usage. Files
+//! are read in any format the library supports, detected the same way
+//! [`config_lib::parse_file`] does unless `--format` says otherwise, and
+//! `-` reads standard input.
+
+// The tool reports through stdout / stderr by design; everything else
+// follows the library's REPS lint discipline.
+#![deny(clippy::unwrap_used)]
+#![deny(clippy::expect_used)]
+#![warn(clippy::pedantic)]
+#![allow(clippy::print_stdout, clippy::print_stderr)]
+
+mod args;
+mod output;
+
+use args::{Args, Invocation, Output, UsageError};
+use config_lib::lint::{self, LintReport};
+use config_lib::{formatter, parsers, Config, Error, Schema, ValidationSeverity, Value};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::Read as _;
+use std::path::Path;
+use std::process::ExitCode;
+
+const USAGE: &str = "\
+Usage: config-lib <command> [options]
+
+Commands:
+  get <file> <path>            Print the value at <path>
+  set <file> <path> <value>    Set <path> to <value> and save the file in place
+  convert <file> --to <format> Print the file converted to <format>
+  validate <file> --schema <schema>
+                               Check the file against a JSON Schema style document
+  detect [file]                Print the detected format (reads stdin without a file)
+  keys <file> [path]           List every key path, or those under <path>
+  fmt <file>...                Rewrite files in canonical style (`-` prints to stdout)
+  lint <file>...               Report likely mistakes; fails on warnings and errors
+  lint-rules                   List the rules `lint` checks
+
+Options:
+  -o, --output <mode>    plain (default), json, export or sarif
+      --json             Same as --output json
+      --export           Same as --output export (`export NAME='value'` lines)
+      --sarif            Same as --output sarif (a SARIF 2.1.0 log from `lint`)
+      --prefix <prefix>  Prepend <prefix> to exported variable names
+  -f, --format <format>  Read the input as <format> instead of detecting it
+      --to <format>      Target format for `convert`
+      --strict           Make `convert` fail if any value would change, and
+                         `detect` fail rather than guess between formats
+      --string           Store the value given to `set` as a string
+      --schema <file>    Schema for `validate`
+      --check            Make `fmt` list files that need formatting instead of
+                         rewriting them, and fail if there are any
+      --sort             Make `fmt` sort keys and sections
+      --no-align         Make `fmt` leave `=` signs unaligned
+      --disable <rules>  Comma-separated `lint` rules to skip
+  -h, --help             Print this help
+  -V, --version          Print the version
+
+Paths use the library's syntax: `database.host`, `servers[0].port`,
+`\"key.with.dots\"`. A <file> of `-` reads standard input.
+";
+
+fn main() -> ExitCode {
+    match args::parse(std::env::args().skip(1)) {
+        Ok(Invocation::Help) => {
+            print!("{USAGE}");
+            ExitCode::SUCCESS
+        }
+        Ok(Invocation::Version) => {
+            println!("config-lib {}", env!("CARGO_PKG_VERSION"));
+            ExitCode::SUCCESS
+        }
+        Ok(Invocation::Run(args)) => match run(&args) {
+            Ok(code) => code,
+            Err(Failure::Usage(error)) => usage_error(&error),
+            Err(Failure::Error(error)) => {
+                eprintln!("config-lib: {error}");
+                ExitCode::FAILURE
+            }
+        },
+        Err(error) => usage_error(&error),
+    }
+}
+
+fn usage_error(error: &UsageError) -> ExitCode {
+    eprintln!("config-lib: {error}\n\n{USAGE}");
+    ExitCode::from(2)
+}
+
+/// Why a command failed: bad arguments (exit status 2) or an error from
+/// the library (exit status 1).
+enum Failure {
+    Usage(UsageError),
+    Error(Error),
+}
+
+impl From<UsageError> for Failure {
+    fn from(error: UsageError) -> Self {
+        Failure::Usage(error)
+    }
+}
+
+impl From<Error> for Failure {
+    fn from(error: Error) -> Self {
+        Failure::Error(error)
+    }
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Failure::Usage(error) => error.fmt(f),
+            Failure::Error(error) => error.fmt(f),
+        }
+    }
+}
+
+type CommandResult = Result<ExitCode, Failure>;
+
+fn run(args: &Args) -> CommandResult {
+    if args.output == Output::Sarif && args.command != "lint" {
+        return Err(UsageError("SARIF output is only for `lint`".to_string()).into());
+    }
+    match args.command.as_str() {
+        "get" => get(args),
+        "set" => set(args),
+        "convert" => convert(args),
+        "validate" => validate(args),
+        "detect" => detect(args),
+        "keys" => keys(args),
+        "fmt" => fmt(args),
+        "lint" => lint(args),
+        "lint-rules" => lint_rules(args),
+        other => Err(UsageError(format!("unknown command `{other}`")).into()),
+    }
+}
+
+/// Read `file` (`-` for stdin) and its format: `format` when given, else
+/// detected from the name and content.
+fn read(file: &str, format: Option<&str>) -> Result<(String, String), Failure> {
+    let (content, detected) = if file == "-" {
+        let mut content = String::new();
+        std::io::stdin()
+            .read_to_string(&mut content)
+            .map_err(|e| Error::io("<stdin>", e))?;
+        let format = parsers::detect_format(&content);
+        (content, format)
+    } else {
+        let content = std::fs::read_to_string(file).map_err(|e| Error::io(file, e))?;
+        let format = parsers::detect_file_format(Path::new(file), &content);
+        (content, format)
+    };
+    Ok((content, format.unwrap_or(detected).to_string()))
+}
+
+fn load(file: &str, args: &Args) -> Result<Config, Failure> {
+    let (content, format) = read(file, args.format.as_deref())?;
+    Ok(Config::from_string(&content, Some(&format))?)
+}
+
+/// Write `value` (found at `path`) in the selected output mode.
+fn print_value(args: &Args, path: &str, value: &Value) -> Result<(), Failure> {
+    match args.output {
+        Output::Plain => print!("{}", output::plain(path, value)),
+        Output::Json | Output::Sarif => print!("{}", output::json(value)?),
+        Output::Export => print!("{}", output::export(&args.prefix, path, value)),
+    }
+    Ok(())
+}
+
+fn get(args: &Args) -> CommandResult {
+    args.at_most(2)?;
+    let file = args.required(0, "file")?;
+    let path = args.required(1, "path")?;
+    let config = load(file, args)?;
+    let value = config.get(path).ok_or_else(|| Error::key_not_found(path))?;
+    print_value(args, path, value)?;
+    Ok(ExitCode::SUCCESS)
+}
+
+fn set(args: &Args) -> CommandResult {
+    args.at_most(3)?;
+    let file = args.required(0, "file")?;
+    let path = args.required(1, "path")?;
+    let text = args.required(2, "value")?;
+    if file == "-" {
+        return Err(UsageError("`set` needs a file to save to, not `-`".to_string()).into());
+    }
+    let mut config = load(file, args)?;
+    let value = if args.string {
+        Value::string(text)
+    } else {
+        typed(text)
+    };
+    config.set(path, value)?;
+    config.save_to_file(file)?;
+    Ok(ExitCode::SUCCESS)
+}
+
+/// A command-line value as a boolean, integer or float when it reads as
+/// one, else a string.
+fn typed(text: &str) -> Value {
+    match text {
+        "true" => return Value::bool(true),
+        "false" => return Value::bool(false),
+        _ => {}
+    }
+    if let Ok(integer) = text.parse::<i64>() {
+        return Value::integer(integer);
+    }
+    match text.parse::<f64>() {
+        Ok(float) if float.is_finite() => Value::float(float),
+        _ => Value::string(text),
+    }
+}
+
+fn convert(args: &Args) -> CommandResult {
+    args.at_most(1)?;
+    let file = args.required(0, "file")?;
+    let Some(to) = args.to.as_deref() else {
+        return Err(UsageError("`convert` needs `--to <format>`".to_string()).into());
+    };
+    let config = load(file, args)?;
+    let (converted, report) = config.convert_to(to)?;
+    let mut text = converted.serialize()?;
+    if !text.is_empty() && !text.ends_with('\n') {
+        text.push('\n');
+    }
+    if args.strict && !report.is_lossless() {
+        eprintln!("{report}");
+        return Ok(ExitCode::FAILURE);
+    }
+
+    match args.output {
+        Output::Plain => {
+            print!("{text}");
+            if !report.is_lossless() {
+                eprintln!("{report}");
+            }
+        }
+        Output::Json | Output::Export | Output::Sarif => {
+            let mut result = BTreeMap::new();
+            result.insert("format".to_string(), Value::string(to));
+            result.insert("output".to_string(), Value::string(text));
+            if let Some(losses) = report.to_value().get("losses") {
+                result.insert("losses".to_string(), losses.clone());
+            }
+            print_value(args, "", &Value::table(result))?;
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn validate(args: &Args) -> CommandResult {
+    args.at_most(1)?;
+    let file = args.required(0, "file")?;
+    let Some(schema_file) = args.schema.as_deref() else {
+        return Err(UsageError("`validate` needs `--schema <file>`".to_string()).into());
+    };
+    let config = load(file, args)?;
+    // `--format` describes the file being validated; the schema's own
+    // format is always detected
+    let (content, format) = read(schema_file, None)?;
+    let schema = Schema::from_value(&config_lib::parse(&content, Some(&format))?)?;
+
+    let mut errors: Vec<(String, String)> = Vec::new();
+    for error in schema.validate_all(config.as_value()) {
+        match error {
+            Error::Schema { path, message, .. } => errors.push((path, message)),
+            other => return Err(other.into()),
+        }
+    }
+
+    match args.output {
+        Output::Plain => {
+            if errors.is_empty() {
+                println!("{file}: valid");
+            }
+            for (path, message) in &errors {
+                let path = if path.is_empty() { "<root>" } else { path };
+                println!("{file}: {path}: {message}");
+            }
+        }
+        Output::Json | Output::Export | Output::Sarif => {
+            let list = errors
+                .iter()
+                .map(|(path, message)| {
+                    let mut entry = BTreeMap::new();
+                    entry.insert("path".to_string(), Value::string(path));
+                    entry.insert("message".to_string(), Value::string(message));
+                    Value::table(entry)
+                })
+                .collect();
+            let mut result = BTreeMap::new();
+            result.insert("valid".to_string(), Value::bool(errors.is_empty()));
+            result.insert("errors".to_string(), Value::array(list));
+            print_value(args, "", &Value::table(result))?;
+        }
+    }
+    Ok(if errors.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
+
+fn detect(args: &Args) -> CommandResult {
+    args.at_most(1)?;
+    let file = args.positional.first().map_or("-", String::as_str);
+    let (content, mut format) = read(file, args.format.as_deref())?;
+    if args.strict && args.format.is_none() {
+        format = if file == "-" {
+            parsers::detect_format_strict(&content)?
+        } else {
+            parsers::detect_file_format_strict(Path::new(file), &content)?
+        }
+        .to_string();
+    }
+    match args.output {
+        Output::Plain => println!("{format}"),
+        Output::Json | Output::Export | Output::Sarif => {
+            let mut result = BTreeMap::new();
+            result.insert("format".to_string(), Value::string(format));
+            print_value(args, "", &Value::table(result))?;
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn keys(args: &Args) -> CommandResult {
+    args.at_most(2)?;
+    let file = args.required(0, "file")?;
+    let path = args.positional.get(1).map_or("", String::as_str);
+    let config = load(file, args)?;
+    let value = config.get(path).ok_or_else(|| Error::key_not_found(path))?;
+
+    match args.output {
+        Output::Plain => {
+            for (key, _) in output::leaves(path, value) {
+                println!("{key}");
+            }
+        }
+        Output::Json | Output::Sarif => {
+            let keys = output::leaves(path, value)
+                .into_iter()
+                .map(|(key, _)| Value::string(key))
+                .collect();
+            print!("{}", output::json(&Value::array(keys))?);
+        }
+        // Every key with its value, ready for `eval`
+        Output::Export => print!("{}", output::export(&args.prefix, path, value)),
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn fmt(args: &Args) -> CommandResult {
+    if args.positional.is_empty() {
+        return Err(UsageError("`fmt` needs at least one file".to_string()).into());
+    }
+    if args.output == Output::Export {
+        return Err(UsageError("`fmt` has no export output".to_string()).into());
+    }
+    if args.output == Output::Json && !args.check && args.positional.iter().any(|f| f == "-") {
+        return Err(
+            UsageError("`fmt -` prints the formatted text; drop `--json`".to_string()).into(),
+        );
+    }
+
+    // Files that are (or, with `--check`, would be) rewritten
+    let mut changed = Vec::new();
+    let mut failed = false;
+    for file in &args.positional {
+        let result = read(file, args.format.as_deref()).and_then(|(content, format)| {
+            let formatted = formatter::format(&content, &format, &args.style)?;
+            if file == "-" && !args.check {
+                print!("{formatted}");
+            } else if formatted != content && !args.check {
+                std::fs::write(file, &formatted).map_err(|e| Error::io(file.as_str(), e))?;
+            }
+            Ok(formatted != content)
+        });
+        match result {
+            Ok(true) => changed.push(file.as_str()),
+            Ok(false) => {}
+            Err(error) => {
+                eprintln!("config-lib: {file}: {error}");
+                failed = true;
+            }
+        }
+    }
+
+    match args.output {
+        Output::Json => {
+            let list = changed.iter().map(|file| Value::string(*file)).collect();
+            let mut result = BTreeMap::new();
+            result.insert("changed".to_string(), Value::array(list));
+            print!("{}", output::json(&Value::table(result))?);
+        }
+        _ if args.check => {
+            for file in &changed {
+                println!("{file}");
+            }
+        }
+        _ => {}
+    }
+    Ok(if failed || args.check && !changed.is_empty() {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    })
+}
+
+fn lint(args: &Args) -> CommandResult {
+    if args.positional.is_empty() {
+        return Err(UsageError("`lint` needs at least one file".to_string()).into());
+    }
+    if args.output == Output::Export {
+        return Err(UsageError("`lint` has no export output".to_string()).into());
+    }
+    if let Some(unknown) = args
+        .lint
+        .disabled
+        .iter()
+        .find(|id| lint::rule(id).is_none())
+    {
+        return Err(UsageError(format!("unknown lint rule `{unknown}`")).into());
+    }
+
+    let mut reports = Vec::new();
+    let mut failed = false;
+    for file in &args.positional {
+        let result = read(file, args.format.as_deref())
+            .and_then(|(content, format)| Ok(lint::lint(&content, &format, &args.lint)?));
+        match result {
+            Ok(findings) => reports.push(LintReport::new(file.as_str(), findings)),
+            Err(error) => {
+                eprintln!("config-lib: {file}: {error}");
+                failed = true;
+            }
+        }
+    }
+
+    match args.output {
+        Output::Json => {
+            let list = reports.iter().map(LintReport::to_value).collect();
+            print!("{}", output::json(&Value::array(list))?);
+        }
+        Output::Sarif => print!("{}", output::json(&lint::sarif(&reports))?),
+        _ => {
+            for report in reports.iter().filter(|report| !report.findings.is_empty()) {
+                println!("{report}");
+            }
+        }
+    }
+    // Info findings are reported but do not fail the run
+    let failing = reports
+        .iter()
+        .filter_map(LintReport::worst)
+        .any(|worst| worst >= ValidationSeverity::Warning);
+    Ok(if failed || failing {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    })
+}
+
+fn lint_rules(args: &Args) -> CommandResult {
+    args.at_most(0)?;
+    if args.output == Output::Plain {
+        for rule in lint::RULES {
+            println!("{rule}");
+        }
+    } else {
+        let list = lint::RULES.iter().map(lint::Rule::to_value).collect();
+        print_value(args, "", &Value::array(list))?;
+    }
+    Ok(ExitCode::SUCCESS)
+}
@@ -0,0 +1,106 @@
+//! Rendering values as plain text, JSON or shell `export` lines.
+
+use config_lib::{path, Result, Value};
+use std::fmt::Write as _;
+
+/// Every scalar (and empty table or array) under `value`, with its full
+/// path below `base`.
+pub fn leaves<'v>(base: &str, value: &'v Value) -> Vec<(String, &'v Value)> {
+    fn walk<'v>(path: String, value: &'v Value, out: &mut Vec<(String, &'v Value)>) {
+        match value {
+            Value::Table(table) if !table.is_empty() => {
+                for (key, child) in table {
+                    walk(path::join_key(&path, key), child, out);
+                }
+            }
+            Value::Array(items) if !items.is_empty() => {
+                for (index, child) in items.iter().enumerate() {
+                    walk(path::join_index(&path, index), child, out);
+                }
+            }
+            leaf => out.push((path, leaf)),
+        }
+    }
+
+    let mut out = Vec::new();
+    walk(base.to_string(), value, &mut out);
+    out
+}
+
+/// A scalar as plain text: strings unquoted, empty containers as `{}` /
+/// `[]`.
+pub fn scalar(value: &Value) -> String {
+    match value {
+        Value::Table(_) => "{}".to_string(),
+        Value::Array(_) => "[]".to_string(),
+        other => other.to_string(),
+    }
+}
+
+/// `value` as plain text: a scalar on its own, a table or array as one
+/// `path = value` line per entry.
+pub fn plain(base: &str, value: &Value) -> String {
+    if !matches!(value, Value::Table(_) | Value::Array(_)) {
+        return format!("{}\n", scalar(value));
+    }
+    let mut out = String::new();
+    for (path, leaf) in leaves(base, value) {
+        let _ = writeln!(out, "{path} = {}", scalar(leaf));
+    }
+    out
+}
+
+/// `value` as a pretty-printed JSON document.
+pub fn json(value: &Value) -> Result<String> {
+    let mut out = config_lib::parsers::json_parser::serialize(value)?;
+    out.push('\n');
+    Ok(out)
+}
+
+/// `value` as `export NAME='value'` lines, one per entry, named after
+/// their paths the way environment overrides are (`database.host` becomes
+/// `DATABASE_HOST`).
+pub fn export(prefix: &str, base: &str, value: &Value) -> String {
+    let mut out = String::new();
+    for (path, leaf) in leaves(base, value) {
+        let text = match leaf {
+            Value::Null => String::new(),
+            other => scalar(other),
+        };
+        let _ = writeln!(
+            out,
+            "export {}={}",
+            variable_name(prefix, &path),
+            shell_quote(&text)
+        );
+    }
+    out
+}
+
+/// `prefix` followed by `path` upper-cased, with every run of characters
+/// that cannot appear in a variable name replaced by `_`.
+fn variable_name(prefix: &str, path: &str) -> String {
+    let mut name = prefix.to_string();
+    let mut separated = true;
+    for ch in path.chars() {
+        if ch.is_ascii_alphanumeric() {
+            name.push(ch.to_ascii_uppercase());
+            separated = false;
+        } else if !separated {
+            name.push('_');
+            separated = true;
+        }
+    }
+    while name.ends_with('_') && name.len() > prefix.len() {
+        name.pop();
+    }
+    if name.is_empty() || name.starts_with(|ch: char| ch.is_ascii_digit()) {
+        name.insert(0, '_');
+    }
+    name
+}
+
+/// Quote `text` for a POSIX shell.
+fn shell_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', r"'\''"))
+}
@@ -4,7 +4,10 @@
 //! loading, modifying, validating, and saving configurations with format preservation.
 
 use crate::error::{Error, Result};
+use crate::layered::{self, LayeredBuilder, Origin, Origins};
+use crate::merge::{self, MergeOptions};
 use crate::parsers;
+use crate::patch::{self, PatchOp};
 use crate::value::Value;
 use dashmap::DashMap;
 use std::collections::BTreeMap;
@@ -99,15 +102,43 @@ pub struct Config {
     /// are operator-provided fallbacks, not user-supplied data).
     defaults: Arc<RwLock<BTreeMap<String, Value>>>,
 
+    /// Per-path provenance, keyed by canonical path. Populated by
+    /// [`LayeredBuilder`](crate::LayeredBuilder) and carried through
+    /// [`Config::merge`]; empty for single-source configurations.
+    origins: Origins,
+
     /// Format-specific preservation data
     #[cfg(feature = "noml")]
     noml_document: Option<noml::Document>,
 
+    /// Layout of the source this configuration was parsed from (CONF, INI,
+    /// dotenv, XML, HCL and the JSON dialects); [`Config::serialize`] reconciles it
+    /// with `values` so comments, ordering and XML attributes survive a save.
+    /// HCL keeps only its block labels: entries are written in key order,
+    /// and a file with comments or evaluated expressions refuses to save.
+    source_document: Option<SourceDocument>,
+
     /// Validation rules for this configuration
     #[cfg(feature = "validation")]
     validation_rules: Option<ValidationRuleSet>,
 }
 
+/// Layout-preserving parse of the original source, per format.
+#[derive(Debug, Clone)]
+enum SourceDocument {
+    Conf(parsers::conf::ConfDocument),
+    Ini(parsers::ini_parser::IniDocument),
+    Dotenv(parsers::dotenv_parser::DotenvDocument),
+    #[cfg(feature = "xml")]
+    Xml(parsers::xml_parser::XmlDocument),
+    #[cfg(feature = "hcl")]
+    Hcl(parsers::hcl_parser::HclDocument),
+    #[cfg(feature = "json")]
+    Json(parsers::json5_parser::JsonDocument),
+    #[cfg(feature = "toml")]
+    Toml(parsers::toml_parser::TomlDocument),
+}
+
 impl Config {
     /// Create a new empty configuration
     pub fn new() -> Self {
@@ -121,8 +152,10 @@ impl Config {
             cache_misses: AtomicU64::new(0),
             cache: DashMap::new(),
             defaults: Arc::new(RwLock::new(BTreeMap::new())),
+            origins: Origins::new(),
             #[cfg(feature = "noml")]
             noml_document: None,
+            source_document: None,
             #[cfg(feature = "validation")]
             validation_rules: None,
         }
@@ -132,7 +165,56 @@ impl Config {
     pub fn from_string(source: &str, format: Option<&str>) -> Result<Self> {
         let detected_format = format.unwrap_or_else(|| parsers::detect_format(source));
 
-        let values = parsers::parse_string(source, Some(detected_format))?;
+        let source_document = match detected_format {
+            "conf" => Some(SourceDocument::Conf(parsers::conf::parse_document(source)?)),
+            "ini" => Some(SourceDocument::Ini(parsers::ini_parser::parse_document(
+                source,
+            )?)),
+            "dotenv" => Some(SourceDocument::Dotenv(
+                parsers::dotenv_parser::parse_document(source)?,
+            )),
+            #[cfg(feature = "xml")]
+            "xml" => Some(SourceDocument::Xml(parsers::xml_parser::parse_document(
+                source,
+            )?)),
+            #[cfg(feature = "hcl")]
+            "hcl" => Some(SourceDocument::Hcl(parsers::hcl_parser::parse_document(
+                source,
+            )?)),
+            #[cfg(feature = "json")]
+            "json" => Some(SourceDocument::Json(parsers::json_parser::parse_document(
+                source,
+            )?)),
+            #[cfg(feature = "json")]
+            "jsonc" => Some(SourceDocument::Json(parsers::json5_parser::parse_document(
+                source,
+                parsers::json5_parser::Dialect::Jsonc,
+            )?)),
+            #[cfg(feature = "json")]
+            "json5" => Some(SourceDocument::Json(parsers::json5_parser::parse_document(
+                source,
+                parsers::json5_parser::Dialect::Json5,
+            )?)),
+            #[cfg(feature = "toml")]
+            "toml" => Some(SourceDocument::Toml(parsers::toml_parser::parse_document(
+                source,
+            )?)),
+            _ => None,
+        };
+        let values = match &source_document {
+            Some(SourceDocument::Conf(document)) => document.value().clone(),
+            Some(SourceDocument::Ini(document)) => document.value().clone(),
+            Some(SourceDocument::Dotenv(document)) => document.value().clone(),
+            #[cfg(feature = "xml")]
+            Some(SourceDocument::Xml(document)) => document.value().clone(),
+            #[cfg(feature = "hcl")]
+            Some(SourceDocument::Hcl(document)) => document.value().clone(),
+            #[cfg(feature = "json")]
+            Some(SourceDocument::Json(document)) => document.value().clone(),
+            #[cfg(feature = "toml")]
+            Some(SourceDocument::Toml(document)) => document.value().clone(),
+            None => parsers::parse_string(source, Some(detected_format))?,
+        };
 
         #[cfg(feature = "noml")]
         let mut config = Self {
@@ -145,7 +227,9 @@ impl Config {
             cache_misses: AtomicU64::new(0),
             cache: DashMap::new(),
             defaults: Arc::new(RwLock::new(BTreeMap::new())),
+            origins: Origins::new(),
             noml_document: None,
+            source_document,
             #[cfg(feature = "validation")]
             validation_rules: None,
         };
@@ -161,13 +245,15 @@ impl Config {
             cache_misses: AtomicU64::new(0),
             cache: DashMap::new(),
             defaults: Arc::new(RwLock::new(BTreeMap::new())),
+            origins: Origins::new(),
+            source_document,
             #[cfg(feature = "validation")]
             validation_rules: None,
         };
 
         // Store format-specific preservation data
         #[cfg(feature = "noml")]
-        if detected_format == "noml" || detected_format == "toml" {
+        if detected_format == "noml" {
             if let Ok(document) = noml::parse_string(source, None) {
                 config.noml_document = Some(document);
             }
@@ -176,14 +262,55 @@ impl Config {
         Ok(config)
     }
 
+    /// Build a configuration from any `Serialize` type.
+    ///
+    /// `format` selects the output format used by [`Config::serialize`] /
+    /// [`Config::save_to_file`], so typed Rust defaults can be written out
+    /// as a fully populated default config file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `value` cannot be represented as a [`Value`] or
+    /// does not serialize to a table at the root.
+    ///
+    /// ```rust
+    /// use config_lib::Config;
+    /// use serde::Serialize;
+    ///
+    /// #[derive(Serialize)]
+    /// struct Defaults {
+    ///     port: u16,
+    ///     name: String,
+    /// }
+    ///
+    /// # fn main() -> Result<(), config_lib::Error> {
+    /// let defaults = Defaults { port: 8080, name: "app".into() };
+    /// let config = Config::from_serialize(&defaults, "conf")?;
+    /// assert!(config.serialize()?.contains("port = 8080"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_serialize<T: serde::Serialize + ?Sized>(value: &T, format: &str) -> Result<Self> {
+        let values = Value::from_serialize(value)?;
+        if !values.is_table() {
+            return Err(Error::type_error(
+                "Configuration root must be a table",
+                "table",
+                values.type_name(),
+            ));
+        }
+        let mut config = Self::from(values);
+        config.format = format.to_string();
+        Ok(config)
+    }
+
     /// Load configuration from a file
     pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
         let path = path.as_ref();
         let content =
             std::fs::read_to_string(path).map_err(|e| Error::io(path.display().to_string(), e))?;
 
-        let format = parsers::detect_format_from_path(path)
-            .unwrap_or_else(|| parsers::detect_format(&content));
+        let format = parsers::detect_file_format(path, &content);
 
         let mut config = Self::from_string(&content, Some(format))?;
         config.file_path = Some(path.to_path_buf());
@@ -199,8 +326,7 @@ impl Config {
             .await
             .map_err(|e| Error::io(path.display().to_string(), e))?;
 
-        let format = parsers::detect_format_from_path(path)
-            .unwrap_or_else(|| parsers::detect_format(&content));
+        let format = parsers::detect_file_format(path, &content);
 
         let mut config = Self::from_string(&content, Some(format))?;
         config.file_path = Some(path.to_path_buf());
@@ -209,6 +335,9 @@ impl Config {
     }
 
     /// Get a value by path
+    ///
+    /// Supports dotted keys, `[n]` / `[-n]` array indices and quoted keys
+    /// such as `"a.b".c` — see [`crate::path`].
     pub fn get(&self, path: &str) -> Option<&Value> {
         self.values.get(path)
     }
@@ -232,6 +361,14 @@ impl Config {
     pub fn set<V: Into<Value>>(&mut self, path: &str, value: V) -> Result<()> {
         self.ensure_writable()?;
         self.values.set_nested(path, value.into())?;
+        if !self.origins.is_empty() {
+            layered::set_origin(
+                &self.values,
+                &mut self.origins,
+                path,
+                Origin::new("runtime"),
+            );
+        }
         self.modified = true;
         self.cache.clear();
         Ok(())
@@ -248,8 +385,16 @@ impl Config {
     /// - The path is malformed
     pub fn remove(&mut self, path: &str) -> Result<Option<Value>> {
         self.ensure_writable()?;
+        let canonical = if self.origins.is_empty() {
+            None
+        } else {
+            layered::canonical_path(&self.values, path)
+        };
         let result = self.values.remove(path)?;
         if result.is_some() {
+            if let Some(canonical) = canonical {
+                layered::forget_subtree(&mut self.origins, &canonical);
+            }
             self.modified = true;
             self.cache.clear();
         }
@@ -333,24 +478,159 @@ impl Config {
     }
 
     /// Serialize the configuration to string format
+    ///
+    /// Configurations parsed from CONF, INI, dotenv, JSON, JSONC or JSON5 keep their
+    /// original layout: only entries whose values changed are rewritten.
     pub fn serialize(&self) -> Result<String> {
-        match self.format.as_str() {
+        self.serialize_values(&self.values, &self.format)
+    }
+
+    /// Serialize the configuration as `format` (`"json"`, `"toml"`, ...)
+    /// instead of the format it was loaded from.
+    ///
+    /// The source layout is only kept when `format` is the original format.
+    /// Nothing checks that the output reads back to the same values; use
+    /// [`Config::convert_to`] for a report of what the target format loses.
+    ///
+    /// An XML document has a single document element, so unless the root
+    /// is one key holding a single element, XML output is wrapped in
+    /// `<config>`. Use [`xml_parser::serialize_with`] to pick another name.
+    ///
+    /// [`xml_parser::serialize_with`]: crate::parsers::xml_parser::serialize_with
+    ///
+    /// ```rust
+    /// use config_lib::Config;
+    ///
+    /// # fn main() -> Result<(), config_lib::Error> {
+    /// let config = Config::from_string("[server]\nport = 8080", Some("conf"))?;
+    /// assert_eq!(config.serialize_as("properties")?, "server.port=8080\n");
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::UnknownFormat`] for an unknown format,
+    /// [`Error::FeatureNotEnabled`] if its feature is disabled, or the
+    /// serializer's error if the values cannot be written in `format` (for
+    /// example arrays in INI).
+    pub fn serialize_as(&self, format: &str) -> Result<String> {
+        self.serialize_values(&self.values, format)
+    }
+
+    /// Convert the configuration to `format`, reporting every value that
+    /// does not survive the trip.
+    ///
+    /// The values are written as `format` and read back; the returned
+    /// configuration holds what was read, and the
+    /// [`ConversionReport`](crate::convert::ConversionReport) lists each
+    /// path where it differs from this one (nulls written as empty strings,
+    /// nested tables flattened to dotted keys, datetimes read back as
+    /// strings, ...). Nulls are left out when converting to TOML, which has
+    /// no null, and reported as dropped. INI output stores arrays under
+    /// index keys (`hosts.0`) and writes values it cannot spell, such as the
+    /// string `"42"`, as what they read back as, reporting both. When XML output is wrapped in
+    /// `<config>` (see [`Config::serialize_as`]) the report compares the
+    /// values inside it. The returned configuration has no file path; write
+    /// it with [`Config::save_to_file`].
+    ///
+    /// ```rust
+    /// use config_lib::convert::LossKind;
+    /// use config_lib::Config;
+    ///
+    /// # fn main() -> Result<(), config_lib::Error> {
+    /// let config = Config::from_string("[database]\nhost = db\nport = 5432", Some("conf"))?;
+    /// let (properties, report) = config.convert_to("properties")?;
+    ///
+    /// assert_eq!(properties.serialize()?, "database.host=db\ndatabase.port=5432\n");
+    /// assert_eq!(report.losses()[0].path, "database");
+    /// assert_eq!(report.losses()[0].kind, LossKind::Flattened);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`Config::serialize_as`], or a parse error
+    /// if the output cannot be read back.
+    pub fn convert_to(&self, format: &str) -> Result<(Config, crate::convert::ConversionReport)> {
+        let mut values = self.values.clone();
+        match format {
+            "toml" => crate::convert::remove_nulls(&mut values),
+            "ini" => parsers::ini_parser::make_writable(&mut values),
+            _ => {}
+        }
+        let output = self.serialize_values(&values, format)?;
+        let converted = Config::from_string(&output, Some(format))?;
+        let empty = Value::table(BTreeMap::new());
+        let read_back = match xml_wrapper(&values) {
+            Some(root) if format == "xml" => converted.values.get(root).unwrap_or(&empty),
+            _ => &converted.values,
+        };
+        let report =
+            crate::convert::ConversionReport::new(&self.format, format, &self.values, read_back);
+        Ok((converted, report))
+    }
+
+    /// Write `values` as `format`, replaying them onto the source layout
+    /// when `format` is the one the configuration was loaded from.
+    fn serialize_values(&self, values: &Value, format: &str) -> Result<String> {
+        let source_document = if format == self.format {
+            self.source_document.as_ref()
+        } else {
+            None
+        };
+        #[cfg(feature = "noml")]
+        let noml_document = if format == self.format {
+            self.noml_document.as_ref()
+        } else {
+            None
+        };
+        match format {
             "json" => {
                 #[cfg(feature = "json")]
-                return crate::parsers::json_parser::serialize(&self.values);
+                {
+                    // Keep key order, indentation and number spelling when
+                    // the file was parsed
+                    if let Some(SourceDocument::Json(document)) = source_document {
+                        let mut document = document.clone();
+                        document.sync(values)?;
+                        return Ok(document.to_string());
+                    }
+                    crate::parsers::json_parser::serialize(values)
+                }
+                #[cfg(not(feature = "json"))]
+                return Err(Error::feature_not_enabled("json"));
+            }
+            "jsonc" | "json5" => {
+                #[cfg(feature = "json")]
+                {
+                    // Keep comments and layout when the file was parsed
+                    if let Some(SourceDocument::Json(document)) = source_document {
+                        let mut document = document.clone();
+                        document.sync(values)?;
+                        return Ok(document.to_string());
+                    }
+                    let dialect = if format == "jsonc" {
+                        parsers::json5_parser::Dialect::Jsonc
+                    } else {
+                        parsers::json5_parser::Dialect::Json5
+                    };
+                    parsers::json5_parser::serialize_with(values, dialect)
+                }
                 #[cfg(not(feature = "json"))]
                 return Err(Error::feature_not_enabled("json"));
             }
             "toml" => {
                 #[cfg(feature = "toml")]
                 {
-                    // Use NOML's serializer for format preservation
-                    #[cfg(feature = "noml")]
-                    if let Some(ref document) = self.noml_document {
-                        return Ok(noml::serialize_document(document)?);
+                    // Edit the original text so comments and layout survive
+                    if let Some(SourceDocument::Toml(document)) = source_document {
+                        let mut document = document.clone();
+                        document.sync(values)?;
+                        return Ok(document.to_string());
                     }
-                    // Fallback to basic serialization
-                    self.serialize_as_toml()
+                    parsers::toml_parser::serialize(values)
                 }
                 #[cfg(not(feature = "toml"))]
                 return Err(Error::feature_not_enabled("toml"));
@@ -358,7 +638,7 @@ impl Config {
             "noml" => {
                 #[cfg(feature = "noml")]
                 {
-                    if let Some(ref document) = self.noml_document {
+                    if let Some(document) = noml_document {
                         Ok(noml::serialize_document(document)?)
                     } else {
                         Err(Error::internal("NOML document not preserved"))
@@ -367,15 +647,82 @@ impl Config {
                 #[cfg(not(feature = "noml"))]
                 return Err(Error::feature_not_enabled("noml"));
             }
-            "conf" => self.serialize_as_conf(),
-            _ => Err(Error::unknown_format(&self.format)),
+            "conf" => {
+                // Replay edits onto the original layout; shapes CONF cannot
+                // hold losslessly are refused rather than regenerating the
+                // file without its comments
+                if let Some(SourceDocument::Conf(document)) = source_document {
+                    let mut document = document.clone();
+                    document.sync(values)?;
+                    return Ok(document.to_string());
+                }
+                self.serialize_as_conf(values)
+            }
+            "ini" => {
+                if let Some(SourceDocument::Ini(document)) = source_document {
+                    let mut document = document.clone();
+                    document.sync(values)?;
+                    return Ok(document.to_string());
+                }
+                parsers::ini_parser::serialize(values)
+            }
+            "dotenv" => {
+                if let Some(SourceDocument::Dotenv(document)) = source_document {
+                    let mut document = document.clone();
+                    document.sync(values)?;
+                    return Ok(document.to_string());
+                }
+                parsers::dotenv_parser::serialize(values)
+            }
+            "properties" => parsers::properties_parser::serialize(values),
+            "yaml" => {
+                #[cfg(feature = "yaml")]
+                return parsers::yaml_parser::serialize(values);
+                #[cfg(not(feature = "yaml"))]
+                return Err(Error::feature_not_enabled("yaml"));
+            }
+            "hcl" => {
+                #[cfg(feature = "hcl")]
+                {
+                    // Keep labelled blocks as blocks when the file was parsed
+                    if let Some(SourceDocument::Hcl(document)) = source_document {
+                        let mut document = document.clone();
+                        document.sync(values)?;
+                        return document.render();
+                    }
+                    parsers::hcl_parser::serialize(values)
+                }
+                #[cfg(not(feature = "hcl"))]
+                return Err(Error::feature_not_enabled("hcl"));
+            }
+            "xml" => {
+                #[cfg(feature = "xml")]
+                {
+                    // Keep attributes as attributes when the file was parsed
+                    if let Some(SourceDocument::Xml(document)) = source_document {
+                        let mut document = document.clone();
+                        document.sync(values)?;
+                        return document.render();
+                    }
+                    match xml_wrapper(values) {
+                        Some(root) => parsers::xml_parser::serialize_with(
+                            values,
+                            &parsers::xml_parser::XmlOptions::new().root(root),
+                        ),
+                        None => parsers::xml_parser::serialize(values),
+                    }
+                }
+                #[cfg(not(feature = "xml"))]
+                return Err(Error::feature_not_enabled("xml"));
+            }
+            _ => Err(Error::unknown_format(format)),
         }
     }
 
     /// Serialize as CONF format
-    fn serialize_as_conf(&self) -> Result<String> {
+    fn serialize_as_conf(&self, values: &Value) -> Result<String> {
         let mut output = String::new();
-        if let Value::Table(table) = &self.values {
+        if let Value::Table(table) = values {
             self.write_conf_table(&mut output, table, "")?;
         }
         Ok(output)
@@ -443,16 +790,6 @@ impl Config {
         }
     }
 
-    /// Serialize as TOML format (basic implementation)
-    #[cfg(feature = "toml")]
-    fn serialize_as_toml(&self) -> Result<String> {
-        // This is a simplified TOML serializer
-        // In practice, you'd use the NOML library for proper TOML serialization
-        Err(Error::internal(
-            "Basic TOML serialization not implemented - use NOML library",
-        ))
-    }
-
     /// Validate the configuration against a schema
     #[cfg(feature = "schema")]
     pub fn validate_schema(&self, schema: &Schema) -> Result<()> {
@@ -464,6 +801,59 @@ impl Config {
         &self.values
     }
 
+    /// Deserialize the whole configuration into a typed value.
+    ///
+    /// Works with any `#[derive(Deserialize)]` type regardless of the
+    /// source format. Errors are [`Error::Deserialize`] and name the dotted
+    /// path of the failing field.
+    ///
+    /// ```rust
+    /// use config_lib::Config;
+    /// use serde::Deserialize;
+    ///
+    /// #[derive(Deserialize)]
+    /// struct App {
+    ///     name: String,
+    ///     port: u16,
+    /// }
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let config = Config::from_string("name = demo\nport = 8080", Some("conf"))?;
+    /// let app: App = config.try_deserialize()?;
+    /// assert_eq!(app.port, 8080);
+    /// # let _ = app.name;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn try_deserialize<T: serde::de::DeserializeOwned>(&self) -> Result<T> {
+        crate::de::from_value(&self.values)
+    }
+
+    /// Deserialize the value at `path` into a typed value.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::KeyNotFound`] if `path` does not resolve, or
+    /// [`Error::Deserialize`] (with a root-relative path) if the value does
+    /// not fit `T`.
+    pub fn get_as<T: serde::de::DeserializeOwned>(&self, path: &str) -> Result<T> {
+        let value = self.get(path).ok_or_else(|| Error::key_not_found(path))?;
+        crate::de::from_value_at(value, path)
+    }
+
+    /// Run a query (wildcards, `**`, slices, predicates) against the
+    /// configuration. See [`crate::query`] for the syntax.
+    ///
+    /// Returned paths use the same grammar as [`Config::get`] and
+    /// [`Config::set`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Parse`] if the pattern is malformed.
+    pub fn query(&self, pattern: &str) -> Result<Vec<crate::query::QueryMatch<'_>>> {
+        self.values.query(pattern)
+    }
+
     /// Merge another configuration into this one
     ///
     /// Invalidates the entire resolved-path cache.
@@ -473,47 +863,141 @@ impl Config {
     /// Returns an error if the configuration was constructed with
     /// [`ConfigOptions::read_only`].
     pub fn merge(&mut self, other: &Config) -> Result<()> {
+        self.merge_with(other, &MergeOptions::default())
+    }
+
+    /// Merge another configuration into this one using the given
+    /// [`MergeOptions`] (array strategies, per-path overrides, deletion
+    /// markers). See [`crate::merge`].
+    ///
+    /// Invalidates the entire resolved-path cache.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the configuration was constructed with
+    /// [`ConfigOptions::read_only`].
+    pub fn merge_with(&mut self, other: &Config, options: &MergeOptions) -> Result<()> {
         self.ensure_writable()?;
-        self.merge_value(&other.values)?;
+        let mut origin_for = |path: &str| other.origins.get(path).cloned();
+        let mut tracker = layered::Tracker::new(&mut self.origins, &mut origin_for);
+        merge::merge_tracked(
+            &mut self.values,
+            &other.values,
+            "",
+            "",
+            &mut tracker,
+            options,
+        );
         self.modified = true;
         self.cache.clear();
         Ok(())
     }
 
-    /// Helper to merge values recursively
-    fn merge_value(&mut self, other: &Value) -> Result<()> {
-        match (&mut self.values, other) {
-            (Value::Table(self_table), Value::Table(other_table)) => {
-                for (key, other_value) in other_table {
-                    match self_table.get_mut(key) {
-                        Some(self_value) => {
-                            if let (Value::Table(_), Value::Table(_)) = (&*self_value, other_value)
-                            {
-                                // Create a temporary config for recursive merging
-                                let mut temp_config = Config::new();
-                                temp_config.values = self_value.clone();
-                                temp_config.merge_value(other_value)?;
-                                *self_value = temp_config.values;
-                            } else {
-                                // Replace value
-                                *self_value = other_value.clone();
-                            }
-                        }
-                        None => {
-                            // Insert new value
-                            self_table.insert(key.clone(), other_value.clone());
-                        }
+    /// Apply an RFC 6902 JSON Patch. See [`crate::patch`].
+    ///
+    /// The patch is atomic: if any operation fails, the configuration is
+    /// left unchanged. Invalidates the entire resolved-path cache on
+    /// success; patched keys report an origin of `patch`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the configuration was constructed with
+    /// [`ConfigOptions::read_only`], or as [`Value::apply_patch`] does.
+    pub fn apply_patch(&mut self, ops: &[PatchOp]) -> Result<()> {
+        self.ensure_writable()?;
+        if self.origins.is_empty() {
+            self.values.apply_patch(ops)?;
+        } else {
+            let mut values = self.values.clone();
+            let mut origins = self.origins.clone();
+            for op in ops {
+                if let PatchOp::Remove { path } | PatchOp::Move { from: path, .. } = op {
+                    if let Some(removed) = patch::pointer_to_path(&values, path) {
+                        layered::forget_subtree(&mut origins, &removed);
+                    }
+                }
+                patch::apply_op(&mut values, op)?;
+                if !matches!(op, PatchOp::Remove { .. } | PatchOp::Test { .. }) {
+                    if let Some(target) = patch::pointer_to_path(&values, op.path()) {
+                        layered::forget_subtree(&mut origins, &target);
+                        origins.insert(target, Origin::new("patch"));
                     }
                 }
             }
-            _ => {
-                // Replace entire value
-                self.values = other.clone();
-            }
+            self.values = values;
+            self.origins = origins;
         }
+        self.modified = true;
+        self.cache.clear();
         Ok(())
     }
 
+    /// Apply an RFC 7396 JSON Merge Patch: tables merge recursively,
+    /// `null` deletes a key and anything else, arrays included, replaces
+    /// unchanged. See [`crate::patch`].
+    ///
+    /// Invalidates the entire resolved-path cache; patched keys report an
+    /// origin of `patch`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the configuration was constructed with
+    /// [`ConfigOptions::read_only`].
+    pub fn apply_merge_patch(&mut self, patch: &Value) -> Result<()> {
+        self.ensure_writable()?;
+        let tracked = !self.origins.is_empty();
+        let mut origin_for = |_: &str| tracked.then(|| Origin::new("patch"));
+        let mut tracker = layered::Tracker::new(&mut self.origins, &mut origin_for);
+        merge::merge_tracked(
+            &mut self.values,
+            patch,
+            "",
+            "",
+            &mut tracker,
+            &patch::merge_patch_options(),
+        );
+        self.modified = true;
+        self.cache.clear();
+        Ok(())
+    }
+
+    /// List what changed from this configuration (old) to `other` (new).
+    /// See [`crate::diff`].
+    pub fn diff(&self, other: &Config) -> Vec<crate::diff::Change> {
+        self.values.diff(&other.values)
+    }
+
+    /// Start a [`LayeredBuilder`] that assembles a configuration from
+    /// ordered sources and records per-key provenance.
+    pub fn layered() -> LayeredBuilder {
+        LayeredBuilder::new()
+    }
+
+    /// Where the value at `path` came from, for configurations built with
+    /// [`Config::layered`] (or merged from one).
+    ///
+    /// Returns `None` when no origin was recorded, e.g. for paths that do
+    /// not exist or configurations loaded from a single source.
+    pub fn origin(&self, path: &str) -> Option<&Origin> {
+        layered::canonical_path(&self.values, path)
+            .and_then(|canonical| self.origins.get(&canonical))
+            .or_else(|| self.origins.get(crate::path::quote_key(path).as_ref()))
+    }
+
+    /// Iterate over every recorded origin as `(canonical path, origin)`.
+    pub fn origins(&self) -> impl Iterator<Item = (&str, &Origin)> {
+        self.origins
+            .iter()
+            .map(|(path, origin)| (path.as_str(), origin))
+    }
+
+    pub(crate) fn from_layers(values: Value, format: String, origins: Origins) -> Self {
+        let mut config = Self::from(values);
+        config.format = format;
+        config.origins = origins;
+        config
+    }
+
     // =====================================================================
     // Validation Methods (Feature-gated)
     // =====================================================================
@@ -787,6 +1271,10 @@ impl Config {
     /// (default `true`) toggles the cache layer; with it disabled,
     /// every `get_arc` call walks the tree and allocates a fresh
     /// `Arc<Value>`.
+    ///
+    /// Paths resolve with the same grammar as [`Config::get`] (see
+    /// [`crate::path`]), including `[n]` indices and quoted keys. Cache
+    /// entries are keyed by the path string as written.
     pub fn get_arc(&self, path: &str) -> Option<Arc<Value>> {
         if self.options.cache_enabled {
             if let Some(entry) = self.cache.get(path) {
@@ -1059,14 +1547,25 @@ impl From<Value> for Config {
             cache_misses: AtomicU64::new(0),
             cache: DashMap::new(),
             defaults: Arc::new(RwLock::new(BTreeMap::new())),
+            origins: Origins::new(),
             #[cfg(feature = "noml")]
             noml_document: None,
+            source_document: None,
             #[cfg(feature = "validation")]
             validation_rules: None,
         }
     }
 }
 
+/// Document element wrapped around XML output of `values`, unless its
+/// root is a single key holding one element
+fn xml_wrapper(values: &Value) -> Option<&'static str> {
+    match values {
+        Value::Table(table) if table.len() == 1 && !table.values().any(Value::is_array) => None,
+        _ => Some("config"),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1115,4 +1614,83 @@ mod tests {
         assert_eq!(config1.get("b.y").unwrap().as_integer().unwrap(), 3);
         assert_eq!(config1.get("c").unwrap().as_integer().unwrap(), 4);
     }
+
+    #[test]
+    fn test_indexed_paths() {
+        let mut config = Config::new();
+        config.set("servers[0].host", "a").unwrap();
+        config.set("servers[1].host", "b").unwrap();
+        config.set("\"app.example.com\".ip", "10.0.0.1").unwrap();
+
+        assert_eq!(
+            config
+                .get_arc("servers[-1].host")
+                .unwrap()
+                .as_string()
+                .unwrap(),
+            "b"
+        );
+        assert_eq!(
+            config
+                .get("\"app.example.com\".ip")
+                .unwrap()
+                .as_string()
+                .unwrap(),
+            "10.0.0.1"
+        );
+        assert_eq!(
+            config
+                .remove("servers[0]")
+                .unwrap()
+                .unwrap()
+                .get("host")
+                .unwrap(),
+            &Value::string("a")
+        );
+        assert_eq!(
+            config
+                .get_arc("servers[0].host")
+                .unwrap()
+                .as_string()
+                .unwrap(),
+            "b"
+        );
+    }
+
+    #[test]
+    fn test_patch() {
+        let mut config = Config::new();
+        config.set("server.port", 80).unwrap();
+        assert_eq!(
+            config.get_arc("server.port").unwrap().as_integer().unwrap(),
+            80
+        );
+
+        config
+            .apply_patch(&[PatchOp::Replace {
+                path: "/server/port".into(),
+                value: Value::integer(8080),
+            }])
+            .unwrap();
+        assert_eq!(
+            config.get_arc("server.port").unwrap().as_integer().unwrap(),
+            8080
+        );
+
+        let mut patch = BTreeMap::new();
+        patch.insert("server".to_string(), Value::Null);
+        config.apply_merge_patch(&Value::table(patch)).unwrap();
+        assert!(config.get_arc("server.port").is_none());
+        assert!(config.is_modified());
+
+        config.make_read_only();
+        let op = PatchOp::Add {
+            path: "/x".into(),
+            value: Value::bool(true),
+        };
+        assert!(config.apply_patch(&[op]).is_err());
+        assert!(config
+            .apply_merge_patch(&Value::table(BTreeMap::new()))
+            .is_err());
+    }
 }
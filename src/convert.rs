@@ -0,0 +1,496 @@
+//! # Format Conversion
+//!
+//! Report what a configuration loses when it is written in another format.
+//! [`Config::convert_to`] writes the values in the target format, reads the
+//! output back and compares the two trees; each difference becomes a
+//! [`Loss`] naming the path (in the [`path`](crate::path) grammar) and what
+//! happened to it:
+//!
+//! - nested tables stored as flat keys (Properties, INI, dotenv), and
+//!   arrays stored as indexed keys (`hosts[0]`, `hosts.0`, `HOSTS_0`)
+//! - nulls written as empty strings (INI, Properties) or left out (TOML)
+//! - values that read back as another type, such as datetimes as strings
+//!   in CONF or strings that look like numbers
+//!
+//! ```rust
+//! use config_lib::Config;
+//!
+//! # fn main() -> Result<(), config_lib::Error> {
+//! let config = Config::from_string("name = api\nproxy = null", Some("conf"))?;
+//! let (ini, report) = config.convert_to("ini")?;
+//!
+//! assert_eq!(ini.serialize()?, "name = api\nproxy = \"\"\n");
+//! assert_eq!(
+//!     report.to_string(),
+//!     "conf -> ini: 1 lossy step\n  proxy: null written as \"\""
+//! );
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`Config::convert_to`]: crate::Config::convert_to
+//! [`Loss`]: crate::convert::Loss
+
+use crate::diff::Literal;
+use crate::path;
+use crate::value::Value;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// The kind of a [`Loss`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum LossKind {
+    /// A nested table or array is stored as flat keys (`database.host`,
+    /// `SERVERS_0`); its values are reported separately if they changed.
+    Flattened,
+    /// A null is written as another value, such as an empty string.
+    NullReplaced,
+    /// The value reads back as a different type.
+    TypeChanged,
+    /// The value reads back as the same type with a different value.
+    ValueChanged,
+    /// The value is missing after conversion.
+    Dropped,
+}
+
+impl LossKind {
+    /// Lower-case name used by [`Loss::to_value`].
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LossKind::Flattened => "flattened",
+            LossKind::NullReplaced => "null_replaced",
+            LossKind::TypeChanged => "type_changed",
+            LossKind::ValueChanged => "value_changed",
+            LossKind::Dropped => "dropped",
+        }
+    }
+}
+
+/// One value that did not survive a conversion unchanged.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Loss {
+    /// Path of the value in the original configuration.
+    pub path: String,
+    /// What happened at `path`.
+    pub kind: LossKind,
+    /// Human-readable description, e.g. `null written as ""`.
+    pub message: String,
+}
+
+impl Loss {
+    /// Represent the loss as a table with `path`, `kind` and `message`
+    /// entries.
+    pub fn to_value(&self) -> Value {
+        let mut table = BTreeMap::new();
+        table.insert("path".to_string(), Value::string(&self.path));
+        table.insert("kind".to_string(), Value::string(self.kind.as_str()));
+        table.insert("message".to_string(), Value::string(&self.message));
+        Value::table(table)
+    }
+}
+
+impl fmt::Display for Loss {
+    /// One line: `path: message`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = if self.path.is_empty() {
+            "<root>"
+        } else {
+            &self.path
+        };
+        write!(f, "{path}: {}", self.message)
+    }
+}
+
+/// What a conversion between two formats lost, as returned by
+/// [`Config::convert_to`](crate::Config::convert_to).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConversionReport {
+    from: String,
+    to: String,
+    losses: Vec<Loss>,
+}
+
+impl ConversionReport {
+    /// Compare `original` (in format `from`) with `converted`, the same
+    /// values written as `to` and read back.
+    pub(crate) fn new(from: &str, to: &str, original: &Value, converted: &Value) -> Self {
+        let mut walk = Walk {
+            to,
+            root: converted,
+            steps: Vec::new(),
+            losses: Vec::new(),
+        };
+        walk.compare(original, Some(converted));
+        Self {
+            from: from.to_string(),
+            to: to.to_string(),
+            losses: walk.losses,
+        }
+    }
+
+    /// The format the configuration was converted from.
+    pub fn from(&self) -> &str {
+        &self.from
+    }
+
+    /// The format the configuration was converted to.
+    pub fn to(&self) -> &str {
+        &self.to
+    }
+
+    /// Every loss, in path order.
+    pub fn losses(&self) -> &[Loss] {
+        &self.losses
+    }
+
+    /// Whether the converted configuration reads back identically.
+    pub fn is_lossless(&self) -> bool {
+        self.losses.is_empty()
+    }
+
+    /// Represent the report as a table with `from`, `to` and a `losses`
+    /// array of [`Loss::to_value`] tables.
+    pub fn to_value(&self) -> Value {
+        let mut table = BTreeMap::new();
+        table.insert("from".to_string(), Value::string(&self.from));
+        table.insert("to".to_string(), Value::string(&self.to));
+        table.insert(
+            "losses".to_string(),
+            Value::array(self.losses.iter().map(Loss::to_value).collect()),
+        );
+        Value::table(table)
+    }
+}
+
+impl fmt::Display for ConversionReport {
+    /// A `from -> to` summary line followed by one indented line per loss.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.losses.len() {
+            0 => return write!(f, "{} -> {}: lossless", self.from, self.to),
+            1 => write!(f, "{} -> {}: 1 lossy step", self.from, self.to)?,
+            n => write!(f, "{} -> {}: {n} lossy steps", self.from, self.to)?,
+        }
+        for loss in &self.losses {
+            write!(f, "\n  {loss}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Remove nulls from tables and arrays, for formats that cannot hold them.
+pub(crate) fn remove_nulls(value: &mut Value) {
+    match value {
+        Value::Table(table) => {
+            table.retain(|_, value| !value.is_null());
+            table.values_mut().for_each(remove_nulls);
+        }
+        Value::Array(items) => {
+            items.retain(|value| !value.is_null());
+            items.iter_mut().for_each(remove_nulls);
+        }
+        _ => {}
+    }
+}
+
+#[derive(Clone)]
+enum Step {
+    Key(String),
+    Index(usize),
+}
+
+/// Comparison state: the converted root (for flat-key lookups) and the
+/// path of the value being compared.
+struct Walk<'a> {
+    to: &'a str,
+    root: &'a Value,
+    steps: Vec<Step>,
+    losses: Vec<Loss>,
+}
+
+impl Walk<'_> {
+    fn path(&self) -> String {
+        self.steps
+            .iter()
+            .fold(String::new(), |parent, step| match step {
+                Step::Key(key) => path::join_key(&parent, key),
+                Step::Index(index) => path::join_index(&parent, *index),
+            })
+    }
+
+    fn lose(&mut self, kind: LossKind, message: String) {
+        let path = self.path();
+        self.losses.push(Loss {
+            path,
+            kind,
+            message,
+        });
+    }
+
+    fn compare(&mut self, original: &Value, converted: Option<&Value>) {
+        let Some(converted) = converted else {
+            self.missing(original);
+            return;
+        };
+        if same(original, converted) {
+            return;
+        }
+        match (original, converted) {
+            (Value::Table(before), Value::Table(after)) => {
+                for (key, value) in before {
+                    self.steps.push(Step::Key(key.clone()));
+                    self.compare(value, after.get(key));
+                    self.steps.pop();
+                }
+            }
+            (Value::Array(before), Value::Array(after)) if before.len() == after.len() => {
+                for (index, (value, read)) in before.iter().zip(after).enumerate() {
+                    self.steps.push(Step::Index(index));
+                    self.compare(value, Some(read));
+                    self.steps.pop();
+                }
+            }
+            (Value::Array(before), Value::Array(after)) => self.lose(
+                LossKind::ValueChanged,
+                format!(
+                    "array of {} items reads back with {}",
+                    before.len(),
+                    after.len()
+                ),
+            ),
+            (Value::Null, _) => self.lose(
+                LossKind::NullReplaced,
+                format!("null written as {}", Literal(converted)),
+            ),
+            _ if original.type_name() == converted.type_name() => self.lose(
+                LossKind::ValueChanged,
+                format!("{} reads back as {}", Literal(original), Literal(converted)),
+            ),
+            _ => self.lose(
+                LossKind::TypeChanged,
+                format!(
+                    "{} {} reads back as {} {}",
+                    original.type_name(),
+                    Literal(original),
+                    converted.type_name(),
+                    Literal(converted)
+                ),
+            ),
+        }
+    }
+
+    /// `original` has no counterpart at its path: look for its values under
+    /// flattened names before reporting it dropped.
+    fn missing(&mut self, original: &Value) {
+        let mut leaves = Vec::new();
+        collect_leaves(original, &mut self.steps.clone(), &mut leaves);
+        let container = matches!(original, Value::Table(t) if !t.is_empty())
+            || matches!(original, Value::Array(a) if !a.is_empty());
+        let found: Vec<_> = leaves
+            .iter()
+            .map(|(steps, _)| flat_lookup(self.root, steps))
+            .collect();
+
+        let Some((name, _)) = found.iter().flatten().next().filter(|_| container) else {
+            let message = if original.is_null() {
+                format!("null left out ({} has no null)", self.to)
+            } else {
+                format!("not present after conversion to {}", self.to)
+            };
+            self.lose(LossKind::Dropped, message);
+            return;
+        };
+        let shape = if original.is_table() {
+            "nested table"
+        } else {
+            "array"
+        };
+        self.lose(
+            LossKind::Flattened,
+            format!("{shape} stored as flat keys such as `{name}`"),
+        );
+
+        let outer = std::mem::take(&mut self.steps);
+        for ((steps, value), found) in leaves.into_iter().zip(found) {
+            self.steps = steps;
+            self.compare(value, found.map(|(_, value)| value));
+        }
+        self.steps = outer;
+    }
+}
+
+/// Find a leaf under the names flattening formats give it: dotted
+/// (`database.host`, `servers[0]`), dotted with index keys (`servers.0`,
+/// as INI stores arrays) or environment style (`DATABASE_HOST`,
+/// `SERVERS_0`).
+fn flat_lookup<'a>(root: &'a Value, steps: &[Step]) -> Option<(String, &'a Value)> {
+    let Value::Table(table) = root else {
+        return None;
+    };
+    let mut dotted = String::new();
+    let mut indexed = String::new();
+    let mut env = String::new();
+    for step in steps {
+        match step {
+            Step::Key(key) => {
+                if !dotted.is_empty() {
+                    dotted.push('.');
+                    indexed.push('.');
+                }
+                dotted.push_str(key);
+                indexed.push_str(key);
+                if !env.is_empty() {
+                    env.push('_');
+                }
+                env.push_str(&key.to_uppercase());
+            }
+            Step::Index(index) => {
+                dotted.push_str(&format!("[{index}]"));
+                indexed.push_str(&format!(".{index}"));
+                env.push_str(&format!("_{index}"));
+            }
+        }
+    }
+    [dotted, indexed, env]
+        .into_iter()
+        .find_map(|name| table.get(&name).map(|value| (name, value)))
+}
+
+/// Scalars and empty containers under `value`, with their paths.
+fn collect_leaves<'v>(
+    value: &'v Value,
+    steps: &mut Vec<Step>,
+    out: &mut Vec<(Vec<Step>, &'v Value)>,
+) {
+    match value {
+        Value::Table(table) if !table.is_empty() => {
+            for (key, value) in table {
+                steps.push(Step::Key(key.clone()));
+                collect_leaves(value, steps, out);
+                steps.pop();
+            }
+        }
+        Value::Array(items) if !items.is_empty() => {
+            for (index, value) in items.iter().enumerate() {
+                steps.push(Step::Index(index));
+                collect_leaves(value, steps, out);
+                steps.pop();
+            }
+        }
+        leaf => out.push((steps.clone(), leaf)),
+    }
+}
+
+/// Equality that treats NaN as equal to itself.
+fn same(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Float(x), Value::Float(y)) => {
+            #[allow(clippy::float_cmp)]
+            let equal = x == y || (x.is_nan() && y.is_nan());
+            equal
+        }
+        _ => a == b,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Config;
+
+    use super::*;
+
+    #[test]
+    fn test_report_values_and_lossless_round_trip() -> crate::Result<()> {
+        let config = Config::from_string(
+            "name = api\nproxy = null\n\n[database]\nhost = db\n",
+            Some("conf"),
+        )?;
+        let (_, report) = config.convert_to("conf")?;
+        assert!(report.is_lossless());
+
+        let (ini, report) = config.convert_to("ini")?;
+        assert_eq!(ini.get("database.host"), Some(&Value::string("db")));
+        let table = report.to_value();
+        assert_eq!(table.get("to"), Some(&Value::string("ini")));
+        assert_eq!(
+            table.get("losses[0].kind"),
+            Some(&Value::string("flattened"))
+        );
+        assert_eq!(report.losses()[1].kind, LossKind::NullReplaced);
+        assert_eq!(
+            report.losses()[1].to_string(),
+            "proxy: null written as \"\""
+        );
+        Ok(())
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_lossy_conversions_are_reported() -> crate::Result<()> {
+        let config = Config::from_string(
+            r#"{"app": {"name": "api", "port": "8080", "proxy": null}, "hosts": ["a", "b"], "ratio": 1.0}"#,
+            Some("json"),
+        )?;
+
+        let (_, report) = config.convert_to("json")?;
+        assert!(report.is_lossless());
+        assert_eq!(report.to_string(), "json -> json: lossless");
+
+        let (properties, report) = config.convert_to("properties")?;
+        assert_eq!(properties.get("app.name"), Some(&Value::string("api")));
+        assert_eq!(
+            report.to_string(),
+            "json -> properties: 4 lossy steps
+  app: nested table stored as flat keys such as `app.name`
+  app.port: string \"8080\" reads back as integer 8080
+  app.proxy: null written as \"\"
+  hosts: array stored as flat keys such as `hosts[0]`"
+        );
+
+        let (ini, report) = config.convert_to("ini")?;
+        assert_eq!(ini.get("hosts.1"), Some(&Value::string("b")));
+        assert_eq!(
+            report.to_string(),
+            "json -> ini: 4 lossy steps
+  app: nested table stored as flat keys such as `app.name`
+  app.port: string \"8080\" reads back as integer 8080
+  app.proxy: null written as \"\"
+  hosts: array stored as flat keys such as `hosts.0`"
+        );
+        let workers = Config::from_string(r#"{"workers": 1, "idle": 0}"#, Some("json"))?;
+        assert!(workers.convert_to("ini")?.1.is_lossless());
+
+        let (toml, report) = config.convert_to("toml")?;
+        assert!(toml.get("app.proxy").is_none());
+        let kinds: Vec<_> = report
+            .losses()
+            .iter()
+            .map(|loss| (loss.path.as_str(), loss.kind))
+            .collect();
+        assert_eq!(kinds, vec![("app.proxy", LossKind::Dropped)]);
+        assert_eq!(
+            report.losses()[0].message,
+            "null left out (toml has no null)"
+        );
+        Ok(())
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_datetime_to_conf_is_a_type_change() -> crate::Result<()> {
+        let mut config = Config::new();
+        let at = chrono::DateTime::parse_from_rfc3339("2025-01-02T03:04:05Z")
+            .map_err(|e| crate::Error::internal(e.to_string()))?
+            .with_timezone(&chrono::Utc);
+        config.set("deployed", Value::datetime(at))?;
+
+        let (conf, report) = config.convert_to("conf")?;
+        assert_eq!(
+            conf.get("deployed"),
+            Some(&Value::string("2025-01-02T03:04:05+00:00"))
+        );
+        assert_eq!(report.losses().len(), 1);
+        assert_eq!(report.losses()[0].kind, LossKind::TypeChanged);
+        Ok(())
+    }
+}
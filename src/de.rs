@@ -0,0 +1,682 @@
+//! # Serde Deserialize Bridge
+//!
+//! Maps a parsed [`Value`] tree onto any `#[derive(Deserialize)]` type, so
+//! typed settings structs can be filled from every supported format without
+//! hand-walking `get(..).as_integer()` chains.
+//!
+//! Conversions follow the same leniency as the [`Value`] accessors: numeric
+//! and boolean targets accept their string spellings (`"8080"`, `"yes"`),
+//! and a scalar is accepted where a sequence is expected (CONF collapses a
+//! one-element space-separated list to a scalar). Errors carry the dotted
+//! path of the failing field, e.g. `servers[1].port`.
+//!
+//! ```rust
+//! use config_lib::Config;
+//! use serde::Deserialize;
+//!
+//! #[derive(Deserialize)]
+//! struct Database {
+//!     host: String,
+//!     port: u16,
+//! }
+//!
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let config = Config::from_string("[database]\nhost = db.local\nport = 5432", Some("conf"))?;
+//! let db: Database = config.get_as("database")?;
+//! assert_eq!(db.host, "db.local");
+//! assert_eq!(db.port, 5432);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::{Error, Result};
+use crate::path;
+use crate::value::Value;
+use serde::de::{
+    self, DeserializeOwned, DeserializeSeed, EnumAccess, IntoDeserializer, MapAccess, SeqAccess,
+    VariantAccess, Visitor,
+};
+use std::collections::btree_map;
+
+/// Deserialize a `T` from a borrowed [`Value`].
+///
+/// Errors are [`Error::Deserialize`] values whose `path` names the failing
+/// field relative to `value`.
+pub fn from_value<T: DeserializeOwned>(value: &Value) -> Result<T> {
+    T::deserialize(ValueDeserializer::new(value))
+}
+
+/// Deserialize a `T` from a [`Value`] that lives at `path` inside a larger
+/// tree. Error paths are reported relative to the tree root.
+pub(crate) fn from_value_at<T: DeserializeOwned>(value: &Value, path: &str) -> Result<T> {
+    T::deserialize(ValueDeserializer::with_path(value, path.to_string()))
+        .map_err(|e| e.with_path(path))
+}
+
+/// A [`serde::Deserializer`] over a borrowed [`Value`] that tracks the path
+/// of the node being visited for error reporting.
+///
+/// Strings are always handed to visitors as owned copies, so the
+/// deserializer works for any `'de` lifetime; targets that borrow `&str`
+/// from the input are not supported (use `String`).
+pub struct ValueDeserializer<'a> {
+    value: &'a Value,
+    path: String,
+}
+
+impl<'a> ValueDeserializer<'a> {
+    /// Create a deserializer rooted at `value`.
+    pub fn new(value: &'a Value) -> Self {
+        Self {
+            value,
+            path: String::new(),
+        }
+    }
+
+    fn with_path(value: &'a Value, path: String) -> Self {
+        Self { value, path }
+    }
+
+    fn child_key(&self, key: &str) -> String {
+        path::join_key(&self.path, key)
+    }
+
+    fn child_index(&self, index: usize) -> String {
+        path::join_index(&self.path, index)
+    }
+
+    fn invalid_type(&self, expected: &str) -> Error {
+        Error::deserialize(
+            self.path.clone(),
+            format!(
+                "invalid type: {}, expected {expected}",
+                self.value.type_name()
+            ),
+        )
+    }
+
+    fn convert<T>(&self, result: Result<T>, expected: &str) -> Result<T> {
+        result.map_err(|_| self.invalid_type(expected))
+    }
+
+    fn integer(&self) -> Result<i64> {
+        match self.value {
+            // Refuse to silently truncate 1.5 into 1.
+            Value::Float(f) if f.fract() != 0.0 => Err(self.invalid_type("an integer")),
+            other => self.convert(other.as_integer(), "an integer"),
+        }
+    }
+}
+
+macro_rules! deserialize_integer {
+    ($($method:ident => $visit:ident: $ty:ty),* $(,)?) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+                let n = self.integer()?;
+                let narrowed = <$ty>::try_from(n).map_err(|_| {
+                    Error::deserialize(
+                        self.path.clone(),
+                        format!("integer {n} out of range for {}", stringify!($ty)),
+                    )
+                })?;
+                visitor.$visit(narrowed)
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for ValueDeserializer<'_> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.value {
+            Value::Null => visitor.visit_unit(),
+            Value::Bool(b) => visitor.visit_bool(*b),
+            Value::Integer(i) => visitor.visit_i64(*i),
+            Value::Float(f) => visitor.visit_f64(*f),
+            Value::String(s) => visitor.visit_str(s),
+            Value::Array(_) => self.deserialize_seq(visitor),
+            Value::Table(_) => self.deserialize_map(visitor),
+            #[cfg(feature = "chrono")]
+            Value::DateTime(dt) => visitor.visit_string(dt.to_rfc3339()),
+        }
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let b = self.convert(self.value.as_bool(), "a boolean")?;
+        visitor.visit_bool(b)
+    }
+
+    deserialize_integer! {
+        deserialize_i8 => visit_i8: i8,
+        deserialize_i16 => visit_i16: i16,
+        deserialize_i32 => visit_i32: i32,
+        deserialize_i64 => visit_i64: i64,
+        deserialize_u8 => visit_u8: u8,
+        deserialize_u16 => visit_u16: u16,
+        deserialize_u32 => visit_u32: u32,
+        deserialize_u64 => visit_u64: u64,
+    }
+
+    fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let f = self.convert(self.value.as_float(), "a float")?;
+        visitor.visit_f32(f as f32)
+    }
+
+    fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let f = self.convert(self.value.as_float(), "a float")?;
+        visitor.visit_f64(f)
+    }
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.value {
+            Value::String(s) => visitor.visit_str(s),
+            #[cfg(feature = "chrono")]
+            Value::DateTime(dt) => visitor.visit_string(dt.to_rfc3339()),
+            // Scalars read from untyped formats (CONF, INI, Properties)
+            // may have been inferred as numbers or booleans; a `String`
+            // target still accepts them.
+            Value::Bool(_) | Value::Integer(_) | Value::Float(_) => {
+                let s = self.convert(self.value.to_string_representation(), "a string")?;
+                visitor.visit_string(s)
+            }
+            _ => Err(self.invalid_type("a string")),
+        }
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.value {
+            Value::String(s) => visitor.visit_bytes(s.as_bytes()),
+            _ => self.deserialize_seq(visitor),
+        }
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.value {
+            Value::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.value {
+            Value::Null => visitor.visit_unit(),
+            _ => Err(self.invalid_type("null")),
+        }
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.value {
+            Value::Array(items) => visitor.visit_seq(SeqDeserializer {
+                items: items.iter().enumerate(),
+                parent: &self,
+            }),
+            Value::Null => visitor.visit_seq(SeqDeserializer {
+                items: [].iter().enumerate(),
+                parent: &self,
+            }),
+            Value::Table(_) => Err(self.invalid_type("a sequence")),
+            // A lone scalar is a one-element list.
+            single => visitor.visit_seq(SeqDeserializer {
+                items: std::slice::from_ref(single).iter().enumerate(),
+                parent: &self,
+            }),
+        }
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.value {
+            Value::Table(table) => visitor.visit_map(MapDeserializer {
+                entries: table.iter(),
+                pending: None,
+                parent: &self,
+            }),
+            _ => Err(self.invalid_type("a table")),
+        }
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        match self.value {
+            Value::String(variant) => visitor.visit_enum(variant.as_str().into_deserializer()),
+            Value::Table(table) if table.len() == 1 => {
+                let Some((variant, content)) = table.iter().next() else {
+                    return Err(self.invalid_type("an enum"));
+                };
+                visitor.visit_enum(EnumDeserializer {
+                    variant,
+                    content: ValueDeserializer::with_path(content, self.child_key(variant)),
+                })
+            }
+            _ => Err(self.invalid_type("a string or single-key table for an enum")),
+        }
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_unit()
+    }
+}
+
+/// Sequence access over an array, tracking element indices for error paths.
+struct SeqDeserializer<'a, 'p> {
+    items: std::iter::Enumerate<std::slice::Iter<'a, Value>>,
+    parent: &'p ValueDeserializer<'a>,
+}
+
+impl<'de> SeqAccess<'de> for SeqDeserializer<'_, '_> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>> {
+        match self.items.next() {
+            Some((index, item)) => {
+                let path = self.parent.child_index(index);
+                seed.deserialize(ValueDeserializer::with_path(item, path.clone()))
+                    .map(Some)
+                    .map_err(|e| e.with_path(&path))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.items.len())
+    }
+}
+
+/// Map access over a table, tracking keys for error paths.
+struct MapDeserializer<'a, 'p> {
+    entries: btree_map::Iter<'a, String, Value>,
+    pending: Option<(&'a String, &'a Value)>,
+    parent: &'p ValueDeserializer<'a>,
+}
+
+impl<'de> MapAccess<'de> for MapDeserializer<'_, '_> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
+        match self.entries.next() {
+            Some((key, value)) => {
+                self.pending = Some((key, value));
+                // Keys are strings in every format; routing them through a
+                // `ValueDeserializer` lets `HashMap<u16, _>` style targets
+                // parse numeric keys the same way scalar values do.
+                let key_value = Value::String(key.clone());
+                let path = self.parent.child_key(key);
+                seed.deserialize(ValueDeserializer::with_path(&key_value, path))
+                    .map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
+        let (key, value) = self
+            .pending
+            .take()
+            .ok_or_else(|| Error::internal("next_value_seed called before next_key_seed"))?;
+        let path = self.parent.child_key(key);
+        seed.deserialize(ValueDeserializer::with_path(value, path.clone()))
+            .map_err(|e| e.with_path(&path))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.entries.len())
+    }
+}
+
+/// Enum access for the externally tagged `{ variant = content }` shape.
+struct EnumDeserializer<'a> {
+    variant: &'a str,
+    content: ValueDeserializer<'a>,
+}
+
+impl<'de, 'a> EnumAccess<'de> for EnumDeserializer<'a> {
+    type Error = Error;
+    type Variant = ValueDeserializer<'a>;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self::Variant)> {
+        let variant = seed.deserialize(de::value::StrDeserializer::<Error>::new(self.variant))?;
+        Ok((variant, self.content))
+    }
+}
+
+impl<'de> VariantAccess<'de> for ValueDeserializer<'_> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        de::Deserialize::deserialize(self)
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+        de::Deserializer::deserialize_seq(self, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        de::Deserializer::deserialize_map(self, visitor)
+    }
+}
+
+macro_rules! forward_to_value_deserializer {
+    ($($method:ident($($arg:ident: $ty:ty),*)),* $(,)?) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, $($arg: $ty,)* visitor: V) -> Result<V::Value> {
+                ValueDeserializer::new(self).$method($($arg,)* visitor)
+            }
+        )*
+    };
+}
+
+/// `&Value` is itself a deserializer, so `T::deserialize(&value)` works
+/// directly. Paths in errors are relative to the borrowed value.
+impl<'de> de::Deserializer<'de> for &Value {
+    type Error = Error;
+
+    forward_to_value_deserializer! {
+        deserialize_any(),
+        deserialize_bool(),
+        deserialize_i8(),
+        deserialize_i16(),
+        deserialize_i32(),
+        deserialize_i64(),
+        deserialize_u8(),
+        deserialize_u16(),
+        deserialize_u32(),
+        deserialize_u64(),
+        deserialize_f32(),
+        deserialize_f64(),
+        deserialize_char(),
+        deserialize_str(),
+        deserialize_string(),
+        deserialize_bytes(),
+        deserialize_byte_buf(),
+        deserialize_option(),
+        deserialize_unit(),
+        deserialize_unit_struct(name: &'static str),
+        deserialize_newtype_struct(name: &'static str),
+        deserialize_seq(),
+        deserialize_tuple(len: usize),
+        deserialize_tuple_struct(name: &'static str, len: usize),
+        deserialize_map(),
+        deserialize_struct(name: &'static str, fields: &'static [&'static str]),
+        deserialize_enum(name: &'static str, variants: &'static [&'static str]),
+        deserialize_identifier(),
+        deserialize_ignored_any(),
+    }
+}
+
+/// `Value` deserializes from any self-describing format, so it can be
+/// embedded in user types as an untyped "anything goes" field.
+impl<'de> de::Deserialize<'de> for Value {
+    fn deserialize<D: de::Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Self, D::Error> {
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
+
+/// Builds a [`Value`] from whatever the source format reports.
+struct ValueVisitor;
+
+impl<'de> Visitor<'de> for ValueVisitor {
+    type Value = Value;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("any configuration value")
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> std::result::Result<Value, E> {
+        Ok(Value::Bool(v))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> std::result::Result<Value, E> {
+        Ok(Value::Integer(v))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> std::result::Result<Value, E> {
+        i64::try_from(v)
+            .map(Value::Integer)
+            .map_err(|_| E::custom(format!("integer {v} does not fit in i64")))
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> std::result::Result<Value, E> {
+        Ok(Value::Float(v))
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> std::result::Result<Value, E> {
+        Ok(Value::String(v.to_string()))
+    }
+
+    fn visit_string<E: de::Error>(self, v: String) -> std::result::Result<Value, E> {
+        Ok(Value::String(v))
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> std::result::Result<Value, E> {
+        Ok(Value::Array(
+            v.iter().map(|b| Value::Integer(i64::from(*b))).collect(),
+        ))
+    }
+
+    fn visit_none<E: de::Error>(self) -> std::result::Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_unit<E: de::Error>(self) -> std::result::Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_some<D: de::Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> std::result::Result<Value, D::Error> {
+        de::Deserialize::deserialize(deserializer)
+    }
+
+    fn visit_newtype_struct<D: de::Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> std::result::Result<Value, D::Error> {
+        de::Deserialize::deserialize(deserializer)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> std::result::Result<Value, A::Error> {
+        // The hint comes from the input; don't let it reserve unbounded memory
+        let mut items = Vec::with_capacity(seq.size_hint().unwrap_or(0).min(4096));
+        while let Some(item) = seq.next_element()? {
+            items.push(item);
+        }
+        Ok(Value::Array(items))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> std::result::Result<Value, A::Error> {
+        let mut table = std::collections::BTreeMap::new();
+        while let Some((key, value)) = map.next_entry::<String, Value>()? {
+            table.insert(key, value);
+        }
+        Ok(Value::Table(table))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Config;
+    use serde::Deserialize;
+    use std::collections::{BTreeMap, HashMap};
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Server {
+        host: String,
+        port: u16,
+        #[serde(default)]
+        tls: bool,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    #[serde(rename_all = "lowercase")]
+    enum Level {
+        Debug,
+        Info,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct App {
+        name: String,
+        level: Level,
+        servers: Vec<Server>,
+        limits: HashMap<String, f64>,
+        timeout: Option<u32>,
+    }
+
+    fn sample() -> Value {
+        let mut root = Value::table(BTreeMap::new());
+        root.set_nested("name", Value::string("svc")).unwrap();
+        root.set_nested("level", Value::string("info")).unwrap();
+        let mut a = Value::table(BTreeMap::new());
+        a.set_nested("host", Value::string("a")).unwrap();
+        a.set_nested("port", Value::integer(80)).unwrap();
+        let mut b = Value::table(BTreeMap::new());
+        b.set_nested("host", Value::string("b")).unwrap();
+        b.set_nested("port", Value::string("443")).unwrap();
+        b.set_nested("tls", Value::string("yes")).unwrap();
+        root.set_nested("servers", Value::array(vec![a, b]))
+            .unwrap();
+        root.set_nested("limits.cpu", Value::float(1.5)).unwrap();
+        root.set_nested("limits.mem", Value::integer(2)).unwrap();
+        root
+    }
+
+    #[test]
+    fn test_deserialize_struct() {
+        let app: App = from_value(&sample()).unwrap();
+        assert_eq!(app.name, "svc");
+        assert_eq!(app.level, Level::Info);
+        assert_eq!(app.servers.len(), 2);
+        assert_eq!(
+            app.servers[1],
+            Server {
+                host: "b".into(),
+                port: 443,
+                tls: true
+            }
+        );
+        assert_eq!(app.limits["mem"], 2.0);
+        assert_eq!(app.timeout, None);
+    }
+
+    #[test]
+    fn test_error_carries_path() {
+        let mut value = sample();
+        value
+            .set_nested("servers", {
+                let mut bad = Value::table(BTreeMap::new());
+                bad.set_nested("host", Value::string("x")).unwrap();
+                bad.set_nested("port", Value::integer(70000)).unwrap();
+                Value::array(vec![bad])
+            })
+            .unwrap();
+        match from_value::<App>(&value) {
+            Err(Error::Deserialize { path, message }) => {
+                assert_eq!(path, "servers[0].port");
+                assert!(message.contains("out of range"));
+            }
+            other => panic!("unexpected result: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_missing_field_path() {
+        let config =
+            Config::from_string("[db]\nhost = localhost\n[other]\nx = 1", Some("conf")).unwrap();
+        let err = config.get_as::<Server>("db").unwrap_err();
+        match err {
+            Error::Deserialize { path, message } => {
+                assert_eq!(path, "db");
+                assert!(message.contains("port"));
+            }
+            other => panic!("unexpected error: {other:?}"),
+        }
+        assert!(matches!(
+            config.get_as::<Server>("missing"),
+            Err(Error::KeyNotFound { .. })
+        ));
+    }
+
+    #[test]
+    fn test_rejects_fractional_integer() {
+        assert!(from_value::<i32>(&Value::float(1.5)).is_err());
+        assert_eq!(from_value::<i32>(&Value::float(2.0)).unwrap(), 2);
+    }
+
+    #[test]
+    fn test_scalar_as_single_element_seq() {
+        let config = Config::from_string("hosts = alpha", Some("conf")).unwrap();
+        let hosts: Vec<String> = config.get_as("hosts").unwrap();
+        assert_eq!(hosts, vec!["alpha".to_string()]);
+    }
+}
@@ -0,0 +1,509 @@
+//! # Structured Diff
+//!
+//! Compute what changed between two [`Value`] trees or [`Config`]
+//! snapshots. Each [`Change`] names the full path (in the
+//! [`path`](crate::path) grammar) together with the old and/or new value.
+//!
+//! - Tables are compared key by key; a key present on only one side is a
+//!   single `Added` / `Removed` change carrying the whole subtree.
+//! - Arrays are compared by index, or — for paths registered with
+//!   [`DiffOptions::array_key`] — by an identifying field, so reordering
+//!   `servers` does not show up as a wall of modifications.
+//! - `1` and `1.0` are equal unless [`DiffOptions::strict_numbers`] is set,
+//!   and `NaN` equals `NaN`.
+//!
+//! ```rust
+//! use config_lib::diff::ChangeKind;
+//! use config_lib::Config;
+//!
+//! # fn main() -> Result<(), config_lib::Error> {
+//! let before = Config::from_string("port = 80\nhost = a", Some("conf"))?;
+//! let after = Config::from_string("port = 8080\ndebug = true", Some("conf"))?;
+//!
+//! let changes = before.diff(&after);
+//! assert_eq!(changes.len(), 3);
+//! assert_eq!(changes[0].kind, ChangeKind::Added);
+//! assert_eq!(changes[0].path, "debug");
+//! println!("{}", config_lib::diff::render(&changes));
+//! // + debug = true
+//! // - host = "a"
+//! // ~ port: 80 -> 8080
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`Config`]: crate::Config
+//! [`Change`]: crate::diff::Change
+//! [`DiffOptions::array_key`]: crate::diff::DiffOptions::array_key
+//! [`DiffOptions::strict_numbers`]: crate::diff::DiffOptions::strict_numbers
+
+use crate::error::Result;
+use crate::path;
+use crate::query::Query;
+use crate::value::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Write as _};
+
+/// The kind of a [`Change`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ChangeKind {
+    /// The path exists only in the new tree.
+    Added,
+    /// The path exists only in the old tree.
+    Removed,
+    /// The path exists in both trees with different values.
+    Modified,
+}
+
+impl ChangeKind {
+    /// Lower-case name used by the JSON rendering.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ChangeKind::Added => "added",
+            ChangeKind::Removed => "removed",
+            ChangeKind::Modified => "modified",
+        }
+    }
+}
+
+/// One difference between two value trees.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change {
+    /// Full path of the changed value. For keyed arrays every index is
+    /// one of the old tree, and added elements are numbered after the
+    /// old elements.
+    pub path: String,
+    /// What happened at `path`.
+    pub kind: ChangeKind,
+    /// Value before the change (`None` for [`ChangeKind::Added`]).
+    pub old: Option<Value>,
+    /// Value after the change (`None` for [`ChangeKind::Removed`]).
+    pub new: Option<Value>,
+}
+
+impl Change {
+    fn added(path: String, new: &Value) -> Self {
+        Self {
+            path,
+            kind: ChangeKind::Added,
+            old: None,
+            new: Some(new.clone()),
+        }
+    }
+
+    fn removed(path: String, old: &Value) -> Self {
+        Self {
+            path,
+            kind: ChangeKind::Removed,
+            old: Some(old.clone()),
+            new: None,
+        }
+    }
+
+    fn modified(path: String, old: &Value, new: &Value) -> Self {
+        Self {
+            path,
+            kind: ChangeKind::Modified,
+            old: Some(old.clone()),
+            new: Some(new.clone()),
+        }
+    }
+
+    /// Represent the change as a table with `path`, `kind`, `old` and
+    /// `new` entries (absent sides are omitted).
+    pub fn to_value(&self) -> Value {
+        let mut table = BTreeMap::new();
+        table.insert("path".to_string(), Value::string(&self.path));
+        table.insert("kind".to_string(), Value::string(self.kind.as_str()));
+        if let Some(old) = &self.old {
+            table.insert("old".to_string(), old.clone());
+        }
+        if let Some(new) = &self.new {
+            table.insert("new".to_string(), new.clone());
+        }
+        Value::table(table)
+    }
+}
+
+impl fmt::Display for Change {
+    /// One line: `+ path = new`, `- path = old` or `~ path: old -> new`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = if self.path.is_empty() {
+            "<root>"
+        } else {
+            &self.path
+        };
+        match (&self.old, &self.new) {
+            (Some(old), Some(new)) => {
+                write!(f, "~ {path}: {} -> {}", Literal(old), Literal(new))
+            }
+            (None, Some(new)) => write!(f, "+ {path} = {}", Literal(new)),
+            (Some(old), None) => write!(f, "- {path} = {}", Literal(old)),
+            (None, None) => write!(f, "  {path}"),
+        }
+    }
+}
+
+/// Displays a value with strings quoted, so `"1"` and `1` are
+/// distinguishable in a diff.
+pub(crate) struct Literal<'a>(pub(crate) &'a Value);
+
+impl fmt::Display for Literal<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Value::String(s) => write!(f, "{s:?}"),
+            Value::Array(items) => {
+                f.write_str("[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}", Literal(item))?;
+                }
+                f.write_str("]")
+            }
+            Value::Table(table) => {
+                f.write_str("{")?;
+                for (i, (key, value)) in table.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{} = {}", path::quote_key(key), Literal(value))?;
+                }
+                f.write_str("}")
+            }
+            other => write!(f, "{other}"),
+        }
+    }
+}
+
+/// Options for [`Value::diff_with`].
+#[derive(Debug, Clone, Default)]
+pub struct DiffOptions {
+    strict_numbers: bool,
+    array_keys: Vec<(Query, String)>,
+}
+
+impl DiffOptions {
+    /// Create the default options: index-based arrays, `1 == 1.0`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Treat an integer and a float of equal value as different.
+    pub fn strict_numbers(mut self, strict: bool) -> Self {
+        self.strict_numbers = strict;
+        self
+    }
+
+    /// Compare arrays at paths matching `pattern` (a
+    /// [`query`](crate::query) pattern such as `servers` or
+    /// `services.*.ports`) by the table field `field` instead of by index.
+    /// Changes in such arrays are reported at the elements' old indices;
+    /// added elements are numbered after the old ones.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Parse`](crate::Error::Parse) if the pattern is
+    /// malformed.
+    pub fn array_key(mut self, pattern: &str, field: impl Into<String>) -> Result<Self> {
+        self.array_keys.push((Query::parse(pattern)?, field.into()));
+        Ok(self)
+    }
+
+    fn key_for(&self, path: &str) -> Option<&str> {
+        if self.array_keys.is_empty() {
+            return None;
+        }
+        let segments = path::parse(path).ok()?;
+        self.array_keys
+            .iter()
+            .rev()
+            .find(|(query, _)| query.matches_segments(&segments))
+            .map(|(_, field)| field.as_str())
+    }
+
+    pub(crate) fn equal(&self, a: &Value, b: &Value) -> bool {
+        match (a, b) {
+            (Value::Integer(i), Value::Float(x)) | (Value::Float(x), Value::Integer(i))
+                if !self.strict_numbers =>
+            {
+                // Exact comparison is intended: `1 == 1.0`, `1 != 1.000001`.
+                #[allow(clippy::float_cmp)]
+                let equal = *i as f64 == *x;
+                equal
+            }
+            (Value::Float(x), Value::Float(y)) => {
+                #[allow(clippy::float_cmp)]
+                let equal = x == y || (x.is_nan() && y.is_nan());
+                equal
+            }
+            (Value::Array(x), Value::Array(y)) => {
+                x.len() == y.len() && x.iter().zip(y).all(|(a, b)| self.equal(a, b))
+            }
+            (Value::Table(x), Value::Table(y)) => {
+                x.len() == y.len()
+                    && x.iter()
+                        .zip(y)
+                        .all(|((ka, va), (kb, vb))| ka == kb && self.equal(va, vb))
+            }
+            _ => a == b,
+        }
+    }
+}
+
+impl Value {
+    /// List the differences between `self` (old) and `other` (new) with
+    /// the default [`DiffOptions`], in document order.
+    pub fn diff(&self, other: &Value) -> Vec<Change> {
+        self.diff_with(other, &DiffOptions::default())
+    }
+
+    /// List the differences between `self` (old) and `other` (new), in
+    /// document order (keyed-array removals follow the array's other
+    /// changes).
+    pub fn diff_with(&self, other: &Value, options: &DiffOptions) -> Vec<Change> {
+        let mut changes = Vec::new();
+        diff_into(self, other, "", options, &mut changes);
+        changes
+    }
+}
+
+fn diff_into(old: &Value, new: &Value, at: &str, options: &DiffOptions, out: &mut Vec<Change>) {
+    match (old, new) {
+        (Value::Table(old_table), Value::Table(new_table)) => {
+            let keys: BTreeSet<&String> = old_table.keys().chain(new_table.keys()).collect();
+            for key in keys {
+                let child = path::join_key(at, key);
+                match (old_table.get(key), new_table.get(key)) {
+                    (Some(a), Some(b)) => diff_into(a, b, &child, options, out),
+                    (Some(a), None) => out.push(Change::removed(child, a)),
+                    (None, Some(b)) => out.push(Change::added(child, b)),
+                    (None, None) => {}
+                }
+            }
+        }
+        (Value::Array(old_items), Value::Array(new_items)) => match options.key_for(at) {
+            Some(field) => diff_keyed(old_items, new_items, at, field, options, out),
+            None => {
+                for (index, (a, b)) in old_items.iter().zip(new_items).enumerate() {
+                    diff_into(a, b, &path::join_index(at, index), options, out);
+                }
+                for (index, b) in new_items.iter().enumerate().skip(old_items.len()) {
+                    out.push(Change::added(path::join_index(at, index), b));
+                }
+                for (index, a) in old_items.iter().enumerate().skip(new_items.len()) {
+                    out.push(Change::removed(path::join_index(at, index), a));
+                }
+            }
+        },
+        _ => {
+            if !options.equal(old, new) {
+                out.push(Change::modified(at.to_string(), old, new));
+            }
+        }
+    }
+}
+
+/// Match array elements by `field`; elements without the field (or that
+/// are not tables) are matched by equality. Order is ignored, so paths use
+/// the old indices, with added elements appended after them.
+fn diff_keyed(
+    old_items: &[Value],
+    new_items: &[Value],
+    at: &str,
+    field: &str,
+    options: &DiffOptions,
+    out: &mut Vec<Change>,
+) {
+    let id = |item: &Value| item.as_table().ok().and_then(|t| t.get(field)).cloned();
+    let mut unmatched: Vec<Option<&Value>> = old_items.iter().map(Some).collect();
+    let mut appended = old_items.len();
+
+    for new_item in new_items {
+        let new_id = id(new_item);
+        let found = unmatched.iter().position(|slot| {
+            slot.is_some_and(|old_item| match &new_id {
+                Some(new_id) => id(old_item).is_some_and(|old_id| options.equal(&old_id, new_id)),
+                None => options.equal(old_item, new_item),
+            })
+        });
+        match found.and_then(|index| unmatched[index].take().map(|old| (index, old))) {
+            Some((index, old_item)) => {
+                diff_into(
+                    old_item,
+                    new_item,
+                    &path::join_index(at, index),
+                    options,
+                    out,
+                );
+            }
+            None => {
+                out.push(Change::added(path::join_index(at, appended), new_item));
+                appended += 1;
+            }
+        }
+    }
+
+    for (index, slot) in unmatched.into_iter().enumerate() {
+        if let Some(old_item) = slot {
+            out.push(Change::removed(path::join_index(at, index), old_item));
+        }
+    }
+}
+
+/// Render changes as one human-readable line each (see
+/// [`Change`]'s `Display`).
+pub fn render(changes: &[Change]) -> String {
+    let mut out = String::new();
+    for change in changes {
+        let _ = writeln!(out, "{change}");
+    }
+    out
+}
+
+/// Represent changes as an array of [`Change::to_value`] tables, ready
+/// for any serializer (e.g. [`Config::from`](crate::Config) + `serialize`).
+pub fn to_value(changes: &[Change]) -> Value {
+    Value::array(changes.iter().map(Change::to_value).collect())
+}
+
+/// Render changes as a JSON array of `{"path", "kind", "old", "new"}`
+/// objects.
+///
+/// # Errors
+///
+/// Returns [`Error::FeatureNotEnabled`](crate::Error::FeatureNotEnabled)
+/// when built without the `json` feature.
+pub fn to_json(changes: &[Change]) -> Result<String> {
+    #[cfg(feature = "json")]
+    return crate::parsers::json_parser::serialize(&to_value(changes));
+    #[cfg(not(feature = "json"))]
+    {
+        let _ = changes;
+        Err(crate::error::Error::feature_not_enabled("json"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn value(pairs: &[(&str, Value)]) -> Value {
+        let mut root = Value::table(BTreeMap::new());
+        for (path, v) in pairs {
+            root.set_nested(path, v.clone()).unwrap();
+        }
+        root
+    }
+
+    #[test]
+    fn test_table_changes() {
+        let old = value(&[
+            ("a.x", Value::integer(1)),
+            ("a.y", Value::integer(2)),
+            ("b", Value::string("keep")),
+        ]);
+        let new = value(&[
+            ("a.x", Value::integer(10)),
+            ("b", Value::string("keep")),
+            ("c.d", Value::bool(true)),
+        ]);
+        let changes = old.diff(&new);
+        let summary: Vec<(&str, ChangeKind)> =
+            changes.iter().map(|c| (c.path.as_str(), c.kind)).collect();
+        assert_eq!(
+            summary,
+            [
+                ("a.x", ChangeKind::Modified),
+                ("a.y", ChangeKind::Removed),
+                ("c", ChangeKind::Added),
+            ]
+        );
+        assert_eq!(
+            render(&changes),
+            "~ a.x: 1 -> 10\n- a.y = 2\n+ c = {d = true}\n"
+        );
+    }
+
+    #[test]
+    fn test_numeric_equivalence() {
+        let old = value(&[("n", Value::integer(1))]);
+        let new = value(&[("n", Value::float(1.0))]);
+        assert!(old.diff(&new).is_empty());
+        let strict = DiffOptions::new().strict_numbers(true);
+        assert_eq!(old.diff_with(&new, &strict).len(), 1);
+
+        let nan = value(&[("n", Value::float(f64::NAN))]);
+        assert!(nan.diff_with(&nan.clone(), &strict).is_empty());
+    }
+
+    #[test]
+    fn test_index_arrays() {
+        let old = value(&[("l", Value::array(vec![1.into(), 2.into(), 3.into()]))]);
+        let new = value(&[("l", Value::array(vec![1.into(), 5.into()]))]);
+        let changes = old.diff(&new);
+        assert_eq!(changes[0].path, "l[1]");
+        assert_eq!(changes[0].kind, ChangeKind::Modified);
+        assert_eq!(changes[1].path, "l[2]");
+        assert_eq!(changes[1].kind, ChangeKind::Removed);
+    }
+
+    #[test]
+    fn test_keyed_arrays() {
+        let server = |name: &str, port: i64| {
+            value(&[
+                ("name", Value::string(name)),
+                ("port", Value::integer(port)),
+            ])
+        };
+        let old = value(&[(
+            "servers",
+            Value::array(vec![server("a", 1), server("b", 2), server("c", 3)]),
+        )]);
+        let new = value(&[(
+            "servers",
+            Value::array(vec![server("c", 3), server("a", 10), server("d", 4)]),
+        )]);
+
+        let options = DiffOptions::new().array_key("servers", "name").unwrap();
+        let changes = old.diff_with(&new, &options);
+        assert_eq!(
+            render(&changes),
+            "~ servers[0].port: 1 -> 10\n+ servers[3] = {name = \"d\", port = 4}\n- servers[1] = {name = \"b\", port = 2}\n"
+        );
+
+        // The changes apply to the old tree as a patch; only the order of
+        // the elements differs from the new tree.
+        let mut patched = old.clone();
+        patched
+            .apply_patch(&crate::patch::from_changes(&changes).unwrap())
+            .unwrap();
+        let names: Vec<&str> = (0..3)
+            .map(|i| {
+                patched
+                    .get(&format!("servers[{i}].name"))
+                    .unwrap()
+                    .as_string()
+                    .unwrap()
+            })
+            .collect();
+        assert_eq!(names, ["a", "c", "d"]);
+        assert_eq!(patched.get("servers[0].port"), Some(&Value::integer(10)));
+
+        // Without a key every position differs.
+        assert_eq!(old.diff(&new).len(), 6);
+    }
+
+    #[test]
+    fn test_change_values() {
+        let old = value(&[("a", Value::integer(1))]);
+        let new = value(&[("a", Value::string("1"))]);
+        let changes = old.diff(&new);
+        assert_eq!(changes[0].to_string(), "~ a: 1 -> \"1\"");
+        let rendered = to_value(&changes);
+        assert_eq!(rendered.get("[0].kind"), Some(&Value::string("modified")));
+        assert_eq!(rendered.get("[0].old"), Some(&Value::integer(1)));
+    }
+}
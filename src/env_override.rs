@@ -14,6 +14,14 @@
 //! - Docker-style: `DATABASE__HOST` -> `database.host`
 //! - Kubernetes-style: `DATABASE_HOST` with section mapping
 //! - Custom mapping patterns
+//!
+//! Variables are read from the process environment, or from a fixed set
+//! such as a `.env` file ([`EnvOverrideSystem::with_vars`],
+//! [`EnvOverrideSystem::with_dotenv_file`]) without touching the process
+//! environment.
+//!
+//! [`EnvOverrideSystem::with_vars`]: crate::env_override::EnvOverrideSystem::with_vars
+//! [`EnvOverrideSystem::with_dotenv_file`]: crate::env_override::EnvOverrideSystem::with_dotenv_file
 
 use crate::{error::Error, Result, Value};
 use std::collections::HashMap;
@@ -51,6 +59,8 @@ impl Default for EnvOverrideConfig {
 pub struct EnvOverrideSystem {
     config: EnvOverrideConfig,
     cache: Arc<RwLock<HashMap<String, Option<String>>>>,
+    /// Variables to read instead of the process environment
+    vars: Option<Arc<HashMap<String, String>>>,
 }
 
 impl EnvOverrideSystem {
@@ -59,6 +69,7 @@ impl EnvOverrideSystem {
         Self {
             config,
             cache: Arc::new(RwLock::new(HashMap::new())),
+            vars: None,
         }
     }
 
@@ -76,36 +87,81 @@ impl EnvOverrideSystem {
         Self::new(config)
     }
 
+    /// Read variables from `vars` instead of the process environment.
+    ///
+    /// Only these variables are consulted; the process environment is
+    /// neither read nor modified.
+    pub fn with_vars<K, V>(mut self, vars: impl IntoIterator<Item = (K, V)>) -> Self
+    where
+        K: Into<String>,
+        V: Into<String>,
+    {
+        let vars = vars
+            .into_iter()
+            .map(|(key, value)| (key.into(), value.into()))
+            .collect();
+        self.vars = Some(Arc::new(vars));
+        self
+    }
+
+    /// Read variables from a `.env` file instead of the process
+    /// environment (see [`with_vars`](Self::with_vars)).
+    ///
+    /// `${VAR}` references in the file still expand against the process
+    /// environment, which is only read.
+    pub fn with_dotenv_file<P: AsRef<std::path::Path>>(self, path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let source =
+            std::fs::read_to_string(path).map_err(|e| Error::io(path.display().to_string(), e))?;
+        let vars = crate::parsers::dotenv_parser::parse_vars(&source)?;
+        Ok(self.with_vars(vars))
+    }
+
     /// Apply environment variable overrides to a configuration value
-    pub fn apply_overrides(&self, mut value: Value) -> Result<Value> {
-        self.apply_overrides_recursive(&mut value, String::new())?;
-        Ok(value)
+    pub fn apply_overrides(&self, value: Value) -> Result<Value> {
+        self.apply_overrides_tracked(value).map(|(value, _)| value)
+    }
+
+    /// Apply overrides and report which ones fired.
+    ///
+    /// Returns the overridden value together with `(config path,
+    /// environment variable)` pairs in the order they were applied, which
+    /// is what provenance tracking records.
+    pub fn apply_overrides_tracked(
+        &self,
+        mut value: Value,
+    ) -> Result<(Value, Vec<(String, String)>)> {
+        let mut applied = Vec::new();
+        self.apply_overrides_recursive(&mut value, String::new(), &mut applied)?;
+        Ok((value, applied))
     }
 
     /// Recursively apply overrides to nested configuration
-    fn apply_overrides_recursive(&self, value: &mut Value, path: String) -> Result<()> {
+    fn apply_overrides_recursive(
+        &self,
+        value: &mut Value,
+        path: String,
+        applied: &mut Vec<(String, String)>,
+    ) -> Result<()> {
         match value {
             Value::Table(ref mut table) => {
                 for (key, val) in table.iter_mut() {
-                    let nested_path = if path.is_empty() {
-                        key.clone()
-                    } else {
-                        format!("{path}.{key}")
-                    };
+                    let nested_path = crate::path::join_key(&path, key);
 
                     // Check for environment override
-                    if let Some(env_value) = self.get_env_override(&nested_path)? {
+                    if let Some((env_key, env_value)) = self.get_env_override(&nested_path)? {
                         *val = env_value;
+                        applied.push((nested_path, env_key));
                     } else {
                         // Recurse into nested structures
-                        self.apply_overrides_recursive(val, nested_path)?;
+                        self.apply_overrides_recursive(val, nested_path, applied)?;
                     }
                 }
             }
             Value::Array(ref mut array) => {
                 for (index, val) in array.iter_mut().enumerate() {
-                    let nested_path = format!("{path}[{index}]");
-                    self.apply_overrides_recursive(val, nested_path)?;
+                    let nested_path = crate::path::join_index(&path, index);
+                    self.apply_overrides_recursive(val, nested_path, applied)?;
                 }
             }
             _ => {}
@@ -113,8 +169,9 @@ impl EnvOverrideSystem {
         Ok(())
     }
 
-    /// Get environment variable override for a configuration key
-    fn get_env_override(&self, key: &str) -> Result<Option<Value>> {
+    /// Get environment variable override (variable name and parsed value)
+    /// for a configuration key
+    fn get_env_override(&self, key: &str) -> Result<Option<(String, Value)>> {
         // Try different override patterns
         let env_keys = vec![
             self.generate_env_key(key),
@@ -124,14 +181,14 @@ impl EnvOverrideSystem {
 
         for env_key in env_keys {
             if let Some(env_value) = self.get_cached_env(&env_key)? {
-                return Ok(Some(self.parse_env_value(&env_value)));
+                return Ok(Some((env_key, self.parse_env_value(&env_value))));
             }
         }
 
         // Check custom mappings
         if let Some(custom_key) = self.config.custom_mappings.get(key) {
             if let Some(env_value) = self.get_cached_env(custom_key)? {
-                return Ok(Some(self.parse_env_value(&env_value)));
+                return Ok(Some((custom_key.clone(), self.parse_env_value(&env_value))));
             }
         }
 
@@ -140,30 +197,25 @@ impl EnvOverrideSystem {
 
     /// Generate environment variable key from config key
     fn generate_env_key(&self, key: &str) -> String {
-        let key = if self.config.lowercase_keys {
-            key.to_uppercase()
-        } else {
-            key.to_string()
-        };
-
-        let env_key = key.replace('.', &self.config.separator);
+        let env_key = env_suffix(key, &self.config.separator, self.config.lowercase_keys);
         format!("{}{}", self.config.prefix, env_key)
     }
 
     /// Generate Docker-style environment key (double underscore)
     fn generate_docker_style_key(&self, key: &str) -> String {
-        let key = key.to_uppercase().replace('.', "__");
-        format!("{}{}", self.config.prefix, key)
+        format!("{}{}", self.config.prefix, env_suffix(key, "__", true))
     }
 
     /// Generate Kubernetes-style environment key (single underscore, no dots)
     fn generate_k8s_style_key(&self, key: &str) -> String {
-        let key = key.to_uppercase().replace('.', "_");
-        format!("{}{}", self.config.prefix, key)
+        format!("{}{}", self.config.prefix, env_suffix(key, "_", true))
     }
 
     /// Get environment variable with caching
     fn get_cached_env(&self, key: &str) -> Result<Option<String>> {
+        if let Some(vars) = &self.vars {
+            return Ok(vars.get(key).cloned());
+        }
         if !self.config.enable_cache {
             return Ok(env::var(key).ok());
         }
@@ -304,6 +356,31 @@ impl EnvOverrideSystem {
     }
 }
 
+/// Turn a config path into the variable-name part of an environment key.
+///
+/// Path segments (keys and `[n]` indices, see [`crate::path`]) are joined
+/// with `separator`, so `servers[0].host` becomes `SERVERS_0_HOST`.
+/// Dots inside a quoted key, such as INI's flat `"server.host"`, are
+/// replaced with `separator` too.
+fn env_suffix(key: &str, separator: &str, uppercase: bool) -> String {
+    let parts: Vec<String> = match crate::path::parse(key) {
+        Ok(segments) => segments
+            .iter()
+            .map(|segment| match segment {
+                crate::path::PathSegment::Key(k) => k.replace('.', separator),
+                crate::path::PathSegment::Index(i) => i.to_string(),
+            })
+            .collect(),
+        Err(_) => key.split('.').map(str::to_string).collect(),
+    };
+    let joined = parts.join(separator);
+    if uppercase {
+        joined.to_uppercase()
+    } else {
+        joined
+    }
+}
+
 /// Apply environment variable overrides to configuration
 pub fn apply_env_overrides(value: Value, config: EnvOverrideConfig) -> Result<Value> {
     let system = EnvOverrideSystem::new(config);
@@ -345,6 +422,34 @@ mod tests {
             system.generate_k8s_style_key("database.host"),
             "APP_DATABASE_HOST"
         );
+        assert_eq!(
+            system.generate_env_key("servers[0].host"),
+            "APP_SERVERS_0_HOST"
+        );
+        assert_eq!(
+            system.generate_docker_style_key("servers[1].host"),
+            "APP_SERVERS__1__HOST"
+        );
+    }
+
+    #[test]
+    fn test_docker_style_overrides_flat_ini_key() {
+        let value = crate::parsers::ini_parser::parse("[server]\nhost = localhost\n").unwrap();
+        let system =
+            EnvOverrideSystem::with_prefix("APP_").with_vars([("APP_SERVER__HOST", "example.com")]);
+
+        let (value, applied) = system.apply_overrides_tracked(value).unwrap();
+        assert_eq!(
+            value.get("server.host").unwrap(),
+            &Value::string("example.com")
+        );
+        assert_eq!(
+            applied,
+            vec![(
+                "\"server.host\"".to_string(),
+                "APP_SERVER__HOST".to_string()
+            )]
+        );
     }
 
     #[test]
@@ -373,6 +478,33 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_vars_replace_process_environment() {
+        let system = EnvOverrideSystem::with_prefix("DOTENV_TEST_")
+            .with_vars([("DOTENV_TEST_DATABASE_PORT", "6543")]);
+
+        let mut database = std::collections::BTreeMap::new();
+        database.insert("port".to_string(), Value::integer(5432));
+        database.insert("host".to_string(), Value::string("localhost"));
+        let mut root = std::collections::BTreeMap::new();
+        root.insert("database".to_string(), Value::table(database));
+
+        let (value, applied) = system.apply_overrides_tracked(Value::table(root)).unwrap();
+        assert_eq!(value.get("database.port").unwrap(), &Value::integer(6543));
+        assert_eq!(
+            value.get("database.host").unwrap(),
+            &Value::string("localhost")
+        );
+        assert_eq!(
+            applied,
+            vec![(
+                "database.port".to_string(),
+                "DOTENV_TEST_DATABASE_PORT".to_string()
+            )]
+        );
+        assert!(env::var("DOTENV_TEST_DATABASE_PORT").is_err());
+    }
+
     #[test]
     fn test_cache_operations() {
         let system = EnvOverrideSystem::with_defaults();
@@ -86,6 +86,15 @@ pub enum Error {
         expected: Option<String>,
     },
 
+    /// Deserialization errors - when a `Value` cannot be mapped onto a Rust type
+    #[error("Deserialize error{}: {message}", display_path(path))]
+    Deserialize {
+        /// Dotted path of the failing field (empty for the root value)
+        path: String,
+        /// Description of the mismatch
+        message: String,
+    },
+
     /// General validation errors
     #[error("Validation error: {message}")]
     Validation {
@@ -248,6 +257,31 @@ impl Error {
         }
     }
 
+    /// Create a deserialization error at the given path
+    pub fn deserialize(path: impl Into<String>, message: impl Into<String>) -> Self {
+        Self::Deserialize {
+            path: path.into(),
+            message: message.into(),
+        }
+    }
+
+    /// Attach a path to a deserialization error that does not carry one yet.
+    ///
+    /// Errors raised deep in a value tree keep their (more specific)
+    /// original path; every other variant is returned unchanged.
+    pub fn with_path(self, path: &str) -> Self {
+        match self {
+            Self::Deserialize {
+                path: existing,
+                message,
+            } if existing.is_empty() => Self::Deserialize {
+                path: path.to_string(),
+                message,
+            },
+            other => other,
+        }
+    }
+
     /// Create a validation error
     pub fn validation(message: impl Into<String>) -> Self {
         Self::Validation {
@@ -288,3 +322,18 @@ impl From<io::Error> for Error {
         }
     }
 }
+
+/// Render the ` at '<path>'` suffix used by [`Error::Deserialize`].
+fn display_path(path: &str) -> String {
+    if path.is_empty() {
+        String::new()
+    } else {
+        format!(" at '{path}'")
+    }
+}
+
+impl serde::de::Error for Error {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        Self::deserialize("", msg.to_string())
+    }
+}
@@ -0,0 +1,580 @@
+//! # Canonical Formatting
+//!
+//! Rewrite a configuration file in one canonical style, the way `rustfmt`
+//! or `terraform fmt` do for code:
+//!
+//! - CONF, INI and Java Properties: one `key = value` per line with the
+//!   `=` signs of neighbouring entries aligned, values in their plainest
+//!   spelling (quoted only when needed), one comment marker per format
+//!   (`#`, or `;` for INI), single blank lines, and a blank line before
+//!   every section
+//! - JSON, JSONC and JSON5: two-space indentation, double-quoted keys and
+//!   strings, no trailing commas, and every comment as a `//` line
+//! - HCL and XML: the layout of their serializers (`terraform fmt` style
+//!   and two-space indented elements), with entries in the file's order
+//!
+//! Comments are kept, except in HCL and XML, whose documents cannot hold
+//! them: files in those formats are only formatted when they have no
+//! comments. The formatter refuses (with an [`Error::General`]) rather
+//! than change what the file means: the output is parsed again and must
+//! produce the same [`Value`] as the input, and commented HCL and XML
+//! files are rejected.
+//!
+//! ```rust
+//! use config_lib::formatter::{self, FormatOptions};
+//!
+//! let source = "; Listener\nport: 8080\nhost_name='localhost'\n[db]\nurl = x\n";
+//! let formatted = formatter::format(source, "ini", &FormatOptions::new())?;
+//! assert_eq!(
+//!     formatted,
+//!     "; Listener\nport      = 8080\nhost_name = localhost\n\n[db]\nurl = x\n"
+//! );
+//! assert!(formatter::is_formatted(&formatted, "ini", &FormatOptions::new())?);
+//! # Ok::<(), config_lib::Error>(())
+//! ```
+
+use crate::error::{Error, Result};
+use crate::parsers;
+
+use std::fmt::Write as _;
+
+/// Formats [`format()`] can rewrite. HCL and XML files are only supported
+/// without comments.
+pub const FORMATS: &[&str] = &[
+    "conf",
+    "ini",
+    "properties",
+    "json",
+    "jsonc",
+    "json5",
+    "hcl",
+    "xml",
+];
+
+/// Options for [`format()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct FormatOptions {
+    /// Sort keys, and sections, by name instead of keeping the file's
+    /// order. Comments directly above an entry move with it. HCL object
+    /// values are always written in key order, and sorted HCL bodies put
+    /// attributes before blocks.
+    pub sort_keys: bool,
+    /// Pad keys so the `=` signs of consecutive CONF, INI, Properties and
+    /// HCL entries line up. A blank line or section header starts a new
+    /// group. XML has no `=` signs to align.
+    pub align: bool,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self {
+            sort_keys: false,
+            align: true,
+        }
+    }
+}
+
+impl FormatOptions {
+    /// Default options: original order, aligned `=` signs.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Toggle key sorting. See [`FormatOptions::sort_keys`].
+    pub fn sort_keys(mut self, sort_keys: bool) -> Self {
+        self.sort_keys = sort_keys;
+        self
+    }
+
+    /// Toggle alignment. See [`FormatOptions::align`].
+    pub fn align(mut self, align: bool) -> Self {
+        self.align = align;
+        self
+    }
+}
+
+/// Rewrite `source`, written in `format`, in canonical style.
+///
+/// Line endings follow the source (`\r\n` if it uses them) and the output
+/// ends with a newline.
+///
+/// # Errors
+///
+/// Returns a parse error if `source` is not valid `format`,
+/// [`Error::FeatureNotEnabled`] if the format's feature is off, and
+/// [`Error::General`] if the format is not one of [`FORMATS`], the file
+/// has comments the format's document cannot keep (HCL, XML), or the
+/// formatted text would not parse back to the same values.
+pub fn format(source: &str, format: &str, options: &FormatOptions) -> Result<String> {
+    let formatted = match format {
+        "conf" => render_lines(parsers::conf::parse_document(source)?.lines()?, options),
+        "ini" => render_lines(
+            parsers::ini_parser::parse_document(source)?.lines()?,
+            options,
+        ),
+        "properties" => render_lines(parsers::properties_parser::lines(source)?, options),
+        #[cfg(feature = "json")]
+        "json" | "jsonc" | "json5" => {
+            use parsers::json5_parser::{self, Dialect};
+            let dialect = Dialect::from_name(format).unwrap_or(Dialect::Json5);
+            json5_parser::parse_document(source, dialect)?.formatted(options.sort_keys)?
+        }
+        #[cfg(feature = "hcl")]
+        "hcl" => parsers::hcl_parser::parse_document(source)?
+            .formatted(options.sort_keys, options.align)?,
+        #[cfg(feature = "xml")]
+        "xml" => parsers::xml_parser::parse_document(source)?.formatted(options.sort_keys)?,
+        other => {
+            return Err(match other {
+                "json" | "jsonc" | "json5" => Error::feature_not_enabled("json"),
+                "hcl" | "xml" => Error::feature_not_enabled(other),
+                _ => Error::serialize(format!(
+                    "Cannot format {other} files; supported formats are {}",
+                    FORMATS.join(", ")
+                )),
+            })
+        }
+    };
+    let formatted = if source.contains("\r\n") {
+        formatted.replace('\n', "\r\n")
+    } else {
+        formatted
+    };
+
+    let before = parsers::parse_string(source, Some(format))?;
+    let after = parsers::parse_string(&formatted, Some(format))
+        .map_err(|e| Error::serialize(format!("Formatting would produce invalid {format}: {e}")))?;
+    let exact = crate::diff::DiffOptions::new().strict_numbers(true);
+    if let Some(change) = before.diff_with(&after, &exact).into_iter().next() {
+        let path = if change.path.is_empty() {
+            "<root>"
+        } else {
+            &change.path
+        };
+        return Err(Error::serialize(format!(
+            "Formatting would change the value at {path}; the file was left as it is"
+        )));
+    }
+    Ok(formatted)
+}
+
+/// Whether `source` is already in canonical style, i.e. [`format()`] would
+/// return it unchanged.
+///
+/// # Errors
+///
+/// Returns the errors of [`format()`].
+pub fn is_formatted(source: &str, format: &str, options: &FormatOptions) -> Result<bool> {
+    Ok(self::format(source, format, options)? == source)
+}
+
+/// One line of a CONF, INI or Properties file, as the formatter sees it.
+#[derive(Debug, Clone)]
+pub(crate) enum Line {
+    Blank,
+    /// A full-line comment, marker already normalized.
+    Comment(String),
+    /// A `[name]` header with its inline comment.
+    Section {
+        name: String,
+        comment: Option<String>,
+    },
+    /// An entry, key and value in the format's canonical spelling.
+    Entry {
+        key: String,
+        value: String,
+        comment: Option<String>,
+    },
+}
+
+/// Comment syntax of a line-oriented format.
+pub(crate) struct Comments {
+    /// Characters that start a comment.
+    pub(crate) markers: &'static [char],
+    /// The marker written by the formatter.
+    pub(crate) canonical: char,
+}
+
+impl Comments {
+    /// Push the blank lines and comments in `trivia`, the text before an
+    /// item. The indentation of the item's own line is dropped.
+    pub(crate) fn push_lines(&self, trivia: &str, lines: &mut Vec<Line>) {
+        for line in trivia.split_inclusive('\n') {
+            let text = line.trim();
+            if !text.is_empty() {
+                lines.push(Line::Comment(self.normalize(text)));
+            } else if line.ends_with('\n') {
+                lines.push(Line::Blank);
+            }
+        }
+    }
+
+    /// The comment in `trailing`, the text after an item on its line.
+    pub(crate) fn inline(&self, trailing: &str) -> Option<String> {
+        let text = trailing.trim();
+        (!text.is_empty()).then(|| self.normalize(text))
+    }
+
+    /// Rewrite the leading run of markers with the canonical one, so
+    /// `### Title` and `;;; Title` keep their shape.
+    fn normalize(&self, text: &str) -> String {
+        let body = text.trim_start_matches(self.markers);
+        let markers = text[..text.len() - body.len()].chars().count();
+        let mut out: String = std::iter::repeat(self.canonical).take(markers).collect();
+        out.push_str(body);
+        out
+    }
+}
+
+/// An entry or section header with the comments and blank lines above it.
+struct Group {
+    leading: Vec<Line>,
+    line: Line,
+}
+
+impl Group {
+    fn name(&self) -> &str {
+        match &self.line {
+            Line::Section { name, .. } => name,
+            Line::Entry { key, .. } => key,
+            Line::Blank | Line::Comment(_) => "",
+        }
+    }
+}
+
+/// The root (before the first header) or a section.
+#[derive(Default)]
+struct Scope {
+    header: Option<Group>,
+    /// Comments at the top of the scope that describe it rather than its
+    /// first entry; kept in place when sorting.
+    intro: Vec<Line>,
+    entries: Vec<Group>,
+    /// For the root: comments before the first header that stay there
+    /// when sections are sorted.
+    outro: Vec<Line>,
+}
+
+/// A line of output before alignment.
+enum Row {
+    Blank,
+    Header(String),
+    Text(String),
+    Entry {
+        key: String,
+        value: String,
+        comment: Option<String>,
+    },
+}
+
+/// Write `lines` in canonical layout.
+pub(crate) fn render_lines(lines: Vec<Line>, options: &FormatOptions) -> String {
+    let mut scopes = vec![Scope::default()];
+    let mut pending = Vec::new();
+    for line in lines {
+        match line {
+            Line::Blank | Line::Comment(_) => pending.push(line),
+            Line::Section { .. } => scopes.push(Scope {
+                header: Some(Group {
+                    leading: std::mem::take(&mut pending),
+                    line,
+                }),
+                ..Scope::default()
+            }),
+            Line::Entry { .. } => {
+                if let Some(scope) = scopes.last_mut() {
+                    scope.entries.push(Group {
+                        leading: std::mem::take(&mut pending),
+                        line,
+                    });
+                }
+            }
+        }
+    }
+    let trailer = pending;
+
+    if options.sort_keys {
+        for scope in &mut scopes {
+            if let Some(first) = scope.entries.first_mut() {
+                scope.intro = split_detached(&mut first.leading);
+            }
+            scope.entries.sort_by(|a, b| a.name().cmp(b.name()));
+        }
+        if let Some(first) = scopes.get_mut(1).and_then(|scope| scope.header.as_mut()) {
+            let outro = split_detached(&mut first.leading);
+            scopes[0].outro = outro;
+        }
+        scopes[1..].sort_by(|a, b| {
+            let name = |scope: &Scope| {
+                scope
+                    .header
+                    .as_ref()
+                    .map(|header| header.name().to_string())
+            };
+            name(a).cmp(&name(b))
+        });
+    }
+
+    let mut rows = Rows::default();
+    for scope in scopes {
+        if let Some(header) = scope.header {
+            rows.section_break = true;
+            rows.extend(header.leading);
+            if let Line::Section { name, comment } = header.line {
+                rows.push(Row::Header(with_comment(format!("[{name}]"), comment)));
+            }
+            rows.after_header = true;
+        }
+        rows.extend(scope.intro);
+        for entry in scope.entries {
+            rows.extend(entry.leading);
+            if let Line::Entry {
+                key,
+                value,
+                comment,
+            } = entry.line
+            {
+                rows.push(Row::Entry {
+                    key,
+                    value,
+                    comment,
+                });
+            }
+        }
+        rows.extend(scope.outro);
+    }
+    rows.extend(trailer);
+    rows.render(options.align)
+}
+
+/// Split off the lines up to and including the last blank line.
+fn split_detached(leading: &mut Vec<Line>) -> Vec<Line> {
+    match leading.iter().rposition(|line| matches!(line, Line::Blank)) {
+        Some(blank) => leading.drain(..=blank).collect(),
+        None => Vec::new(),
+    }
+}
+
+fn with_comment(text: String, comment: Option<String>) -> String {
+    match comment {
+        Some(comment) => format!("{text} {comment}"),
+        None => text,
+    }
+}
+
+/// Output rows with blank lines normalized: never at the start or end,
+/// never doubled, never directly under a header, always before a section.
+#[derive(Default)]
+struct Rows {
+    emitted: Vec<Row>,
+    blank: bool,
+    section_break: bool,
+    after_header: bool,
+}
+
+impl Rows {
+    fn extend(&mut self, lines: Vec<Line>) {
+        for line in lines {
+            match line {
+                Line::Blank => self.blank = true,
+                Line::Comment(text) => self.push(Row::Text(text)),
+                Line::Section { .. } | Line::Entry { .. } => {}
+            }
+        }
+    }
+
+    fn push(&mut self, row: Row) {
+        let wanted = self.section_break || self.blank && !self.after_header;
+        if wanted && !self.emitted.is_empty() {
+            self.emitted.push(Row::Blank);
+        }
+        self.blank = false;
+        self.section_break = false;
+        self.after_header = false;
+        self.emitted.push(row);
+    }
+
+    fn render(self, align: bool) -> String {
+        let mut out = String::new();
+        let mut block_start = 0;
+        for (index, row) in self.emitted.iter().enumerate() {
+            match row {
+                Row::Blank => {
+                    out.push('\n');
+                    block_start = index + 1;
+                }
+                Row::Header(text) => {
+                    out.push_str(text);
+                    out.push('\n');
+                    block_start = index + 1;
+                }
+                Row::Text(text) => {
+                    out.push_str(text);
+                    out.push('\n');
+                }
+                Row::Entry {
+                    key,
+                    value,
+                    comment,
+                } => {
+                    let width = if align {
+                        self.block_width(block_start)
+                    } else {
+                        0
+                    };
+                    let pad = width.saturating_sub(key.chars().count());
+                    let line = format!("{key}{:pad$} = {value}", "");
+                    let line = with_comment(line.trim_end().to_string(), comment.clone());
+                    let _ = writeln!(out, "{line}");
+                }
+            }
+        }
+        out
+    }
+
+    /// Widest key in the group of entries starting at `start`.
+    fn block_width(&self, start: usize) -> usize {
+        self.emitted[start..]
+            .iter()
+            .take_while(|row| match row {
+                Row::Blank | Row::Header(_) => false,
+                Row::Text(_) | Row::Entry { .. } => true,
+            })
+            .filter_map(|row| match row {
+                Row::Entry { key, .. } => Some(key.chars().count()),
+                _ => None,
+            })
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_conf() -> Result<()> {
+        let source = "\n\n#Server\nport=8080   #  listener\nhost_name = \"localhost\"\n\n\n\nlist = [\"a\", \"b\"]\n[db]\n\nurl='postgres://db'\n# end";
+        let formatted = format(source, "conf", &FormatOptions::new())?;
+        assert_eq!(
+            formatted,
+            "#Server\nport      = 8080 #  listener\nhost_name = localhost\n\nlist = a b\n\n[db]\nurl = postgres://db\n# end\n"
+        );
+        assert!(is_formatted(&formatted, "conf", &FormatOptions::new())?);
+
+        let unaligned = format(source, "conf", &FormatOptions::new().align(false))?;
+        assert!(unaligned.contains("port = 8080 #  listener\nhost_name = localhost\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_sort_keeps_comments_with_entries() -> Result<()> {
+        let source = "; Settings file\n\n; the zone\nzone = b\nalpha = 1\n\n[z]\nk = 1\n\n[a]\n; first key\nk = 2\n";
+        let formatted = format(source, "ini", &FormatOptions::new().sort_keys(true))?;
+        assert_eq!(
+            formatted,
+            "; Settings file\n\nalpha = 1\n; the zone\nzone  = b\n\n[a]\n; first key\nk = 2\n\n[z]\nk = 1\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_properties_comment_markers() -> Result<()> {
+        let source = "! Generated\napp.name:demo\napp.greeting = hello \\\n    world\n#done\n";
+        let formatted = format(source, "properties", &FormatOptions::new())?;
+        assert_eq!(
+            formatted,
+            "# Generated\napp.name     = demo\napp.greeting = hello world\n#done\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_unsupported_format() {
+        assert!(matches!(
+            format("a = 1", "dotenv", &FormatOptions::new()),
+            Err(Error::General { .. })
+        ));
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_format_jsonc() -> Result<()> {
+        let source = "// App settings\n{\n    /* listener */ \"port\": 8080, // default\n    'name': 'app',\n    \"tags\": [1,2,],\n    \"empty\": {},\n    // trailing note\n}\n";
+        let formatted = format(source, "json5", &FormatOptions::new())?;
+        assert_eq!(
+            formatted,
+            "// App settings\n{\n  // listener\n  \"port\": 8080, // default\n  \"name\": \"app\",\n  \"tags\": [\n    1,\n    2\n  ],\n  \"empty\": {}\n  // trailing note\n}\n"
+        );
+
+        let sorted = format(source, "json5", &FormatOptions::new().sort_keys(true))?;
+        assert!(sorted.starts_with("// App settings\n{\n  \"empty\": {},\n  \"name\": \"app\",\n"));
+        assert!(is_formatted(&formatted, "json5", &FormatOptions::new())?);
+
+        let special = format(
+            "{limit: NaN, max: Infinity}",
+            "json5",
+            &FormatOptions::new(),
+        )?;
+        assert_eq!(special, "{\n  \"limit\": NaN,\n  \"max\": Infinity\n}\n");
+        Ok(())
+    }
+
+    #[cfg(feature = "hcl")]
+    #[test]
+    fn test_hcl_honours_options() -> Result<()> {
+        let source = "zone = \"eu\"\nservice \"web\" {\nport=80\n}\nid = 1\n";
+        assert_eq!(
+            format(source, "hcl", &FormatOptions::new())?,
+            "zone = \"eu\"\n\nservice \"web\" {\n  port = 80\n}\n\nid = 1\n"
+        );
+        assert_eq!(
+            format(source, "hcl", &FormatOptions::new().sort_keys(true))?,
+            "id   = 1\nzone = \"eu\"\n\nservice \"web\" {\n  port = 80\n}\n"
+        );
+        assert_eq!(
+            format(
+                source,
+                "hcl",
+                &FormatOptions::new().sort_keys(true).align(false)
+            )?,
+            "id = 1\nzone = \"eu\"\n\nservice \"web\" {\n  port = 80\n}\n"
+        );
+        Ok(())
+    }
+
+    #[cfg(feature = "xml")]
+    #[test]
+    fn test_xml_keeps_order_unless_sorted() -> Result<()> {
+        let source = "<config><zone>eu</zone><id>1</id></config>";
+        assert_eq!(
+            format(source, "xml", &FormatOptions::new())?,
+            "<config>\n  <zone>eu</zone>\n  <id>1</id>\n</config>\n"
+        );
+        assert_eq!(
+            format(source, "xml", &FormatOptions::new().sort_keys(true))?,
+            "<config>\n  <id>1</id>\n  <zone>eu</zone>\n</config>\n"
+        );
+        assert!(format(
+            "<config><!-- id --><id>1</id></config>",
+            "xml",
+            &FormatOptions::new()
+        )
+        .is_err());
+        Ok(())
+    }
+
+    #[cfg(feature = "hcl")]
+    #[test]
+    fn test_hcl_comments_are_refused() -> Result<()> {
+        let formatted = format("region=\"us\"\n", "hcl", &FormatOptions::new())?;
+        assert_eq!(formatted, "region = \"us\"\n");
+        assert!(matches!(
+            format("# note\nregion = \"us\"\n", "hcl", &FormatOptions::new()),
+            Err(Error::General { .. })
+        ));
+        Ok(())
+    }
+}
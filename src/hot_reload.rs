@@ -49,14 +49,20 @@ use std::time::{Duration, SystemTime};
 /// **Stability:** `ConfigChangeEvent` is `#[non_exhaustive]` so the
 /// v1.x SemVer contract can add new variants (e.g. `Renamed`,
 /// `PermissionDenied`) in MINOR releases without breaking user code.
-/// Callers must use a wildcard arm when pattern-matching.
+/// Callers must use a wildcard arm when pattern-matching. `Reloaded`
+/// is itself `#[non_exhaustive]` so it can carry more detail later:
+/// match it with `Reloaded { path, .. }`.
 #[derive(Debug, Clone)]
 #[non_exhaustive]
 pub enum ConfigChangeEvent {
     /// Configuration successfully reloaded
+    #[non_exhaustive]
     Reloaded {
         /// Path to the configuration file that was reloaded
         path: PathBuf,
+        /// What changed between the previous and the reloaded
+        /// configuration (see [`crate::diff`])
+        changes: Vec<crate::diff::Change>,
         /// Timestamp when the reload completed
         timestamp: SystemTime,
     },
@@ -487,16 +493,21 @@ impl HotReloadConfig {
 
         match Config::from_file(&self.file_path) {
             Ok(new_config) => {
-                {
-                    let mut config = self.current.write().map_err(|_| {
-                        Error::concurrency("Failed to acquire write lock".to_string())
-                    })?;
-                    *config = new_config;
-                }
+                // Diff under the read lock so readers are only blocked
+                // for the swap itself
+                let changes = self
+                    .current
+                    .read()
+                    .map_err(|_| Error::concurrency("Failed to acquire read lock".to_string()))?
+                    .diff(&new_config);
+                *self.current.write().map_err(|_| {
+                    Error::concurrency("Failed to acquire write lock".to_string())
+                })? = new_config;
                 self.last_modified = modified;
 
                 self.handlers.dispatch(&ConfigChangeEvent::Reloaded {
                     path: self.file_path.clone(),
+                    changes,
                     timestamp: SystemTime::now(),
                 });
                 Ok(true)
@@ -571,11 +582,16 @@ impl HotReloadConfig {
 
                             match Config::from_file(&file_path) {
                                 Ok(new_config) => {
-                                    if let Ok(mut config) = current.write() {
+                                    let changes =
+                                        current.read().map(|config| config.diff(&new_config));
+                                    if let (Ok(changes), Ok(mut config)) =
+                                        (changes, current.write())
+                                    {
                                         *config = new_config;
                                         last_modified = modified;
                                         handlers.dispatch(&ConfigChangeEvent::Reloaded {
                                             path: file_path.clone(),
+                                            changes,
                                             timestamp: SystemTime::now(),
                                         });
                                     }
@@ -727,13 +743,18 @@ impl HotReloadConfig {
 
                         match Config::from_file(&target_file) {
                             Ok(new_config) => {
-                                if let Ok(mut cfg) = current_for_worker.write() {
+                                let changes =
+                                    current_for_worker.read().map(|cfg| cfg.diff(&new_config));
+                                if let (Ok(changes), Ok(mut cfg)) =
+                                    (changes, current_for_worker.write())
+                                {
                                     *cfg = new_config;
                                     if let Some(m) = modified {
                                         last_modified_seen = m;
                                     }
                                     handlers_for_worker.dispatch(&ConfigChangeEvent::Reloaded {
                                         path: target_file.clone(),
+                                        changes,
                                         timestamp: SystemTime::now(),
                                     });
                                 }
@@ -943,7 +964,11 @@ mod tests {
 
         let event = receiver.try_recv().unwrap();
         match event {
-            ConfigChangeEvent::Reloaded { path, .. } => assert_eq!(path, config_path),
+            ConfigChangeEvent::Reloaded { path, changes, .. } => {
+                assert_eq!(path, config_path);
+                assert_eq!(changes.len(), 1);
+                assert_eq!(changes[0].to_string(), "~ key: \"value1\" -> \"value2\"");
+            }
             _ => panic!("Expected Reloaded event"),
         }
     }
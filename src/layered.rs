@@ -0,0 +1,681 @@
+//! # Layered Configuration
+//!
+//! Build one [`Config`] from an ordered stack of sources — built-in
+//! defaults, system and user files, environment variables, command-line
+//! flags — while remembering which source set every value.
+//!
+//! Later layers win: tables are deep-merged, everything else is replaced
+//! (the same rules as [`Config::merge`], configurable through
+//! [`LayeredBuilder::merge_options`]). The resulting configuration
+//! answers [`Config::origin`] with the layer name, file and line that
+//! produced a value.
+//!
+//! ```rust,no_run
+//! use config_lib::{Config, Value};
+//!
+//! # fn main() -> Result<(), config_lib::Error> {
+//! let mut defaults = Value::table(Default::default());
+//! defaults.set_nested("database.port", Value::integer(5432))?;
+//!
+//! let config = Config::layered()
+//!     .with_defaults(defaults)
+//!     .with_optional_file("/etc/app.conf")
+//!     .with_optional_file("/home/me/.config/app.json")
+//!     // e.g. `app --database.port=6543 --verbose run`
+//!     .with_args(std::env::args().skip(1))
+//!     .build()?;
+//!
+//! if let Some(origin) = config.origin("database.port") {
+//!     println!("database.port set by {origin}");
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::config::Config;
+use crate::error::{Error, Result};
+use crate::merge::{merge_tracked, MergeOptions};
+use crate::parsers;
+use crate::path::{self, PathSegment};
+use crate::value::Value;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::Bound;
+use std::path::{Path, PathBuf};
+
+/// Where a configuration value came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Origin {
+    /// Layer name: `"defaults"`, `"file"`, `"env"`, `"args"`, `"runtime"`,
+    /// `"patch"` or the name given to [`LayeredBuilder::with_value`] /
+    /// [`LayeredBuilder::with_string`].
+    pub source: String,
+    /// Source-side key when it differs from the config path: the
+    /// environment variable or command-line flag that set the value.
+    pub source_key: Option<String>,
+    /// File the value was read from.
+    pub file: Option<PathBuf>,
+    /// 1-indexed line of the definition, when the format supports it.
+    pub line: Option<usize>,
+}
+
+impl Origin {
+    /// Create an origin with only a source name.
+    pub fn new(source: impl Into<String>) -> Self {
+        Self {
+            source: source.into(),
+            source_key: None,
+            file: None,
+            line: None,
+        }
+    }
+
+    fn with_source_key(mut self, key: impl Into<String>) -> Self {
+        self.source_key = Some(key.into());
+        self
+    }
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)?;
+        if let Some(key) = &self.source_key {
+            write!(f, " {key}")?;
+        }
+        if let Some(file) = &self.file {
+            write!(f, " {}", file.display())?;
+            if let Some(line) = self.line {
+                write!(f, ":{line}")?;
+            }
+        } else if let Some(line) = self.line {
+            write!(f, " line {line}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Per-path origins, keyed by canonical path.
+pub(crate) type Origins = BTreeMap<String, Origin>;
+
+enum Layer {
+    Value {
+        name: String,
+        value: Value,
+    },
+    Source {
+        name: String,
+        source: String,
+        format: Option<String>,
+    },
+    File {
+        path: PathBuf,
+        required: bool,
+    },
+    #[cfg(feature = "env-override")]
+    Env(crate::env_override::EnvOverrideSystem),
+    Args(Vec<String>),
+}
+
+/// Builder for a [`Config`] assembled from ordered layers.
+///
+/// Layers are loaded in [`build`](LayeredBuilder::build), in the order
+/// they were added; each one overrides the ones before it.
+#[derive(Default)]
+pub struct LayeredBuilder {
+    layers: Vec<Layer>,
+    format: Option<String>,
+    merge_options: MergeOptions,
+}
+
+impl LayeredBuilder {
+    /// Create an empty builder.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add built-in defaults (origin source `"defaults"`).
+    pub fn with_defaults(self, defaults: impl Into<Value>) -> Self {
+        self.with_value("defaults", defaults)
+    }
+
+    /// Add an in-memory value tree under the given source name.
+    pub fn with_value(mut self, name: impl Into<String>, value: impl Into<Value>) -> Self {
+        self.layers.push(Layer::Value {
+            name: name.into(),
+            value: value.into(),
+        });
+        self
+    }
+
+    /// Add configuration text under the given source name. The format is
+    /// detected when `format` is `None`.
+    pub fn with_string(
+        mut self,
+        name: impl Into<String>,
+        source: impl Into<String>,
+        format: Option<&str>,
+    ) -> Self {
+        self.layers.push(Layer::Source {
+            name: name.into(),
+            source: source.into(),
+            format: format.map(str::to_string),
+        });
+        self
+    }
+
+    /// Add a file that must exist.
+    pub fn with_file(mut self, path: impl AsRef<Path>) -> Self {
+        self.layers.push(Layer::File {
+            path: path.as_ref().to_path_buf(),
+            required: true,
+        });
+        self
+    }
+
+    /// Add a file that is skipped when it does not exist.
+    pub fn with_optional_file(mut self, path: impl AsRef<Path>) -> Self {
+        self.layers.push(Layer::File {
+            path: path.as_ref().to_path_buf(),
+            required: false,
+        });
+        self
+    }
+
+    /// Apply environment variable overrides to the keys merged so far
+    /// (origin source `"env"`, with the variable name as `source_key`).
+    #[cfg(feature = "env-override")]
+    pub fn with_env(mut self, overrides: crate::env_override::EnvOverrideSystem) -> Self {
+        self.layers.push(Layer::Env(overrides));
+        self
+    }
+
+    /// Apply command-line flags (origin source `"args"`).
+    ///
+    /// Accepts `--path=value` and bare `--path` (which sets `true`), where
+    /// `path` uses the [`crate::path`] grammar. `--path value` is accepted
+    /// only for paths that an earlier layer already set to a non-boolean,
+    /// so `--verbose run` stays a switch followed by a positional argument.
+    /// Values are typed as booleans, integers or floats when they parse as
+    /// such. Positional arguments are ignored and `--` ends flag parsing.
+    pub fn with_args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.layers
+            .push(Layer::Args(args.into_iter().map(Into::into).collect()));
+        self
+    }
+
+    /// Merge strategies applied when each layer is merged over the ones
+    /// before it (see [`crate::merge`]). Defaults to [`MergeOptions::default`].
+    pub fn merge_options(mut self, options: MergeOptions) -> Self {
+        self.merge_options = options;
+        self
+    }
+
+    /// Format used when the built configuration is serialized. Defaults to
+    /// the format of the last file or string layer, or `conf`.
+    pub fn format(mut self, format: impl Into<String>) -> Self {
+        self.format = Some(format.into());
+        self
+    }
+
+    /// Load and merge every layer.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a required file is missing or unreadable, a
+    /// layer fails to parse, or a command-line flag names an invalid path.
+    pub fn build(self) -> Result<Config> {
+        let mut values = Value::table(BTreeMap::new());
+        let mut origins = Origins::new();
+        let mut format = None;
+
+        for layer in self.layers {
+            match layer {
+                Layer::Value { name, value } => {
+                    let origin = Origin::new(name);
+                    let mut origin_for = |_: &str| Some(origin.clone());
+                    let mut tracker = Tracker::new(&mut origins, &mut origin_for);
+                    merge_tracked(
+                        &mut values,
+                        &value,
+                        "",
+                        "",
+                        &mut tracker,
+                        &self.merge_options,
+                    );
+                }
+                Layer::Source {
+                    name,
+                    source,
+                    format: hint,
+                } => {
+                    let detected = hint.unwrap_or_else(|| parsers::detect_format(&source).into());
+                    let layer = parsers::parse_string(&source, Some(&detected))?;
+                    let lines = parsers::locate::key_lines(&source, &detected);
+                    let mut origin_for = |p: &str| {
+                        let mut origin = Origin::new(name.as_str());
+                        origin.line = line_for(&lines, p);
+                        Some(origin)
+                    };
+                    let mut tracker = Tracker::new(&mut origins, &mut origin_for);
+                    merge_tracked(
+                        &mut values,
+                        &layer,
+                        "",
+                        "",
+                        &mut tracker,
+                        &self.merge_options,
+                    );
+                    format = Some(detected);
+                }
+                Layer::File { path, required } => {
+                    if !required && !path.exists() {
+                        continue;
+                    }
+                    let source = std::fs::read_to_string(&path)
+                        .map_err(|e| Error::io(path.display().to_string(), e))?;
+                    let detected = parsers::detect_file_format(&path, &source);
+                    let layer = parsers::parse_string(&source, Some(detected))?;
+                    let lines = parsers::locate::key_lines(&source, detected);
+                    let mut origin_for = |p: &str| {
+                        Some(Origin {
+                            source: "file".to_string(),
+                            source_key: None,
+                            file: Some(path.clone()),
+                            line: line_for(&lines, p),
+                        })
+                    };
+                    let mut tracker = Tracker::new(&mut origins, &mut origin_for);
+                    merge_tracked(
+                        &mut values,
+                        &layer,
+                        "",
+                        "",
+                        &mut tracker,
+                        &self.merge_options,
+                    );
+                    format = Some(detected.to_string());
+                }
+                #[cfg(feature = "env-override")]
+                Layer::Env(overrides) => {
+                    let (overridden, applied) = overrides.apply_overrides_tracked(values)?;
+                    values = overridden;
+                    for (key, var) in applied {
+                        let origin = Origin::new("env").with_source_key(var);
+                        let mut origin_for = |_: &str| Some(origin.clone());
+                        let mut tracker = Tracker::new(&mut origins, &mut origin_for);
+                        tracker.forget(&key);
+                        if let Some(value) = values.get(&key) {
+                            tracker.record_subtree(value, &key, &key);
+                        }
+                    }
+                }
+                Layer::Args(args) => {
+                    for (key, flag, value) in parse_args(&args, &values) {
+                        values.set_nested(&key, value)?;
+                        let origin = Origin::new("args").with_source_key(flag);
+                        set_origin(&values, &mut origins, &key, origin);
+                    }
+                }
+            }
+        }
+
+        let format = self.format.or(format).unwrap_or_else(|| "conf".to_string());
+        Ok(Config::from_layers(values, format, origins))
+    }
+}
+
+/// Split `--path=value` / `--path value` / `--path` flags into
+/// `(path, flag text, value)` triples. A bare `--path` only takes the next
+/// argument as its value when `path` already holds a non-boolean in
+/// `values`; otherwise it is a `true` switch and the next argument stays
+/// positional.
+fn parse_args(args: &[String], values: &Value) -> Vec<(String, String, Value)> {
+    let mut flags = Vec::new();
+    let mut iter = args.iter().peekable();
+    while let Some(arg) = iter.next() {
+        if arg == "--" {
+            break;
+        }
+        let Some(body) = arg.strip_prefix("--") else {
+            continue;
+        };
+        match body.split_once('=') {
+            Some((key, value)) => {
+                flags.push((key.to_string(), arg.clone(), parse_scalar(value)));
+            }
+            None => {
+                let takes_value = values
+                    .get(body)
+                    .is_some_and(|existing| !matches!(existing, Value::Bool(_)));
+                let value = match iter.peek() {
+                    Some(next) if takes_value && !next.starts_with("--") => {
+                        iter.next().map_or(Value::Bool(true), |v| parse_scalar(v))
+                    }
+                    _ => Value::Bool(true),
+                };
+                flags.push((body.to_string(), arg.clone(), value));
+            }
+        }
+    }
+    flags
+}
+
+/// Type a command-line value: bool, integer, float, or string.
+fn parse_scalar(text: &str) -> Value {
+    match text {
+        "true" => Value::Bool(true),
+        "false" => Value::Bool(false),
+        _ => text
+            .parse::<i64>()
+            .map(Value::Integer)
+            .or_else(|_| text.parse::<f64>().map(Value::Float))
+            .unwrap_or_else(|_| Value::String(text.to_string())),
+    }
+}
+
+/// Records origins while a value tree is merged: looks up the origin of
+/// each path in the incoming tree and stores it under the matching path of
+/// the target tree.
+pub(crate) struct Tracker<'a> {
+    origins: &'a mut Origins,
+    origin_for: &'a mut dyn FnMut(&str) -> Option<Origin>,
+}
+
+impl<'a> Tracker<'a> {
+    pub(crate) fn new(
+        origins: &'a mut Origins,
+        origin_for: &'a mut dyn FnMut(&str) -> Option<Origin>,
+    ) -> Self {
+        Self {
+            origins,
+            origin_for,
+        }
+    }
+
+    /// Record the origin of the node at `path` (read from `source`).
+    pub(crate) fn record(&mut self, path: &str, source: &str) {
+        if path.is_empty() {
+            return;
+        }
+        if let Some(origin) = (self.origin_for)(source) {
+            self.origins.insert(path.to_string(), origin);
+        }
+    }
+
+    /// Record origins for `path` and everything below it.
+    pub(crate) fn record_subtree(&mut self, value: &Value, path: &str, source: &str) {
+        self.record(path, source);
+        match value {
+            Value::Table(table) => {
+                for (key, child) in table {
+                    self.record_subtree(
+                        child,
+                        &path::join_key(path, key),
+                        &path::join_key(source, key),
+                    );
+                }
+            }
+            Value::Array(items) => {
+                for (index, child) in items.iter().enumerate() {
+                    self.record_subtree(
+                        child,
+                        &path::join_index(path, index),
+                        &path::join_index(source, index),
+                    );
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Drop the origins of `path` and its descendants.
+    pub(crate) fn forget(&mut self, path: &str) {
+        forget_subtree(self.origins, path);
+    }
+}
+
+/// Nearest recorded line for `path`: formats without per-element lines
+/// (e.g. CONF arrays) report the line of the enclosing key.
+fn line_for(lines: &BTreeMap<String, usize>, path: &str) -> Option<usize> {
+    if let Some(line) = lines.get(path) {
+        return Some(*line);
+    }
+    let mut segments = path::parse(path).ok()?;
+    while segments.pop().is_some() && !segments.is_empty() {
+        if let Some(line) = lines.get(&path::to_path_string(&segments)) {
+            return Some(*line);
+        }
+    }
+    None
+}
+
+/// Drop the origins of `path` and all of its descendants.
+pub(crate) fn forget_subtree(origins: &mut Origins, path: &str) {
+    if path.is_empty() {
+        origins.clear();
+        return;
+    }
+    let stale: Vec<String> = origins
+        .range::<str, _>((Bound::Included(path), Bound::Unbounded))
+        .map(|(key, _)| key)
+        .take_while(|key| key.starts_with(path))
+        .filter(|key| matches!(key.as_bytes().get(path.len()), None | Some(b'.' | b'[')))
+        .cloned()
+        .collect();
+    for key in stale {
+        origins.remove(&key);
+    }
+}
+
+/// Replace the origin of the subtree at `path` (as written by a single
+/// `set`) with `origin`.
+pub(crate) fn set_origin(values: &Value, origins: &mut Origins, path: &str, origin: Origin) {
+    if let Some(canonical) = canonical_path(values, path) {
+        forget_subtree(origins, &canonical);
+        origins.insert(canonical, origin);
+    }
+}
+
+/// Resolve `path` against `values` into the canonical form used as the
+/// origin key: keys quoted as needed and negative indices made absolute.
+/// Where a table has no key for the next segment, the longest run of
+/// following keys joined by `.` is tried as one literal key, so that
+/// `server.host.name` finds a CONF `host.name = x` under `[server]`.
+pub(crate) fn canonical_path(values: &Value, path: &str) -> Option<String> {
+    let segments = path::parse(path).ok()?;
+    let mut canonical = String::new();
+    let mut node = Some(values);
+    let mut rest = segments.as_slice();
+    while let Some((segment, tail)) = rest.split_first() {
+        rest = tail;
+        match segment {
+            PathSegment::Key(key) => {
+                let Some(Value::Table(table)) = node else {
+                    canonical = path::join_key(&canonical, key);
+                    node = None;
+                    continue;
+                };
+                let mut key = key.to_string();
+                if !table.contains_key(&key) {
+                    let run = rest
+                        .iter()
+                        .take_while(|segment| matches!(segment, PathSegment::Key(_)))
+                        .count();
+                    for len in (1..=run).rev() {
+                        let joined = std::iter::once(key.as_str())
+                            .chain(rest[..len].iter().filter_map(|segment| match segment {
+                                PathSegment::Key(key) => Some(key.as_ref()),
+                                PathSegment::Index(_) => None,
+                            }))
+                            .collect::<Vec<_>>()
+                            .join(".");
+                        if table.contains_key(&joined) {
+                            key = joined;
+                            rest = &rest[len..];
+                            break;
+                        }
+                    }
+                }
+                canonical = path::join_key(&canonical, &key);
+                node = table.get(&key);
+            }
+            PathSegment::Index(_) => {
+                let Some(Value::Array(items)) = node else {
+                    return None;
+                };
+                let index = segment.resolve_index(items.len())?;
+                canonical = path::join_index(&canonical, index);
+                node = items.get(index);
+            }
+        }
+    }
+    Some(canonical)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layer_precedence_and_origins() {
+        let mut defaults = Value::table(BTreeMap::new());
+        defaults
+            .set_nested("database.port", Value::integer(5432))
+            .unwrap();
+        defaults
+            .set_nested("database.host", Value::string("localhost"))
+            .unwrap();
+        defaults.set_nested("debug", Value::bool(false)).unwrap();
+
+        let config = LayeredBuilder::new()
+            .with_defaults(defaults)
+            .with_string("system", "[database]\nhost = db.internal\n", Some("conf"))
+            .with_args(["serve", "--database.port=6543", "--debug"])
+            .build()
+            .unwrap();
+
+        assert_eq!(config.get("database.port"), Some(&Value::integer(6543)));
+        assert_eq!(config.get("debug"), Some(&Value::bool(true)));
+
+        let host = config.origin("database.host").unwrap();
+        assert_eq!(host.source, "system");
+        assert_eq!(host.line, Some(2));
+
+        let port = config.origin("database.port").unwrap();
+        assert_eq!(port.to_string(), "args --database.port=6543");
+        assert_eq!(config.origin("debug").unwrap().source, "args");
+        assert!(config.origin("missing").is_none());
+    }
+
+    #[test]
+    fn test_origin_lines_for_dotted_keys_and_other_formats() {
+        let config = LayeredBuilder::new()
+            .with_string("conf", "[server]\nhost.name = x\n", Some("conf"))
+            .build()
+            .unwrap();
+        let host = config.origin("server.host.name").unwrap();
+        assert_eq!((host.source.as_str(), host.line), ("conf", Some(2)));
+        assert_eq!(config.origin("server.\"host.name\"").unwrap().line, Some(2));
+        assert!(config.origin("server.host").is_none());
+
+        #[cfg(feature = "toml")]
+        {
+            let source = "[server]\nport = 1\n\n[[server.routes]]\npath = \"/\"\n";
+            let config = LayeredBuilder::new()
+                .with_string("toml", source, Some("toml"))
+                .build()
+                .unwrap();
+            assert_eq!(config.origin("server.port").unwrap().line, Some(2));
+            assert_eq!(
+                config.origin("server.routes[0].path").unwrap().line,
+                Some(5)
+            );
+        }
+    }
+
+    #[test]
+    fn test_replaced_subtree_forgets_origins() {
+        let mut base = Value::table(BTreeMap::new());
+        base.set_nested("list[0].name", Value::string("a")).unwrap();
+        let mut over = Value::table(BTreeMap::new());
+        over.set_nested("list", Value::array(vec![Value::integer(1)]))
+            .unwrap();
+
+        let mut config = LayeredBuilder::new()
+            .with_value("base", base)
+            .with_value("over", over)
+            .build()
+            .unwrap();
+
+        assert!(config.origin("list[0].name").is_none());
+        assert_eq!(config.origin("list[-1]").unwrap().source, "over");
+
+        config.set("list[0]", 2).unwrap();
+        assert_eq!(config.origin("list[0]").unwrap().source, "runtime");
+        config
+            .apply_patch(&[
+                crate::patch::PatchOp::Add {
+                    path: "/list/-".into(),
+                    value: Value::integer(3),
+                },
+                crate::patch::PatchOp::Test {
+                    path: "/list/0".into(),
+                    value: Value::integer(2),
+                },
+            ])
+            .unwrap();
+        assert_eq!(config.origin("list[-1]").unwrap().source, "patch");
+        assert_eq!(config.origin("list[0]").unwrap().source, "runtime");
+
+        config.remove("list").unwrap();
+        assert!(config.origin("list").is_none());
+    }
+
+    #[test]
+    fn test_merge_options_apply_between_layers() {
+        let config = LayeredBuilder::new()
+            .with_string("base", "tags = a b\n", Some("conf"))
+            .with_string("site", "\ntags = c d\n", Some("conf"))
+            .merge_options(MergeOptions::new().arrays(crate::merge::MergeStrategy::Append))
+            .build()
+            .unwrap();
+
+        assert_eq!(config.get("tags").unwrap().as_array().unwrap().len(), 4);
+        assert_eq!(config.origin("tags[0]").unwrap().source, "base");
+        let appended = config.origin("tags[2]").unwrap();
+        assert_eq!((appended.source.as_str(), appended.line), ("site", Some(2)));
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let mut values = Value::table(BTreeMap::new());
+        values.set_nested("b", Value::string("")).unwrap();
+        values.set_nested("d.e", Value::float(1.0)).unwrap();
+        values.set_nested("g", Value::bool(false)).unwrap();
+        let args: Vec<String> = [
+            "--a=1", "pos", "--b", "x y", "--c", "run", "--d.e", "2.5", "--g", "on", "--", "--f",
+        ]
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+        let flags: Vec<(String, Value)> = parse_args(&args, &values)
+            .into_iter()
+            .map(|(key, _, value)| (key, value))
+            .collect();
+        assert_eq!(
+            flags,
+            vec![
+                ("a".to_string(), Value::integer(1)),
+                ("b".to_string(), Value::string("x y")),
+                ("c".to_string(), Value::bool(true)),
+                ("d.e".to_string(), Value::float(2.5)),
+                ("g".to_string(), Value::bool(true)),
+            ]
+        );
+    }
+}
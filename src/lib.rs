@@ -132,12 +132,31 @@
 )]
 
 pub mod config;
+/// Cross-format conversion with a report of lossy steps.
+pub mod convert;
+/// Serde bridge: deserialize typed structs straight from a [`Value`] tree.
+pub mod de;
+/// Structured diffs between value trees and configuration snapshots.
+pub mod diff;
 /// Enterprise-grade configuration management with advanced caching, performance optimizations,
 /// and multi-instance support. Provides thread-safe caching with `Arc<RwLock>` for high-concurrency
 /// environments and sub-50ns access times for cached values.
 pub mod enterprise; // Enterprise API with caching and performance
 pub mod error;
+/// Canonical formatting that keeps comments and refuses to change values.
+pub mod formatter;
+/// Layered sources with per-key provenance.
+pub mod layered;
+/// Configurable merge strategies for values, configs and layers.
+pub mod merge;
 pub mod parsers;
+/// RFC 6902 JSON Patch and RFC 7396 Merge Patch over [`Value`] trees.
+pub mod patch;
+pub mod path;
+/// Wildcard, recursive-descent and predicate queries over [`Value`] trees.
+pub mod query;
+/// Serde bridge: build a [`Value`] tree from any `Serialize` type.
+pub mod ser;
 pub mod value;
 
 #[cfg(feature = "schema")]
@@ -146,6 +165,10 @@ pub mod schema;
 #[cfg(feature = "validation")]
 pub mod validation;
 
+/// Lint rules for likely mistakes, with text, JSON and SARIF reports.
+#[cfg(feature = "lint")]
+pub mod lint;
+
 /// Hot reloading system for zero-downtime configuration updates
 pub mod hot_reload;
 
@@ -162,6 +185,7 @@ pub use config::{CacheStats, Config, ConfigBuilder, ConfigOptions, ConfigValue};
 // see `enterprise.rs` — the items themselves carry the deprecation notices
 pub use enterprise::{ConfigManager, EnterpriseConfig};
 pub use error::{Error, Result};
+pub use layered::{LayeredBuilder, Origin};
 pub use value::Value;
 
 #[cfg(feature = "schema")]
@@ -250,6 +274,29 @@ pub fn parse_file<P: AsRef<Path>>(path: P) -> Result<Value> {
     parsers::parse_file(path)
 }
 
+/// Parse configuration from a file without guessing its format
+///
+/// Like [`parse_file`], but a file without a known extension is detected
+/// by trial-parsing its content (see [`parsers::detect_format_scored`])
+/// instead of being read as CONF, and an ambiguous detection is an error.
+///
+/// # Errors
+///
+/// Returns [`Error::UnknownFormat`] if the content could be several
+/// formats or fits none, and otherwise the errors of [`parse_file`].
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// // `settings` has no extension, so its content decides the format
+/// let config = config_lib::parse_file_strict("settings")?;
+/// # let _ = config;
+/// # Ok::<(), config_lib::Error>(())
+/// ```
+pub fn parse_file_strict<P: AsRef<Path>>(path: P) -> Result<Value> {
+    parsers::parse_file_strict(path)
+}
+
 /// Validate configuration against a schema
 ///
 /// Performs comprehensive validation of configuration data against a provided
@@ -0,0 +1,892 @@
+//! # Linting
+//!
+//! Find likely mistakes in files that parse without error: keys defined
+//! twice (the later value silently wins), keys that differ only by case,
+//! sections without keys, values that look wrong for their key, and
+//! inconsistent separators or indentation. Each [`Finding`] names a rule
+//! from the [`RULES`] catalog, carries that rule's [`ValidationSeverity`]
+//! and, where the format allows, the line and column it refers to.
+//!
+//! Positions come from the parsers, which outline where each key is
+//! written; only NOML gets the value checks without them. The TOML, YAML
+//! and HCL parsers already reject duplicate keys, and XML collects
+//! repeated elements into arrays. Indentation is checked in every format.
+//!
+//! ```rust
+//! use config_lib::lint::{self, LintOptions};
+//!
+//! let source = "[server]\nport = 8080\nport = 80800\n";
+//! let findings = lint::lint(source, "conf", &LintOptions::new())?;
+//!
+//! let rules: Vec<&str> = findings.iter().map(|finding| finding.rule).collect();
+//! assert_eq!(rules, ["duplicate-key", "port-range"]);
+//! assert_eq!(
+//!     findings[0].to_string(),
+//!     "3:1: error[duplicate-key] server.port: already defined on line 2; this value replaces it"
+//! );
+//! # Ok::<(), config_lib::Error>(())
+//! ```
+//!
+//! [`LintReport`] adds the file name, and [`sarif`] turns reports into a
+//! SARIF 2.1.0 log for code-scanning tools.
+//!
+//! [`Finding`]: crate::lint::Finding
+//! [`RULES`]: crate::lint::RULES
+//! [`LintReport`]: crate::lint::LintReport
+//! [`sarif`]: crate::lint::sarif
+
+use crate::error::{Error, Result};
+use crate::parsers;
+use crate::parsers::locate::{self, outline, Mark};
+use crate::path;
+use crate::validation::ValidationSeverity;
+use crate::value::Value;
+use std::collections::btree_map::Entry;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::path::Path;
+
+const DUPLICATE_KEY: &str = "duplicate-key";
+const CASE_CONFLICT: &str = "case-conflict";
+const EMPTY_SECTION: &str = "empty-section";
+const STRING_BOOLEAN: &str = "string-boolean";
+const PORT_RANGE: &str = "port-range";
+const EMPTY_STRING: &str = "empty-string";
+const MIXED_SEPARATORS: &str = "mixed-separators";
+const MIXED_INDENTATION: &str = "mixed-indentation";
+
+/// A check the linter runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rule {
+    /// Stable identifier, e.g. `duplicate-key`.
+    pub id: &'static str,
+    /// Severity of the rule's findings.
+    pub severity: ValidationSeverity,
+    /// What the rule looks for, in one sentence.
+    pub summary: &'static str,
+}
+
+/// Every rule, in the order [`sarif`] lists them.
+pub const RULES: &[Rule] = &[
+    Rule {
+        id: DUPLICATE_KEY,
+        severity: ValidationSeverity::Error,
+        summary: "A key is defined more than once in the same section or object; only the last value is kept.",
+    },
+    Rule {
+        id: CASE_CONFLICT,
+        severity: ValidationSeverity::Warning,
+        summary: "Two keys or sections in the same table differ only by letter case.",
+    },
+    Rule {
+        id: EMPTY_SECTION,
+        severity: ValidationSeverity::Info,
+        summary: "A section or table has no keys.",
+    },
+    Rule {
+        id: STRING_BOOLEAN,
+        severity: ValidationSeverity::Warning,
+        summary: "A string holds `true` or `false` where a boolean was probably meant.",
+    },
+    Rule {
+        id: PORT_RANGE,
+        severity: ValidationSeverity::Error,
+        summary: "A port number (a key named `port` or ending in `_port`, `-port` or `Port`) is outside 0-65535.",
+    },
+    Rule {
+        id: EMPTY_STRING,
+        severity: ValidationSeverity::Info,
+        summary: "A value is an empty string.",
+    },
+    Rule {
+        id: MIXED_SEPARATORS,
+        severity: ValidationSeverity::Warning,
+        summary: "Entries separate keys from values with both `=` and `:`.",
+    },
+    Rule {
+        id: MIXED_INDENTATION,
+        severity: ValidationSeverity::Warning,
+        summary: "Lines are indented with tabs in some places and spaces in others.",
+    },
+];
+
+impl Rule {
+    /// Represent the rule as a table with `id`, `severity` and `summary`
+    /// entries.
+    pub fn to_value(&self) -> Value {
+        let mut table = BTreeMap::new();
+        table.insert("id".to_string(), Value::string(self.id));
+        table.insert(
+            "severity".to_string(),
+            Value::string(severity_name(self.severity)),
+        );
+        table.insert("summary".to_string(), Value::string(self.summary));
+        Value::table(table)
+    }
+}
+
+impl fmt::Display for Rule {
+    /// One line: `id (severity): summary`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} ({}): {}",
+            self.id,
+            severity_name(self.severity),
+            self.summary
+        )
+    }
+}
+
+/// The rule with id `id`, if there is one.
+pub fn rule(id: &str) -> Option<&'static Rule> {
+    RULES.iter().find(|rule| rule.id == id)
+}
+
+/// A 1-indexed line and column; columns count characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Location {
+    /// Line number, starting at 1.
+    pub line: usize,
+    /// Column number, starting at 1.
+    pub column: usize,
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// One problem the linter found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    /// Id of the [`Rule`] that produced the finding.
+    pub rule: &'static str,
+    /// How serious the finding is.
+    pub severity: ValidationSeverity,
+    /// Path of the key involved, in the [`path`] grammar;
+    /// empty for findings about the file's layout.
+    pub path: String,
+    /// Where the finding is, when the format's parser can tell.
+    pub location: Option<Location>,
+    /// Human-readable description.
+    pub message: String,
+}
+
+impl Finding {
+    /// Represent the finding as a table with `rule`, `severity`, `path`
+    /// and `message` entries, plus `line` and `column` when known.
+    pub fn to_value(&self) -> Value {
+        let mut table = BTreeMap::new();
+        table.insert("rule".to_string(), Value::string(self.rule));
+        table.insert(
+            "severity".to_string(),
+            Value::string(severity_name(self.severity)),
+        );
+        table.insert("path".to_string(), Value::string(&self.path));
+        table.insert("message".to_string(), Value::string(&self.message));
+        if let Some(location) = self.location {
+            table.insert("line".to_string(), position(location.line));
+            table.insert("column".to_string(), position(location.column));
+        }
+        Value::table(table)
+    }
+}
+
+impl fmt::Display for Finding {
+    /// One line: `line:column: severity[rule] path: message`, leaving out
+    /// what is unknown or empty.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(location) = self.location {
+            write!(f, "{location}: ")?;
+        }
+        write!(f, "{}[{}] ", severity_name(self.severity), self.rule)?;
+        if !self.path.is_empty() {
+            write!(f, "{}: ", self.path)?;
+        }
+        f.write_str(&self.message)
+    }
+}
+
+/// Options for [`lint`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct LintOptions {
+    /// Ids of rules to skip.
+    pub disabled: BTreeSet<String>,
+}
+
+impl LintOptions {
+    /// Every rule enabled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Skip the rule with id `rule`.
+    pub fn disable(mut self, rule: impl Into<String>) -> Self {
+        self.disabled.insert(rule.into());
+        self
+    }
+}
+
+/// The findings for one file, as returned by [`lint_file`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LintReport {
+    /// The file, as it was named to the linter.
+    pub file: String,
+    /// Its findings, in source order.
+    pub findings: Vec<Finding>,
+}
+
+impl LintReport {
+    /// A report of `findings` in `file`.
+    pub fn new(file: impl Into<String>, findings: Vec<Finding>) -> Self {
+        Self {
+            file: file.into(),
+            findings,
+        }
+    }
+
+    /// The most serious severity among the findings; `None` if there are
+    /// none.
+    pub fn worst(&self) -> Option<ValidationSeverity> {
+        self.findings.iter().map(|finding| finding.severity).max()
+    }
+
+    /// Represent the report as a table with `file` and `findings` entries.
+    pub fn to_value(&self) -> Value {
+        let mut table = BTreeMap::new();
+        table.insert("file".to_string(), Value::string(&self.file));
+        table.insert(
+            "findings".to_string(),
+            Value::array(self.findings.iter().map(Finding::to_value).collect()),
+        );
+        Value::table(table)
+    }
+}
+
+impl fmt::Display for LintReport {
+    /// One line per finding, prefixed with the file name.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, finding) in self.findings.iter().enumerate() {
+            if index > 0 {
+                f.write_str("\n")?;
+            }
+            write!(f, "{}:", self.file)?;
+            if finding.location.is_none() {
+                f.write_str(" ")?;
+            }
+            write!(f, "{finding}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Lint `source`, read as `format`.
+///
+/// # Errors
+///
+/// Returns the parser's error if `source` is not valid `format`, and
+/// [`Error::General`] if `options` disables a rule that does not exist.
+pub fn lint(source: &str, format: &str, options: &LintOptions) -> Result<Vec<Finding>> {
+    if let Some(unknown) = options.disabled.iter().find(|id| rule(id).is_none()) {
+        return Err(Error::general(format!("Unknown lint rule `{unknown}`")));
+    }
+
+    let value = parsers::parse_string(source, Some(format))?;
+    let linter = match outline(source, format)? {
+        Some(marks) => Linter::new(source, marks, true),
+        None => Linter::new(source, value_marks(&value), false),
+    };
+
+    let mut findings = Vec::new();
+    linter.duplicate_keys(&mut findings);
+    linter.case_conflicts(&mut findings);
+    let sections = linter.empty_sections(&mut findings);
+    let mut walk = Walk {
+        linter: &linter,
+        typed: format != "dotenv",
+        sections: &sections,
+        findings: &mut findings,
+    };
+    walk.value(&value, "", "", None);
+    linter.separators(&mut findings);
+    linter.indentation(&mut findings);
+
+    findings.retain(|finding| !options.disabled.contains(finding.rule));
+    findings.sort_by_key(|finding| (finding.location.is_none(), finding.location));
+    Ok(findings)
+}
+
+/// Lint the file at `path`, detecting its format the way
+/// [`parse_file`](crate::parse_file) does.
+///
+/// # Errors
+///
+/// Returns [`Error::Io`] if the file cannot be read, and otherwise what
+/// [`lint`] returns.
+pub fn lint_file<P: AsRef<Path>>(path: P, options: &LintOptions) -> Result<LintReport> {
+    let path = path.as_ref();
+    let name = path.display().to_string();
+    let source = std::fs::read_to_string(path).map_err(|e| Error::io(name.as_str(), e))?;
+    let format = parsers::detect_file_format(path, &source);
+    let findings = lint(&source, format, options)?;
+    Ok(LintReport::new(name, findings))
+}
+
+/// A SARIF 2.1.0 log of `reports`, for code-scanning tools; serialize it
+/// as JSON, e.g. with `parsers::json_parser::serialize`.
+///
+/// The log has one run whose driver lists every rule in [`RULES`].
+/// Severities map to SARIF levels (`Info` becomes `note`), and columns
+/// are counted in characters (`columnKind` is `unicodeCodePoints`).
+pub fn sarif(reports: &[LintReport]) -> Value {
+    let rules = RULES
+        .iter()
+        .map(|rule| {
+            table([
+                ("id", Value::string(rule.id)),
+                (
+                    "shortDescription",
+                    table([("text", Value::string(rule.summary))]),
+                ),
+                (
+                    "defaultConfiguration",
+                    table([("level", Value::string(sarif_level(rule.severity)))]),
+                ),
+            ])
+        })
+        .collect();
+
+    let mut results = Vec::new();
+    for report in reports {
+        for finding in &report.findings {
+            let mut physical = BTreeMap::new();
+            physical.insert(
+                "artifactLocation".to_string(),
+                table([("uri", Value::string(report.file.replace('\\', "/")))]),
+            );
+            if let Some(location) = finding.location {
+                physical.insert(
+                    "region".to_string(),
+                    table([
+                        ("startLine", position(location.line)),
+                        ("startColumn", position(location.column)),
+                    ]),
+                );
+            }
+            let mut place = BTreeMap::new();
+            place.insert("physicalLocation".to_string(), Value::table(physical));
+            if !finding.path.is_empty() {
+                place.insert(
+                    "logicalLocations".to_string(),
+                    Value::array(vec![table([(
+                        "fullyQualifiedName",
+                        Value::string(&finding.path),
+                    )])]),
+                );
+            }
+
+            let index = RULES
+                .iter()
+                .position(|rule| rule.id == finding.rule)
+                .unwrap_or_default();
+            results.push(table([
+                ("ruleId", Value::string(finding.rule)),
+                ("ruleIndex", position(index)),
+                ("level", Value::string(sarif_level(finding.severity))),
+                (
+                    "message",
+                    table([("text", Value::string(&finding.message))]),
+                ),
+                ("locations", Value::array(vec![Value::table(place)])),
+            ]));
+        }
+    }
+
+    let driver = table([
+        ("name", Value::string("config-lib")),
+        ("version", Value::string(env!("CARGO_PKG_VERSION"))),
+        (
+            "informationUri",
+            Value::string(env!("CARGO_PKG_REPOSITORY")),
+        ),
+        ("rules", Value::array(rules)),
+    ]);
+    let run = table([
+        ("tool", table([("driver", driver)])),
+        ("columnKind", Value::string("unicodeCodePoints")),
+        ("results", Value::array(results)),
+    ]);
+    table([
+        (
+            "$schema",
+            Value::string("https://json.schemastore.org/sarif-2.1.0.json"),
+        ),
+        ("version", Value::string("2.1.0")),
+        ("runs", Value::array(vec![run])),
+    ])
+}
+
+/// Lower-case name of a severity, as used in text and JSON output.
+fn severity_name(severity: ValidationSeverity) -> &'static str {
+    match severity {
+        ValidationSeverity::Critical => "critical",
+        ValidationSeverity::Error => "error",
+        ValidationSeverity::Warning => "warning",
+        ValidationSeverity::Info => "info",
+    }
+}
+
+fn sarif_level(severity: ValidationSeverity) -> &'static str {
+    match severity {
+        ValidationSeverity::Critical | ValidationSeverity::Error => "error",
+        ValidationSeverity::Warning => "warning",
+        ValidationSeverity::Info => "note",
+    }
+}
+
+fn table<const N: usize>(entries: [(&str, Value); N]) -> Value {
+    Value::table(
+        entries
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value))
+            .collect(),
+    )
+}
+
+/// A line, column or index as an integer value.
+fn position(number: usize) -> Value {
+    Value::integer(i64::try_from(number).unwrap_or(i64::MAX))
+}
+
+/// Marks without positions for every key in `value`, for formats
+/// without an outline.
+fn value_marks(value: &Value) -> Vec<Mark> {
+    fn walk(value: &Value, scope: &str, marks: &mut Vec<Mark>) {
+        match value {
+            Value::Table(table) => {
+                for (key, child) in table {
+                    let path = path::join_key(scope, key);
+                    marks.push(Mark::entry(0, scope, key, path.clone()));
+                    walk(child, &path, marks);
+                }
+            }
+            Value::Array(items) => {
+                for (index, child) in items.iter().enumerate() {
+                    walk(child, &path::join_index(scope, index), marks);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut marks = Vec::new();
+    walk(value, "", &mut marks);
+    marks
+}
+
+struct Linter<'a> {
+    source: &'a str,
+    marks: Vec<Mark>,
+    /// Byte offset of every line start; `None` when the marks have no
+    /// positions.
+    line_starts: Option<Vec<usize>>,
+    /// Index of the last mark for each path.
+    by_path: BTreeMap<String, usize>,
+}
+
+impl<'a> Linter<'a> {
+    fn new(source: &'a str, marks: Vec<Mark>, positioned: bool) -> Self {
+        let line_starts = positioned.then(|| locate::line_starts(source));
+        let by_path = marks
+            .iter()
+            .enumerate()
+            .map(|(index, mark)| (mark.path.clone(), index))
+            .collect();
+        Self {
+            source,
+            marks,
+            line_starts,
+            by_path,
+        }
+    }
+
+    fn locate(&self, mark: &Mark) -> Option<Location> {
+        let starts = self.line_starts.as_ref()?;
+        let line = locate::line_of(starts, mark.offset);
+        let start = starts.get(line.checked_sub(1)?)?;
+        let column = self.source.get(*start..mark.offset)?.chars().count() + 1;
+        Some(Location { line, column })
+    }
+
+    /// `" on line N"` for `mark`, or nothing when positions are unknown.
+    fn on_line(&self, mark: &Mark) -> String {
+        self.locate(mark)
+            .map(|location| format!(" on line {}", location.line))
+            .unwrap_or_default()
+    }
+
+    fn finding(&self, rule: &'static str, mark: &Mark, message: String) -> Finding {
+        found(rule, &mark.path, self.locate(mark), message)
+    }
+
+    fn keys(&self) -> impl Iterator<Item = (&Mark, &str)> {
+        self.marks
+            .iter()
+            .filter_map(|mark| mark.key.as_deref().map(|key| (mark, key)))
+    }
+
+    fn duplicate_keys(&self, out: &mut Vec<Finding>) {
+        let mut defined: BTreeMap<(&str, &str), &Mark> = BTreeMap::new();
+        for (mark, key) in self.keys().filter(|(mark, _)| !mark.section) {
+            if let Some(previous) = defined.insert((&mark.scope, key), mark) {
+                out.push(self.finding(
+                    DUPLICATE_KEY,
+                    mark,
+                    format!(
+                        "already defined{}; this value replaces it",
+                        self.on_line(previous)
+                    ),
+                ));
+            }
+        }
+    }
+
+    fn case_conflicts(&self, out: &mut Vec<Finding>) {
+        // The first spelling of each key, by lower-case spelling
+        let mut first: BTreeMap<(&str, String), (&Mark, &str)> = BTreeMap::new();
+        let mut spellings = BTreeSet::new();
+        for (mark, key) in self.keys() {
+            // The same spelling again is a duplicate, not a conflict
+            if !spellings.insert((mark.scope.as_str(), key)) {
+                continue;
+            }
+            match first.entry((&mark.scope, key.to_lowercase())) {
+                Entry::Vacant(slot) => {
+                    slot.insert((mark, key));
+                }
+                Entry::Occupied(slot) => {
+                    let (other, spelling) = *slot.get();
+                    out.push(self.finding(
+                        CASE_CONFLICT,
+                        mark,
+                        format!(
+                            "differs only by case from `{spelling}`{}",
+                            self.on_line(other)
+                        ),
+                    ));
+                }
+            }
+        }
+    }
+
+    /// Report section headers with no keys under them; returns their
+    /// paths.
+    fn empty_sections(&self, out: &mut Vec<Finding>) -> BTreeSet<String> {
+        // A TOML `[a.b]` header fills `[a]` as much as a key would
+        let used: BTreeSet<&str> = self.marks.iter().map(|mark| mark.scope.as_str()).collect();
+        let mut reported = BTreeSet::new();
+        for mark in self.marks.iter().filter(|mark| mark.section) {
+            if !used.contains(mark.path.as_str()) && reported.insert(mark.path.clone()) {
+                out.push(self.finding(EMPTY_SECTION, mark, "section has no keys".to_string()));
+            }
+        }
+        reported
+    }
+
+    fn separators(&self, out: &mut Vec<Finding>) {
+        let mut first: Option<(&Mark, char)> = None;
+        for mark in &self.marks {
+            let Some(separator) = mark.separator else {
+                continue;
+            };
+            match first {
+                None => first = Some((mark, separator)),
+                Some((other, expected)) if separator != expected => out.push(self.finding(
+                    MIXED_SEPARATORS,
+                    mark,
+                    format!(
+                        "`{separator}` separator where the entry{} uses `{expected}`",
+                        self.on_line(other)
+                    ),
+                )),
+                Some(_) => {}
+            }
+        }
+    }
+
+    fn indentation(&self, out: &mut Vec<Finding>) {
+        let name = |tabs: bool| if tabs { "tabs" } else { "spaces" };
+        // The first indented line and whether it uses tabs
+        let mut first: Option<(usize, bool)> = None;
+        for (index, line) in self.source.split('\n').enumerate() {
+            let body = line.trim_start_matches([' ', '\t']);
+            let indent = &line[..line.len() - body.len()];
+            if indent.is_empty() || body.trim().is_empty() {
+                continue;
+            }
+            let location = Some(Location {
+                line: index + 1,
+                column: 1,
+            });
+            let tabs = indent.contains('\t');
+            if tabs && indent.contains(' ') {
+                out.push(found(
+                    MIXED_INDENTATION,
+                    "",
+                    location,
+                    "indentation mixes tabs and spaces".to_string(),
+                ));
+                continue;
+            }
+            match first {
+                None => first = Some((index + 1, tabs)),
+                Some((line, expected)) if tabs != expected => out.push(found(
+                    MIXED_INDENTATION,
+                    "",
+                    location,
+                    format!(
+                        "indented with {} where line {line} uses {}",
+                        name(tabs),
+                        name(expected)
+                    ),
+                )),
+                Some(_) => {}
+            }
+        }
+    }
+}
+
+fn found(rule: &'static str, path: &str, location: Option<Location>, message: String) -> Finding {
+    Finding {
+        rule,
+        severity: self::rule(rule).map_or(ValidationSeverity::Warning, |rule| rule.severity),
+        path: path.to_string(),
+        location,
+        message,
+    }
+}
+
+/// The checks on parsed values.
+struct Walk<'a> {
+    linter: &'a Linter<'a>,
+    /// Whether the format has non-string scalars.
+    typed: bool,
+    /// Paths already reported as empty sections.
+    sections: &'a BTreeSet<String>,
+    findings: &'a mut Vec<Finding>,
+}
+
+impl Walk<'_> {
+    /// Check `value` at `path`, held by `key` (array elements are held by
+    /// the array's key). Values without a mark of their own are reported
+    /// at `near`, the position of their closest ancestor.
+    fn value(&mut self, value: &Value, path: &str, key: &str, near: Option<Location>) {
+        let here = self
+            .linter
+            .by_path
+            .get(path)
+            .and_then(|&index| self.linter.marks.get(index))
+            .and_then(|mark| self.linter.locate(mark))
+            .or(near);
+        let mut report = |rule, message: String| {
+            self.findings.push(found(rule, path, here, message));
+        };
+
+        match value {
+            Value::Table(table)
+                if table.is_empty() && !path.is_empty() && !self.sections.contains(path) =>
+            {
+                report(EMPTY_SECTION, "table has no keys".to_string());
+            }
+            Value::String(text) if text.is_empty() => {
+                report(EMPTY_STRING, "empty string".to_string());
+            }
+            Value::String(text)
+                if self.typed
+                    && (text.eq_ignore_ascii_case("true")
+                        || text.eq_ignore_ascii_case("false")) =>
+            {
+                report(
+                    STRING_BOOLEAN,
+                    format!("the string \"{text}\" where a boolean was probably meant"),
+                );
+            }
+            Value::String(text) if is_port_key(key) => {
+                if let Some(port) = text.parse::<i64>().ok().filter(|port| !is_port(*port)) {
+                    report(
+                        PORT_RANGE,
+                        format!("{port} is outside the port range 0-65535"),
+                    );
+                }
+            }
+            Value::Integer(port) if is_port_key(key) && !is_port(*port) => {
+                report(
+                    PORT_RANGE,
+                    format!("{port} is outside the port range 0-65535"),
+                );
+            }
+            Value::Table(table) => {
+                for (child_key, child) in table {
+                    self.value(child, &path::join_key(path, child_key), child_key, here);
+                }
+            }
+            Value::Array(items) => {
+                for (index, child) in items.iter().enumerate() {
+                    self.value(child, &path::join_index(path, index), key, here);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Whether `key` names a port: `port`, or ending in `_port`, `-port` or
+/// (camel case) `Port`. Only the part after the last `.` counts.
+fn is_port_key(key: &str) -> bool {
+    let name = key.rsplit_once('.').map_or(key, |(_, name)| name);
+    let lower = name.to_ascii_lowercase();
+    lower == "port"
+        || lower.ends_with("_port")
+        || lower.ends_with("-port")
+        || name.ends_with("Port")
+}
+
+fn is_port(number: i64) -> bool {
+    (0..=65535).contains(&number)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(findings: &[Finding]) -> Vec<&'static str> {
+        findings.iter().map(|finding| finding.rule).collect()
+    }
+
+    #[test]
+    fn test_duplicate_and_case_conflicts_in_ini() {
+        let source = "[server]\nhost = a\nPort = 80\nhost = b\n\n[Server]\nport: 81\n";
+        let findings = lint(source, "ini", &LintOptions::new()).unwrap();
+        assert_eq!(
+            rules(&findings),
+            [DUPLICATE_KEY, CASE_CONFLICT, MIXED_SEPARATORS]
+        );
+        assert_eq!(findings[0].location, Some(Location { line: 4, column: 1 }));
+        assert_eq!(
+            findings[0].message,
+            "already defined on line 2; this value replaces it"
+        );
+        assert_eq!(findings[1].location, Some(Location { line: 6, column: 1 }));
+        assert_eq!(
+            findings[1].message,
+            "differs only by case from `server` on line 1"
+        );
+        assert_eq!(findings[2].path, "\"Server.port\"");
+    }
+
+    #[test]
+    fn test_value_rules_and_positions() {
+        let source = "{\n  \"db\": {\n    \"port\": 70000,\n    \"ssl\": \"true\",\n    \"user\": \"\",\n    \"opts\": {}\n  },\n  \"db\": 1\n}\n";
+        let findings = lint(source, "json5", &LintOptions::new());
+        #[cfg(not(feature = "json"))]
+        assert!(findings.is_err());
+        #[cfg(feature = "json")]
+        {
+            let findings = findings.unwrap();
+            assert_eq!(rules(&findings), [DUPLICATE_KEY]);
+
+            let source = source.replace("\"db\": 1\n", "\"name\": \"x\"\n");
+            let findings = lint(&source, "json", &LintOptions::new()).unwrap();
+            assert_eq!(
+                rules(&findings),
+                [PORT_RANGE, STRING_BOOLEAN, EMPTY_STRING, EMPTY_SECTION]
+            );
+            assert_eq!(
+                findings[0].to_string(),
+                "3:5: error[port-range] db.port: 70000 is outside the port range 0-65535"
+            );
+            assert_eq!(findings[3].location, Some(Location { line: 6, column: 5 }));
+        }
+    }
+
+    #[test]
+    fn test_empty_sections_and_layout() {
+        let source = "[empty]\n[app]\n\tname = x\n    mode = y\n \tlevel = z\n";
+        let findings = lint(source, "conf", &LintOptions::new()).unwrap();
+        assert_eq!(
+            rules(&findings),
+            [EMPTY_SECTION, MIXED_INDENTATION, MIXED_INDENTATION]
+        );
+        assert_eq!(findings[0].path, "empty");
+        assert_eq!(
+            findings[1].message,
+            "indented with spaces where line 3 uses tabs"
+        );
+        assert_eq!(findings[2].message, "indentation mixes tabs and spaces");
+
+        let quiet = LintOptions::new()
+            .disable(MIXED_INDENTATION)
+            .disable(EMPTY_SECTION);
+        assert!(lint(source, "conf", &quiet).unwrap().is_empty());
+        assert!(lint(source, "conf", &LintOptions::new().disable("nope")).is_err());
+    }
+
+    #[test]
+    fn test_properties_and_dotenv() {
+        let findings = lint(
+            "a = 1\nb: 2\nhttpPort = 99999\n",
+            "properties",
+            &LintOptions::new(),
+        )
+        .unwrap();
+        assert_eq!(rules(&findings), [MIXED_SEPARATORS, PORT_RANGE]);
+        assert_eq!(findings[1].location, Some(Location { line: 3, column: 1 }));
+
+        // dotenv values are all strings, so `true` is not suspicious
+        let findings = lint(
+            "DEBUG=true\nexport APP_PORT=70000\nDEBUG=false\n",
+            "dotenv",
+            &LintOptions::new(),
+        )
+        .unwrap();
+        assert_eq!(rules(&findings), [PORT_RANGE, DUPLICATE_KEY]);
+        assert_eq!(findings[0].location, Some(Location { line: 2, column: 8 }));
+    }
+
+    #[test]
+    fn test_reports_and_sarif() {
+        let findings = lint("port = -1\nname = \"\"\n", "conf", &LintOptions::new()).unwrap();
+        let report = LintReport::new("app.conf", findings);
+        assert_eq!(report.worst(), Some(ValidationSeverity::Error));
+        assert_eq!(
+            report.to_string(),
+            "app.conf:1:1: error[port-range] port: -1 is outside the port range 0-65535\n\
+             app.conf:2:1: info[empty-string] name: empty string"
+        );
+
+        let log = sarif(&[report]);
+        assert_eq!(log.get("version").unwrap().as_string().unwrap(), "2.1.0");
+        let result = log.get("runs[0].results[1]").unwrap();
+        assert_eq!(result.get("level").unwrap().as_string().unwrap(), "note");
+        assert_eq!(result.get("ruleIndex").unwrap().as_integer().unwrap(), 5);
+        assert_eq!(
+            result
+                .get("locations[0].physicalLocation.region.startLine")
+                .unwrap()
+                .as_integer()
+                .unwrap(),
+            2
+        );
+        assert_eq!(
+            log.get("runs[0].tool.driver.rules")
+                .unwrap()
+                .as_array()
+                .unwrap()
+                .len(),
+            RULES.len()
+        );
+    }
+}
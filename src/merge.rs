@@ -0,0 +1,447 @@
+//! # Merge Strategies
+//!
+//! Controls how [`Config::merge_with`](crate::Config::merge_with),
+//! [`Value::merge_with`] and [`LayeredBuilder`](crate::LayeredBuilder)
+//! combine an incoming value with an existing one.
+//!
+//! The default ([`MergeOptions::default`]) is what [`Config::merge`]
+//! always did: tables merge key by key, everything else — arrays
+//! included — is replaced. On top of that you can pick:
+//!
+//! - an array strategy ([`MergeStrategy::Append`], [`MergeStrategy::Union`],
+//!   [`MergeStrategy::UnionBy`] to merge `servers` entries by `name`, ...),
+//! - per-path strategies, using [`query`](crate::query) patterns such as
+//!   `services.*.env` or `**.tls`,
+//! - deletion of inherited keys with `null` and/or a tombstone value,
+//! - a marker key that makes an incoming table replace instead of merge.
+//!
+//! ```rust
+//! use config_lib::merge::{MergeOptions, MergeStrategy};
+//! use config_lib::Config;
+//!
+//! # fn main() -> Result<(), config_lib::Error> {
+//! let mut base = Config::new();
+//! base.set("servers[0].name", "a")?;
+//! base.set("servers[0].port", 1)?;
+//! base.set("debug", true)?;
+//!
+//! let mut overlay = Config::new();
+//! overlay.set("servers[0].name", "a")?;
+//! overlay.set("servers[0].port", 2)?;
+//! overlay.set("servers[1].name", "b")?;
+//! overlay.set("debug", config_lib::Value::Null)?;
+//!
+//! let options = MergeOptions::new()
+//!     .delete_on_null(true)
+//!     .strategy("servers", MergeStrategy::UnionBy("name".into()))?;
+//! base.merge_with(&overlay, &options)?;
+//!
+//! assert_eq!(base.get("servers[0].port").unwrap().as_integer()?, 2);
+//! assert_eq!(base.get("servers[1].name").unwrap().as_string()?, "b");
+//! assert!(base.get("debug").is_none());
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`Config::merge`]: crate::Config::merge
+//! [`MergeOptions::default`]: crate::merge::MergeOptions::default
+//! [`MergeStrategy::Append`]: crate::merge::MergeStrategy::Append
+//! [`MergeStrategy::Union`]: crate::merge::MergeStrategy::Union
+//! [`MergeStrategy::UnionBy`]: crate::merge::MergeStrategy::UnionBy
+
+use crate::error::Result;
+use crate::layered::{Origins, Tracker};
+use crate::path;
+use crate::query::Query;
+use crate::value::Value;
+
+/// How to combine an incoming value with the existing value at a path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum MergeStrategy {
+    /// Tables merge key by key; arrays merge element by element (by index).
+    Deep,
+    /// The incoming value replaces the existing one.
+    Replace,
+    /// Incoming array elements are appended to the existing array.
+    Append,
+    /// Incoming array elements are appended unless an equal element
+    /// already exists.
+    Union,
+    /// Array elements are tables identified by the named field: elements
+    /// with a matching field deep-merge, the rest are appended. Elements
+    /// without the field behave as in [`MergeStrategy::Union`].
+    UnionBy(String),
+}
+
+/// Options for a merge. See the [module documentation](self).
+#[derive(Debug, Clone)]
+pub struct MergeOptions {
+    arrays: MergeStrategy,
+    delete_on_null: bool,
+    tombstone: Option<Value>,
+    replace_marker: Option<String>,
+    overrides: Vec<(Query, MergeStrategy)>,
+}
+
+impl Default for MergeOptions {
+    fn default() -> Self {
+        Self {
+            arrays: MergeStrategy::Replace,
+            delete_on_null: false,
+            tombstone: None,
+            replace_marker: None,
+            overrides: Vec::new(),
+        }
+    }
+}
+
+impl MergeOptions {
+    /// Create the default options (deep-merge tables, replace the rest).
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Strategy for arrays that have no per-path strategy. Defaults to
+    /// [`MergeStrategy::Replace`].
+    pub fn arrays(mut self, strategy: MergeStrategy) -> Self {
+        self.arrays = strategy;
+        self
+    }
+
+    /// Treat an incoming `null` as "delete this key". Only table members
+    /// are deleted; a `null` inside an incoming array is kept.
+    pub fn delete_on_null(mut self, enabled: bool) -> Self {
+        self.delete_on_null = enabled;
+        self
+    }
+
+    /// Treat an incoming value equal to `tombstone` as "delete this key",
+    /// e.g. `"~delete"` for formats without `null`.
+    pub fn tombstone(mut self, tombstone: impl Into<Value>) -> Self {
+        self.tombstone = Some(tombstone.into());
+        self
+    }
+
+    /// Name of a marker key: an incoming table containing `marker = true`
+    /// replaces the existing table instead of merging into it. The marker
+    /// itself is dropped.
+    pub fn replace_marker(mut self, marker: impl Into<String>) -> Self {
+        self.replace_marker = Some(marker.into());
+        self
+    }
+
+    /// Use `strategy` for every path matching `pattern` (a
+    /// [`query`](crate::query) pattern without predicates). When several
+    /// patterns match, the one added last wins.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Parse`](crate::Error::Parse) if the pattern is
+    /// malformed.
+    pub fn strategy(mut self, pattern: &str, strategy: MergeStrategy) -> Result<Self> {
+        self.overrides.push((Query::parse(pattern)?, strategy));
+        Ok(self)
+    }
+
+    fn strategy_for(&self, path: &str, target: &Value, incoming: &Value) -> MergeStrategy {
+        let chosen = if self.overrides.is_empty() {
+            None
+        } else {
+            path::parse(path).ok().and_then(|segments| {
+                self.overrides
+                    .iter()
+                    .rev()
+                    .find(|(query, _)| query.matches_segments(&segments))
+                    .map(|(_, strategy)| strategy.clone())
+            })
+        };
+
+        match (target, incoming) {
+            // Array strategies do not apply to tables; they deep-merge
+            // unless asked to replace.
+            (Value::Table(_), Value::Table(incoming)) => {
+                let marked = self
+                    .replace_marker
+                    .as_ref()
+                    .is_some_and(|marker| incoming.get(marker) == Some(&Value::Bool(true)));
+                if marked || chosen == Some(MergeStrategy::Replace) {
+                    MergeStrategy::Replace
+                } else {
+                    MergeStrategy::Deep
+                }
+            }
+            (Value::Array(_), Value::Array(_)) => chosen.unwrap_or_else(|| self.arrays.clone()),
+            _ => MergeStrategy::Replace,
+        }
+    }
+
+    fn deletes(&self, value: &Value) -> bool {
+        (self.delete_on_null && value.is_null()) || self.tombstone.as_ref() == Some(value)
+    }
+
+    /// Copy `value` with tombstones and replace markers removed from its
+    /// tables. Arrays are copied as they are, as in RFC 7396.
+    fn clean(&self, value: &Value) -> Value {
+        match value {
+            Value::Table(table) => Value::Table(
+                table
+                    .iter()
+                    .filter(|(key, child)| {
+                        !self.deletes(child) && self.replace_marker.as_ref() != Some(*key)
+                    })
+                    .map(|(key, child)| (key.clone(), self.clean(child)))
+                    .collect(),
+            ),
+            other => other.clone(),
+        }
+    }
+}
+
+impl Value {
+    /// Merge `other` into this value using `options`.
+    pub fn merge_with(&mut self, other: &Value, options: &MergeOptions) {
+        let mut origins = Origins::new();
+        let mut origin_for = |_: &str| None;
+        let mut tracker = Tracker::new(&mut origins, &mut origin_for);
+        merge_tracked(self, other, "", "", &mut tracker, options);
+    }
+}
+
+/// Merge `incoming` into `target`, keeping the tracker's origins in sync.
+///
+/// `path` is the location in the target tree and `source` the location
+/// of `incoming` in its own tree; they differ once array elements are
+/// appended or matched by key.
+pub(crate) fn merge_tracked(
+    target: &mut Value,
+    incoming: &Value,
+    path: &str,
+    source: &str,
+    tracker: &mut Tracker<'_>,
+    options: &MergeOptions,
+) {
+    let strategy = options.strategy_for(path, target, incoming);
+
+    match (&mut *target, incoming, strategy) {
+        (Value::Table(table), Value::Table(incoming_table), MergeStrategy::Deep) => {
+            tracker.record(path, source);
+            for (key, value) in incoming_table {
+                let child = path::join_key(path, key);
+                let child_source = path::join_key(source, key);
+                if options.deletes(value) {
+                    if table.remove(key).is_some() {
+                        tracker.forget(&child);
+                    }
+                    continue;
+                }
+                match table.get_mut(key) {
+                    Some(existing) => {
+                        merge_tracked(existing, value, &child, &child_source, tracker, options);
+                    }
+                    None => {
+                        let value = options.clean(value);
+                        tracker.record_subtree(&value, &child, &child_source);
+                        table.insert(key.clone(), value);
+                    }
+                }
+            }
+        }
+        (Value::Array(items), Value::Array(incoming_items), MergeStrategy::Deep) => {
+            tracker.record(path, source);
+            for (index, value) in incoming_items.iter().enumerate() {
+                let child_source = path::join_index(source, index);
+                match items.get_mut(index) {
+                    Some(existing) => {
+                        let child = path::join_index(path, index);
+                        merge_tracked(existing, value, &child, &child_source, tracker, options);
+                    }
+                    None => push(items, value, path, &child_source, tracker, options),
+                }
+            }
+        }
+        (Value::Array(items), Value::Array(incoming_items), MergeStrategy::Append) => {
+            tracker.record(path, source);
+            for (index, value) in incoming_items.iter().enumerate() {
+                let child_source = path::join_index(source, index);
+                push(items, value, path, &child_source, tracker, options);
+            }
+        }
+        (Value::Array(items), Value::Array(incoming_items), MergeStrategy::Union) => {
+            tracker.record(path, source);
+            for (index, value) in incoming_items.iter().enumerate() {
+                if !items.contains(value) {
+                    let child_source = path::join_index(source, index);
+                    push(items, value, path, &child_source, tracker, options);
+                }
+            }
+        }
+        (Value::Array(items), Value::Array(incoming_items), MergeStrategy::UnionBy(field)) => {
+            tracker.record(path, source);
+            for (index, value) in incoming_items.iter().enumerate() {
+                let child_source = path::join_index(source, index);
+                let id = value.as_table().ok().and_then(|t| t.get(&field));
+                let existing = id.and_then(|id| {
+                    items.iter().position(|item| {
+                        item.as_table().ok().and_then(|t| t.get(&field)) == Some(id)
+                    })
+                });
+                match existing {
+                    Some(at) => merge_tracked(
+                        &mut items[at],
+                        value,
+                        &path::join_index(path, at),
+                        &child_source,
+                        tracker,
+                        options,
+                    ),
+                    None if id.is_none() && items.contains(value) => {}
+                    None => push(items, value, path, &child_source, tracker, options),
+                }
+            }
+        }
+        _ => {
+            tracker.forget(path);
+            *target = options.clean(incoming);
+            tracker.record_subtree(target, path, source);
+        }
+    }
+}
+
+/// Append a cleaned copy of `value` to `items`, recording its origins.
+fn push(
+    items: &mut Vec<Value>,
+    value: &Value,
+    path: &str,
+    source: &str,
+    tracker: &mut Tracker<'_>,
+    options: &MergeOptions,
+) {
+    let value = options.clean(value);
+    tracker.record_subtree(&value, &path::join_index(path, items.len()), source);
+    items.push(value);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn table(entries: &[(&str, Value)]) -> Value {
+        Value::table(
+            entries
+                .iter()
+                .map(|(k, v)| ((*k).to_string(), v.clone()))
+                .collect::<BTreeMap<_, _>>(),
+        )
+    }
+
+    fn ints(values: &[i64]) -> Value {
+        Value::array(values.iter().copied().map(Value::integer).collect())
+    }
+
+    #[test]
+    fn test_default_replaces_arrays() {
+        let mut base = table(&[("a", ints(&[1, 2])), ("b", Value::integer(1))]);
+        base.merge_with(&table(&[("a", ints(&[3]))]), &MergeOptions::new());
+        assert_eq!(base.get("a"), Some(&ints(&[3])));
+        assert_eq!(base.get("b"), Some(&Value::integer(1)));
+    }
+
+    #[test]
+    fn test_array_strategies() {
+        let incoming = table(&[("a", ints(&[2, 3]))]);
+        for (strategy, expected) in [
+            (MergeStrategy::Append, ints(&[1, 2, 2, 3])),
+            (MergeStrategy::Union, ints(&[1, 2, 3])),
+            (MergeStrategy::Deep, ints(&[2, 3])),
+            (MergeStrategy::Replace, ints(&[2, 3])),
+        ] {
+            let mut base = table(&[("a", ints(&[1, 2]))]);
+            base.merge_with(&incoming, &MergeOptions::new().arrays(strategy.clone()));
+            assert_eq!(base.get("a"), Some(&expected), "{strategy:?}");
+        }
+    }
+
+    #[test]
+    fn test_union_by_key_and_per_path_strategy() {
+        let server = |name: &str, port: i64| {
+            table(&[
+                ("name", Value::string(name)),
+                ("port", Value::integer(port)),
+            ])
+        };
+        let mut base = table(&[
+            (
+                "servers",
+                Value::array(vec![server("a", 1), server("b", 2)]),
+            ),
+            ("tags", ints(&[1])),
+        ]);
+        let incoming = table(&[
+            (
+                "servers",
+                Value::array(vec![server("b", 20), server("c", 3)]),
+            ),
+            ("tags", ints(&[2])),
+        ]);
+        let options = MergeOptions::new()
+            .strategy("servers", MergeStrategy::UnionBy("name".into()))
+            .unwrap()
+            .strategy("**.tags", MergeStrategy::Append)
+            .unwrap();
+        base.merge_with(&incoming, &options);
+
+        assert_eq!(
+            base.get("servers"),
+            Some(&Value::array(vec![
+                server("a", 1),
+                server("b", 20),
+                server("c", 3)
+            ]))
+        );
+        assert_eq!(base.get("tags"), Some(&ints(&[1, 2])));
+    }
+
+    #[test]
+    fn test_deletion_and_replace_marker() {
+        let mut base = table(&[
+            ("keep", Value::integer(1)),
+            ("drop", Value::integer(2)),
+            ("gone", Value::integer(3)),
+            (
+                "tls",
+                table(&[("cert", Value::string("a")), ("key", Value::string("b"))]),
+            ),
+        ]);
+        let incoming = table(&[
+            ("drop", Value::Null),
+            ("gone", Value::string("~delete")),
+            (
+                "tls",
+                table(&[
+                    ("$replace", Value::bool(true)),
+                    ("cert", Value::string("c")),
+                ]),
+            ),
+            (
+                "new",
+                table(&[("x", Value::Null), ("y", Value::integer(1))]),
+            ),
+        ]);
+        let options = MergeOptions::new()
+            .delete_on_null(true)
+            .tombstone("~delete")
+            .replace_marker("$replace");
+        base.merge_with(&incoming, &options);
+
+        assert_eq!(
+            base,
+            table(&[
+                ("keep", Value::integer(1)),
+                ("tls", table(&[("cert", Value::string("c"))])),
+                ("new", table(&[("y", Value::integer(1))])),
+            ])
+        );
+    }
+}
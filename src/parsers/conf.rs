@@ -20,21 +20,599 @@
 //! array = item1 item2 item3
 //! comma_array = item1, item2, item3
 //! ```
+//!
+//! [`parse_document`] keeps the source layout (comments, blank lines, key
+//! order, quoting style) in a [`ConfDocument`], so edits can be written
+//! back with every untouched line byte-identical:
+//!
+//! ```rust
+//! use config_lib::parsers::conf;
+//! use config_lib::Value;
+//!
+//! let source = "# Listener\nport = 8080  # default\n\n[db]\nhost = 'localhost'\n";
+//! let mut doc = conf::parse_document(source)?;
+//! doc.set("port", Value::integer(9000))?;
+//! doc.set("db.host", Value::string("db.internal"))?;
+//! assert_eq!(
+//!     doc.to_string(),
+//!     "# Listener\nport = 9000  # default\n\n[db]\nhost = 'db.internal'\n"
+//! );
+//! # Ok::<(), config_lib::Error>(())
+//! ```
+//!
+//! [`parse_document`]: crate::parsers::conf::parse_document
 
 use crate::error::{Error, Result};
+use crate::formatter::{Comments, Line};
 use crate::value::Value;
 use std::collections::BTreeMap;
+use std::fmt;
 
 /// Parse CONF format configuration
 pub fn parse(source: &str) -> Result<Value> {
+    parse_document(source).map(ConfDocument::into_value)
+}
+
+/// Parse CONF source into a lossless, editable [`ConfDocument`].
+pub fn parse_document(source: &str) -> Result<ConfDocument> {
     let mut parser = ConfParser::new(source);
     parser.parse()
 }
 
+/// A parsed CONF file that remembers its layout.
+///
+/// The document is a sequence of section headers and `key = value` entries,
+/// each carrying the exact text around it (leading comments and blank lines,
+/// separator, inline comment). [`set`](Self::set), [`remove`](Self::remove)
+/// and [`sync`](Self::sync) rewrite only the affected entries; new keys are
+/// appended to their section, reusing a sibling's indentation and
+/// separator. Changed values keep their quoting style where possible.
+#[derive(Debug, Clone)]
+pub struct ConfDocument {
+    items: Vec<Item>,
+    /// Comments and blank lines after the last item.
+    trailer: String,
+    newline: &'static str,
+    value: Value,
+}
+
+#[derive(Debug, Clone)]
+enum Item {
+    Section(Section),
+    Entry(Entry),
+}
+
+#[derive(Debug, Clone)]
+struct Section {
+    /// Comments, blank lines and indentation before the header.
+    leading: String,
+    /// The `[name]` text as written.
+    header: String,
+    name: String,
+    /// Whitespace, inline comment and line ending after the header.
+    trailing: String,
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    /// Comments, blank lines and indentation before the key.
+    leading: String,
+    key: String,
+    /// The text between key and value, e.g. `" = "`.
+    separator: String,
+    /// The value as written.
+    raw: String,
+    /// Whitespace, inline comment and line ending after the value.
+    trailing: String,
+    value: Value,
+}
+
+impl Item {
+    fn trailing_mut(&mut self) -> &mut String {
+        match self {
+            Item::Section(section) => &mut section.trailing,
+            Item::Entry(entry) => &mut entry.trailing,
+        }
+    }
+}
+
+impl ConfDocument {
+    /// The parsed values.
+    pub fn value(&self) -> &Value {
+        &self.value
+    }
+
+    /// Consume the document, keeping only the values.
+    pub fn into_value(self) -> Value {
+        self.value
+    }
+
+    /// Set the value at `path` (see [`crate::path`]), editing the owning
+    /// entry in place or appending it to its section.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the path is invalid or the result cannot be
+    /// written as CONF (e.g. tables nested inside a section); the document
+    /// is unchanged in that case.
+    pub fn set(&mut self, path: &str, value: Value) -> Result<()> {
+        let mut target = self.value.clone();
+        target.set_nested(path, value)?;
+        self.sync(&target)
+    }
+
+    /// Remove the value at `path`, dropping its entry (with the comments
+    /// directly above it) or section.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the path is invalid or the result cannot be
+    /// written as CONF.
+    pub fn remove(&mut self, path: &str) -> Result<Option<Value>> {
+        let mut target = self.value.clone();
+        let removed = target.remove(path)?;
+        if removed.is_some() {
+            self.sync(&target)?;
+        }
+        Ok(removed)
+    }
+
+    /// Reconcile the document with `target`: entries whose value differs
+    /// are rewritten, missing keys and sections are appended and stale
+    /// ones removed. Everything else keeps its original text.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `target` is not a table or has a shape CONF
+    /// cannot express (nested tables inside a section, keys with spaces,
+    /// values that would not parse back identically); the document is
+    /// unchanged in that case.
+    pub fn sync(&mut self, target: &Value) -> Result<()> {
+        let table = target.as_table()?;
+        let mut doc = self.clone();
+
+        let root: BTreeMap<&str, &Value> = table
+            .iter()
+            .filter(|(_, value)| !value.is_table())
+            .map(|(key, value)| (key.as_str(), value))
+            .collect();
+        doc.sync_scope(None, &root)?;
+
+        doc.remove_sections(|name| !matches!(table.get(name), Some(Value::Table(_))));
+        for (name, value) in table {
+            if let Value::Table(entries) = value {
+                let entries: BTreeMap<&str, &Value> = entries
+                    .iter()
+                    .map(|(key, value)| (key.as_str(), value))
+                    .collect();
+                if !doc.has_section(name) {
+                    doc.push_section(name)?;
+                }
+                doc.sync_scope(Some(name), &entries)?;
+            }
+        }
+
+        doc.value = target.clone();
+        *self = doc;
+        Ok(())
+    }
+
+    /// Flags the items belonging to `scope` (`None` is the root, before the
+    /// first header). Section headers count as part of their own scope.
+    fn scope_mask(&self, scope: Option<&str>) -> Vec<bool> {
+        let mut current = None;
+        self.items
+            .iter()
+            .map(|item| {
+                if let Item::Section(section) = item {
+                    current = Some(section.name.as_str());
+                }
+                current == scope
+            })
+            .collect()
+    }
+
+    fn sync_scope(&mut self, scope: Option<&str>, desired: &BTreeMap<&str, &Value>) -> Result<()> {
+        let mask = self.scope_mask(scope);
+        let stale: Vec<bool> = self
+            .items
+            .iter()
+            .zip(&mask)
+            .map(|(item, in_scope)| match item {
+                Item::Entry(entry) => *in_scope && !desired.contains_key(entry.key.as_str()),
+                Item::Section(_) => false,
+            })
+            .collect();
+        self.remove_items(&stale);
+
+        let mask = self.scope_mask(scope);
+        let mut missing = Vec::new();
+        for (key, value) in desired {
+            let last = self
+                .items
+                .iter_mut()
+                .enumerate()
+                .rev()
+                .find_map(|(i, item)| match item {
+                    Item::Entry(entry) if mask[i] && entry.key == *key => Some(entry),
+                    _ => None,
+                });
+            match last {
+                Some(entry) if entry.value != **value => {
+                    entry.raw = render_value(value, &entry.raw)?;
+                    entry.value = (*value).clone();
+                }
+                Some(_) => {}
+                None => missing.push((*key, *value)),
+            }
+        }
+        if missing.is_empty() {
+            return Ok(());
+        }
+
+        let at = match mask.iter().rposition(|in_scope| *in_scope) {
+            Some(last) => last + 1,
+            None => 0,
+        };
+        let (indent, separator) = self.items[..at]
+            .iter()
+            .rev()
+            .zip(mask[..at].iter().rev())
+            .find_map(|(item, in_scope)| match item {
+                Item::Entry(entry) if *in_scope => Some((
+                    entry.leading.rsplit('\n').next().unwrap_or("").to_string(),
+                    entry.separator.clone(),
+                )),
+                _ => None,
+            })
+            .unwrap_or_else(|| (String::new(), " = ".to_string()));
+
+        let mut new_items = Vec::with_capacity(missing.len());
+        for (key, value) in missing {
+            if !is_valid_key(key) {
+                return Err(Error::serialize(format!(
+                    "Cannot write key '{key}' as CONF"
+                )));
+            }
+            new_items.push(Item::Entry(Entry {
+                leading: indent.clone(),
+                key: key.to_string(),
+                separator: separator.clone(),
+                raw: render_value(value, "")?,
+                trailing: self.newline.to_string(),
+                value: value.clone(),
+            }));
+        }
+        if at > 0 {
+            self.end_line(at - 1);
+        }
+        self.items.splice(at..at, new_items);
+        Ok(())
+    }
+
+    fn has_section(&self, name: &str) -> bool {
+        self.items
+            .iter()
+            .any(|item| matches!(item, Item::Section(section) if section.name == name))
+    }
+
+    /// Drop every section whose name matches, with all of its entries.
+    fn remove_sections(&mut self, remove: impl Fn(&str) -> bool) {
+        let mut dropping = false;
+        let stale: Vec<bool> = self
+            .items
+            .iter()
+            .map(|item| {
+                if let Item::Section(section) = item {
+                    dropping = remove(&section.name);
+                }
+                dropping
+            })
+            .collect();
+        self.remove_items(&stale);
+    }
+
+    /// Remove the flagged items. Comments directly above a removed run go
+    /// with it; anything above the last blank line before the run (file
+    /// headers, notes about the previous block) is kept.
+    fn remove_items(&mut self, stale: &[bool]) {
+        if !stale.contains(&true) {
+            return;
+        }
+        let mut kept = Vec::with_capacity(self.items.len());
+        let mut carry = String::new();
+        let mut previous_removed = false;
+        for (item, remove) in std::mem::take(&mut self.items).into_iter().zip(stale) {
+            let leading = match &item {
+                Item::Section(section) => &section.leading,
+                Item::Entry(entry) => &entry.leading,
+            };
+            if *remove {
+                if !previous_removed {
+                    carry = detached_trivia(leading).to_string();
+                }
+                previous_removed = true;
+                continue;
+            }
+            previous_removed = false;
+            let mut item = item;
+            if !carry.is_empty() {
+                let leading = match &mut item {
+                    Item::Section(section) => &mut section.leading,
+                    Item::Entry(entry) => &mut entry.leading,
+                };
+                *leading = join_trivia(&std::mem::take(&mut carry), leading);
+            }
+            kept.push(item);
+        }
+        if !carry.is_empty() {
+            self.trailer = join_trivia(&carry, &self.trailer);
+        }
+        self.items = kept;
+    }
+
+    /// Append an empty `[name]` header, separated by a blank line.
+    fn push_section(&mut self, name: &str) -> Result<()> {
+        if name.contains([']', '\n', '\r']) || name.trim() != name {
+            return Err(Error::serialize(format!(
+                "Cannot write section '{name}' as CONF"
+            )));
+        }
+        let leading = if let Some(last) = self.items.len().checked_sub(1) {
+            self.end_line(last);
+            self.newline.to_string()
+        } else {
+            String::new()
+        };
+        self.items.push(Item::Section(Section {
+            leading,
+            header: format!("[{name}]"),
+            name: name.to_string(),
+            trailing: self.newline.to_string(),
+        }));
+        Ok(())
+    }
+
+    /// Make sure item `index` ends its line before something is inserted
+    /// after it.
+    fn end_line(&mut self, index: usize) {
+        let newline = self.newline;
+        let trailing = self.items[index].trailing_mut();
+        if !trailing.ends_with('\n') {
+            trailing.push_str(newline);
+        }
+    }
+
+    /// The document as [`crate::formatter`] lines, values in their
+    /// plainest spelling (booleans keep their `yes` / `on` wording).
+    pub(crate) fn lines(&self) -> Result<Vec<Line>> {
+        let comments = Comments {
+            markers: &['#'],
+            canonical: '#',
+        };
+        let mut lines = Vec::new();
+        for item in &self.items {
+            match item {
+                Item::Section(section) => {
+                    comments.push_lines(&section.leading, &mut lines);
+                    lines.push(Line::Section {
+                        name: section.name.clone(),
+                        comment: comments.inline(&section.trailing),
+                    });
+                }
+                Item::Entry(entry) => {
+                    comments.push_lines(&entry.leading, &mut lines);
+                    lines.push(Line::Entry {
+                        key: entry.key.clone(),
+                        value: render_value(&entry.value, boolean_spelling(entry))?,
+                        comment: comments.inline(&entry.trailing),
+                    });
+                }
+            }
+        }
+        comments.push_lines(&self.trailer, &mut lines);
+        Ok(lines)
+    }
+
+    /// Where each section header and key starts, for the document outline.
+    pub(crate) fn marks(&self) -> Vec<super::locate::Mark> {
+        use super::locate::Mark;
+
+        let mut marks = Vec::new();
+        let mut offset = 0;
+        let mut scope = String::new();
+        for item in &self.items {
+            match item {
+                Item::Section(section) => {
+                    offset += section.leading.len();
+                    marks.push(Mark::section(offset, &section.name));
+                    scope = crate::path::join_key("", &section.name);
+                    offset += section.header.len() + section.trailing.len();
+                }
+                Item::Entry(entry) => {
+                    offset += entry.leading.len();
+                    let path = crate::path::join_key(&scope, &entry.key);
+                    marks.push(Mark::entry(offset, &scope, &entry.key, path));
+                    offset += entry.key.len()
+                        + entry.separator.len()
+                        + entry.raw.len()
+                        + entry.trailing.len();
+                }
+            }
+        }
+        marks
+    }
+}
+
+impl fmt::Display for ConfDocument {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for item in &self.items {
+            match item {
+                Item::Section(section) => write!(
+                    f,
+                    "{}{}{}",
+                    section.leading, section.header, section.trailing
+                )?,
+                Item::Entry(entry) => write!(
+                    f,
+                    "{}{}{}{}{}",
+                    entry.leading, entry.key, entry.separator, entry.raw, entry.trailing
+                )?,
+            }
+        }
+        f.write_str(&self.trailer)
+    }
+}
+
+/// The part of an item's leading trivia up to and including its last blank
+/// line, i.e. what does not belong to the item itself.
+pub(crate) fn detached_trivia(leading: &str) -> &str {
+    let mut end = 0;
+    let mut offset = 0;
+    for line in leading.split_inclusive('\n') {
+        offset += line.len();
+        if line.ends_with('\n') && line.trim().is_empty() {
+            end = offset;
+        }
+    }
+    &leading[..end]
+}
+
+/// Prepend carried trivia to `next`, collapsing a doubled blank line.
+pub(crate) fn join_trivia(carry: &str, next: &str) -> String {
+    let next_starts_blank = next
+        .split_inclusive('\n')
+        .next()
+        .is_some_and(|line| line.ends_with('\n') && line.trim().is_empty());
+    let carry = if next_starts_blank {
+        let body = carry.strip_suffix('\n').unwrap_or(carry);
+        let body = body.strip_suffix('\r').unwrap_or(body);
+        match body.rfind('\n') {
+            Some(at) => &carry[..=at],
+            None => "",
+        }
+    } else {
+        carry
+    };
+    format!("{carry}{next}")
+}
+
+/// The raw text of a boolean entry, whose wording the formatter keeps.
+fn boolean_spelling(entry: &Entry) -> &str {
+    match entry.value {
+        Value::Bool(_) => &entry.raw,
+        _ => "",
+    }
+}
+
+/// Keys must match what [`ConfParser::parse_key`] accepts.
+fn is_valid_key(key: &str) -> bool {
+    !key.is_empty()
+        && key
+            .chars()
+            .all(|ch| ch.is_alphanumeric() || matches!(ch, '_' | '-' | '.'))
+}
+
+/// Write `value` as CONF text, preferring the style of `original` (the
+/// previous raw text, empty for new entries). Only spellings that parse
+/// back to exactly `value` are accepted.
+fn render_value(value: &Value, original: &str) -> Result<String> {
+    candidates(value, original)
+        .into_iter()
+        .find(|raw| reparse(raw).as_ref() == Some(value))
+        .ok_or_else(|| {
+            Error::serialize(format!(
+                "Cannot write {} value as CONF without changing it",
+                value.type_name()
+            ))
+        })
+}
+
+fn candidates(value: &Value, original: &str) -> Vec<String> {
+    match value {
+        Value::Null => vec!["null".to_string()],
+        Value::Bool(b) => {
+            let (yes, no) = match original.to_ascii_lowercase().as_str() {
+                "yes" | "no" => ("yes", "no"),
+                "on" | "off" => ("on", "off"),
+                _ => ("true", "false"),
+            };
+            vec![if *b { yes } else { no }.to_string()]
+        }
+        Value::Integer(i) => vec![i.to_string()],
+        Value::Float(f) => vec![f.to_string(), format!("{f:?}")],
+        Value::String(s) => match original.chars().next() {
+            Some('"') => vec![double_quote(s)],
+            Some('\'') => vec![format!("'{s}'"), double_quote(s)],
+            _ => vec![s.clone(), double_quote(s)],
+        },
+        Value::Array(items) => {
+            let mut out = Vec::new();
+            let bare: Option<Vec<String>> = items
+                .iter()
+                .map(|item| match item {
+                    Value::Array(_) | Value::Table(_) => None,
+                    _ => candidates(item, "").into_iter().next(),
+                })
+                .collect();
+            if let Some(bare) = bare {
+                out.push(bare.join(" "));
+                out.push(bare.join(", "));
+            }
+            let quoted: Option<Vec<String>> = items
+                .iter()
+                .map(|item| match item {
+                    Value::String(s) => Some(double_quote(s)),
+                    _ => None,
+                })
+                .collect();
+            if let Some(quoted) = quoted {
+                let bracketed = format!("[{}]", quoted.join(", "));
+                if original.starts_with('[') {
+                    out.insert(0, bracketed);
+                } else {
+                    out.push(bracketed);
+                }
+            }
+            out
+        }
+        Value::Table(_) => Vec::new(),
+        #[cfg(feature = "chrono")]
+        Value::DateTime(dt) => vec![dt.to_rfc3339()],
+    }
+}
+
+fn double_quote(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for ch in s.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+            _ => out.push(ch),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Parse a raw value the way an entry would, requiring it to be consumed
+/// entirely.
+fn reparse(raw: &str) -> Option<Value> {
+    let mut parser = ConfParser::new(raw);
+    let value = parser.parse_value().ok()?;
+    parser.skip_whitespace();
+    parser.is_at_end().then_some(value)
+}
+
 /// High-performance CONF parser with zero-allocation lexing
 /// CONF parser state
 struct ConfParser<'a> {
     input: &'a str,
+    /// Byte offset into `input`
     position: usize,
     line: usize,
     column: usize,
@@ -52,25 +630,49 @@ impl<'a> ConfParser<'a> {
     }
 
     /// Parse the entire configuration
-    fn parse(&mut self) -> Result<Value> {
+    fn parse(&mut self) -> Result<ConfDocument> {
         let mut root = BTreeMap::new();
         let mut current_section = None;
+        let mut items = Vec::new();
 
-        while !self.is_at_end() {
+        loop {
+            let mark = self.position;
             self.skip_whitespace_and_comments();
+            let leading = self.input[mark..self.position].to_string();
 
             if self.is_at_end() {
-                break;
+                return Ok(ConfDocument {
+                    items,
+                    trailer: leading,
+                    newline: if self.input.contains("\r\n") {
+                        "\r\n"
+                    } else {
+                        "\n"
+                    },
+                    value: Value::table(root),
+                });
             }
 
             // Check for section header
             if self.peek() == Some('[') {
-                current_section = Some(self.parse_section_header()?);
+                let start = self.position;
+                let name = self.parse_section_header()?;
+                let header = self.input[start..self.position].to_string();
+                let trailing = self.finish_line(self.position);
+                current_section = Some(name.clone());
+                items.push(Item::Section(Section {
+                    leading,
+                    header,
+                    name,
+                    trailing,
+                }));
                 continue;
             }
 
             // Parse key-value pair
-            let (key, value) = self.parse_key_value()?;
+            let entry = self.parse_entry(leading)?;
+            let (key, value) = (entry.key.clone(), entry.value.clone());
+            items.push(Item::Entry(entry));
 
             match &current_section {
                 Some(section) => {
@@ -89,8 +691,50 @@ impl<'a> ConfParser<'a> {
                 }
             }
         }
+    }
+
+    /// Parse a key-value pair together with its surrounding text
+    fn parse_entry(&mut self, leading: String) -> Result<Entry> {
+        let key = self.parse_key()?;
+        let separator_start = self.position;
+        self.skip_whitespace();
+        self.expect('=')?;
+        self.skip_whitespace();
+        let value_start = self.position;
+        let separator = self.input[separator_start..value_start].to_string();
+        let value = self.parse_value()?;
+        // Unquoted values run up to the comment; keep that padding as trivia.
+        let raw = self.input[value_start..self.position]
+            .trim_end()
+            .to_string();
+        let trailing = self.finish_line(value_start + raw.len());
+
+        Ok(Entry {
+            leading,
+            key,
+            separator,
+            raw,
+            trailing,
+            value,
+        })
+    }
 
-        Ok(Value::table(root))
+    /// Consume trailing whitespace, an inline comment and the line ending,
+    /// returning the text from `from` onwards.
+    fn finish_line(&mut self, from: usize) -> String {
+        self.skip_whitespace();
+        if self.peek() == Some('#') {
+            while !matches!(self.peek(), None | Some('\r' | '\n')) {
+                self.advance();
+            }
+        }
+        if self.peek() == Some('\r') && self.input[self.position..].starts_with("\r\n") {
+            self.advance();
+        }
+        if self.peek() == Some('\n') {
+            self.advance();
+        }
+        self.input[from..self.position].to_string()
     }
 
     /// Parse a section header like [section_name]
@@ -119,17 +763,6 @@ impl<'a> ConfParser<'a> {
         Ok(section_name)
     }
 
-    /// Parse a key-value pair
-    fn parse_key_value(&mut self) -> Result<(String, Value)> {
-        let key = self.parse_key()?;
-        self.skip_whitespace();
-        self.expect('=')?;
-        self.skip_whitespace();
-        let value = self.parse_value()?;
-
-        Ok((key, value))
-    }
-
     /// Parse a configuration key
     fn parse_key(&mut self) -> Result<String> {
         let start = self.position;
@@ -404,13 +1037,13 @@ impl<'a> ConfParser<'a> {
 
     /// Peek at the current character
     fn peek(&self) -> Option<char> {
-        self.input.chars().nth(self.position)
+        self.input[self.position..].chars().next()
     }
 
     /// Advance to the next character
     fn advance(&mut self) -> Option<char> {
         if let Some(ch) = self.peek() {
-            self.position += 1;
+            self.position += ch.len_utf8();
             if ch == '\n' {
                 self.line += 1;
                 self.column = 1;
@@ -501,4 +1134,58 @@ mod tests {
         let config = parse("# This is a comment\nkey = value # inline comment").unwrap();
         assert_eq!(config.get("key").unwrap().as_string().unwrap(), "value");
     }
+
+    const LAYOUT: &str = "# App settings\r\nname = \"demo app\"   # shown in UI\r\ndebug = yes\r\n\r\n[server]\r\n  host = 'localhost'\r\n  # tuned for prod\r\n  port=8080\r\n\r\n# café — trailing notes\r\n";
+
+    #[test]
+    fn test_document_round_trip() {
+        let doc = parse_document(LAYOUT).unwrap();
+        assert_eq!(doc.to_string(), LAYOUT);
+        assert_eq!(doc.value(), &parse(LAYOUT).unwrap());
+        assert_eq!(
+            doc.value()
+                .get("server.port")
+                .unwrap()
+                .as_integer()
+                .unwrap(),
+            8080
+        );
+    }
+
+    #[test]
+    fn test_document_edits_in_place() {
+        let mut doc = parse_document(LAYOUT).unwrap();
+        doc.set("name", Value::string("prod")).unwrap();
+        doc.set("debug", Value::bool(false)).unwrap();
+        doc.set("server.host", Value::string("it's")).unwrap();
+        doc.set("server.workers", Value::integer(4)).unwrap();
+        doc.remove("server.port").unwrap();
+        doc.set("cache.ttl", Value::float(1.0)).unwrap();
+        doc.set(
+            "tags",
+            Value::array(vec![Value::string("a b"), Value::string("c")]),
+        )
+        .unwrap();
+
+        let expected = "# App settings\r\nname = \"prod\"   # shown in UI\r\ndebug = no\r\ntags = [\"a b\", \"c\"]\r\n\r\n[server]\r\n  host = \"it's\"\r\n  workers=4\r\n\r\n[cache]\r\nttl = 1.0\r\n\r\n# café — trailing notes\r\n";
+        assert_eq!(doc.to_string(), expected);
+        assert_eq!(&parse(expected).unwrap(), doc.value());
+    }
+
+    #[test]
+    fn test_document_sections_and_errors() {
+        let mut doc = parse_document("a = 1\n[old]\nx = 1\n[keep]\ny = 2").unwrap();
+        doc.remove("old").unwrap();
+        doc.set("keep.z", Value::integer(3)).unwrap();
+        assert_eq!(doc.to_string(), "a = 1\n[keep]\ny = 2\nz = 3\n");
+
+        let mut spaced = parse_document("a = 1\n\n# about b\nb = 2\n\nc = 3\n").unwrap();
+        spaced.remove("b").unwrap();
+        assert_eq!(spaced.to_string(), "a = 1\n\nc = 3\n");
+
+        let before = doc.to_string();
+        assert!(doc.set("keep.nested.deep", Value::integer(1)).is_err());
+        assert!(doc.set("\"bad key\"", Value::integer(1)).is_err());
+        assert_eq!(doc.to_string(), before);
+    }
 }
@@ -0,0 +1,852 @@
+//! Dotenv (`.env`) format parser and writer
+//!
+//! Supports the syntax shared by the common dotenv loaders:
+//! - `KEY=value` lines, with an optional `export ` prefix
+//! - Comments: `# comment` lines, and ` # comment` after a value
+//! - Single-quoted values, taken literally
+//! - Double-quoted values with `\n`, `\r`, `\t`, `\"`, `\\` and `\$`
+//!   escapes, which may span several lines
+//! - `${VAR}`, `$VAR`, `${VAR:-default}` and `${VAR-default}` expansion in
+//!   unquoted and double-quoted values
+//!
+//! Variables expand to earlier definitions in the same file, then to the
+//! process environment (read, never modified); [`parse_with`] takes any
+//! other lookup. Unset variables expand to an empty string.
+//!
+//! Values are stored as strings in a flat table keyed by variable name.
+//! [`parse_vars`] returns the same pairs for
+//! [`EnvOverrideSystem::with_vars`](crate::env_override::EnvOverrideSystem::with_vars),
+//! and [`parse_document`] keeps the source layout so edited files can be
+//! saved with untouched lines byte-identical.
+//!
+//! [`parse_with`]: crate::parsers::dotenv_parser::parse_with
+//! [`parse_vars`]: crate::parsers::dotenv_parser::parse_vars
+//! [`parse_document`]: crate::parsers::dotenv_parser::parse_document
+
+use super::conf::{detached_trivia, join_trivia};
+use crate::error::{Error, Result};
+use crate::value::Value;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Deepest `${A:-${B:-...}}` default nesting accepted
+const MAX_DEPTH: usize = 32;
+
+/// Most bytes variable expansion may produce in one file, so chained
+/// references (`B=$A$A`, `C=$B$B`, ...) cannot exhaust memory
+const MAX_EXPANSION: usize = 16 * 1024 * 1024;
+
+/// Parse dotenv format configuration
+pub fn parse(source: &str) -> Result<Value> {
+    Ok(parse_document(source)?.into_value())
+}
+
+/// Parse dotenv source, resolving variables not defined in the file
+/// through `lookup` instead of the process environment
+pub fn parse_with(source: &str, lookup: &dyn Fn(&str) -> Option<String>) -> Result<Value> {
+    let document = DotenvParser::new(source, lookup).parse()?;
+    Ok(document.into_value())
+}
+
+/// Parse dotenv source into `(name, value)` pairs, later definitions
+/// replacing earlier ones
+pub fn parse_vars(source: &str) -> Result<BTreeMap<String, String>> {
+    let document = parse_document(source)?;
+    Ok(document
+        .entries
+        .into_iter()
+        .map(|entry| (entry.key, entry.value))
+        .collect())
+}
+
+/// Parse dotenv source, keeping its layout for in-place edits
+pub fn parse_document(source: &str) -> Result<DotenvDocument> {
+    DotenvParser::new(source, &process_env).parse()
+}
+
+fn process_env(name: &str) -> Option<String> {
+    std::env::var(name).ok()
+}
+
+/// Serialize a value tree as dotenv.
+///
+/// Top-level keys are written as they are; nested tables and arrays are
+/// flattened the way [`EnvOverrideSystem`](crate::env_override::EnvOverrideSystem)
+/// names variables, so `database.host` becomes `DATABASE_HOST` and
+/// `servers[0]` becomes `SERVERS_0`. Values that are not plain words are
+/// double-quoted, with `$` escaped so nothing expands on reload. `Null` is
+/// written as an empty value.
+///
+/// # Errors
+///
+/// Returns [`Error::General`] if the root is not a table, a name is not a
+/// valid variable name, or two paths flatten to the same name.
+pub fn serialize(value: &Value) -> Result<String> {
+    let mut out = String::new();
+    for (key, value) in flatten(value)? {
+        out.push_str(&key);
+        out.push('=');
+        out.push_str(&render_value(&value));
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+/// A parsed `.env` file that remembers its layout
+///
+/// Each entry keeps the text around it (leading comments and blank lines,
+/// `export` prefix, inline comment). [`set`](Self::set),
+/// [`remove`](Self::remove) and [`sync`](Self::sync) rewrite only the
+/// affected lines; new variables are appended, following the file's use of
+/// `export`.
+#[derive(Debug, Clone)]
+pub struct DotenvDocument {
+    entries: Vec<Entry>,
+    /// Comments and blank lines after the last entry.
+    trailer: String,
+    newline: &'static str,
+    value: Value,
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    /// Comments, blank lines and indentation before the entry.
+    leading: String,
+    /// `export `, the name and the `=` with its surrounding spaces.
+    head: String,
+    key: String,
+    /// The value as written, including quotes.
+    raw: String,
+    /// Whitespace, inline comment and line ending after the value.
+    trailing: String,
+    /// The value after unquoting and expansion.
+    value: String,
+}
+
+impl DotenvDocument {
+    /// The parsed values (a flat table of strings).
+    pub fn value(&self) -> &Value {
+        &self.value
+    }
+
+    /// Consume the document, keeping only the values.
+    pub fn into_value(self) -> Value {
+        self.value
+    }
+
+    /// Set variable `key`, editing its line in place or appending it.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::General`] if `key` is not a valid variable name
+    /// or the value is a table or array; the document is unchanged in that
+    /// case.
+    pub fn set(&mut self, key: &str, value: Value) -> Result<()> {
+        let mut target = self.value.clone();
+        if let Value::Table(table) = &mut target {
+            table.insert(key.to_string(), value);
+        }
+        self.sync(&target)
+    }
+
+    /// Remove variable `key` along with the comments directly above it.
+    pub fn remove(&mut self, key: &str) -> Option<Value> {
+        let mut target = self.value.clone();
+        let removed = match &mut target {
+            Value::Table(table) => table.remove(key)?,
+            _ => return None,
+        };
+        // Removing an entry never needs to render anything.
+        let _ = self.sync(&target);
+        Some(removed)
+    }
+
+    /// Reconcile the document with `target`, which may be flat (as parsed)
+    /// or nested (flattened as by [`serialize`]).
+    ///
+    /// A value that other entries expand (`URL=http://${HOST}`) changing
+    /// would change them too, so those are rewritten with their current
+    /// value spelled out.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `target` is not a table or cannot be written as
+    /// dotenv; the document is unchanged in that case.
+    pub fn sync(&mut self, target: &Value) -> Result<()> {
+        let desired = flatten(target)?;
+        let mut doc = self.clone();
+        doc.remove_stale(&desired);
+
+        let mut last = BTreeMap::new();
+        for (index, entry) in doc.entries.iter().enumerate() {
+            last.insert(entry.key.clone(), index);
+        }
+        let export = doc
+            .entries
+            .last()
+            .is_some_and(|entry| entry.head.trim_start().starts_with("export"));
+        for (key, value) in &desired {
+            match last.get(key) {
+                Some(&index) => {
+                    let entry = &mut doc.entries[index];
+                    if entry.value != *value {
+                        entry.raw = render_value(value);
+                        entry.value.clone_from(value);
+                    }
+                }
+                None => {
+                    if let Some(previous) = doc.entries.last_mut() {
+                        if !previous.trailing.ends_with('\n') {
+                            previous.trailing.push_str(doc.newline);
+                        }
+                    }
+                    let export = if export { "export " } else { "" };
+                    doc.entries.push(Entry {
+                        leading: String::new(),
+                        head: format!("{export}{key}="),
+                        key: key.clone(),
+                        raw: render_value(value),
+                        trailing: doc.newline.to_string(),
+                        value: value.clone(),
+                    });
+                }
+            }
+        }
+
+        // Entries expanding a changed variable now read differently:
+        // spell out their values.
+        let reparsed = parse_document(&doc.to_string())?;
+        for (entry, now) in doc.entries.iter_mut().zip(&reparsed.entries) {
+            if now.value != entry.value {
+                entry.raw = render_value(&entry.value);
+            }
+        }
+
+        *self = parse_document(&doc.to_string())?;
+        Ok(())
+    }
+
+    /// Drop entries whose key is not in `desired`, keeping comments that
+    /// are separated from them by a blank line.
+    fn remove_stale(&mut self, desired: &BTreeMap<String, String>) {
+        let mut kept = Vec::with_capacity(self.entries.len());
+        let mut carry = String::new();
+        let mut previous_removed = false;
+        for mut entry in std::mem::take(&mut self.entries) {
+            if !desired.contains_key(&entry.key) {
+                if !previous_removed {
+                    carry = detached_trivia(&entry.leading).to_string();
+                }
+                previous_removed = true;
+                continue;
+            }
+            previous_removed = false;
+            if !carry.is_empty() {
+                entry.leading = join_trivia(&std::mem::take(&mut carry), &entry.leading);
+            }
+            kept.push(entry);
+        }
+        if !carry.is_empty() {
+            self.trailer = join_trivia(&carry, &self.trailer);
+        }
+        self.entries = kept;
+    }
+
+    /// Where each variable name starts, for the document outline.
+    pub(crate) fn marks(&self) -> Vec<super::locate::Mark> {
+        let mut marks = Vec::new();
+        let mut offset = 0;
+        for entry in &self.entries {
+            // The head is `[export ]KEY=` with optional blanks around each part
+            let name = entry.head.trim_start_matches([' ', '\t']);
+            let name = match name.strip_prefix("export") {
+                Some(after) if after.starts_with([' ', '\t']) => {
+                    after.trim_start_matches([' ', '\t'])
+                }
+                _ => name,
+            };
+            let at = offset + entry.leading.len() + entry.head.len() - name.len();
+            let path = crate::path::join_key("", &entry.key);
+            marks.push(super::locate::Mark::entry(at, "", &entry.key, path));
+            offset +=
+                entry.leading.len() + entry.head.len() + entry.raw.len() + entry.trailing.len();
+        }
+        marks
+    }
+}
+
+impl fmt::Display for DotenvDocument {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for entry in &self.entries {
+            f.write_str(&entry.leading)?;
+            f.write_str(&entry.head)?;
+            f.write_str(&entry.raw)?;
+            f.write_str(&entry.trailing)?;
+        }
+        f.write_str(&self.trailer)
+    }
+}
+
+/// Flatten `value` into variable names and string values.
+fn flatten(value: &Value) -> Result<BTreeMap<String, String>> {
+    fn nested(value: &Value, name: String, out: &mut BTreeMap<String, String>) -> Result<()> {
+        match value {
+            Value::Table(table) => {
+                for (key, value) in table {
+                    nested(value, format!("{name}_{}", key.to_uppercase()), out)?;
+                }
+                Ok(())
+            }
+            Value::Array(items) => {
+                for (index, value) in items.iter().enumerate() {
+                    nested(value, format!("{name}_{index}"), out)?;
+                }
+                Ok(())
+            }
+            scalar => insert(name, scalar, out),
+        }
+    }
+
+    fn insert(name: String, value: &Value, out: &mut BTreeMap<String, String>) -> Result<()> {
+        if !is_valid_key(&name) {
+            return Err(Error::serialize(format!(
+                "Cannot write '{name}' as a dotenv variable name"
+            )));
+        }
+        let text = match value {
+            Value::Null => String::new(),
+            Value::String(s) => s.clone(),
+            other => other.to_string(),
+        };
+        if out.insert(name.clone(), text).is_some() {
+            return Err(Error::serialize(format!(
+                "More than one value flattens to dotenv variable '{name}'"
+            )));
+        }
+        Ok(())
+    }
+
+    let Value::Table(table) = value else {
+        return Err(Error::serialize("Dotenv root must be a table".to_string()));
+    };
+    let mut out = BTreeMap::new();
+    for (key, value) in table {
+        match value {
+            Value::Table(_) | Value::Array(_) => nested(value, key.to_uppercase(), &mut out)?,
+            scalar => insert(key.clone(), scalar, &mut out)?,
+        }
+    }
+    Ok(out)
+}
+
+/// Write `value` bare when it is a plain word, else double-quoted.
+fn render_value(value: &str) -> String {
+    let plain = value.chars().all(|ch| {
+        ch.is_ascii_alphanumeric() || matches!(ch, '_' | '-' | '.' | '/' | ':' | '@' | '+' | ',')
+    });
+    if plain {
+        return value.to_string();
+    }
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+    for ch in value.chars() {
+        match ch {
+            '\\' => out.push_str("\\\\"),
+            '"' => out.push_str("\\\""),
+            '$' => out.push_str("\\$"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            ch => out.push(ch),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Names must match what [`DotenvParser`] accepts.
+fn is_valid_key(key: &str) -> bool {
+    let mut chars = key.chars();
+    chars
+        .next()
+        .is_some_and(|ch| ch.is_ascii_alphabetic() || ch == '_')
+        && chars.all(|ch| ch.is_ascii_alphanumeric() || matches!(ch, '_' | '.' | '-'))
+}
+
+/// Offset of the `}` closing a `${`, skipping nested `${...}` in defaults.
+fn closing_brace(body: &str) -> Option<usize> {
+    let mut depth = 0usize;
+    let mut previous = '\0';
+    for (at, ch) in body.char_indices() {
+        match ch {
+            '{' if previous == '$' => depth += 1,
+            '}' if depth == 0 => return Some(at),
+            '}' => depth -= 1,
+            _ => {}
+        }
+        previous = ch;
+    }
+    None
+}
+
+fn is_name_char(ch: char) -> bool {
+    ch.is_ascii_alphanumeric() || ch == '_'
+}
+
+/// Line-oriented dotenv parser
+struct DotenvParser<'a> {
+    source: &'a str,
+    pos: usize,
+    line: usize,
+    lookup: &'a dyn Fn(&str) -> Option<String>,
+    /// Variables defined so far, for expansion
+    defined: BTreeMap<String, String>,
+    /// Bytes produced by expansion so far
+    expanded: usize,
+    /// Nesting of `${VAR:-default}` defaults being expanded
+    depth: usize,
+}
+
+impl<'a> DotenvParser<'a> {
+    fn new(source: &'a str, lookup: &'a dyn Fn(&str) -> Option<String>) -> Self {
+        Self {
+            source,
+            pos: 0,
+            line: 1,
+            lookup,
+            defined: BTreeMap::new(),
+            expanded: 0,
+            depth: 0,
+        }
+    }
+
+    fn parse(mut self) -> Result<DotenvDocument> {
+        let newline = if self.source.contains("\r\n") {
+            "\r\n"
+        } else {
+            "\n"
+        };
+        let mut entries = Vec::new();
+        let mut leading_start = 0;
+        while self.pos < self.source.len() {
+            let line_start = self.pos;
+            let line_end = self.source[line_start..]
+                .find('\n')
+                .map_or(self.source.len(), |i| line_start + i + 1);
+            let content = self.source[line_start..line_end].trim();
+            if content.is_empty() || content.starts_with('#') {
+                self.pos = line_end;
+                self.line += 1;
+                continue;
+            }
+            let leading = self.source[leading_start..line_start].to_string();
+            let entry = self.entry(leading)?;
+            self.defined.insert(entry.key.clone(), entry.value.clone());
+            entries.push(entry);
+            leading_start = self.pos;
+        }
+
+        let table = entries
+            .iter()
+            .map(|entry| (entry.key.clone(), Value::String(entry.value.clone())))
+            .collect();
+        Ok(DotenvDocument {
+            entries,
+            trailer: self.source[leading_start..].to_string(),
+            newline,
+            value: Value::Table(table),
+        })
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.source[self.pos..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn skip_blanks(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start_matches([' ', '\t']).len();
+    }
+
+    /// Parse one `[export] KEY=value` entry, starting at its line.
+    fn entry(&mut self, leading: String) -> Result<Entry> {
+        let head_start = self.pos;
+        self.skip_blanks();
+        if let Some(after) = self.rest().strip_prefix("export") {
+            if after.starts_with([' ', '\t']) {
+                self.pos += "export".len();
+                self.skip_blanks();
+            }
+        }
+
+        let key_len = self
+            .rest()
+            .find(|ch: char| !(is_name_char(ch) || matches!(ch, '.' | '-')))
+            .unwrap_or(self.rest().len());
+        let key = self.rest()[..key_len].to_string();
+        if !is_valid_key(&key) {
+            return Err(self.error("expected a variable name"));
+        }
+        self.pos += key_len;
+        self.skip_blanks();
+        if self.peek() != Some('=') {
+            return Err(self.error(format!("expected `=` after `{key}`")));
+        }
+        self.pos += 1;
+        self.skip_blanks();
+        let head = self.source[head_start..self.pos].to_string();
+
+        let raw_start = self.pos;
+        let value = match self.peek() {
+            Some('\'') => self.single_quoted()?,
+            Some('"') => self.double_quoted()?,
+            _ => self.unquoted()?,
+        };
+        let raw = self.source[raw_start..self.pos].to_string();
+
+        // Only whitespace and a comment may follow the value
+        let trailing_start = self.pos;
+        self.skip_blanks();
+        match self.peek() {
+            None | Some('\n' | '\r' | '#') => {}
+            Some(ch) => return Err(self.error(format!("unexpected {ch:?} after value"))),
+        }
+        let end = self
+            .rest()
+            .find('\n')
+            .map_or(self.source.len(), |i| self.pos + i + 1);
+        self.pos = end;
+        self.line += 1;
+
+        Ok(Entry {
+            leading,
+            head,
+            key,
+            raw,
+            trailing: self.source[trailing_start..end].to_string(),
+            value,
+        })
+    }
+
+    fn single_quoted(&mut self) -> Result<String> {
+        let start = self.pos;
+        let start_line = self.line;
+        let body = &self.rest()[1..];
+        let Some(len) = body.find('\'') else {
+            return Err(self.error_on(start_line, "unterminated single-quoted value"));
+        };
+        let value = body[..len].to_string();
+        self.line += value.matches('\n').count();
+        self.pos = start + len + 2;
+        Ok(value)
+    }
+
+    fn double_quoted(&mut self) -> Result<String> {
+        let start_line = self.line;
+        self.pos += 1;
+        let mut out = String::new();
+        loop {
+            let Some(ch) = self.peek() else {
+                return Err(self.error_on(start_line, "unterminated double-quoted value"));
+            };
+            self.pos += ch.len_utf8();
+            match ch {
+                '"' => return Ok(out),
+                '\\' => {
+                    let escaped = match self.peek() {
+                        Some('n') => Some('\n'),
+                        Some('r') => Some('\r'),
+                        Some('t') => Some('\t'),
+                        Some(ch @ ('"' | '\\' | '$')) => Some(ch),
+                        _ => None,
+                    };
+                    match escaped {
+                        Some(decoded) => {
+                            out.push(decoded);
+                            self.pos += 1;
+                        }
+                        // Other backslashes are kept as written
+                        None => out.push('\\'),
+                    }
+                }
+                '$' => self.expand(&mut out)?,
+                '\n' => {
+                    self.line += 1;
+                    out.push(ch);
+                }
+                ch => out.push(ch),
+            }
+        }
+    }
+
+    fn unquoted(&mut self) -> Result<String> {
+        let rest = self.rest();
+        let line_len = rest.find(['\r', '\n']).unwrap_or(rest.len());
+        // A `#` after whitespace starts a comment
+        let mut len = line_len;
+        let bytes = rest.as_bytes();
+        for (i, &b) in bytes[..line_len].iter().enumerate() {
+            if b == b'#' && i > 0 && matches!(bytes[i - 1], b' ' | b'\t') {
+                len = i;
+                break;
+            }
+        }
+        let text = rest[..len].trim_end_matches([' ', '\t']);
+        let end = self.pos + text.len();
+        let mut out = String::new();
+        while self.pos < end {
+            let Some(ch) = self.peek() else { break };
+            self.pos += ch.len_utf8();
+            if ch == '$' {
+                self.expand_within(&mut out, end)?;
+            } else {
+                out.push(ch);
+            }
+        }
+        Ok(out)
+    }
+
+    /// Expand a variable reference after `$` inside a double-quoted value.
+    fn expand(&mut self, out: &mut String) -> Result<()> {
+        self.expand_within(out, self.source.len())
+    }
+
+    /// Expand a variable reference after `$`, reading no further than
+    /// `end`. A `$` not followed by a name is kept as written.
+    fn expand_within(&mut self, out: &mut String, end: usize) -> Result<()> {
+        let rest = &self.source[self.pos..end];
+        if let Some(body) = rest.strip_prefix('{') {
+            let Some(close) = closing_brace(body) else {
+                return Err(self.error("unterminated `${`"));
+            };
+            let inner = &body[..close];
+            let name_len = inner
+                .find(|ch: char| !is_name_char(ch))
+                .unwrap_or(inner.len());
+            let (name, modifier) = inner.split_at(name_len);
+            if name.is_empty() {
+                return Err(self.error(format!("invalid variable reference `${{{inner}}}`")));
+            }
+            let value = self.resolve(name);
+            let expanded = match (modifier.strip_prefix(":-"), modifier.strip_prefix('-')) {
+                _ if modifier.is_empty() => value.unwrap_or_default(),
+                (Some(default), _) => match value {
+                    Some(value) if !value.is_empty() => value,
+                    _ => self.expand_default(default)?,
+                },
+                (None, Some(default)) => match value {
+                    Some(value) => value,
+                    None => self.expand_default(default)?,
+                },
+                (None, None) => {
+                    return Err(self.error(format!("invalid variable reference `${{{inner}}}`")))
+                }
+            };
+            self.push_expansion(out, &expanded)?;
+            self.pos += close + 2;
+            return Ok(());
+        }
+
+        let name_len = rest
+            .find(|ch: char| !is_name_char(ch))
+            .unwrap_or(rest.len());
+        let name = &rest[..name_len];
+        if name.is_empty() || name.starts_with(|ch: char| ch.is_ascii_digit()) {
+            out.push('$');
+            return Ok(());
+        }
+        let expanded = self.resolve(name).unwrap_or_default();
+        self.push_expansion(out, &expanded)?;
+        self.pos += name_len;
+        Ok(())
+    }
+
+    fn push_expansion(&mut self, out: &mut String, expanded: &str) -> Result<()> {
+        self.expanded += expanded.len();
+        if self.expanded > MAX_EXPANSION {
+            return Err(self.error(format!("variable expansion exceeds {MAX_EXPANSION} bytes")));
+        }
+        out.push_str(expanded);
+        Ok(())
+    }
+
+    /// Expand variables in a `${VAR:-default}` default.
+    fn expand_default(&mut self, default: &str) -> Result<String> {
+        if self.depth >= MAX_DEPTH {
+            return Err(self.error(format!(
+                "variable defaults nested deeper than {MAX_DEPTH} levels"
+            )));
+        }
+        let mut nested = DotenvParser {
+            source: default,
+            pos: 0,
+            line: self.line,
+            lookup: self.lookup,
+            defined: std::mem::take(&mut self.defined),
+            expanded: self.expanded,
+            depth: self.depth + 1,
+        };
+        let mut out = String::new();
+        let mut result = Ok(());
+        while let Some(ch) = nested.peek() {
+            nested.pos += ch.len_utf8();
+            if ch == '$' {
+                result = nested.expand(&mut out);
+                if result.is_err() {
+                    break;
+                }
+            } else {
+                out.push(ch);
+            }
+        }
+        self.defined = nested.defined;
+        self.expanded = nested.expanded;
+        result.map(|()| out)
+    }
+
+    fn resolve(&self, name: &str) -> Option<String> {
+        self.defined
+            .get(name)
+            .cloned()
+            .or_else(|| (self.lookup)(name))
+    }
+
+    fn error(&self, message: impl Into<String>) -> Error {
+        let line_start = self.source[..self.pos].rfind('\n').map_or(0, |i| i + 1);
+        let column = self.source[line_start..self.pos].chars().count() + 1;
+        Error::parse(message, self.line, column)
+    }
+
+    fn error_on(&self, line: usize, message: impl Into<String>) -> Error {
+        Error::parse(message, line, 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn no_env(_: &str) -> Option<String> {
+        None
+    }
+
+    #[test]
+    fn test_dotenv_syntax() -> crate::Result<()> {
+        let source = r#"# Database
+export DB_HOST=localhost
+DB_PORT = 5432   # inline comment
+DB_URL="postgres://${DB_HOST}:$DB_PORT/app"
+LITERAL='no ${expansion} \n here'
+EMPTY=
+HASH=a#b
+CERT="-----BEGIN-----
+abc
+-----END-----"
+ESCAPES="tab\there \"quoted\" \$HOME"
+FALLBACK=${MISSING:-default-${DB_HOST}}
+UNSET=[${MISSING}]
+"#;
+        let value = parse_with(source, &no_env)?;
+        let get = |key: &str| value.get(key).unwrap().as_string().unwrap().to_string();
+        assert_eq!(get("DB_HOST"), "localhost");
+        assert_eq!(get("DB_PORT"), "5432");
+        assert_eq!(get("DB_URL"), "postgres://localhost:5432/app");
+        assert_eq!(get("LITERAL"), "no ${expansion} \\n here");
+        assert_eq!(get("EMPTY"), "");
+        assert_eq!(get("HASH"), "a#b");
+        assert_eq!(get("CERT"), "-----BEGIN-----\nabc\n-----END-----");
+        assert_eq!(get("ESCAPES"), "tab\there \"quoted\" $HOME");
+        assert_eq!(get("FALLBACK"), "default-localhost");
+        assert_eq!(get("UNSET"), "[]");
+
+        let lookup = |name: &str| (name == "HOME").then(|| "/home/app".to_string());
+        let value = parse_with(
+            "CACHE=${HOME}/.cache\nHOME=/srv\nDATA=$HOME/data\n",
+            &lookup,
+        )?;
+        assert_eq!(value.get("CACHE").unwrap().as_string()?, "/home/app/.cache");
+        assert_eq!(value.get("DATA").unwrap().as_string()?, "/srv/data");
+        Ok(())
+    }
+
+    #[test]
+    fn test_dotenv_errors() {
+        let nested = format!("A={}x{}\n", "${B:-".repeat(40), "}".repeat(40));
+        // Each line doubles the previous one
+        let mut doubling = String::from("V0=xxxxxxxxxxxxxxxx\n");
+        for i in 1..40 {
+            doubling.push_str(&format!("V{i}=$V{}$V{}\n", i - 1, i - 1));
+        }
+        for (source, line) in [
+            ("A=1\nnot a line\n", 2),
+            ("A=\"open\nB=2\n", 1),
+            ("A='x' trailing\n", 1),
+            ("1A=x\n", 1),
+            ("A=${B\n", 1),
+            (nested.as_str(), 1),
+            (doubling.as_str(), 21),
+        ] {
+            match parse_with(source, &no_env) {
+                Err(Error::Parse { line: got, .. }) => assert_eq!(got, line, "{source:?}"),
+                other => panic!("{source:?}: expected a parse error, got {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_document_edits_keep_layout() -> crate::Result<()> {
+        let source = "# App\nexport HOST=localhost # dev box\nexport URL=http://${HOST}:8080\n\n# Secrets\nexport TOKEN='abc'\n";
+        let mut document = parse_document(source)?;
+        document.set("HOST", Value::string("example.com"))?;
+        document.set("GREETING", Value::string("hello world"))?;
+        document.remove("TOKEN");
+
+        assert_eq!(
+            document.to_string(),
+            "# App\nexport HOST=example.com # dev box\nexport URL=http://localhost:8080\n\nexport GREETING=\"hello world\"\n"
+        );
+        assert_eq!(
+            document.value().get("URL").unwrap().as_string()?,
+            "http://localhost:8080"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_serialize_round_trip() -> crate::Result<()> {
+        let mut database = BTreeMap::new();
+        database.insert("host".to_string(), Value::string("db"));
+        database.insert("port".to_string(), Value::integer(5432));
+        let mut root = BTreeMap::new();
+        root.insert("database".to_string(), Value::table(database));
+        root.insert("motd".to_string(), Value::string("cost: $5\n\"ok\""));
+        root.insert(
+            "servers".to_string(),
+            Value::array(vec![Value::string("a"), Value::string("b")]),
+        );
+        root.insert("debug".to_string(), Value::bool(true));
+
+        let text = serialize(&Value::table(root))?;
+        assert_eq!(
+            text,
+            "DATABASE_HOST=db\nDATABASE_PORT=5432\nSERVERS_0=a\nSERVERS_1=b\ndebug=true\nmotd=\"cost: \\$5\\n\\\"ok\\\"\"\n"
+        );
+        let reparsed = parse_with(&text, &no_env)?;
+        assert_eq!(
+            reparsed.get("motd").unwrap().as_string()?,
+            "cost: $5\n\"ok\""
+        );
+
+        let mut clash = BTreeMap::new();
+        clash.insert("A_B".to_string(), Value::integer(1));
+        clash.insert(
+            "a".to_string(),
+            Value::table(BTreeMap::from([("b".to_string(), Value::integer(2))])),
+        );
+        assert!(serialize(&Value::table(clash)).is_err());
+        Ok(())
+    }
+}
@@ -1,151 +1,2151 @@
-//! # HCL Configuration Parser  
+//! # HCL Configuration Parser
 //!
-//! HashiCorp Configuration Language parser for DevOps/Infrastructure configurations.
-//! Extremely popular in cloud-native environments (Terraform, Vault, Consul, Nomad).
+//! HashiCorp Configuration Language (HCL2 native syntax) parser for
+//! DevOps/Infrastructure configurations: Terraform, Nomad, Vault, Consul
+//! and Packer files.
 //!
-//! ## Performance Features
-//! - Simple HCL parsing for basic key-value configurations
-//! - Feature-gated to ensure zero impact when disabled
-//! - Supports basic HCL syntax patterns
+//! ## Value mapping
 //!
-//! ## Supported HCL Patterns
-//! - Basic key-value assignments
-//! - String, integer, float, and boolean values
-//! - Comments with # and //
+//! | HCL                                    | `Value`                                   |
+//! |----------------------------------------|-------------------------------------------|
+//! | `name = expr`                          | `name` entry holding the evaluated value  |
+//! | `type { ... }`                         | `type` table holding the block body       |
+//! | `type "a" "b" { ... }`                 | nested tables: the body is at `type.a.b`  |
+//! | the same block type and labels again   | an array of the bodies, in source order   |
+//! | `[a, b]` / `{ k = v }`                 | `Array` / `Table`                         |
+//! | `"..."` and `<<EOT` / `<<-EOT` heredocs | `String`                                 |
+//! | numbers, `true` / `false`, `null`      | `Integer` or `Float`, `Bool`, `Null`      |
+//!
+//! Blocks of one type with different labels share the type's table, so
+//! two `resource "aws_instance" ...` blocks end up side by side under
+//! `resource.aws_instance`. Defining an attribute twice, or an attribute
+//! and a block with the same name, is an error.
+//!
+//! ## Expressions
+//!
+//! Expressions that don't need variables are evaluated: arithmetic,
+//! comparison and logic operators, conditionals, string templates,
+//! indexing, splats and `for` expressions over literal collections.
+//! Anything that needs a variable or a function call (`var.region`,
+//! `upper(name)`) is kept as its source text wrapped in `${...}`, the form
+//! Terraform's JSON syntax uses. Inside a string only the unresolved
+//! interpolation is kept that way (`"web-${count.index}"`), and template
+//! directives (`%{ if }`, `%{ for }`) are kept as written.
+//!
+//! Errors report the line and column of the offending input.
 
+use super::locate::{self, Mark};
 use crate::{Error, Result, Value};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 
-/// HCL configuration parser for HashiCorp Configuration Language
-#[cfg(feature = "hcl")]
-pub struct HclParser<'a> {
-    content: &'a str,
-}
+/// Deepest nesting of blocks and expressions accepted
+const MAX_DEPTH: usize = 128;
 
 /// Parse HCL format configuration
-#[cfg(feature = "hcl")]
 pub fn parse(source: &str) -> Result<Value> {
     parse_hcl(source)
 }
 
-/// Parse HCL format configuration (fallback when HCL is not available)
-#[cfg(not(feature = "hcl"))]
-pub fn parse(_source: &str) -> Result<Value> {
-    Err(crate::Error::feature_not_enabled("hcl"))
-}
-
 /// Parse HCL configuration from string
-#[cfg(feature = "hcl")]
 pub fn parse_hcl(content: &str) -> Result<Value> {
     let mut parser = HclParser::new(content);
     parser.parse()
 }
 
+/// Where each attribute and block starts, for the document outline.
+pub(crate) fn marks(source: &str) -> Result<Vec<Mark>> {
+    let mut parser = HclParser::new(source);
+    parser.marks = Some(Vec::new());
+    let value = parser.parse()?;
+    Ok(parser
+        .marks
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|(offset, steps)| locate::resolve(&value, *offset, steps))
+        .collect())
+}
+
+/// HCL configuration parser for HashiCorp Configuration Language
+pub struct HclParser<'a> {
+    source: &'a str,
+    pos: usize,
+    /// Whether newlines are plain whitespace (inside brackets, parentheses
+    /// and interpolations) rather than separators
+    multiline: bool,
+    depth: usize,
+    layout: Layout,
+    /// Whether any comment was skipped
+    comments: bool,
+    /// Whether an operator, traversal, conditional or `for` expression
+    /// was evaluated into a value, losing its source form
+    folded: bool,
+    /// Where each attribute and block starts, when asked for; block
+    /// indices are resolved once the whole file is read
+    marks: Option<Vec<(usize, Vec<locate::Step>)>>,
+    /// Steps to the body being read
+    steps: Vec<locate::Step>,
+}
+
+/// Which body entries of a parsed file were blocks and which attributes,
+/// keyed by their path with block labels included and array indices left
+/// out (repeated blocks share one path)
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Layout {
+    /// Block type paths and their label counts
+    blocks: BTreeMap<Vec<String>, usize>,
+    attributes: BTreeSet<Vec<String>>,
+    /// Entry names of each body, and labels under each block type, in
+    /// source order
+    order: BTreeMap<Vec<String>, Vec<String>>,
+}
+
+impl Layout {
+    /// Note `name` under `path`, in the order first seen.
+    fn record(&mut self, path: &[String], name: &str) {
+        let names = self.order.entry(path.to_vec()).or_default();
+        if !names.iter().any(|known| known == name) {
+            names.push(name.to_string());
+        }
+    }
+}
+
+/// How a body entry was defined
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    Attribute,
+    /// A table of block labels
+    Labels,
+    Block,
+}
+
+/// A parsed expression and the source span it came from
+struct Expr {
+    kind: ExprKind,
+    start: usize,
+    end: usize,
+}
+
+enum ExprKind {
+    Literal(Value),
+    Template(Vec<Part>),
+    Tuple(Vec<Expr>),
+    Object(Vec<(Expr, Expr)>),
+    Variable(String),
+    /// Function calls are never evaluated
+    Call,
+    Traversal(Box<Expr>, Vec<Step>),
+    Splat(Box<Expr>, Vec<Step>),
+    Unary(char, Box<Expr>),
+    Binary(Op, Box<Expr>, Box<Expr>),
+    Conditional(Box<Expr>, Box<Expr>, Box<Expr>),
+    For(Box<ForExpr>),
+    Paren(Box<Expr>),
+}
+
+enum Step {
+    Attribute(String),
+    Index(Expr),
+}
+
+enum Part {
+    Literal(String),
+    Interpolation(Expr),
+}
+
+struct ForExpr {
+    key_var: Option<String>,
+    value_var: String,
+    collection: Expr,
+    /// The key expression of an object `for` (`{for ... : k => v}`)
+    key: Option<Expr>,
+    value: Expr,
+    /// `...` after the value: collect values with equal keys into arrays
+    group: bool,
+    condition: Option<Expr>,
+}
+
+#[derive(Clone, Copy)]
+enum Op {
+    Or,
+    And,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+}
+
+/// Binary operators from lowest to highest precedence
+const OPERATORS: [&[(&str, Op)]; 6] = [
+    &[("||", Op::Or)],
+    &[("&&", Op::And)],
+    &[("==", Op::Eq), ("!=", Op::Ne)],
+    &[("<=", Op::Le), (">=", Op::Ge), ("<", Op::Lt), (">", Op::Gt)],
+    &[("+", Op::Add), ("-", Op::Sub)],
+    &[("*", Op::Mul), ("/", Op::Div), ("%", Op::Mod)],
+];
+
+/// Variables bound by enclosing `for` expressions
+type Scope = HashMap<String, Value>;
+
+fn is_id_start(ch: char) -> bool {
+    ch.is_alphabetic() || ch == '_'
+}
+
+fn is_id_continue(ch: char) -> bool {
+    ch.is_alphanumeric() || ch == '_' || ch == '-'
+}
+
+impl Expr {
+    fn new(kind: ExprKind, start: usize, end: usize) -> Self {
+        Self { kind, start, end }
+    }
+}
+
 impl<'a> HclParser<'a> {
     /// Create a new HCL parser
     pub fn new(content: &'a str) -> Self {
-        Self { content }
+        Self {
+            source: content,
+            pos: 0,
+            multiline: false,
+            depth: 0,
+            layout: Layout::default(),
+            comments: false,
+            folded: false,
+            marks: None,
+            steps: Vec::new(),
+        }
     }
 
     /// Parse HCL content into a Value tree
     pub fn parse(&mut self) -> Result<Value> {
-        let mut map = std::collections::BTreeMap::new();
-        let lines: Vec<&str> = self.content.lines().collect();
-        let mut i = 0;
+        self.pos = if self.source.starts_with('\u{feff}') {
+            '\u{feff}'.len_utf8()
+        } else {
+            0
+        };
+        self.multiline = false;
+        self.depth = 0;
+        self.layout = Layout::default();
+        self.comments = false;
+        self.folded = false;
+        self.steps.clear();
+        if let Some(marks) = &mut self.marks {
+            marks.clear();
+        }
+        self.body(false, &[])
+    }
+
+    // ---------------------------------------------------------------
+    // Bodies
+    // ---------------------------------------------------------------
+
+    /// Parse attributes and blocks up to the end of input or, for a
+    /// `nested` body, the closing `}` (left for the caller). `path` is
+    /// where the body sits, for the [`Layout`].
+    fn body(&mut self, nested: bool, path: &[String]) -> Result<Value> {
+        let mut entries = BTreeMap::new();
+        let mut kinds: HashMap<Vec<String>, Kind> = HashMap::new();
+        // How many blocks with each type and labels came before, for marks
+        let mut repeats: HashMap<Vec<String>, usize> = HashMap::new();
+        loop {
+            self.skip_trivia(true)?;
+            match self.peek() {
+                None if nested => return Err(self.unexpected("`}`")),
+                None => break,
+                Some('}') if nested => break,
+                Some(ch) if is_id_start(ch) => {}
+                Some(_) => return Err(self.unexpected("an attribute or block")),
+            }
+
+            let start = self.pos;
+            let name = self.identifier();
+            let mut entry_path = path.to_vec();
+            entry_path.push(name.clone());
+            self.skip_trivia(false)?;
+            if self.eat('=') {
+                self.mark(
+                    start,
+                    vec![locate::Step {
+                        key: name.clone(),
+                        occurrence: None,
+                    }],
+                );
+                let expr = self.expression()?;
+                let value = self.materialize(&expr, &Scope::new())?;
+                match kinds.insert(vec![name.clone()], Kind::Attribute) {
+                    None => {
+                        self.layout.record(path, &name);
+                        entries.insert(name, value);
+                        self.layout.attributes.insert(entry_path);
+                    }
+                    Some(Kind::Attribute) => {
+                        return Err(self.error_at(start, format!("duplicate attribute `{name}`")))
+                    }
+                    Some(_) => {
+                        return Err(
+                            self.error_at(start, format!("`{name}` is already defined as a block"))
+                        )
+                    }
+                }
+            } else {
+                let labels = self.labels()?;
+                self.enter()?;
+                self.pos += 1;
+                self.layout.blocks.insert(entry_path.clone(), labels.len());
+                self.layout.record(path, &name);
+                for label in &labels {
+                    self.layout.record(&entry_path, label);
+                    entry_path.push(label.clone());
+                }
+                let outer = self.steps.len();
+                if self.marks.is_some() {
+                    let segments: Vec<String> = std::iter::once(name.clone())
+                        .chain(labels.iter().cloned())
+                        .collect();
+                    let count = repeats.entry(segments.clone()).or_insert(0);
+                    let last = segments.len() - 1;
+                    let steps: Vec<locate::Step> = segments
+                        .into_iter()
+                        .enumerate()
+                        .map(|(index, key)| locate::Step {
+                            key,
+                            occurrence: (index == last).then_some(*count),
+                        })
+                        .collect();
+                    *count += 1;
+                    self.mark(start, steps.clone());
+                    self.steps.extend(steps);
+                }
+                let body = self.body(true, &entry_path);
+                self.steps.truncate(outer);
+                let body = body?;
+                self.expect('}', "`}`")?;
+                self.depth -= 1;
+                insert_block(&mut entries, &mut kinds, name, labels, body)
+                    .map_err(|message| self.error_at(start, message))?;
+            }
+            self.item_end()?;
+        }
+        Ok(Value::table(entries))
+    }
+
+    /// Record an attribute or block at `start`, `steps` below the current
+    /// body.
+    fn mark(&mut self, start: usize, steps: Vec<locate::Step>) {
+        if let Some(marks) = &mut self.marks {
+            let mut path = self.steps.clone();
+            path.extend(steps);
+            marks.push((start, path));
+        }
+    }
+
+    /// Block labels up to (not including) the opening `{`.
+    fn labels(&mut self) -> Result<Vec<String>> {
+        let mut labels = Vec::new();
+        loop {
+            match self.peek() {
+                Some('{') => return Ok(labels),
+                Some('"') => {
+                    let start = self.pos;
+                    let label = self.quoted_template()?;
+                    match label.kind {
+                        ExprKind::Template(parts) if parts.len() <= 1 => {
+                            labels.push(match parts.into_iter().next() {
+                                Some(Part::Literal(text)) => text,
+                                Some(Part::Interpolation(_)) => {
+                                    return Err(self.error_at(
+                                        start,
+                                        "block labels cannot contain interpolations",
+                                    ))
+                                }
+                                None => String::new(),
+                            });
+                        }
+                        _ => {
+                            return Err(
+                                self.error_at(start, "block labels cannot contain interpolations")
+                            )
+                        }
+                    }
+                }
+                Some(ch) if is_id_start(ch) => labels.push(self.identifier()),
+                _ => return Err(self.unexpected("`=`, a block label or `{`")),
+            }
+            self.skip_trivia(false)?;
+        }
+    }
+
+    /// The end of an attribute or block: a newline, the end of input, or
+    /// the `}` of a single-line block.
+    fn item_end(&mut self) -> Result<()> {
+        self.skip_trivia(false)?;
+        match self.peek() {
+            None | Some('\n' | '}') => Ok(()),
+            Some('\r') if self.rest().starts_with("\r\n") => Ok(()),
+            _ => Err(self.unexpected("a newline")),
+        }
+    }
+
+    // ---------------------------------------------------------------
+    // Expressions
+    // ---------------------------------------------------------------
+
+    fn expression(&mut self) -> Result<Expr> {
+        self.enter()?;
+        self.skip_trivia(self.multiline)?;
+        let condition = self.binary(0)?;
+        self.skip_trivia(self.multiline)?;
+        let expr = if self.eat('?') {
+            let then = self.expression()?;
+            self.skip_trivia(self.multiline)?;
+            self.expect(':', "`:`")?;
+            let otherwise = self.expression()?;
+            let (start, end) = (condition.start, otherwise.end);
+            Expr::new(
+                ExprKind::Conditional(Box::new(condition), Box::new(then), Box::new(otherwise)),
+                start,
+                end,
+            )
+        } else {
+            condition
+        };
+        self.depth -= 1;
+        Ok(expr)
+    }
+
+    fn binary(&mut self, level: usize) -> Result<Expr> {
+        let Some(operators) = OPERATORS.get(level) else {
+            return self.unary();
+        };
+        let mut left = self.binary(level + 1)?;
+        let depth = self.depth;
+        loop {
+            self.skip_trivia(self.multiline)?;
+            let rest = self.rest();
+            let Some(&(token, op)) = operators.iter().find(|(token, _)| rest.starts_with(token))
+            else {
+                break;
+            };
+            // Each operator nests the tree one level deeper
+            self.enter()?;
+            self.pos += token.len();
+            self.skip_trivia(self.multiline)?;
+            let right = self.binary(level + 1)?;
+            let (start, end) = (left.start, right.end);
+            left = Expr::new(
+                ExprKind::Binary(op, Box::new(left), Box::new(right)),
+                start,
+                end,
+            );
+        }
+        self.depth = depth;
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Expr> {
+        let start = self.pos;
+        if let Some(op @ ('-' | '!')) = self.peek() {
+            self.pos += 1;
+            self.enter()?;
+            self.skip_trivia(self.multiline)?;
+            let operand = self.unary()?;
+            self.depth -= 1;
+            let end = operand.end;
+            return Ok(Expr::new(
+                ExprKind::Unary(op, Box::new(operand)),
+                start,
+                end,
+            ));
+        }
+        self.postfix()
+    }
+
+    /// A primary expression followed by attribute, index and splat
+    /// traversals.
+    fn postfix(&mut self) -> Result<Expr> {
+        let expr = self.primary()?;
+        let start = expr.start;
+        let mut steps = Vec::new();
+        loop {
+            let rest = self.rest();
+            if rest.starts_with(".*") || rest.starts_with("[*]") {
+                let full = rest.starts_with('[');
+                self.pos += if full { 3 } else { 2 };
+                let base = if steps.is_empty() {
+                    expr
+                } else {
+                    Expr::new(ExprKind::Traversal(Box::new(expr), steps), start, self.pos)
+                };
+                let mut splat_steps = Vec::new();
+                while let Some(step) = self.step(full)? {
+                    splat_steps.push(step);
+                }
+                let kind = ExprKind::Splat(Box::new(base), splat_steps);
+                return Ok(Expr::new(kind, start, self.pos));
+            }
+            match self.step(true)? {
+                Some(step) => steps.push(step),
+                None => break,
+            }
+        }
+        if steps.is_empty() {
+            Ok(expr)
+        } else {
+            let end = self.pos;
+            Ok(Expr::new(
+                ExprKind::Traversal(Box::new(expr), steps),
+                start,
+                end,
+            ))
+        }
+    }
+
+    /// One `.name`, `.0` or (when `index` is set) `[expr]` traversal.
+    fn step(&mut self, index: bool) -> Result<Option<Step>> {
+        let rest = self.rest();
+        if rest.starts_with('.') && rest[1..].starts_with(|c: char| is_id_start(c)) {
+            self.pos += 1;
+            return Ok(Some(Step::Attribute(self.identifier())));
+        }
+        if rest.starts_with('.') && rest[1..].starts_with(|c: char| c.is_ascii_digit()) {
+            self.pos += 1;
+            let digits = self.rest().len()
+                - self
+                    .rest()
+                    .trim_start_matches(|c: char| c.is_ascii_digit())
+                    .len();
+            let name = self.rest()[..digits].to_string();
+            self.pos += digits;
+            return Ok(Some(Step::Attribute(name)));
+        }
+        if index && rest.starts_with('[') && !rest.starts_with("[*]") {
+            self.pos += 1;
+            let multiline = std::mem::replace(&mut self.multiline, true);
+            let key = self.expression()?;
+            self.skip_trivia(true)?;
+            self.expect(']', "`]`")?;
+            self.multiline = multiline;
+            return Ok(Some(Step::Index(key)));
+        }
+        Ok(None)
+    }
+
+    fn primary(&mut self) -> Result<Expr> {
+        let start = self.pos;
+        match self.peek() {
+            Some('"') => self.quoted_template(),
+            Some('<') if self.rest().starts_with("<<") => self.heredoc(),
+            Some('[') => self.bracketed(']'),
+            Some('{') => self.bracketed('}'),
+            Some('(') => {
+                self.pos += 1;
+                let multiline = std::mem::replace(&mut self.multiline, true);
+                let inner = self.expression()?;
+                self.skip_trivia(true)?;
+                self.expect(')', "`)`")?;
+                self.multiline = multiline;
+                Ok(Expr::new(ExprKind::Paren(Box::new(inner)), start, self.pos))
+            }
+            Some(ch) if ch.is_ascii_digit() => Ok(self.number()),
+            Some(ch) if is_id_start(ch) => {
+                let name = self.identifier();
+                let kind = match name.as_str() {
+                    "true" => ExprKind::Literal(Value::bool(true)),
+                    "false" => ExprKind::Literal(Value::bool(false)),
+                    "null" => ExprKind::Literal(Value::Null),
+                    _ if self.rest().starts_with("::") || self.peek() == Some('(') => {
+                        self.call()?;
+                        ExprKind::Call
+                    }
+                    _ => ExprKind::Variable(name),
+                };
+                Ok(Expr::new(kind, start, self.pos))
+            }
+            _ => Err(self.unexpected("an expression")),
+        }
+    }
+
+    fn number(&mut self) -> Expr {
+        let start = self.pos;
+        let digits = |parser: &mut Self| {
+            while matches!(parser.peek(), Some('0'..='9')) {
+                parser.pos += 1;
+            }
+        };
+        digits(self);
+        let mut integral = true;
+        if self.rest().starts_with('.')
+            && self.rest()[1..].starts_with(|c: char| c.is_ascii_digit())
+        {
+            integral = false;
+            self.pos += 1;
+            digits(self);
+        }
+        let rest = self.rest();
+        if rest.starts_with(['e', 'E']) {
+            let sign = usize::from(rest[1..].starts_with(['+', '-']));
+            if rest[1 + sign..].starts_with(|c: char| c.is_ascii_digit()) {
+                integral = false;
+                self.pos += 1 + sign;
+                digits(self);
+            }
+        }
+        let text = &self.source[start..self.pos];
+        let value = match text.parse::<i64>() {
+            Ok(i) if integral => Value::integer(i),
+            _ => Value::float(text.parse::<f64>().unwrap_or(f64::INFINITY)),
+        };
+        Expr::new(ExprKind::Literal(value), start, self.pos)
+    }
 
-        while i < lines.len() {
-            let line = lines[i].trim();
+    /// The rest of a function call after its name: optional `::`
+    /// namespace segments and the argument list.
+    fn call(&mut self) -> Result<()> {
+        while self.rest().starts_with("::") {
+            self.pos += 2;
+            if !self.peek().is_some_and(is_id_start) {
+                return Err(self.unexpected("a function name"));
+            }
+            self.identifier();
+        }
+        self.expect('(', "`(`")?;
+        let multiline = std::mem::replace(&mut self.multiline, true);
+        loop {
+            self.skip_trivia(true)?;
+            if self.eat(')') {
+                break;
+            }
+            self.expression()?;
+            self.skip_trivia(true)?;
+            if self.rest().starts_with("...") {
+                self.pos += 3;
+                self.skip_trivia(true)?;
+            }
+            if !self.eat(',') {
+                self.expect(')', "`,` or `)`")?;
+                break;
+            }
+        }
+        self.multiline = multiline;
+        Ok(())
+    }
+
+    /// A tuple (`close` is `]`) or object (`}`) constructor, or a `for`
+    /// expression producing one.
+    fn bracketed(&mut self, close: char) -> Result<Expr> {
+        let start = self.pos;
+        self.pos += 1;
+        self.enter()?;
+        // Newlines separate object items but are whitespace in tuples
+        let multiline = std::mem::replace(&mut self.multiline, close == ']');
+        self.skip_trivia(true)?;
+        let expr = if self.at_keyword("for") {
+            self.multiline = true;
+            self.for_expr(close, start)?
+        } else if close == ']' {
+            self.tuple(start)?
+        } else {
+            self.object(start)?
+        };
+        self.multiline = multiline;
+        self.depth -= 1;
+        Ok(expr)
+    }
 
-            // Skip empty lines and comments
-            if line.is_empty() || line.starts_with('#') || line.starts_with("//") {
-                i += 1;
+    fn tuple(&mut self, start: usize) -> Result<Expr> {
+        let mut items = Vec::new();
+        loop {
+            self.skip_trivia(true)?;
+            if self.eat(']') {
+                break;
+            }
+            items.push(self.expression()?);
+            self.skip_trivia(true)?;
+            if !self.eat(',') {
+                self.expect(']', "`,` or `]`")?;
+                break;
+            }
+        }
+        Ok(Expr::new(ExprKind::Tuple(items), start, self.pos))
+    }
+
+    fn object(&mut self, start: usize) -> Result<Expr> {
+        let mut items = Vec::new();
+        loop {
+            self.skip_trivia(true)?;
+            if self.eat('}') {
+                break;
+            }
+            let key = self.object_key()?;
+            self.skip_trivia(false)?;
+            if !(self.eat('=') || self.eat(':')) {
+                return Err(self.unexpected("`=` or `:`"));
+            }
+            let value = self.expression()?;
+            items.push((key, value));
+            self.skip_trivia(false)?;
+            if !self.eat(',') && !matches!(self.peek(), Some('\n' | '\r' | '}')) {
+                return Err(self.unexpected("`,`, a newline or `}`"));
+            }
+        }
+        Ok(Expr::new(ExprKind::Object(items), start, self.pos))
+    }
+
+    /// An object key: a bare identifier is the literal key, anything else
+    /// is an expression.
+    fn object_key(&mut self) -> Result<Expr> {
+        let start = self.pos;
+        if self.peek().is_some_and(is_id_start) {
+            let name = self.identifier();
+            let after = self.rest().trim_start_matches([' ', '\t']);
+            if (after.starts_with('=') && !after.starts_with("==")) || after.starts_with(':') {
+                return Ok(Expr::new(
+                    ExprKind::Literal(Value::string(name)),
+                    start,
+                    self.pos,
+                ));
+            }
+            self.pos = start;
+        }
+        self.expression()
+    }
+
+    /// `[for k, v in coll : value if cond]` or
+    /// `{for k, v in coll : key => value... if cond}`, after the bracket.
+    fn for_expr(&mut self, close: char, start: usize) -> Result<Expr> {
+        self.pos += "for".len();
+        self.skip_trivia(true)?;
+        let first = self.variable_name()?;
+        self.skip_trivia(true)?;
+        let (key_var, value_var) = if self.eat(',') {
+            self.skip_trivia(true)?;
+            (Some(first), self.variable_name()?)
+        } else {
+            (None, first)
+        };
+        self.skip_trivia(true)?;
+        if !self.at_keyword("in") {
+            return Err(self.unexpected("`in`"));
+        }
+        self.pos += "in".len();
+        let collection = self.expression()?;
+        self.skip_trivia(true)?;
+        self.expect(':', "`:`")?;
+
+        let mut key = None;
+        let mut group = false;
+        if close == '}' {
+            key = Some(self.expression()?);
+            self.skip_trivia(true)?;
+            if !self.rest().starts_with("=>") {
+                return Err(self.unexpected("`=>`"));
+            }
+            self.pos += 2;
+        }
+        let value = self.expression()?;
+        self.skip_trivia(true)?;
+        if key.is_some() && self.rest().starts_with("...") {
+            self.pos += 3;
+            group = true;
+            self.skip_trivia(true)?;
+        }
+        let condition = if self.at_keyword("if") {
+            self.pos += "if".len();
+            let condition = self.expression()?;
+            self.skip_trivia(true)?;
+            Some(condition)
+        } else {
+            None
+        };
+        self.expect(close, if close == ']' { "`]`" } else { "`}`" })?;
+
+        let expr = ForExpr {
+            key_var,
+            value_var,
+            collection,
+            key,
+            value,
+            group,
+            condition,
+        };
+        Ok(Expr::new(ExprKind::For(Box::new(expr)), start, self.pos))
+    }
+
+    fn variable_name(&mut self) -> Result<String> {
+        if self.peek().is_some_and(is_id_start) {
+            Ok(self.identifier())
+        } else {
+            Err(self.unexpected("a variable name"))
+        }
+    }
+
+    // ---------------------------------------------------------------
+    // Templates
+    // ---------------------------------------------------------------
+
+    fn quoted_template(&mut self) -> Result<Expr> {
+        let start = self.pos;
+        self.pos += 1;
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        loop {
+            match self.peek() {
+                None | Some('\n' | '\r') => {
+                    return Err(self.error_at(start, "unterminated string"));
+                }
+                Some('"') => {
+                    self.pos += 1;
+                    break;
+                }
+                Some('\\') => literal.push(self.escape()?),
+                _ => self.template_item(&mut parts, &mut literal)?,
+            }
+        }
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+        Ok(Expr::new(ExprKind::Template(parts), start, self.pos))
+    }
+
+    fn heredoc(&mut self) -> Result<Expr> {
+        let start = self.pos;
+        self.pos += 2;
+        let indented = self.eat('-');
+        if !self.peek().is_some_and(is_id_start) {
+            return Err(self.unexpected("a heredoc delimiter"));
+        }
+        let marker = self.identifier();
+        if !(self.eat('\n') || self.rest().starts_with("\r\n") && self.eat('\r') && self.eat('\n'))
+        {
+            return Err(self.unexpected("a newline after the heredoc delimiter"));
+        }
+
+        // Find the closing delimiter line
+        let content_start = self.pos;
+        let mut line_starts = Vec::new();
+        let (content_end, after) = loop {
+            if self.pos >= self.source.len() {
+                return Err(self.error_at(
+                    start,
+                    format!("unterminated heredoc; expected a closing `{marker}` line"),
+                ));
+            }
+            let line_start = self.pos;
+            let line_end = self
+                .rest()
+                .find('\n')
+                .map_or(self.source.len(), |i| self.pos + i + 1);
+            let line = &self.source[line_start..line_end];
+            let trimmed = line.trim_start_matches([' ', '\t']);
+            if trimmed.trim_end() == marker {
+                let indent = line.len() - trimmed.len();
+                break (line_start, line_start + indent + marker.len());
+            }
+            line_starts.push(line_start);
+            self.pos = line_end;
+        };
+
+        // `<<-` strips the smallest indentation of the non-blank lines
+        let indent = if indented {
+            line_starts
+                .iter()
+                .map(|&at| {
+                    let line = self.source[at..content_end].lines().next().unwrap_or("");
+                    (
+                        line,
+                        line.len() - line.trim_start_matches([' ', '\t']).len(),
+                    )
+                })
+                .filter(|(line, _)| !line.trim().is_empty())
+                .map(|(_, indent)| indent)
+                .min()
+                .unwrap_or(0)
+        } else {
+            0
+        };
+
+        self.pos = content_start;
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut next_line = 0;
+        while self.pos < content_end {
+            while line_starts.get(next_line).is_some_and(|&at| at < self.pos) {
+                next_line += 1;
+            }
+            if indent > 0 && line_starts.get(next_line) == Some(&self.pos) {
+                next_line += 1;
+                let mut skipped = 0;
+                while skipped < indent && matches!(self.peek(), Some(' ' | '\t')) {
+                    self.pos += 1;
+                    skipped += 1;
+                }
                 continue;
             }
+            self.template_item(&mut parts, &mut literal)?;
+        }
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+        self.pos = after;
+        Ok(Expr::new(ExprKind::Template(parts), start, self.pos))
+    }
+
+    /// One character, escape, interpolation or directive of a template.
+    fn template_item(&mut self, parts: &mut Vec<Part>, literal: &mut String) -> Result<()> {
+        let rest = self.rest();
+        if rest.starts_with("$${") || rest.starts_with("%%{") {
+            literal.push_str(&rest[1..3]);
+            self.pos += 3;
+        } else if rest.starts_with("${") {
+            self.interpolation(parts, literal)?;
+        } else if rest.starts_with("%{") {
+            self.directive(literal)?;
+        } else if let Some(ch) = self.bump() {
+            literal.push(ch);
+        }
+        Ok(())
+    }
 
-            // Check if this is a block
-            if line.contains('{') && !line.contains('=') {
-                // Extract block name
-                let block_name = line
-                    .split('{')
-                    .next()
-                    .unwrap_or("")
-                    .trim()
-                    .trim_matches('"');
-                i += 1; // Move past the opening brace line
-
-                // Parse block content
-                let mut block_map = std::collections::BTreeMap::new();
-                while i < lines.len() {
-                    let block_line = lines[i].trim();
-
-                    // Check for closing brace
-                    if block_line == "}" {
-                        i += 1; // Move past closing brace
-                        break;
+    fn interpolation(&mut self, parts: &mut Vec<Part>, literal: &mut String) -> Result<()> {
+        self.pos += 2;
+        if self.eat('~') {
+            literal.truncate(literal.trim_end().len());
+        }
+        if !literal.is_empty() {
+            parts.push(Part::Literal(std::mem::take(literal)));
+        }
+        let multiline = std::mem::replace(&mut self.multiline, true);
+        let expr = self.expression()?;
+        self.skip_trivia(true)?;
+        let strip = self.eat('~');
+        self.expect('}', "`}`")?;
+        self.multiline = multiline;
+        parts.push(Part::Interpolation(expr));
+        if strip {
+            while matches!(self.peek(), Some(' ' | '\t' | '\n' | '\r')) {
+                self.pos += 1;
+            }
+        }
+        Ok(())
+    }
+
+    /// A `%{ ... }` directive, kept verbatim.
+    fn directive(&mut self, literal: &mut String) -> Result<()> {
+        let start = self.pos;
+        self.pos += 2;
+        let mut depth = 1;
+        let mut quoted = false;
+        while depth > 0 {
+            match self.bump() {
+                None => return Err(self.error_at(start, "unterminated template directive")),
+                Some('\\') if quoted => {
+                    self.bump();
+                }
+                Some('"') => quoted = !quoted,
+                Some('{') if !quoted => depth += 1,
+                Some('}') if !quoted => depth -= 1,
+                Some(_) => {}
+            }
+        }
+        literal.push_str(&self.source[start..self.pos]);
+        Ok(())
+    }
+
+    fn escape(&mut self) -> Result<char> {
+        let start = self.pos;
+        self.pos += 1;
+        let ch = match self.bump() {
+            Some('n') => '\n',
+            Some('r') => '\r',
+            Some('t') => '\t',
+            Some('"') => '"',
+            Some('\\') => '\\',
+            Some('u') => return self.unicode_escape(start, 4),
+            Some('U') => return self.unicode_escape(start, 8),
+            _ => return Err(self.error_at(start, "invalid escape sequence")),
+        };
+        Ok(ch)
+    }
+
+    fn unicode_escape(&mut self, start: usize, len: usize) -> Result<char> {
+        let ch = self
+            .rest()
+            .get(..len)
+            .filter(|digits| digits.chars().all(|c| c.is_ascii_hexdigit()))
+            .and_then(|digits| u32::from_str_radix(digits, 16).ok())
+            .and_then(char::from_u32)
+            .ok_or_else(|| self.error_at(start, "invalid unicode escape"))?;
+        self.pos += len;
+        Ok(ch)
+    }
+
+    // ---------------------------------------------------------------
+    // Evaluation
+    // ---------------------------------------------------------------
+
+    /// Evaluate `expr`, or `None` when it depends on something unknown.
+    fn eval(&self, expr: &Expr, scope: &Scope) -> Result<Option<Value>> {
+        macro_rules! known {
+            ($value:expr) => {
+                match $value? {
+                    Some(value) => value,
+                    None => return Ok(None),
+                }
+            };
+        }
+        let fail = |message: String| self.error_at(expr.start, message);
+
+        let value = match &expr.kind {
+            ExprKind::Literal(value) => value.clone(),
+            ExprKind::Template(parts) => {
+                let mut text = String::new();
+                for part in parts {
+                    match part {
+                        Part::Literal(literal) => text.push_str(literal),
+                        Part::Interpolation(inner) => {
+                            let value = known!(self.eval(inner, scope));
+                            text.push_str(
+                                &template_text(&value)
+                                    .map_err(|message| self.error_at(inner.start, message))?,
+                            );
+                        }
+                    }
+                }
+                Value::string(text)
+            }
+            ExprKind::Tuple(items) => {
+                let mut values = Vec::with_capacity(items.len());
+                for item in items {
+                    values.push(known!(self.eval(item, scope)));
+                }
+                Value::array(values)
+            }
+            ExprKind::Object(items) => {
+                let mut table = BTreeMap::new();
+                for (key, value) in items {
+                    let name = known!(self.eval(key, scope));
+                    let name =
+                        key_text(&name).map_err(|message| self.error_at(key.start, message))?;
+                    let value = known!(self.eval(value, scope));
+                    if table.insert(name.clone(), value).is_some() {
+                        return Err(self.error_at(key.start, format!("duplicate key `{name}`")));
+                    }
+                }
+                Value::table(table)
+            }
+            ExprKind::Variable(name) => match scope.get(name) {
+                Some(value) => value.clone(),
+                None => return Ok(None),
+            },
+            ExprKind::Call => return Ok(None),
+            ExprKind::Traversal(base, steps) => {
+                let mut value = known!(self.eval(base, scope));
+                for step in steps {
+                    value = known!(self.step_into(value, step, scope));
+                }
+                value
+            }
+            ExprKind::Splat(base, steps) => {
+                let items = match known!(self.eval(base, scope)) {
+                    Value::Array(items) => items,
+                    Value::Null => Vec::new(),
+                    other => vec![other],
+                };
+                let mut values = Vec::with_capacity(items.len());
+                for mut value in items {
+                    for step in steps {
+                        value = known!(self.step_into(value, step, scope));
+                    }
+                    values.push(value);
+                }
+                Value::array(values)
+            }
+            ExprKind::Unary(op, operand) => {
+                let value = known!(self.eval(operand, scope));
+                match (op, value) {
+                    ('!', Value::Bool(b)) => Value::bool(!b),
+                    ('-', value) => match number(&value) {
+                        Some(Number::Integer(i)) => match i.checked_neg() {
+                            Some(i) => Value::integer(i),
+                            #[allow(clippy::cast_precision_loss)]
+                            None => Value::float(-(i as f64)),
+                        },
+                        Some(Number::Float(f)) => Value::float(-f),
+                        None => return Err(fail(format!("cannot negate {}", value.type_name()))),
+                    },
+                    (_, value) => {
+                        return Err(fail(format!(
+                            "`!` needs a bool, found {}",
+                            value.type_name()
+                        )))
+                    }
+                }
+            }
+            ExprKind::Binary(op, left, right) => {
+                let left = known!(self.eval(left, scope));
+                let right = known!(self.eval(right, scope));
+                binary(*op, &left, &right).map_err(fail)?
+            }
+            ExprKind::Conditional(condition, then, otherwise) => {
+                match known!(self.eval(condition, scope)) {
+                    Value::Bool(true) => known!(self.eval(then, scope)),
+                    Value::Bool(false) => known!(self.eval(otherwise, scope)),
+                    other => {
+                        return Err(self.error_at(
+                            condition.start,
+                            format!("condition must be a bool, found {}", other.type_name()),
+                        ))
                     }
+                }
+            }
+            ExprKind::For(for_expr) => known!(self.eval_for(for_expr, scope)),
+            ExprKind::Paren(inner) => known!(self.eval(inner, scope)),
+        };
+        Ok(Some(value))
+    }
+
+    fn eval_for(&self, expr: &ForExpr, scope: &Scope) -> Result<Option<Value>> {
+        let Some(collection) = self.eval(&expr.collection, scope)? else {
+            return Ok(None);
+        };
+        let elements: Vec<(Value, Value)> = match collection {
+            Value::Array(items) => (0_i64..).map(Value::integer).zip(items).collect(),
+            Value::Table(table) => table
+                .into_iter()
+                .map(|(key, value)| (Value::string(key), value))
+                .collect(),
+            other => {
+                return Err(self.error_at(
+                    expr.collection.start,
+                    format!("`for` needs a list or object, found {}", other.type_name()),
+                ))
+            }
+        };
+
+        let mut items = Vec::new();
+        let mut table: BTreeMap<String, Value> = BTreeMap::new();
+        for (key, value) in elements {
+            let mut inner = scope.clone();
+            if let Some(name) = &expr.key_var {
+                inner.insert(name.clone(), key);
+            }
+            inner.insert(expr.value_var.clone(), value);
 
-                    // Skip empty lines and comments within block
-                    if block_line.is_empty()
-                        || block_line.starts_with('#')
-                        || block_line.starts_with("//")
-                    {
-                        i += 1;
-                        continue;
+            if let Some(condition) = &expr.condition {
+                match self.eval(condition, &inner)? {
+                    Some(Value::Bool(true)) => {}
+                    Some(Value::Bool(false)) => continue,
+                    Some(other) => {
+                        return Err(self.error_at(
+                            condition.start,
+                            format!("`if` condition must be a bool, found {}", other.type_name()),
+                        ))
                     }
+                    None => return Ok(None),
+                }
+            }
+            let Some(value) = self.eval(&expr.value, &inner)? else {
+                return Ok(None);
+            };
+            let Some(key_expr) = &expr.key else {
+                items.push(value);
+                continue;
+            };
+            let Some(key) = self.eval(key_expr, &inner)? else {
+                return Ok(None);
+            };
+            let key = key_text(&key).map_err(|message| self.error_at(key_expr.start, message))?;
+            if expr.group {
+                match table.entry(key).or_insert_with(|| Value::array(Vec::new())) {
+                    Value::Array(group) => group.push(value),
+                    _ => unreachable!("grouped `for` values are arrays"),
+                }
+            } else if table.insert(key.clone(), value).is_some() {
+                return Err(self.error_at(
+                    key_expr.start,
+                    format!("duplicate key `{key}` in `for` expression; use `...` to group"),
+                ));
+            }
+        }
+        Ok(Some(match expr.key {
+            Some(_) => Value::table(table),
+            None => Value::array(items),
+        }))
+    }
 
-                    // Parse key-value pair within block
-                    if let Some(eq_pos) = block_line.find('=') {
-                        let key = block_line[..eq_pos].trim().trim_matches('"').to_string();
-                        let value_str = block_line[eq_pos + 1..].trim().trim_matches('"');
-                        let value = self.parse_value(value_str);
-                        block_map.insert(key, value);
+    fn step_into(&self, value: Value, step: &Step, scope: &Scope) -> Result<Option<Value>> {
+        let (key, at) = match step {
+            Step::Attribute(name) => (Value::string(name.clone()), None),
+            Step::Index(expr) => match self.eval(expr, scope)? {
+                Some(key) => (key, Some(expr.start)),
+                None => return Ok(None),
+            },
+        };
+        let fail = |message: String| self.error_at(at.unwrap_or(self.pos), message);
+        match value {
+            Value::Array(mut items) => {
+                let index = match number(&key) {
+                    Some(Number::Integer(i)) => usize::try_from(i).ok(),
+                    _ => None,
+                }
+                .ok_or_else(|| fail(format!("invalid index {key} for a list")))?;
+                if index < items.len() {
+                    Ok(Some(items.swap_remove(index)))
+                } else {
+                    Err(fail(format!(
+                        "index {index} is out of range for a list of {}",
+                        items.len()
+                    )))
+                }
+            }
+            Value::Table(mut table) => {
+                let name = key_text(&key).map_err(fail)?;
+                table
+                    .remove(&name)
+                    .map(Some)
+                    .ok_or_else(|| fail(format!("no attribute or key `{name}`")))
+            }
+            other => Err(fail(format!("cannot index into {}", other.type_name()))),
+        }
+    }
+
+    /// Evaluate `expr` for a value position: unknown parts become
+    /// `${source}` strings while known parts keep their values.
+    fn materialize(&mut self, expr: &Expr, scope: &Scope) -> Result<Value> {
+        if let Some(value) = self.eval(expr, scope)? {
+            self.folded |= computed(expr);
+            return Ok(value);
+        }
+        Ok(match &expr.kind {
+            ExprKind::Tuple(items) => Value::array(
+                items
+                    .iter()
+                    .map(|item| self.materialize(item, scope))
+                    .collect::<Result<_>>()?,
+            ),
+            ExprKind::Object(items) => {
+                let mut table = BTreeMap::new();
+                for (key, value) in items {
+                    let Some(name) = self.eval(key, scope)? else {
+                        return Ok(self.unresolved(expr));
+                    };
+                    let name =
+                        key_text(&name).map_err(|message| self.error_at(key.start, message))?;
+                    table.insert(name, self.materialize(value, scope)?);
+                }
+                Value::table(table)
+            }
+            ExprKind::Template(parts) => {
+                let mut text = String::new();
+                for part in parts {
+                    match part {
+                        Part::Literal(literal) => text.push_str(literal),
+                        Part::Interpolation(inner) => match self.eval(inner, scope)? {
+                            Some(value) => {
+                                self.folded |= computed(inner);
+                                text.push_str(
+                                    &template_text(&value)
+                                        .map_err(|message| self.error_at(inner.start, message))?,
+                                );
+                            }
+                            None => {
+                                text.push_str("${");
+                                text.push_str(&self.source[inner.start..inner.end]);
+                                text.push('}');
+                            }
+                        },
                     }
+                }
+                Value::string(text)
+            }
+            ExprKind::Paren(inner) => self.materialize(inner, scope)?,
+            _ => self.unresolved(expr),
+        })
+    }
 
-                    i += 1;
+    fn unresolved(&self, expr: &Expr) -> Value {
+        Value::string(format!("${{{}}}", &self.source[expr.start..expr.end]))
+    }
+
+    // ---------------------------------------------------------------
+    // Cursor helpers
+    // ---------------------------------------------------------------
+
+    fn rest(&self) -> &'a str {
+        &self.source[self.pos..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let ch = self.peek()?;
+        self.pos += ch.len_utf8();
+        Some(ch)
+    }
+
+    fn eat(&mut self, ch: char) -> bool {
+        if self.peek() == Some(ch) {
+            self.pos += ch.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, ch: char, what: &str) -> Result<()> {
+        if self.eat(ch) {
+            Ok(())
+        } else {
+            Err(self.unexpected(what))
+        }
+    }
+
+    fn at_keyword(&self, keyword: &str) -> bool {
+        self.rest()
+            .strip_prefix(keyword)
+            .is_some_and(|after| !after.starts_with(is_id_continue))
+    }
+
+    fn identifier(&mut self) -> String {
+        let start = self.pos;
+        self.bump();
+        while self.peek().is_some_and(is_id_continue) {
+            self.bump();
+        }
+        self.source[start..self.pos].to_string()
+    }
+
+    fn enter(&mut self) -> Result<()> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(self.error(format!("nesting deeper than {MAX_DEPTH} levels")));
+        }
+        Ok(())
+    }
+
+    /// Skip spaces and comments, and newlines too when `newlines` is set.
+    fn skip_trivia(&mut self, newlines: bool) -> Result<()> {
+        loop {
+            let rest = self.rest();
+            if rest.starts_with([' ', '\t']) || newlines && rest.starts_with('\n') {
+                self.pos += 1;
+            } else if newlines && rest.starts_with("\r\n") {
+                self.pos += 2;
+            } else if rest.starts_with('#') || rest.starts_with("//") {
+                self.comments = true;
+                self.pos += rest.find('\n').unwrap_or(rest.len());
+                if self.source[..self.pos].ends_with('\r') {
+                    self.pos -= 1;
                 }
+            } else if rest.starts_with("/*") {
+                self.comments = true;
+                let end = rest
+                    .find("*/")
+                    .ok_or_else(|| self.error("unterminated block comment"))?;
+                self.pos += end + 2;
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    fn error(&self, message: impl Into<String>) -> Error {
+        self.error_at(self.pos, message)
+    }
+
+    fn error_at(&self, pos: usize, message: impl Into<String>) -> Error {
+        let before = &self.source[..pos];
+        let line = before.matches('\n').count() + 1;
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        let column = before[line_start..].chars().count() + 1;
+        Error::parse(message, line, column)
+    }
+
+    fn unexpected(&self, expected: &str) -> Error {
+        match self.peek() {
+            None => self.error(format!("expected {expected}, found end of input")),
+            Some(ch) => self.error(format!("expected {expected}, found {ch:?}")),
+        }
+    }
+}
+
+/// Place a block body at `name.labels...`, sharing label tables with
+/// earlier blocks and collecting repeats into an array.
+fn insert_block(
+    entries: &mut BTreeMap<String, Value>,
+    kinds: &mut HashMap<Vec<String>, Kind>,
+    name: String,
+    labels: Vec<String>,
+    body: Value,
+) -> std::result::Result<(), String> {
+    let segments: Vec<String> = std::iter::once(name).chain(labels).collect();
+    let mut table = entries;
+    for depth in 1..=segments.len() {
+        let path = &segments[..depth];
+        let segment = &segments[depth - 1];
+        let leaf = depth == segments.len();
+        let kind = if leaf { Kind::Block } else { Kind::Labels };
+        match kinds.get(path) {
+            None => {
+                kinds.insert(path.to_vec(), kind);
+            }
+            Some(existing) if *existing == kind => {}
+            Some(Kind::Attribute) => {
+                return Err(format!("`{segment}` is already defined as an attribute"))
+            }
+            Some(_) => {
+                return Err(format!(
+                    "block `{}` is used with different numbers of labels",
+                    segments[0]
+                ))
+            }
+        }
+        if leaf {
+            break;
+        }
+        table = match table
+            .entry(segment.clone())
+            .or_insert_with(|| Value::table(BTreeMap::new()))
+        {
+            Value::Table(child) => child,
+            _ => return Err(format!("`{segment}` is not a block")),
+        };
+    }
+
+    let last = segments[segments.len() - 1].clone();
+    match table.remove(&last) {
+        None => {
+            table.insert(last, body);
+        }
+        Some(Value::Array(mut bodies)) => {
+            bodies.push(body);
+            table.insert(last, Value::array(bodies));
+        }
+        Some(first) => {
+            table.insert(last, Value::array(vec![first, body]));
+        }
+    }
+    Ok(())
+}
+
+enum Number {
+    Integer(i64),
+    Float(f64),
+}
+
+/// A number, converting numeric strings the way HCL does
+fn number(value: &Value) -> Option<Number> {
+    match value {
+        Value::Integer(i) => Some(Number::Integer(*i)),
+        Value::Float(f) => Some(Number::Float(*f)),
+        Value::String(s) => match s.parse::<i64>() {
+            Ok(i) => Some(Number::Integer(i)),
+            Err(_) => s
+                .parse::<f64>()
+                .ok()
+                .filter(|f| f.is_finite())
+                .map(Number::Float),
+        },
+        _ => None,
+    }
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn as_f64(number: &Number) -> f64 {
+    match number {
+        Number::Integer(i) => *i as f64,
+        Number::Float(f) => *f,
+    }
+}
+
+fn binary(op: Op, left: &Value, right: &Value) -> std::result::Result<Value, String> {
+    match op {
+        Op::Eq => return Ok(Value::bool(equal(left, right))),
+        Op::Ne => return Ok(Value::bool(!equal(left, right))),
+        Op::And | Op::Or => {
+            return match (left, right) {
+                (Value::Bool(a), Value::Bool(b)) => Ok(Value::bool(match op {
+                    Op::And => *a && *b,
+                    _ => *a || *b,
+                })),
+                _ => Err(format!(
+                    "logical operators need bools, found {} and {}",
+                    left.type_name(),
+                    right.type_name()
+                )),
+            }
+        }
+        _ => {}
+    }
+
+    let (Some(a), Some(b)) = (number(left), number(right)) else {
+        return Err(format!(
+            "arithmetic and comparison need numbers, found {} and {}",
+            left.type_name(),
+            right.type_name()
+        ));
+    };
+    let (x, y) = (as_f64(&a), as_f64(&b));
+    let value = match op {
+        Op::Lt => Value::bool(x < y),
+        Op::Le => Value::bool(x <= y),
+        Op::Gt => Value::bool(x > y),
+        Op::Ge => Value::bool(x >= y),
+        Op::Div | Op::Mod if y == 0.0 => return Err("division by zero".to_string()),
+        _ => {
+            let integer = match (a, b) {
+                (Number::Integer(a), Number::Integer(b)) => match op {
+                    Op::Add => a.checked_add(b),
+                    Op::Sub => a.checked_sub(b),
+                    Op::Mul => a.checked_mul(b),
+                    Op::Div if a.checked_rem(b) == Some(0) => a.checked_div(b),
+                    Op::Mod => a.checked_rem(b),
+                    _ => None,
+                },
+                _ => None,
+            };
+            match integer {
+                Some(i) => Value::integer(i),
+                None => Value::float(match op {
+                    Op::Add => x + y,
+                    Op::Sub => x - y,
+                    Op::Mul => x * y,
+                    Op::Div => x / y,
+                    _ => x % y,
+                }),
+            }
+        }
+    };
+    Ok(value)
+}
+
+/// Equality with integers and floats of the same value comparing equal
+fn equal(left: &Value, right: &Value) -> bool {
+    match (left, right) {
+        (Value::Integer(_), Value::Float(_)) | (Value::Float(_), Value::Integer(_)) => {
+            number(left).map(|n| as_f64(&n)) == number(right).map(|n| as_f64(&n))
+        }
+        _ => left == right,
+    }
+}
+
+/// The text an interpolated value contributes to a template
+fn template_text(value: &Value) -> std::result::Result<String, String> {
+    match value {
+        Value::String(s) => Ok(s.clone()),
+        Value::Integer(i) => Ok(i.to_string()),
+        Value::Float(f) => Ok(f.to_string()),
+        Value::Bool(b) => Ok(b.to_string()),
+        Value::Null => Err("cannot interpolate a null value".to_string()),
+        Value::Array(_) | Value::Table(_) => Err(format!(
+            "cannot interpolate {}; only strings, numbers and bools",
+            value.type_name()
+        )),
+        #[cfg(feature = "chrono")]
+        Value::DateTime(dt) => Ok(dt.to_rfc3339()),
+    }
+}
+
+/// Whether evaluating `expr` computes a value rather than spelling one
+/// out (a negative number literal still spells one out)
+fn computed(expr: &Expr) -> bool {
+    match &expr.kind {
+        ExprKind::Literal(_) | ExprKind::Variable(_) | ExprKind::Call => false,
+        ExprKind::Unary('-', operand) => !matches!(operand.kind, ExprKind::Literal(_)),
+        ExprKind::Template(parts) => parts
+            .iter()
+            .any(|part| matches!(part, Part::Interpolation(inner) if computed(inner))),
+        ExprKind::Tuple(items) => items.iter().any(computed),
+        ExprKind::Object(items) => items
+            .iter()
+            .any(|(key, value)| computed(key) || computed(value)),
+        ExprKind::Paren(inner) => computed(inner),
+        ExprKind::Unary(..)
+        | ExprKind::Traversal(..)
+        | ExprKind::Splat(..)
+        | ExprKind::Binary(..)
+        | ExprKind::Conditional(..)
+        | ExprKind::For(_) => true,
+    }
+}
+
+/// An object or `for` key, converted to a string
+fn key_text(value: &Value) -> std::result::Result<String, String> {
+    match value {
+        Value::Null | Value::Array(_) | Value::Table(_) => Err(format!(
+            "object keys must be strings, found {}",
+            value.type_name()
+        )),
+        other => template_text(other),
+    }
+}
+
+/// A parsed HCL file that remembers which entries were blocks.
+///
+/// Plain parsing maps `job "web" { ... }` and `job = { web = { ... } }` to
+/// the same values; the document keeps block labels and attributes apart
+/// so [`HclDocument::sync`] followed by [`HclDocument::render`] writes
+/// edited values back as the same blocks. New keys follow the
+/// [`serialize`] convention, and entries are written in key order.
+///
+/// Comments and the source text of evaluated expressions (`1 + 2` is
+/// read as `3`) are not kept, so documents that had either refuse to
+/// render rather than silently drop them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HclDocument {
+    value: Value,
+    layout: Layout,
+    /// Whether the source had comments, which are not kept
+    comments: bool,
+    /// Whether the source had evaluated expressions, which are written
+    /// back as their values
+    folded: bool,
+}
+
+impl HclDocument {
+    /// The parsed values
+    pub fn value(&self) -> &Value {
+        &self.value
+    }
+
+    /// Consume the document, returning the parsed values
+    pub fn into_value(self) -> Value {
+        self.value
+    }
+
+    /// Replace the document's values; the next render reflects them.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::General`] if `value` cannot be written as HCL.
+    pub fn sync(&mut self, value: &Value) -> Result<()> {
+        self.render_value(value)?;
+        self.value = value.clone();
+        Ok(())
+    }
+
+    /// Write the document back as HCL, in the [`serialize`] style with the
+    /// file's blocks. This is also the [`crate::formatter`] layout.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::General`] if the source had comments or evaluated
+    /// expressions, since writing it would remove them.
+    pub fn render(&self) -> Result<String> {
+        self.check_lossless()?;
+        self.render_value(&self.value)
+    }
+
+    fn check_lossless(&self) -> Result<()> {
+        if self.comments {
+            return Err(Error::serialize(
+                "HCL comments are not kept, so writing the file would remove them",
+            ));
+        }
+        if self.folded {
+            return Err(Error::serialize(
+                "HCL expressions such as `1 + 2` are read as their values, \
+                 so writing the file would replace them",
+            ));
+        }
+        Ok(())
+    }
+
+    fn render_value(&self, value: &Value) -> Result<String> {
+        HclWriter::new(Some(&self.layout)).document(value)
+    }
+
+    /// The document in [`crate::formatter`] layout: entries in source order
+    /// unless `sort_keys` is set (object values are always in key order),
+    /// with `=` signs aligned when `align` is set. Refused like
+    /// [`HclDocument::render`].
+    pub(crate) fn formatted(&self, sort_keys: bool, align: bool) -> Result<String> {
+        self.check_lossless()?;
+        let mut writer = HclWriter::new(Some(&self.layout));
+        writer.source_order = !sort_keys;
+        writer.align = align;
+        writer.document(&self.value)
+    }
+}
+
+/// Parse HCL into an [`HclDocument`] that can be written back with the
+/// same blocks
+pub fn parse_document(content: &str) -> Result<HclDocument> {
+    let mut parser = HclParser::new(content);
+    let value = parser.parse()?;
+    Ok(HclDocument {
+        value,
+        layout: parser.layout,
+        comments: parser.comments,
+        folded: parser.folded,
+    })
+}
+
+/// Serialize a value tree as HCL in `terraform fmt` style.
+///
+/// Tables become blocks and everything else attributes: arrays as
+/// `[...]`, tables inside values as `{ ... }` objects. Attributes come
+/// before blocks, consecutive `=` signs are aligned, and indentation is
+/// two spaces. Multi-line strings are written as `<<-EOT` heredocs.
+///
+/// Strings follow the convention of Terraform's JSON syntax: an
+/// unresolved `${...}` interpolation, as produced by [`parse`], is written
+/// back as an expression (`"${var.region}"` becomes `var.region`), while
+/// any other `${` or `%{` is escaped so it reads back literally.
+///
+/// # Errors
+///
+/// Returns [`Error::General`] if the root is not a table, an attribute or
+/// block name is not an HCL identifier, or a float is NaN or infinite.
+pub fn serialize(value: &Value) -> Result<String> {
+    HclWriter::new(None).document(value)
+}
+
+/// Writes values as HCL, using a parsed file's [`Layout`] when there is one
+struct HclWriter<'a> {
+    layout: Option<&'a Layout>,
+    /// Write entries in the layout's source order rather than attributes
+    /// then blocks, each in key order
+    source_order: bool,
+    /// Align the `=` signs of consecutive attributes
+    align: bool,
+    out: String,
+}
 
-                map.insert(block_name.to_string(), Value::table(block_map));
-            } else if line.contains('=') {
-                // Simple key-value pair
-                let eq_pos = line.find('=').ok_or_else(|| Error::Parse {
-                    message: "Expected '=' in assignment".to_string(),
-                    line: i + 1,
-                    column: 0,
-                    file: None,
-                })?;
-                let key = line[..eq_pos].trim().trim_matches('"').to_string();
-                let value_str = line[eq_pos + 1..].trim().trim_matches('"');
-                let value = self.parse_value(value_str);
-                map.insert(key, value);
-                i += 1;
+impl<'a> HclWriter<'a> {
+    fn new(layout: Option<&'a Layout>) -> Self {
+        Self {
+            layout,
+            source_order: false,
+            align: true,
+            out: String::new(),
+        }
+    }
+
+    fn document(mut self, value: &Value) -> Result<String> {
+        let Value::Table(table) = value else {
+            return Err(Error::serialize(format!(
+                "HCL needs a table at the root, found {}",
+                value.type_name()
+            )));
+        };
+        self.body(table, &mut Vec::new(), 0)?;
+        Ok(self.out)
+    }
+
+    fn body(
+        &mut self,
+        table: &BTreeMap<String, Value>,
+        path: &mut Vec<String>,
+        indent: usize,
+    ) -> Result<()> {
+        let mut entries = Vec::new();
+        for (key, value) in self.ordered(path, table) {
+            check_identifier(key)?;
+            path.push(key.clone());
+            let labels = self.label_count(path, value);
+            path.pop();
+            entries.push((key, value, labels));
+        }
+        if !self.source_order {
+            // Attributes before blocks
+            entries.sort_by_key(|(_, _, labels)| labels.is_some());
+        }
+
+        let mut first = true;
+        let mut attributes = Vec::new();
+        for (key, value, labels) in entries {
+            let Some(labels) = labels else {
+                attributes.push((key.clone(), render_value(value, indent, true, self.align)?));
+                continue;
+            };
+            self.attributes(&mut attributes, indent, &mut first);
+            path.push(key.clone());
+            let mut header = vec![key.clone()];
+            self.blocks(&mut header, value, labels, path, indent, &mut first)?;
+            path.pop();
+        }
+        self.attributes(&mut attributes, indent, &mut first);
+        Ok(())
+    }
+
+    /// Write and clear a run of attributes, after a blank line if anything
+    /// came before them.
+    fn attributes(&mut self, run: &mut Vec<(String, String)>, indent: usize, first: &mut bool) {
+        if run.is_empty() {
+            return;
+        }
+        if !std::mem::take(first) {
+            self.out.push('\n');
+        }
+        write_aligned(&mut self.out, run, indent, self.align);
+        run.clear();
+    }
+
+    /// The entries of the body or labels at `path`, in source order when
+    /// asked for and known, else in key order.
+    fn ordered<'t>(
+        &self,
+        path: &[String],
+        table: &'t BTreeMap<String, Value>,
+    ) -> Vec<(&'t String, &'t Value)> {
+        let mut entries: Vec<_> = table.iter().collect();
+        let known = self
+            .layout
+            .filter(|_| self.source_order)
+            .and_then(|layout| layout.order.get(path));
+        if let Some(names) = known {
+            entries.sort_by_key(|(key, _)| {
+                names
+                    .iter()
+                    .position(|name| name == *key)
+                    .unwrap_or(names.len())
+            });
+        }
+        entries
+    }
+
+    /// How many labels the entry at `path` is written with, or `None` to
+    /// write it as an attribute.
+    fn label_count(&self, path: &[String], value: &Value) -> Option<usize> {
+        let layout = self.layout.unwrap_or(&EMPTY_LAYOUT);
+        if let Some(&labels) = layout.blocks.get(path) {
+            return fits_blocks(value, labels).then_some(labels);
+        }
+        if layout.attributes.contains(path) {
+            return None;
+        }
+        // Tables become blocks unless a key can only be written quoted
+        match value {
+            Value::Table(table) if table.keys().all(|key| check_identifier(key).is_ok()) => Some(0),
+            _ => None,
+        }
+    }
+
+    /// Write `value` as blocks, peeling `labels` levels of tables off as
+    /// block labels.
+    fn blocks(
+        &mut self,
+        header: &mut Vec<String>,
+        value: &Value,
+        labels: usize,
+        path: &mut Vec<String>,
+        indent: usize,
+        first: &mut bool,
+    ) -> Result<()> {
+        match value {
+            Value::Table(table) if labels > 0 => {
+                for (label, child) in self.ordered(path, table) {
+                    header.push(quote(label));
+                    path.push(label.clone());
+                    self.blocks(header, child, labels - 1, path, indent, first)?;
+                    path.pop();
+                    header.pop();
+                }
+            }
+            Value::Array(bodies) => {
+                for body in bodies {
+                    self.blocks(header, body, 0, path, indent, first)?;
+                }
+            }
+            Value::Table(body) => {
+                if !std::mem::take(first) {
+                    self.out.push('\n');
+                }
+                pad(&mut self.out, indent);
+                self.out.push_str(&header.join(" "));
+                if body.is_empty() {
+                    self.out.push_str(" {}\n");
+                } else {
+                    self.out.push_str(" {\n");
+                    self.body(body, path, indent + 2)?;
+                    pad(&mut self.out, indent);
+                    self.out.push_str("}\n");
+                }
+            }
+            _ => unreachable!("label_count only accepts tables and arrays of tables"),
+        }
+        Ok(())
+    }
+}
+
+static EMPTY_LAYOUT: Layout = Layout {
+    blocks: BTreeMap::new(),
+    attributes: BTreeSet::new(),
+    order: BTreeMap::new(),
+};
+
+/// Whether `value` has the table shape of blocks with `labels` labels
+fn fits_blocks(value: &Value, labels: usize) -> bool {
+    match value {
+        Value::Table(table) if labels > 0 => {
+            !table.is_empty() && table.values().all(|child| fits_blocks(child, labels - 1))
+        }
+        Value::Table(_) => true,
+        Value::Array(bodies) => {
+            labels == 0 && !bodies.is_empty() && bodies.iter().all(Value::is_table)
+        }
+        _ => false,
+    }
+}
+
+fn check_identifier(name: &str) -> Result<()> {
+    let mut chars = name.chars();
+    if chars.next().is_some_and(is_id_start) && chars.all(is_id_continue) {
+        Ok(())
+    } else {
+        Err(Error::serialize(format!(
+            "'{name}' is not a valid HCL attribute or block name"
+        )))
+    }
+}
+
+fn pad(out: &mut String, indent: usize) {
+    out.extend(std::iter::repeat(' ').take(indent));
+}
+
+/// Write `name = value` lines, aligning the `=` of consecutive single-line
+/// entries when `align` is set; like `terraform fmt`, multi-line values are
+/// not aligned.
+fn write_aligned(out: &mut String, entries: &[(String, String)], indent: usize, align: bool) {
+    let multi_line = |(_, value): &(String, String)| value.contains('\n');
+    let mut run_start = 0;
+    while run_start < entries.len() {
+        let run_end = if multi_line(&entries[run_start]) {
+            run_start + 1
+        } else {
+            entries[run_start..]
+                .iter()
+                .position(multi_line)
+                .map_or(entries.len(), |at| run_start + at)
+        };
+        let run = &entries[run_start..run_end];
+        let width = run
+            .iter()
+            .map(|(name, _)| name.chars().count())
+            .max()
+            .filter(|_| align)
+            .unwrap_or(0);
+        for (name, value) in run {
+            pad(out, indent);
+            out.push_str(name);
+            pad(out, width.saturating_sub(name.chars().count()));
+            out.push_str(" = ");
+            out.push_str(value);
+            out.push('\n');
+        }
+        run_start = run_end;
+    }
+}
+
+/// Render an expression for a value; multi-line output is indented for a
+/// line starting at `indent`. Heredocs are only used when `heredoc` is set,
+/// and object entries are aligned when `align` is.
+fn render_value(value: &Value, indent: usize, heredoc: bool, align: bool) -> Result<String> {
+    Ok(match value {
+        Value::Null => "null".to_string(),
+        Value::Bool(b) => b.to_string(),
+        Value::Integer(i) => i.to_string(),
+        Value::Float(f) if f.is_finite() => format!("{f:?}"),
+        Value::Float(f) => {
+            return Err(Error::serialize(format!(
+                "HCL has no representation for {f}"
+            )))
+        }
+        Value::String(s) => {
+            if let Some(expression) = bare_expression(s) {
+                expression.to_string()
+            } else if let Some(text) = heredoc.then(|| render_heredoc(s, indent)).flatten() {
+                text
             } else {
-                i += 1;
+                quote(s)
+            }
+        }
+        Value::Array(items) if items.is_empty() => "[]".to_string(),
+        Value::Array(items) => {
+            let rendered = items
+                .iter()
+                .map(|item| render_value(item, indent + 2, false, align))
+                .collect::<Result<Vec<_>>>()?;
+            let inline = items
+                .iter()
+                .all(|item| !item.is_table() && !item.is_array())
+                && rendered.iter().all(|item| !item.contains('\n'));
+            if inline {
+                format!("[{}]", rendered.join(", "))
+            } else {
+                let mut text = "[\n".to_string();
+                for item in rendered {
+                    pad(&mut text, indent + 2);
+                    text.push_str(&item);
+                    text.push_str(",\n");
+                }
+                pad(&mut text, indent);
+                text.push(']');
+                text
+            }
+        }
+        Value::Table(table) if table.is_empty() => "{}".to_string(),
+        Value::Table(table) => {
+            let mut entries = Vec::with_capacity(table.len());
+            for (key, value) in table {
+                let name = if check_identifier(key).is_ok() {
+                    key.clone()
+                } else {
+                    quote(key)
+                };
+                entries.push((name, render_value(value, indent + 2, true, align)?));
             }
+            let mut text = "{\n".to_string();
+            write_aligned(&mut text, &entries, indent + 2, align);
+            pad(&mut text, indent);
+            text.push('}');
+            text
         }
+        #[cfg(feature = "chrono")]
+        Value::DateTime(dt) => quote(&dt.to_rfc3339()),
+    })
+}
+
+/// The expression of a string that is exactly one unresolved
+/// interpolation, if it can be written bare on one line
+fn bare_expression(s: &str) -> Option<&str> {
+    if !s.starts_with("${") || verbatim_interpolation(s)? != s.len() {
+        return None;
+    }
+    let inner = s[2..s.len() - 1].trim();
+    let mut parser = HclParser::new(inner);
+    let expr = parser.expression().ok()?;
+    parser.skip_trivia(false).ok()?;
+    // Unresolved collections and templates read back as values of their
+    // own, not as this string
+    let keeps_text = !matches!(
+        expr.kind,
+        ExprKind::Tuple(_) | ExprKind::Object(_) | ExprKind::Template(_) | ExprKind::Paren(_)
+    );
+    (keeps_text && parser.pos == inner.len()).then_some(inner)
+}
 
-        Ok(Value::table(map))
+/// Length of the `${...}` at the start of `text` when it reads back as
+/// the same unresolved interpolation
+fn verbatim_interpolation(text: &str) -> Option<usize> {
+    let mut parser = HclParser::new(text);
+    parser.pos = 2;
+    parser.multiline = true;
+    if parser.rest().starts_with('~') {
+        return None;
     }
+    let expr = parser.expression().ok()?;
+    parser.skip_trivia(true).ok()?;
+    if !parser.eat('}') {
+        return None;
+    }
+    // Parsing keeps just the expression's own text, so surrounding
+    // whitespace or comments would not survive
+    let exact = expr.start == 2 && expr.end + 1 == parser.pos;
+    (exact && matches!(parser.eval(&expr, &Scope::new()), Ok(None))).then_some(parser.pos)
+}
+
+/// Length of the well-formed `%{...}` directive at the start of `text`
+fn verbatim_directive(text: &str) -> Option<usize> {
+    let mut parser = HclParser::new(text);
+    parser.directive(&mut String::new()).ok()?;
+    Some(parser.pos)
+}
 
-    /// Parse a value string into appropriate type
-    fn parse_value(&self, value_str: &str) -> Value {
-        if let Ok(bool_val) = value_str.parse::<bool>() {
-            Value::bool(bool_val)
-        } else if let Ok(int_val) = value_str.parse::<i64>() {
-            Value::integer(int_val)
-        } else if let Ok(float_val) = value_str.parse::<f64>() {
-            Value::float(float_val)
+/// Write template text, keeping unresolved interpolations and directives
+/// and escaping any other `${` or `%{`; `quoted` also escapes characters
+/// for a `"..."` string.
+fn write_template(out: &mut String, text: &str, quoted: bool) {
+    let mut rest = text;
+    while let Some(ch) = rest.chars().next() {
+        let verbatim = if rest.starts_with("${") {
+            verbatim_interpolation(rest)
+        } else if rest.starts_with("%{") {
+            verbatim_directive(rest)
         } else {
-            Value::string(value_str.to_string())
+            None
+        };
+        if let Some(len) = verbatim {
+            out.push_str(&rest[..len]);
+            rest = &rest[len..];
+            continue;
         }
+        if rest.starts_with("$${") || rest.starts_with("%%{") {
+            // An escape in the text itself: escape both of its characters
+            out.push(ch);
+            out.push_str(&rest[..3]);
+            rest = &rest[3..];
+            continue;
+        }
+        if rest.starts_with("${") || rest.starts_with("%{") {
+            out.push(ch);
+        }
+        match ch {
+            '"' if quoted => out.push_str("\\\""),
+            '\\' if quoted => out.push_str("\\\\"),
+            '\n' if quoted => out.push_str("\\n"),
+            '\r' if quoted => out.push_str("\\r"),
+            '\t' if quoted => out.push_str("\\t"),
+            c if quoted && c.is_control() => {
+                out.push_str(&format!("\\u{:04X}", u32::from(c)));
+            }
+            c => out.push(c),
+        }
+        rest = &rest[ch.len_utf8()..];
     }
 }
 
-/// Placeholder when HCL feature is disabled
-#[cfg(not(feature = "hcl"))]
-pub fn parse_hcl(_content: &str) -> Result<Value> {
-    Err(crate::error::Error::feature_not_enabled("hcl"))
+/// A `"..."` string literal
+fn quote(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    write_template(&mut out, s, true);
+    out.push('"');
+    out
 }
 
-#[cfg(all(test, feature = "hcl"))]
+/// A `<<-EOT` heredoc for multi-line text that reads back unchanged
+fn render_heredoc(s: &str, indent: usize) -> Option<String> {
+    let body = s.strip_suffix('\n')?;
+    let lines: Vec<&str> = body.split('\n').collect();
+    // `<<-` strips the common indentation, so some line must have none
+    let readable = lines.len() > 1
+        && lines
+            .iter()
+            .any(|line| !line.is_empty() && !line.starts_with([' ', '\t']))
+        && lines.iter().all(|line| {
+            (line.is_empty() || !line.trim().is_empty())
+                && line.trim() != "EOT"
+                && !line.chars().any(|c| c != '\t' && c.is_control())
+        });
+    if !readable {
+        return None;
+    }
+    let mut text = "<<-EOT\n".to_string();
+    for line in lines {
+        if !line.is_empty() {
+            pad(&mut text, indent + 2);
+            write_template(&mut text, line, false);
+        }
+        text.push('\n');
+    }
+    pad(&mut text, indent);
+    text.push_str("EOT");
+    Some(text)
+}
+
+#[cfg(test)]
 mod tests {
     use super::*;
 
@@ -157,7 +2157,7 @@ mod tests {
           port = 5432
           enabled = true
         }
-        
+
         app {
           name = "MyApp"
           version = "1.0.0"
@@ -192,43 +2192,68 @@ mod tests {
         resource "aws_instance" "web" {
           ami           = "ami-12345678"
           instance_type = "t2.micro"
-          
+          count         = var.instance_count
+
           tags = {
-            Name = "WebServer"
+            Name = "WebServer-${count.index + 1}"
             Environment = "production"
           }
         }
-        
+
+        resource "aws_instance" "db" {
+          ami = "ami-87654321"
+          ebs_block_device {
+            device_name = "/dev/sdb"
+          }
+          ebs_block_device {
+            device_name = "/dev/sdc"
+          }
+        }
+
         variable "region" {
           description = "AWS region"
-          type        = "string"
+          type        = string
           default     = "us-west-2"
         }
-        "#;
 
-        let result = parse_hcl(hcl);
-
-        // Test passes if parsing doesn't panic (HCL syntax can be complex)
-        match result {
-            Ok(Value::Table(_)) => {
-                // Successfully parsed
-            }
-            Ok(_) => panic!("Expected table result"),
-            Err(_) => {
-                // Some HCL syntax variants are not supported by the simplified
-                // parser; the test tolerates any structured `Err` here as long
-                // as it didn't panic. Specific syntax coverage is asserted
-                // separately in `test_terraform_style_hcl`.
-            }
+        output "ips" {
+          value = aws_instance.web[*].public_ip
         }
+        "#;
+
+        let config = parse_hcl(hcl).unwrap();
+        let get = |path: &str| config.get(path).cloned();
+        assert_eq!(
+            get("resource.aws_instance.web.tags.Name"),
+            Some(Value::string("WebServer-${count.index + 1}"))
+        );
+        assert_eq!(
+            get("resource.aws_instance.web.count"),
+            Some(Value::string("${var.instance_count}"))
+        );
+        assert_eq!(
+            get("resource.aws_instance.db.ami"),
+            Some(Value::string("ami-87654321"))
+        );
+        assert_eq!(
+            get("resource.aws_instance.db.ebs_block_device[1].device_name"),
+            Some(Value::string("/dev/sdc"))
+        );
+        assert_eq!(
+            get("variable.region.type"),
+            Some(Value::string("${string}"))
+        );
+        assert_eq!(
+            get("output.ips.value"),
+            Some(Value::string("${aws_instance.web[*].public_ip}"))
+        );
     }
 
     #[test]
-    #[ignore] // Complex HCL structures not supported in simplified parser
     fn test_hcl_arrays_and_objects() {
         let hcl = r#"
         servers = ["web1", "web2", "web3"]
-        
+
         database {
           replicas = [
             {
@@ -236,7 +2261,7 @@ mod tests {
               role = "master"
             },
             {
-              host = "db2.example.com" 
+              host = "db2.example.com"
               role = "slave"
             }
           ]
@@ -271,4 +2296,286 @@ mod tests {
             panic!("Expected table result");
         }
     }
+
+    #[test]
+    fn test_literal_expressions() {
+        let hcl = r#"
+        sum     = 1 + 2 * 3
+        ratio   = 7 / 2
+        modulo  = -7 % 3
+        check   = 2 > 1 && !false
+        pick    = 1 == 1.0 ? "yes" : "no"
+        squares = [for n in [1, 2, 3] : n * n if n != 2]
+        by_name = {for i, name in ["a", "b"] : name => i}
+        grouped = {for w in ["ant", "bee", "ape"] : substr(w) => w...}
+        ports   = [{ port = 80 }, { port = 443 }][*].port
+        second  = ["x", "y"][1]
+        nested  = { a = { b = [10, 20] } }.a.b.1
+        text    = "tab\t\"quoted\" é $${literal} ${"in" }${ 1 + 1 }"
+        mixed   = [1, var.x, "${var.y}-suffix", upper("a")]
+        strip   = "a ${~ "b" ~} c"
+        script  = <<-EOT
+            line one
+              indented ${2 * 21}
+            %{ if true }kept%{ endif }
+            EOT
+        raw = <<EOT
+  as is
+EOT
+        "#;
+        let config = parse_hcl(hcl).unwrap();
+        let get = |path: &str| config.get(path).cloned().unwrap();
+        assert_eq!(get("sum"), Value::integer(7));
+        assert_eq!(get("ratio"), Value::float(3.5));
+        assert_eq!(get("modulo"), Value::integer(-1));
+        assert_eq!(get("check"), Value::bool(true));
+        assert_eq!(get("pick"), Value::string("yes"));
+        assert_eq!(
+            get("squares"),
+            Value::array(vec![Value::integer(1), Value::integer(9)])
+        );
+        assert_eq!(get("by_name.b"), Value::integer(1));
+        assert_eq!(
+            get("grouped"),
+            Value::string("${{for w in [\"ant\", \"bee\", \"ape\"] : substr(w) => w...}}")
+        );
+        assert_eq!(
+            get("ports"),
+            Value::array(vec![Value::integer(80), Value::integer(443)])
+        );
+        assert_eq!(get("second"), Value::string("y"));
+        assert_eq!(get("nested"), Value::integer(20));
+        assert_eq!(
+            get("text"),
+            Value::string("tab\t\"quoted\" \u{e9} ${literal} in2")
+        );
+        assert_eq!(
+            get("mixed"),
+            Value::array(vec![
+                Value::integer(1),
+                Value::string("${var.x}"),
+                Value::string("${var.y}-suffix"),
+                Value::string("${upper(\"a\")}"),
+            ])
+        );
+        assert_eq!(get("strip"), Value::string("abc"));
+        assert_eq!(
+            get("script"),
+            Value::string("line one\n  indented 42\n%{ if true }kept%{ endif }\n")
+        );
+        assert_eq!(get("raw"), Value::string("  as is\n"));
+    }
+
+    #[test]
+    fn test_serialize_terraform_fmt_style() {
+        let value = parse_hcl(
+            "name = \"app\"\nreplicas = 3\nratio = 0.5\nports = [80, 443]\n\
+             labels = { tier = \"web\", \"app.kubernetes.io/name\" = \"app\" }\n\
+             server { host = \"localhost\" }\nempty {}\n",
+        )
+        .unwrap();
+        assert_eq!(
+            serialize(&value).unwrap(),
+            "labels = {\n  \"app.kubernetes.io/name\" = \"app\"\n  tier                     = \"web\"\n}\n\
+             name     = \"app\"\n\
+             ports    = [80, 443]\n\
+             ratio    = 0.5\n\
+             replicas = 3\n\
+             \n\
+             empty {}\n\
+             \n\
+             server {\n  host = \"localhost\"\n}\n"
+        );
+        assert!(serialize(&Value::integer(1)).is_err());
+        assert!(serialize(&parse_hcl("x = 1").unwrap().get("x").cloned().unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_document_keeps_blocks_and_labels() {
+        let source = r#"
+job "web" {
+  datacenters = ["dc1"]
+  meta = {
+    owner = "ops"
+  }
+
+  group "api" {
+    count = var.count
+
+    task "server" {
+      driver = "docker"
+      config {
+        image = "nginx:${var.tag}"
+        args  = ["-g", "daemon off;"]
+      }
+      template {
+        data        = <<-EOH
+          upstream {
+            server ${NOMAD_ADDR_http};
+          }
+        EOH
+        destination = "local/nginx.conf"
+      }
+      template {
+        data        = "x"
+        destination = "local/x"
+      }
+    }
+  }
+}
+"#;
+        let mut document = parse_document(source).unwrap();
+        let value = document.value().clone();
+        assert_eq!(
+            value.get("job.web.group.api.task.server.template[1].destination"),
+            Some(&Value::string("local/x"))
+        );
+
+        let mut edited = value.clone();
+        edited
+            .set_nested("job.web.group.api.count", Value::integer(2))
+            .unwrap();
+        edited
+            .set_nested("job.web.group.api.network.mode", Value::string("bridge"))
+            .unwrap();
+        document.sync(&edited).unwrap();
+        let written = document.render().unwrap();
+        assert_eq!(
+            written,
+            r#"job "web" {
+  datacenters = ["dc1"]
+  meta = {
+    owner = "ops"
+  }
+
+  group "api" {
+    count = 2
+
+    network {
+      mode = "bridge"
+    }
+
+    task "server" {
+      driver = "docker"
+
+      config {
+        args  = ["-g", "daemon off;"]
+        image = "nginx:${var.tag}"
+      }
+
+      template {
+        data = <<-EOT
+          upstream {
+            server ${NOMAD_ADDR_http};
+          }
+        EOT
+        destination = "local/nginx.conf"
+      }
+
+      template {
+        data        = "x"
+        destination = "local/x"
+      }
+    }
+  }
+}
+"#
+        );
+        assert_eq!(parse_hcl(&written).unwrap(), edited);
+        assert_eq!(
+            serialize(&value).unwrap().lines().next(),
+            Some("job {"),
+            "without a document, labels are ordinary blocks"
+        );
+    }
+
+    #[test]
+    fn test_render_refuses_lossy_documents() {
+        let plain = parse_document("offset = -1\nname = \"web-${var.env}\"\n").unwrap();
+        assert_eq!(
+            plain.render().unwrap(),
+            "name   = \"web-${var.env}\"\noffset = -1\n"
+        );
+
+        for source in [
+            "# replicas\ncount = 2\n",
+            "count = 1 + 2\n",
+            "name = \"web-${1 + 1}\"\n",
+            "ports = [for p in [80, 443] : p]\n",
+        ] {
+            let document = parse_document(source).unwrap();
+            assert!(document.render().is_err(), "{source:?}");
+        }
+    }
+
+    #[test]
+    fn test_serialize_round_trips_strings() {
+        let value = parse_hcl(
+            r#"
+            count   = var.count
+            name    = "web-${count.index}"
+            literal = "$${not} %%{ interpolated } $$${x}"
+            dollar  = "100%{ if true }on%{ endif } $5"
+            mixed   = [var.a, "b", { c = local.c }]
+            quotes  = "say \"hi\"\\n\t\u0001"
+            "#,
+        )
+        .unwrap();
+        let written = serialize(&value).unwrap();
+        assert!(written.contains("count   = var.count\n"), "{written}");
+        assert!(
+            written.contains("literal = \"${not} %{ interpolated } $$${x}\"\n"),
+            "{written}"
+        );
+        assert_eq!(parse_hcl(&written).unwrap(), value, "{written}");
+
+        let mut table = BTreeMap::new();
+        table.insert("text".to_string(), Value::string("${1 + 1} and %{"));
+        table.insert("spaced".to_string(), Value::string("${ var.x }"));
+        table.insert("multi".to_string(), Value::string("  indented\nonly\n"));
+        table.insert("tricky".to_string(), Value::string("  all\n  indented\n"));
+        let value = Value::table(table);
+        let written = serialize(&value).unwrap();
+        assert!(
+            written.contains("<<-EOT\n    indented\n  only\nEOT"),
+            "{written}"
+        );
+        assert!(
+            written.contains("tricky = \"  all\\n  indented\\n\""),
+            "{written}"
+        );
+        assert_eq!(parse_hcl(&written).unwrap(), value, "{written}");
+    }
+
+    #[test]
+    fn test_errors_report_position() {
+        let cases = [
+            ("a = 1\na = 2\n", 2, 1, "duplicate attribute"),
+            ("a = 1\na {\n}\n", 2, 1, "already defined"),
+            ("block {\n  x = [1,\n", 3, 1, "expected"),
+            ("x = \"open\n", 1, 5, "unterminated string"),
+            ("x = 1 / 0\n", 1, 5, "division by zero"),
+            ("x = [1, 2][5]\n", 1, 12, "out of range"),
+            ("x = 1 2\n", 1, 7, "expected a newline"),
+            ("x = <<EOT\nnever closed\n", 1, 5, "unterminated heredoc"),
+            ("label \"${x}\" {\n}\n", 1, 7, "interpolations"),
+        ];
+        for (source, line, column, message) in cases {
+            match parse_hcl(source) {
+                Err(Error::Parse {
+                    line: l,
+                    column: c,
+                    message: m,
+                    ..
+                }) => {
+                    assert_eq!((l, c), (line, column), "{source:?}: {m}");
+                    assert!(m.contains(message), "{source:?}: {m}");
+                }
+                other => panic!("{source:?} parsed as {other:?}"),
+            }
+        }
+
+        assert!(parse_hcl(&format!("x = {}1{}\n", "[".repeat(200), "]".repeat(200))).is_err());
+        assert!(parse_hcl(&format!("x = 1{}\n", " + 1".repeat(500))).is_err());
+    }
 }
@@ -7,10 +7,21 @@
 //! - Escape sequences: \n, \t, \\, etc.
 //! - Quoted values with spaces
 //! - Case-sensitive keys and sections
+//!
+//! Values are stored flat at the root as `section.key`. [`serialize`]
+//! writes a value tree back out (nested tables become sections), and
+//! [`parse_document`] keeps the source layout so edited files can be saved
+//! with untouched lines byte-identical.
+//!
+//! [`serialize`]: crate::parsers::ini_parser::serialize
+//! [`parse_document`]: crate::parsers::ini_parser::parse_document
 
+use super::conf::{detached_trivia, join_trivia};
 use crate::error::{Error, Result};
+use crate::formatter::{Comments, Line};
 use crate::value::Value;
 use std::collections::BTreeMap;
+use std::fmt;
 
 /// Parse INI format configuration
 pub fn parse(source: &str) -> Result<Value> {
@@ -19,12 +30,593 @@ pub fn parse(source: &str) -> Result<Value> {
 
 /// Parse INI format string into a Value::Table
 pub fn parse_ini(content: &str) -> Result<Value> {
+    parse_document(content).map(IniDocument::into_value)
+}
+
+/// Parse INI source into a lossless, editable [`IniDocument`].
+pub fn parse_document(content: &str) -> Result<IniDocument> {
     let mut parser = IniParser::new(content);
     parser.parse()
 }
 
+/// Serialize a value tree as INI.
+///
+/// Root scalars are written before the first section; dotted root keys and
+/// nested tables are split into `[section]` headers at the first dot, which
+/// parses back to the same flat keys. `Null` is written as `""`.
+///
+/// # Errors
+///
+/// Returns [`Error::General`], naming the key, if the value contains
+/// arrays, which INI cannot represent, strings that would read back as
+/// another type, or keys the parser could not read back. The parser types
+/// quoted values too, so strings such as `"42"`, `"1.5"` or `"yes"` have
+/// no INI spelling; the integers `0` and `1` are written as `+0` and `+1`
+/// so they do not read back as booleans.
+pub fn serialize(value: &Value) -> Result<String> {
+    let mut document = IniDocument::default();
+    document.sync(value)?;
+    Ok(document.to_string())
+}
+
+/// A parsed INI file that remembers its layout.
+///
+/// Keys are the flat `section.key` form the parser produces.
+/// [`sync`](Self::sync) rewrites only the entries whose values changed,
+/// drops removed ones with the comments directly above them, and appends new
+/// keys to the matching section (reusing a sibling's indentation and
+/// `=` / `:` separator). Changed strings keep their quote character.
+#[derive(Debug, Clone)]
+pub struct IniDocument {
+    items: Vec<Item>,
+    /// Comments and blank lines after the last item.
+    trailer: String,
+    newline: &'static str,
+    value: Value,
+}
+
+impl Default for IniDocument {
+    fn default() -> Self {
+        Self {
+            items: Vec::new(),
+            trailer: String::new(),
+            newline: "\n",
+            value: Value::table(BTreeMap::new()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Item {
+    Section {
+        leading: String,
+        header: String,
+        name: String,
+        trailing: String,
+    },
+    Entry(Entry),
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    leading: String,
+    key: String,
+    /// `section.key`, as stored in the value tree.
+    full_key: String,
+    separator: String,
+    raw: String,
+    trailing: String,
+    value: Value,
+}
+
+impl Item {
+    fn leading_mut(&mut self) -> &mut String {
+        match self {
+            Item::Section { leading, .. } | Item::Entry(Entry { leading, .. }) => leading,
+        }
+    }
+
+    fn trailing_mut(&mut self) -> &mut String {
+        match self {
+            Item::Section { trailing, .. } | Item::Entry(Entry { trailing, .. }) => trailing,
+        }
+    }
+}
+
+impl IniDocument {
+    /// The parsed values (a flat table of `section.key` entries).
+    pub fn value(&self) -> &Value {
+        &self.value
+    }
+
+    /// Consume the document, keeping only the values.
+    pub fn into_value(self) -> Value {
+        self.value
+    }
+
+    /// Set the flat `section.key` entry `key`, editing it in place or
+    /// appending it to its section.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::General`] if the key or value cannot be written
+    /// as INI; the document is unchanged in that case.
+    pub fn set(&mut self, key: &str, value: Value) -> Result<()> {
+        let mut target = self.value.clone();
+        if let Value::Table(table) = &mut target {
+            table.insert(key.to_string(), value);
+        }
+        self.sync(&target)
+    }
+
+    /// Remove the flat `section.key` entry `key`.
+    pub fn remove(&mut self, key: &str) -> Option<Value> {
+        let mut target = self.value.clone();
+        let removed = match &mut target {
+            Value::Table(table) => table.remove(key)?,
+            _ => return None,
+        };
+        // Removing an entry never needs to render anything.
+        let _ = self.sync(&target);
+        Some(removed)
+    }
+
+    /// Reconcile the document with `target`, which may be flat (as parsed)
+    /// or nested (tables become sections).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `target` is not a table or cannot be written as
+    /// INI; the document is unchanged in that case.
+    pub fn sync(&mut self, target: &Value) -> Result<()> {
+        let desired = flatten(target)?;
+        let mut doc = self.clone();
+
+        let stale: Vec<bool> = doc
+            .items
+            .iter()
+            .map(|item| match item {
+                Item::Entry(entry) => !desired.contains_key(&entry.full_key),
+                Item::Section { .. } => false,
+            })
+            .collect();
+        doc.remove_items(&stale);
+
+        // Root keys first, so they land above any section created below.
+        let (root, sectioned): (Vec<_>, Vec<_>) =
+            desired.iter().partition(|(key, _)| !key.contains('.'));
+        for (full_key, value) in root.into_iter().chain(sectioned) {
+            let existing = doc.items.iter_mut().rev().find_map(|item| match item {
+                Item::Entry(entry) if entry.full_key == *full_key => Some(entry),
+                _ => None,
+            });
+            match existing {
+                Some(entry) if entry.value != *value => {
+                    entry.raw = render_value(value, &entry.raw, full_key)?;
+                    entry.value = value.clone();
+                }
+                Some(_) => {}
+                None => doc.insert(full_key, value)?,
+            }
+        }
+
+        let mut flat = BTreeMap::new();
+        for item in &doc.items {
+            if let Item::Entry(entry) = item {
+                flat.insert(entry.full_key.clone(), entry.value.clone());
+            }
+        }
+        doc.value = Value::table(flat);
+        *self = doc;
+        Ok(())
+    }
+
+    /// Add a new entry at the end of the section that owns `full_key`,
+    /// creating the section if needed.
+    fn insert(&mut self, full_key: &str, value: &Value) -> Result<()> {
+        let section = self
+            .items
+            .iter()
+            .filter_map(|item| match item {
+                Item::Section { name, .. }
+                    if full_key.len() > name.len()
+                        && full_key.starts_with(name.as_str())
+                        && full_key.as_bytes()[name.len()] == b'.' =>
+                {
+                    Some(name.clone())
+                }
+                _ => None,
+            })
+            .max_by_key(String::len);
+        let (section, key) = match section {
+            Some(name) => {
+                let key = full_key[name.len() + 1..].to_string();
+                (Some(name), key)
+            }
+            None => match full_key.split_once('.') {
+                Some((name, key)) => {
+                    self.push_section(name)?;
+                    (Some(name.to_string()), key.to_string())
+                }
+                None => (None, full_key.to_string()),
+            },
+        };
+        if !is_valid_key(&key) {
+            return Err(Error::serialize(format!(
+                "Cannot write key '{full_key}' as INI"
+            )));
+        }
+
+        let mut current = None;
+        let mut at = 0;
+        let mut template = None;
+        for (index, item) in self.items.iter().enumerate() {
+            match item {
+                Item::Section { name, .. } => current = Some(name.as_str()),
+                Item::Entry(entry) if current == section.as_deref() => template = Some(entry),
+                Item::Entry(_) => {}
+            }
+            if current == section.as_deref() {
+                at = index + 1;
+            }
+        }
+        let (indent, separator) = template.map_or_else(
+            || (String::new(), " = ".to_string()),
+            |entry| {
+                (
+                    entry.leading.rsplit('\n').next().unwrap_or("").to_string(),
+                    entry.separator.clone(),
+                )
+            },
+        );
+
+        let entry = Entry {
+            leading: indent,
+            key,
+            full_key: full_key.to_string(),
+            separator,
+            raw: render_value(value, "", full_key)?,
+            trailing: self.newline.to_string(),
+            value: value.clone(),
+        };
+        if at > 0 {
+            self.end_line(at - 1);
+        }
+        self.items.insert(at, Item::Entry(entry));
+        Ok(())
+    }
+
+    /// Append an empty `[name]` header, separated by a blank line.
+    fn push_section(&mut self, name: &str) -> Result<()> {
+        if name.is_empty() || name.contains([']', '\n', '\r']) || name.trim() != name {
+            return Err(Error::serialize(format!(
+                "Cannot write section '{name}' as INI"
+            )));
+        }
+        let leading = if let Some(last) = self.items.len().checked_sub(1) {
+            self.end_line(last);
+            self.newline.to_string()
+        } else {
+            String::new()
+        };
+        self.items.push(Item::Section {
+            leading,
+            header: format!("[{name}]"),
+            name: name.to_string(),
+            trailing: self.newline.to_string(),
+        });
+        Ok(())
+    }
+
+    /// Remove the flagged items, keeping trivia above the last blank line
+    /// before each removed run.
+    fn remove_items(&mut self, stale: &[bool]) {
+        if !stale.contains(&true) {
+            return;
+        }
+        let mut kept = Vec::with_capacity(self.items.len());
+        let mut carry = String::new();
+        let mut previous_removed = false;
+        for (mut item, remove) in std::mem::take(&mut self.items).into_iter().zip(stale) {
+            if *remove {
+                if !previous_removed {
+                    carry = detached_trivia(item.leading_mut()).to_string();
+                }
+                previous_removed = true;
+                continue;
+            }
+            previous_removed = false;
+            if !carry.is_empty() {
+                let leading = item.leading_mut();
+                *leading = join_trivia(&std::mem::take(&mut carry), leading);
+            }
+            kept.push(item);
+        }
+        if !carry.is_empty() {
+            self.trailer = join_trivia(&carry, &self.trailer);
+        }
+        self.items = kept;
+    }
+
+    fn end_line(&mut self, index: usize) {
+        let newline = self.newline;
+        let trailing = self.items[index].trailing_mut();
+        if !trailing.ends_with('\n') {
+            trailing.push_str(newline);
+        }
+    }
+
+    /// The document as [`crate::formatter`] lines, with `;` comments and
+    /// values quoted only where needed (booleans keep their wording).
+    pub(crate) fn lines(&self) -> Result<Vec<Line>> {
+        let comments = Comments {
+            markers: &[';', '#'],
+            canonical: ';',
+        };
+        let mut lines = Vec::new();
+        for item in &self.items {
+            match item {
+                Item::Section {
+                    leading,
+                    name,
+                    trailing,
+                    ..
+                } => {
+                    comments.push_lines(leading, &mut lines);
+                    lines.push(Line::Section {
+                        name: name.clone(),
+                        comment: comments.inline(trailing),
+                    });
+                }
+                Item::Entry(entry) => {
+                    comments.push_lines(&entry.leading, &mut lines);
+                    lines.push(Line::Entry {
+                        key: entry.key.clone(),
+                        value: render_value(
+                            &entry.value,
+                            boolean_spelling(entry),
+                            &entry.full_key,
+                        )?,
+                        comment: comments.inline(&entry.trailing),
+                    });
+                }
+            }
+        }
+        comments.push_lines(&self.trailer, &mut lines);
+        Ok(lines)
+    }
+
+    /// Where each section header and key starts, with the `=` or `:`
+    /// after each key, for the document outline.
+    pub(crate) fn marks(&self) -> Vec<super::locate::Mark> {
+        use super::locate::Mark;
+
+        let mut marks = Vec::new();
+        let mut offset = 0;
+        let mut scope = String::new();
+        for item in &self.items {
+            match item {
+                Item::Section {
+                    leading,
+                    header,
+                    name,
+                    trailing,
+                } => {
+                    offset += leading.len();
+                    marks.push(Mark::section(offset, name));
+                    scope = crate::path::join_key("", name);
+                    offset += header.len() + trailing.len();
+                }
+                Item::Entry(entry) => {
+                    offset += entry.leading.len();
+                    let path = crate::path::join_key("", &entry.full_key);
+                    let mut mark = Mark::entry(offset, &scope, &entry.key, path);
+                    if let Some(separator) = entry.separator.trim_start().chars().next() {
+                        mark = mark.separated(separator);
+                    }
+                    marks.push(mark);
+                    offset += entry.key.len()
+                        + entry.separator.len()
+                        + entry.raw.len()
+                        + entry.trailing.len();
+                }
+            }
+        }
+        marks
+    }
+}
+
+impl fmt::Display for IniDocument {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for item in &self.items {
+            match item {
+                Item::Section {
+                    leading,
+                    header,
+                    trailing,
+                    ..
+                } => write!(f, "{leading}{header}{trailing}")?,
+                Item::Entry(entry) => write!(
+                    f,
+                    "{}{}{}{}{}",
+                    entry.leading, entry.key, entry.separator, entry.raw, entry.trailing
+                )?,
+            }
+        }
+        f.write_str(&self.trailer)
+    }
+}
+
+/// Replace what [`serialize`] refuses with what INI would read back, so
+/// a conversion can report the difference instead of failing: arrays
+/// become tables keyed by index (`hosts.0`), and scalars whose spelling
+/// parses as another value (the string `"42"`) become that value.
+pub(crate) fn make_writable(value: &mut Value) {
+    match value {
+        Value::Table(table) => table.values_mut().for_each(make_writable),
+        Value::Array(items) => {
+            let mut table: BTreeMap<String, Value> = std::mem::take(items)
+                .into_iter()
+                .enumerate()
+                .map(|(index, item)| (index.to_string(), item))
+                .collect();
+            table.values_mut().for_each(make_writable);
+            *value = Value::table(table);
+        }
+        scalar => {
+            if render_value(scalar, "", "").is_err() {
+                if let Some(read) = spelling(scalar, "", "").ok().and_then(|raw| reparse(&raw)) {
+                    *scalar = read;
+                }
+            }
+        }
+    }
+}
+
+/// Collect scalar leaves as flat `a.b.c` keys.
+fn flatten(value: &Value) -> Result<BTreeMap<String, Value>> {
+    fn walk(
+        table: &BTreeMap<String, Value>,
+        prefix: &str,
+        out: &mut BTreeMap<String, Value>,
+    ) -> Result<()> {
+        for (key, value) in table {
+            let key = if prefix.is_empty() {
+                key.clone()
+            } else {
+                format!("{prefix}.{key}")
+            };
+            match value {
+                Value::Table(nested) => walk(nested, &key, out)?,
+                Value::Array(_) => {
+                    return Err(Error::serialize(format!(
+                        "INI cannot represent the array at '{key}'"
+                    )))
+                }
+                _ => {
+                    if out.insert(key.clone(), value.clone()).is_some() {
+                        return Err(Error::serialize(format!(
+                            "INI key '{key}' is defined both flat and nested"
+                        )));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    let mut out = BTreeMap::new();
+    walk(value.as_table()?, "", &mut out)?;
+    Ok(out)
+}
+
+/// The raw text of a boolean entry, whose wording the formatter keeps.
+fn boolean_spelling(entry: &Entry) -> &str {
+    match entry.value {
+        Value::Bool(_) => &entry.raw,
+        _ => "",
+    }
+}
+
+/// Keys must survive [`IniParser::parse_key`].
+fn is_valid_key(key: &str) -> bool {
+    !key.is_empty()
+        && key.trim() == key
+        && !key.starts_with('[')
+        && !key.contains(['=', ':', ';', '#', '\n', '\r'])
+}
+
+/// Write `value` as the INI value of `key`, keeping the boolean spelling
+/// and quote character of `original` (empty for new entries). Only a
+/// spelling that parses back to exactly `value` is accepted, apart from
+/// `Null`, which INI can only write as `""`.
+fn render_value(value: &Value, original: &str, key: &str) -> Result<String> {
+    let raw = spelling(value, original, key)?;
+    let equal = |read: Value| {
+        crate::diff::DiffOptions::new()
+            .strict_numbers(true)
+            .equal(&read, value)
+    };
+    if value.is_null() || reparse(&raw).is_some_and(equal) {
+        Ok(raw)
+    } else {
+        Err(Error::serialize(format!(
+            "Cannot write {} value at '{key}' as INI without changing it",
+            value.type_name()
+        )))
+    }
+}
+
+fn spelling(value: &Value, original: &str, key: &str) -> Result<String> {
+    Ok(match value {
+        Value::Null => "\"\"".to_string(),
+        Value::Bool(b) => {
+            let (yes, no) = match original.to_ascii_lowercase().as_str() {
+                "yes" | "no" => ("yes", "no"),
+                "on" | "off" => ("on", "off"),
+                "1" | "0" => ("1", "0"),
+                _ => ("true", "false"),
+            };
+            if *b { yes } else { no }.to_string()
+        }
+        // Bare `0` and `1` read back as booleans
+        Value::Integer(i @ (0 | 1)) => format!("+{i}"),
+        Value::Integer(i) => i.to_string(),
+        Value::Float(f) if f.fract() == 0.0 && f.is_finite() => format!("{f:.1}"),
+        Value::Float(f) => f.to_string(),
+        Value::String(s) => {
+            let quote = original.chars().next().filter(|c| matches!(c, '"' | '\''));
+            let needs_quotes = s.is_empty()
+                || s.trim() != s
+                || s.contains([';', '#', '"', '\'', '\n', '\r', '\t']);
+            match quote.or(needs_quotes.then_some('"')) {
+                Some(q) => {
+                    let mut out = String::with_capacity(s.len() + 2);
+                    out.push(q);
+                    for ch in s.chars() {
+                        match ch {
+                            '\\' => out.push_str("\\\\"),
+                            '\n' => out.push_str("\\n"),
+                            '\t' => out.push_str("\\t"),
+                            '\r' => out.push_str("\\r"),
+                            c if c == q => {
+                                out.push('\\');
+                                out.push(c);
+                            }
+                            c => out.push(c),
+                        }
+                    }
+                    out.push(q);
+                    out
+                }
+                None => s.replace('\\', "\\\\"),
+            }
+        }
+        Value::Array(_) | Value::Table(_) => {
+            return Err(Error::serialize(format!(
+                "INI cannot represent the {} value at '{key}'",
+                value.type_name()
+            )))
+        }
+        #[cfg(feature = "chrono")]
+        Value::DateTime(dt) => dt.to_rfc3339(),
+    })
+}
+
+/// Parse a raw value the way an entry would, requiring it to be consumed
+/// entirely.
+fn reparse(raw: &str) -> Option<Value> {
+    let mut parser = IniParser::new(raw);
+    let value = parser.parse_value().ok()?;
+    parser.content[parser.position..]
+        .trim()
+        .is_empty()
+        .then_some(value)
+}
+
 struct IniParser<'a> {
     content: &'a str,
+    /// Byte offset into `content`
     position: usize,
     line: usize,
     current_section: Option<String>,
@@ -42,39 +634,74 @@ impl<'a> IniParser<'a> {
         }
     }
 
-    fn parse(&mut self) -> Result<Value> {
-        while self.position < self.content.len() {
+    fn parse(&mut self) -> Result<IniDocument> {
+        let mut items = Vec::new();
+        loop {
+            let mark = self.position;
             self.skip_whitespace_and_comments()?;
+            let leading = self.content[mark..self.position].to_string();
 
             if self.position >= self.content.len() {
-                break;
+                return Ok(IniDocument {
+                    items,
+                    trailer: leading,
+                    newline: if self.content.contains("\r\n") {
+                        "\r\n"
+                    } else {
+                        "\n"
+                    },
+                    value: Value::Table(std::mem::take(&mut self.result)),
+                });
             }
 
-            let ch = self.current_char();
-
-            match ch {
-                '[' => self.parse_section()?,
-                '\n' | '\r' => {
-                    self.advance();
-                    self.line += 1;
-                }
-                _ => self.parse_key_value()?,
+            if self.current_char() == '[' {
+                let start = self.position;
+                self.parse_section()?;
+                let header = self.content[start..self.position].to_string();
+                let trailing = self.finish_line(self.position);
+                items.push(Item::Section {
+                    leading,
+                    header,
+                    name: self.current_section.clone().unwrap_or_default(),
+                    trailing,
+                });
+            } else {
+                items.push(Item::Entry(self.parse_key_value(leading)?));
             }
         }
-
-        Ok(Value::Table(self.result.clone()))
     }
 
     fn current_char(&self) -> char {
-        self.content.chars().nth(self.position).unwrap_or('\0')
+        self.content[self.position..].chars().next().unwrap_or('\0')
     }
 
     fn advance(&mut self) {
         if self.position < self.content.len() {
-            self.position += 1;
+            self.position += self.current_char().len_utf8();
         }
     }
 
+    /// Consume trailing whitespace, an inline comment and the line ending,
+    /// returning the text from `from` onwards.
+    fn finish_line(&mut self, from: usize) -> String {
+        while matches!(self.current_char(), ' ' | '\t') {
+            self.advance();
+        }
+        if matches!(self.current_char(), ';' | '#') {
+            while !matches!(self.current_char(), '\r' | '\n' | '\0') {
+                self.advance();
+            }
+        }
+        if self.content[self.position..].starts_with("\r\n") {
+            self.advance();
+        }
+        if self.current_char() == '\n' {
+            self.advance();
+            self.line += 1;
+        }
+        self.content[from..self.position].to_string()
+    }
+
     // Commented out to avoid unused warnings - could be useful for future enhancements
     // fn peek_char(&self, offset: usize) -> char {
     //     self.content.chars().nth(self.position + offset).unwrap_or('\0')
@@ -145,12 +772,19 @@ impl<'a> IniParser<'a> {
         Ok(())
     }
 
-    fn parse_key_value(&mut self) -> Result<()> {
+    fn parse_key_value(&mut self, leading: String) -> Result<Entry> {
+        let key_start = self.position;
         let key = self.parse_key()?;
 
         if key.is_empty() {
-            return Ok(()); // Skip empty lines
+            return Err(Error::Parse {
+                message: "Expected key before separator".to_string(),
+                line: self.line,
+                column: 1,
+                file: None,
+            });
         }
+        let key_end = key_start + key.len();
 
         self.skip_whitespace_and_comments()?;
 
@@ -167,16 +801,30 @@ impl<'a> IniParser<'a> {
         self.advance(); // Skip separator
         self.skip_whitespace_and_comments()?;
 
+        let value_start = self.position;
         let value = self.parse_value()?;
+        // Unquoted values run up to the comment; keep that padding as trivia.
+        let raw = self.content[value_start..self.position]
+            .trim_end()
+            .to_string();
+        let trailing = self.finish_line(value_start + raw.len());
 
         // Store the key-value pair
         let full_key = match &self.current_section {
             Some(section) => format!("{section}.{key}"),
-            None => key,
+            None => key.clone(),
         };
 
-        self.result.insert(full_key, value);
-        Ok(())
+        self.result.insert(full_key.clone(), value.clone());
+        Ok(Entry {
+            leading,
+            key,
+            full_key,
+            separator: self.content[key_end..value_start].to_string(),
+            raw,
+            trailing,
+            value,
+        })
     }
 
     fn parse_key(&mut self) -> Result<String> {
@@ -198,6 +846,7 @@ impl<'a> IniParser<'a> {
     fn parse_value(&mut self) -> Result<Value> {
         let mut value_chars = Vec::new();
         let mut in_quotes = false;
+        let mut was_quoted = false;
         let mut quote_char = '\0';
 
         while self.position < self.content.len() {
@@ -206,6 +855,7 @@ impl<'a> IniParser<'a> {
             match ch {
                 '"' | '\'' if !in_quotes => {
                     in_quotes = true;
+                    was_quoted = true;
                     quote_char = ch;
                     self.advance();
                     // Don't include the opening quote
@@ -231,7 +881,6 @@ impl<'a> IniParser<'a> {
                     }
                 }
                 ch if in_quotes && ch == quote_char => {
-                    in_quotes = false;
                     self.advance();
                     // Don't include the closing quote
                     break;
@@ -246,7 +895,7 @@ impl<'a> IniParser<'a> {
         }
 
         // If we're not in quotes, trim whitespace from the end
-        let value_str = if !in_quotes {
+        let value_str = if !was_quoted {
             value_chars
                 .iter()
                 .collect::<String>()
@@ -257,7 +906,7 @@ impl<'a> IniParser<'a> {
         };
 
         // For unquoted values, still process escape sequences
-        let processed_value = if in_quotes {
+        let processed_value = if was_quoted {
             value_str // Already processed during parsing
         } else {
             self.process_escape_sequences(&value_str)
@@ -511,4 +1160,74 @@ key2:value2
         let content = "key_without_value";
         assert!(parse_ini(content).is_err());
     }
+
+    const LAYOUT: &str = "; Service settings — edit with care\r\n\r\nname = \"svc one\"\r\n\r\n[service]\r\nport: 8080  ; default\r\nenabled=yes\r\npath = 'C:\\\\svc'\r\n\r\n# end\r\n";
+
+    #[test]
+    fn test_document_round_trip() {
+        let doc = parse_document(LAYOUT).unwrap();
+        assert_eq!(doc.to_string(), LAYOUT);
+        assert_eq!(
+            doc.value()
+                .get("service.path")
+                .unwrap()
+                .as_string()
+                .unwrap(),
+            "C:\\svc"
+        );
+    }
+
+    #[test]
+    fn test_document_edits() {
+        let mut doc = parse_document(LAYOUT).unwrap();
+        doc.set("service.port", Value::integer(9090)).unwrap();
+        doc.set("service.enabled", Value::bool(false)).unwrap();
+        doc.set("service.path", Value::string("D:\\it's")).unwrap();
+        doc.set("service.timeout", Value::float(2.0)).unwrap();
+        doc.set("log.level", Value::string("debug ; verbose"))
+            .unwrap();
+        doc.remove("name");
+
+        let expected = "; Service settings — edit with care\r\n\r\n[service]\r\nport: 9090  ; default\r\nenabled=no\r\npath = 'D:\\\\it\\'s'\r\ntimeout = 2.0\r\n\r\n[log]\r\nlevel = \"debug ; verbose\"\r\n\r\n# end\r\n";
+        assert_eq!(doc.to_string(), expected);
+        assert_eq!(&parse(expected).unwrap(), doc.value());
+    }
+
+    #[test]
+    fn test_serialize() {
+        let mut value = Value::table(BTreeMap::new());
+        value
+            .set_nested("title", Value::string(" padded "))
+            .unwrap();
+        value
+            .set_nested("db.host", Value::string("localhost"))
+            .unwrap();
+        value.set_nested("db.pool.size", Value::integer(5)).unwrap();
+        value.set_nested("empty", Value::Null).unwrap();
+
+        let text = serialize(&value).unwrap();
+        assert_eq!(
+            text,
+            "empty = \"\"\ntitle = \" padded \"\n\n[db]\nhost = localhost\npool.size = 5\n"
+        );
+        let reparsed = parse(&text).unwrap();
+        assert_eq!(reparsed.get("db.pool.size"), Some(&Value::integer(5)));
+        assert_eq!(reparsed.get("title"), Some(&Value::string(" padded ")));
+
+        value.set_nested("list", Value::array(vec![])).unwrap();
+        assert!(serialize(&value).is_err());
+        for ambiguous in [Value::string("42"), Value::string("yes")] {
+            let mut value = Value::table(BTreeMap::new());
+            value.set_nested("db.key", ambiguous.clone()).unwrap();
+            let error = serialize(&value).unwrap_err().to_string();
+            assert!(error.contains("'db.key'"), "{error}");
+        }
+        let mut flags = Value::table(BTreeMap::new());
+        flags.set_nested("off", Value::integer(0)).unwrap();
+        flags.set_nested("on", Value::integer(1)).unwrap();
+        let text = serialize(&flags).unwrap();
+        assert_eq!(text, "off = +0\non = +1\n");
+        assert_eq!(parse(&text).unwrap(), flags);
+        assert!(parse_ini("= value").is_err());
+    }
 }
@@ -0,0 +1,1705 @@
+//! # JSON5 and JSONC Parser
+//!
+//! Hand-written parser for the relaxed JSON dialects used by editor and
+//! tool configuration:
+//!
+//! | Dialect | Accepts on top of strict JSON                                   |
+//! |---------|-----------------------------------------------------------------|
+//! | JSONC   | `//` and `/* */` comments, trailing commas (VS Code's `settings.json`, `tsconfig.json`) |
+//! | JSON5   | JSONC plus unquoted keys, single-quoted strings, hexadecimal numbers, leading `+`, leading or trailing decimal points, `Infinity` / `NaN`, escaped line breaks in strings |
+//!
+//! [`parse_document`] keeps the source text together with the span of
+//! every value; [`JsonDocument::sync`] then rewrites only the values that
+//! changed, so comments, key order, indentation and the spelling of
+//! untouched numbers and strings survive a save. New keys are appended to
+//! their object using the indentation of their neighbours.
+//!
+//! Duplicate keys follow `serde_json`: the last one wins. Errors report the
+//! line and column of the offending input.
+//!
+//! [`parse_document`]: crate::parsers::json5_parser::parse_document
+//! [`JsonDocument::sync`]: crate::parsers::json5_parser::JsonDocument::sync
+
+use crate::error::{Error, Result};
+use crate::path;
+use crate::value::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+/// Deepest nesting of arrays and objects accepted
+const MAX_DEPTH: usize = 128;
+
+/// Indentation used when the source has no indented entries to copy
+const DEFAULT_INDENT: &str = "  ";
+
+/// JSON dialect accepted by the parser, from strictest to most relaxed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Dialect {
+    /// Strict JSON (RFC 8259)
+    Json,
+    /// JSON with comments and trailing commas
+    Jsonc,
+    /// JSON5 (<https://spec.json5.org>)
+    Json5,
+}
+
+impl Dialect {
+    /// Format name used by [`crate::Config`]: `json`, `jsonc` or `json5`
+    pub fn name(self) -> &'static str {
+        match self {
+            Dialect::Json => "json",
+            Dialect::Jsonc => "jsonc",
+            Dialect::Json5 => "json5",
+        }
+    }
+
+    /// Dialect for a format name
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "json" => Some(Dialect::Json),
+            "jsonc" => Some(Dialect::Jsonc),
+            "json5" => Some(Dialect::Json5),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Dialect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Dialect::Json => "JSON",
+            Dialect::Jsonc => "JSONC",
+            Dialect::Json5 => "JSON5",
+        })
+    }
+}
+
+/// Parse JSON5 format configuration (JSONC and strict JSON are subsets)
+pub fn parse(source: &str) -> Result<Value> {
+    parse_with(source, Dialect::Json5)
+}
+
+/// Parse JSONC (JSON with comments and trailing commas)
+pub fn parse_jsonc(source: &str) -> Result<Value> {
+    parse_with(source, Dialect::Jsonc)
+}
+
+/// Parse `source` as `dialect`
+pub fn parse_with(source: &str, dialect: Dialect) -> Result<Value> {
+    Ok(parse_document(source, dialect)?.into_value())
+}
+
+/// Parse `source` as `dialect`, keeping its layout for in-place edits
+pub fn parse_document(source: &str, dialect: Dialect) -> Result<JsonDocument> {
+    let mut parser = Parser::new(source, dialect);
+    let (root, value) = parser.parse()?;
+    let indent = indent_unit(source, &root);
+    Ok(JsonDocument {
+        source: source.to_string(),
+        dialect,
+        root,
+        value,
+        indent,
+    })
+}
+
+/// The strictest dialect `source` needs: JSON unless it uses comments or
+/// trailing commas (JSONC) or other JSON5 syntax.
+///
+/// Input that does not parse reports the dialect of the syntax seen before
+/// the error, so the error message comes from the dialect the file was
+/// written in.
+pub fn detect_dialect(source: &str) -> Dialect {
+    let mut parser = Parser::new(source, Dialect::Json5);
+    let _ = parser.parse();
+    parser.used
+}
+
+/// Serialize a value as JSON5: pretty-printed JSON, with non-finite floats
+/// written as `Infinity` / `NaN`
+pub fn serialize(value: &Value) -> Result<String> {
+    serialize_with(value, Dialect::Json5)
+}
+
+/// Serialize a value as pretty-printed `dialect` with two-space indentation
+///
+/// # Errors
+///
+/// Returns [`Error::General`] for non-finite floats unless `dialect` is
+/// JSON5.
+pub fn serialize_with(value: &Value, dialect: Dialect) -> Result<String> {
+    let writer = Writer {
+        dialect,
+        indent: DEFAULT_INDENT,
+        key_quote: '"',
+        newline: "\n",
+    };
+    writer.render(value, "")
+}
+
+/// Where each key and array element starts, for the document outline;
+/// `None` if `source` does not parse. Unlike the parsed value, this keeps
+/// every definition of a duplicated key.
+pub(crate) fn marks(source: &str, dialect: Dialect) -> Option<Vec<super::locate::Mark>> {
+    use super::locate::Mark;
+
+    fn walk(node: &Node, scope: &str, marks: &mut Vec<Mark>) {
+        let (Kind::Array(container) | Kind::Object(container)) = &node.kind else {
+            return;
+        };
+        for (index, entry) in container.entries.iter().enumerate() {
+            let (mark, path) = match &entry.key {
+                Some(key) => {
+                    let path = path::join_key(scope, key);
+                    (Mark::entry(entry.start, scope, key, path.clone()), path)
+                }
+                None => {
+                    let path = path::join_index(scope, index);
+                    (Mark::element(entry.start, scope, path.clone()), path)
+                }
+            };
+            marks.push(mark);
+            walk(&entry.value, &path, marks);
+        }
+    }
+
+    let (root, _) = Parser::new(source, dialect).parse().ok()?;
+    let mut marks = Vec::new();
+    walk(&root, "", &mut marks);
+    Some(marks)
+}
+
+/// A parsed JSON, JSONC or JSON5 document that keeps its source text
+///
+/// Edits through [`set`](Self::set), [`remove`](Self::remove) and
+/// [`sync`](Self::sync) splice new text into the original, and
+/// [`Display`](fmt::Display) writes the result.
+#[derive(Debug, Clone)]
+pub struct JsonDocument {
+    source: String,
+    dialect: Dialect,
+    root: Node,
+    value: Value,
+    /// One level of indentation, as used by the source
+    indent: String,
+}
+
+/// Byte span of a value and, for arrays and objects, of its entries
+#[derive(Debug, Clone)]
+struct Node {
+    start: usize,
+    end: usize,
+    kind: Kind,
+}
+
+#[derive(Debug, Clone)]
+enum Kind {
+    Scalar,
+    Array(Container),
+    Object(Container),
+}
+
+#[derive(Debug, Clone)]
+struct Container {
+    entries: Vec<Entry>,
+    /// Whether the last entry is followed by a comma
+    trailing_comma: bool,
+}
+
+/// One array element or object member.
+///
+/// An entry owns the text from `lead` (after the previous entry's line,
+/// so comments above it are included) to `end` (its comma and the rest of
+/// its line), so removing it takes its comments along.
+#[derive(Debug, Clone)]
+struct Entry {
+    lead: usize,
+    /// Start of the key, or of the value for array elements
+    start: usize,
+    key: Option<String>,
+    /// End of the key token (equal to `start` for array elements)
+    key_end: usize,
+    value: Node,
+    comma: Option<usize>,
+    end: usize,
+}
+
+/// An entry of a rewritten container: an original entry with its new
+/// text (key and value), or new text to insert.
+enum Piece<'a> {
+    Kept(&'a Entry, String),
+    New(String),
+}
+
+impl JsonDocument {
+    /// The parsed values
+    pub fn value(&self) -> &Value {
+        &self.value
+    }
+
+    /// Consume the document, keeping only the values
+    pub fn into_value(self) -> Value {
+        self.value
+    }
+
+    /// The dialect the document was parsed as
+    pub fn dialect(&self) -> Dialect {
+        self.dialect
+    }
+
+    /// Set the value at `path`, editing it in place or appending it to
+    /// its object.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the path cannot be set or the value cannot be
+    /// written in this dialect; the document is unchanged in that case.
+    pub fn set(&mut self, path: &str, value: Value) -> Result<()> {
+        let mut target = self.value.clone();
+        target.set_nested(path, value)?;
+        self.sync(&target)
+    }
+
+    /// Remove the value at `path`, along with the comments above it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `path` is invalid.
+    pub fn remove(&mut self, path: &str) -> Result<Option<Value>> {
+        let mut target = self.value.clone();
+        let removed = target.remove(path)?;
+        if removed.is_some() {
+            self.sync(&target)?;
+        }
+        Ok(removed)
+    }
+
+    /// Reconcile the document with `target`, rewriting only the parts of
+    /// the source whose values differ.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::General`] if a changed value cannot be written in
+    /// this dialect (non-finite floats outside JSON5); the document is
+    /// unchanged in that case.
+    pub fn sync(&mut self, target: &Value) -> Result<()> {
+        if self.value == *target {
+            return Ok(());
+        }
+        let writer = Writer {
+            dialect: self.dialect,
+            indent: &self.indent,
+            key_quote: key_quote(&self.source, &self.root),
+            newline: if self.source.contains("\r\n") {
+                "\r\n"
+            } else {
+                "\n"
+            },
+        };
+        let root = &self.root;
+        let mut text = self.source[..root.start].to_string();
+        text.push_str(&self.rewrite(&writer, root, &self.value, target)?);
+        text.push_str(&self.source[root.end..]);
+
+        *self = parse_document(&text, self.dialect)?;
+        Ok(())
+    }
+
+    /// New text for `node`, which currently holds `old`, so that it
+    /// holds `new`.
+    fn rewrite(
+        &self,
+        writer: &Writer<'_>,
+        node: &Node,
+        old: &Value,
+        new: &Value,
+    ) -> Result<String> {
+        if old == new {
+            return Ok(self.source[node.start..node.end].to_string());
+        }
+        match (&node.kind, old, new) {
+            (Kind::Object(container), Value::Table(old), Value::Table(new)) => {
+                self.rewrite_object(writer, node, container, old, new)
+            }
+            (Kind::Array(container), Value::Array(old), Value::Array(new)) => {
+                self.rewrite_array(writer, node, container, old, new)
+            }
+            (Kind::Scalar, _, Value::String(s)) => {
+                // Keep the quote style of the string being replaced
+                let quote = match self.source[node.start..].chars().next() {
+                    Some('\'') => '\'',
+                    _ => '"',
+                };
+                Ok(quote_string(s, quote))
+            }
+            (
+                Kind::Scalar,
+                Value::Integer(_) | Value::Float(_),
+                Value::Integer(_) | Value::Float(_),
+            ) => match renumber(&self.source[node.start..node.end], new) {
+                Some(text) => Ok(text),
+                None => writer.render(new, ""),
+            },
+            _ => writer.render(new, line_indent(&self.source, node.start)),
+        }
+    }
+
+    fn rewrite_object(
+        &self,
+        writer: &Writer<'_>,
+        node: &Node,
+        container: &Container,
+        old: &BTreeMap<String, Value>,
+        new: &BTreeMap<String, Value>,
+    ) -> Result<String> {
+        let mut last = HashMap::new();
+        for (index, entry) in container.entries.iter().enumerate() {
+            if let Some(key) = &entry.key {
+                last.insert(key.as_str(), index);
+            }
+        }
+
+        let mut pieces = Vec::new();
+        for (index, entry) in container.entries.iter().enumerate() {
+            let Some(key) = entry.key.as_deref() else {
+                continue;
+            };
+            let Some(value) = new.get(key) else {
+                continue;
+            };
+            let text = if last[key] == index {
+                let old = &old[key];
+                let value = self.rewrite(writer, &entry.value, old, value)?;
+                format!("{}{value}", &self.source[entry.start..entry.value.start])
+            } else {
+                // Shadowed duplicate: kept as written
+                self.source[entry.start..entry.value.end].to_string()
+            };
+            pieces.push(Piece::Kept(entry, text));
+        }
+
+        let template = container.entries.last();
+        let separator = template
+            .map(|entry| &self.source[entry.key_end..entry.value.start])
+            .filter(|sep| sep.trim() == ":")
+            .unwrap_or(": ");
+        let quote = template.map_or(writer.key_quote, |entry| quote_style(&self.source, entry));
+        let indent = self.entry_indent(writer, node, container);
+        for (key, value) in new {
+            if old.contains_key(key) {
+                continue;
+            }
+            let key = quote_key(key, quote);
+            let value = self.render_new(writer, node, value, &indent)?;
+            pieces.push(Piece::New(format!("{key}{separator}{value}")));
+        }
+
+        Ok(self.splice(writer, node, container, &pieces, &indent))
+    }
+
+    fn rewrite_array(
+        &self,
+        writer: &Writer<'_>,
+        node: &Node,
+        container: &Container,
+        old: &[Value],
+        new: &[Value],
+    ) -> Result<String> {
+        let entries = &container.entries;
+        let indent = self.entry_indent(writer, node, container);
+        let verbatim = |entry: &Entry| self.source[entry.start..entry.value.end].to_string();
+
+        // A single run of elements removed or inserted keeps the others
+        // (and their comments) in place; anything else is matched up by
+        // position.
+        let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+        let mut pieces = Vec::new();
+        if old.len() > new.len() && new[prefix..] == old[old.len() - (new.len() - prefix)..] {
+            let skip = old.len() - new.len();
+            for (index, entry) in entries.iter().enumerate() {
+                if index < prefix || index >= prefix + skip {
+                    pieces.push(Piece::Kept(entry, verbatim(entry)));
+                }
+            }
+        } else if old.len() < new.len() && new[new.len() - (old.len() - prefix)..] == old[prefix..]
+        {
+            let added = new.len() - old.len();
+            for (index, entry) in entries.iter().enumerate() {
+                if index == prefix {
+                    for value in &new[prefix..prefix + added] {
+                        pieces.push(Piece::New(self.render_new(writer, node, value, &indent)?));
+                    }
+                }
+                pieces.push(Piece::Kept(entry, verbatim(entry)));
+            }
+            if prefix == entries.len() {
+                for value in &new[prefix..] {
+                    pieces.push(Piece::New(self.render_new(writer, node, value, &indent)?));
+                }
+            }
+        } else {
+            for ((entry, old), new) in entries.iter().zip(old).zip(new) {
+                let text = self.rewrite(writer, &entry.value, old, new)?;
+                pieces.push(Piece::Kept(entry, text));
+            }
+            for value in new.iter().skip(entries.len()) {
+                pieces.push(Piece::New(self.render_new(writer, node, value, &indent)?));
+            }
+        }
+
+        Ok(self.splice(writer, node, container, &pieces, &indent))
+    }
+
+    /// Indentation for new entries of `container`: that of its last entry
+    /// when entries sit on their own lines, else one level deeper than the
+    /// line it opens on.
+    fn entry_indent(&self, writer: &Writer<'_>, node: &Node, container: &Container) -> String {
+        match container.entries.last() {
+            Some(entry) if self.multiline(node) => {
+                line_indent(&self.source, entry.start).to_string()
+            }
+            _ => format!("{}{}", line_indent(&self.source, node.start), writer.indent),
+        }
+    }
+
+    fn multiline(&self, node: &Node) -> bool {
+        self.source[node.start..node.end].contains('\n')
+    }
+
+    /// Whether new entries of `node` go on its line: it is written on one
+    /// line, or it is empty and the whole document is.
+    fn compact(&self, node: &Node, container: &Container) -> bool {
+        if container.entries.is_empty() {
+            !self.multiline(&self.root)
+        } else {
+            !self.multiline(node)
+        }
+    }
+
+    /// Render a value added to `node`, on one line if `node` is compact
+    fn render_new(
+        &self,
+        writer: &Writer<'_>,
+        node: &Node,
+        value: &Value,
+        indent: &str,
+    ) -> Result<String> {
+        let compact = match &node.kind {
+            Kind::Object(container) | Kind::Array(container) => self.compact(node, container),
+            Kind::Scalar => false,
+        };
+        if compact {
+            writer.inline(value)
+        } else {
+            writer.render(value, indent)
+        }
+    }
+
+    /// Reassemble `container` from `pieces`, fixing up commas so that only
+    /// the last piece follows the container's trailing-comma style.
+    fn splice(
+        &self,
+        writer: &Writer<'_>,
+        node: &Node,
+        container: &Container,
+        pieces: &[Piece<'_>],
+        indent: &str,
+    ) -> String {
+        let source = &self.source;
+        let newline = writer.newline;
+        let open = &source[node.start..=node.start];
+        let close = &source[node.end - 1..node.end];
+        let inner = &source[node.start + 1..node.end - 1];
+
+        let (Some(first), Some(last)) = (container.entries.first(), container.entries.last())
+        else {
+            if self.compact(node, container) {
+                // Filling an empty container of a one-line document:
+                // entries stay on its line
+                let inner = inner.trim();
+                let mut out = format!("{open}{inner}");
+                for (index, piece) in pieces.iter().enumerate() {
+                    if let Piece::New(text) = piece {
+                        if index > 0 {
+                            out.push_str(", ");
+                        } else if !inner.is_empty() {
+                            out.push(' ');
+                        }
+                        out.push_str(text);
+                    }
+                }
+                out.push_str(close);
+                return out;
+            }
+            // Filling an empty container: one entry per line
+            let outer = line_indent(source, node.start);
+            let mut out = format!("{open}{}{newline}", inner.trim_end());
+            for (index, piece) in pieces.iter().enumerate() {
+                if let Piece::New(text) = piece {
+                    out.push_str(indent);
+                    out.push_str(text);
+                    if index + 1 < pieces.len() {
+                        out.push(',');
+                    }
+                    out.push_str(newline);
+                }
+            }
+            out.push_str(outer);
+            out.push_str(close);
+            return out;
+        };
+
+        let header = &source[node.start + 1..first.lead];
+        let tail = &source[last.end..node.end - 1];
+        if pieces.is_empty() {
+            let rest = format!("{header}{tail}");
+            return if rest.trim().is_empty() {
+                format!("{open}{close}")
+            } else {
+                format!("{open}{rest}{close}")
+            };
+        }
+
+        let multiline = self.multiline(node);
+        let line_per_entry = source[..last.end].ends_with('\n');
+        let mut out = format!("{open}{header}");
+        for (index, piece) in pieces.iter().enumerate() {
+            let comma = index + 1 < pieces.len() || container.trailing_comma;
+            match piece {
+                Piece::Kept(entry, text) => {
+                    // A new first entry takes over the original first's
+                    // spacing, but not its comments
+                    let mut lead = &source[entry.lead..entry.start];
+                    let first_lead = &source[first.lead..first.start];
+                    if index == 0 && first_lead.trim().is_empty() {
+                        lead = first_lead;
+                    }
+                    if out.ends_with(' ') {
+                        out.push_str(lead.trim_start_matches(' '));
+                    } else {
+                        out.push_str(lead);
+                    }
+                    out.push_str(text);
+                    match (entry.comma, comma) {
+                        (Some(at), false) => {
+                            out.push_str(&source[entry.value.end..at]);
+                            out.push_str(&source[at + 1..entry.end]);
+                        }
+                        (None, true) => {
+                            out.push(',');
+                            out.push_str(&source[entry.value.end..entry.end]);
+                        }
+                        _ => out.push_str(&source[entry.value.end..entry.end]),
+                    }
+                }
+                Piece::New(text) => {
+                    if multiline {
+                        if !out.ends_with('\n') {
+                            out.push_str(newline);
+                        }
+                        out.push_str(indent);
+                    } else if !out.ends_with(open) && !out.ends_with(' ') {
+                        out.push(' ');
+                    }
+                    out.push_str(text);
+                    if comma {
+                        out.push(',');
+                    }
+                    if multiline && line_per_entry {
+                        out.push_str(newline);
+                    } else if !multiline && index + 1 < pieces.len() {
+                        out.push(' ');
+                    }
+                }
+            }
+        }
+        if multiline && !line_per_entry && matches!(pieces.last(), Some(Piece::New(_))) {
+            out.push_str(newline);
+            out.push_str(line_indent(source, node.start));
+            out.push_str(tail.trim_start());
+        } else {
+            out.push_str(tail);
+        }
+        out.push_str(close);
+        out
+    }
+}
+
+impl JsonDocument {
+    /// The document in [`crate::formatter`] layout: two-space indentation,
+    /// double-quoted keys and strings, no trailing commas, and every
+    /// comment as `//` lines. Of duplicate keys only the last, the one that
+    /// counts, is written.
+    pub(crate) fn formatted(&self, sort_keys: bool) -> Result<String> {
+        let writer = Writer {
+            dialect: self.dialect,
+            indent: DEFAULT_INDENT,
+            key_quote: '"',
+            newline: "\n",
+        };
+        let mut out = String::new();
+        for comment in comments(&self.source[..self.root.start]) {
+            out.push_str(&comment);
+            out.push('\n');
+        }
+        self.format_node(&writer, &self.root, &self.value, "", sort_keys, &mut out)?;
+        out.push('\n');
+        for comment in comments(&self.source[self.root.end..]) {
+            out.push_str(&comment);
+            out.push('\n');
+        }
+        Ok(out)
+    }
+
+    fn format_node(
+        &self,
+        writer: &Writer<'_>,
+        node: &Node,
+        value: &Value,
+        indent: &str,
+        sort_keys: bool,
+        out: &mut String,
+    ) -> Result<()> {
+        let (open, close, container) = match &node.kind {
+            Kind::Scalar => {
+                out.push_str(&writer.render(value, indent)?);
+                return Ok(());
+            }
+            Kind::Array(container) => ('[', ']', container),
+            Kind::Object(container) => ('{', '}', container),
+        };
+
+        let mut members = Vec::with_capacity(container.entries.len());
+        for (index, entry) in container.entries.iter().enumerate() {
+            let member = match (&entry.key, value) {
+                (Some(key), Value::Table(table)) => {
+                    let overridden = container.entries[index + 1..]
+                        .iter()
+                        .any(|later| later.key.as_ref() == Some(key));
+                    if overridden {
+                        continue;
+                    }
+                    table.get(key)
+                }
+                (None, Value::Array(items)) => items.get(index),
+                _ => None,
+            };
+            let member = member
+                .ok_or_else(|| Error::internal("JSON document out of step with its values"))?;
+            members.push((entry, member));
+        }
+        if sort_keys {
+            members.sort_by(|(a, _), (b, _)| a.key.cmp(&b.key));
+        }
+
+        let inner = format!("{indent}{DEFAULT_INDENT}");
+        let body_start = node.start + open.len_utf8();
+        let body_end = node.end - close.len_utf8();
+        let (opening, dangling) = match (container.entries.first(), container.entries.last()) {
+            (Some(first), Some(last)) => (
+                comments(&self.source[body_start..first.lead]),
+                comments(&self.source[last.end..body_end]),
+            ),
+            _ => (Vec::new(), comments(&self.source[body_start..body_end])),
+        };
+        if members.is_empty() && opening.is_empty() && dangling.is_empty() {
+            out.push(open);
+            out.push(close);
+            return Ok(());
+        }
+
+        out.push(open);
+        out.push('\n');
+        for comment in &opening {
+            out.push_str(&format!("{inner}{comment}\n"));
+        }
+        let count = members.len();
+        for (position, (entry, member)) in members.into_iter().enumerate() {
+            let mut above = comments(&self.source[entry.lead..entry.start]);
+            above.extend(comments(&self.source[entry.key_end..entry.value.start]));
+            for comment in &above {
+                out.push_str(&format!("{inner}{comment}\n"));
+            }
+            out.push_str(&inner);
+            if let Some(key) = &entry.key {
+                out.push_str(&quote_string(key, '"'));
+                out.push_str(": ");
+            }
+            self.format_node(writer, &entry.value, member, &inner, sort_keys, out)?;
+            if position + 1 < count {
+                out.push(',');
+            }
+            let after = comments(&self.source[entry.value.end..entry.end]);
+            if !after.is_empty() {
+                out.push(' ');
+                out.push_str(&after.join(" "));
+            }
+            out.push('\n');
+        }
+        for comment in &dangling {
+            out.push_str(&format!("{inner}{comment}\n"));
+        }
+        out.push_str(indent);
+        out.push(close);
+        Ok(())
+    }
+}
+
+/// The comments in a stretch of whitespace, commas and comments, as `//`
+/// lines; a multi-line block comment gives one line per line of text.
+fn comments(trivia: &str) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut rest = trivia;
+    while let Some(at) = rest.find('/') {
+        rest = &rest[at..];
+        if let Some(body) = rest.strip_prefix("//") {
+            let end = body.find('\n').unwrap_or(body.len());
+            out.push(format!("//{}", body[..end].trim_end()));
+            rest = &body[end..];
+        } else if let Some(body) = rest.strip_prefix("/*") {
+            let end = body.find("*/").unwrap_or(body.len());
+            for line in body[..end].lines() {
+                let text = line.trim().trim_start_matches('*').trim();
+                if !text.is_empty() {
+                    out.push(format!("// {text}"));
+                }
+            }
+            rest = body.get(end + 2..).unwrap_or("");
+        } else {
+            rest = &rest[1..];
+        }
+    }
+    out
+}
+
+impl fmt::Display for JsonDocument {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+/// Pretty-printer for new values
+struct Writer<'a> {
+    dialect: Dialect,
+    /// One level of indentation
+    indent: &'a str,
+    /// Quote for object keys, as for [`quote_key`]
+    key_quote: char,
+    /// Line ending: `\r\n` for documents that use it
+    newline: &'a str,
+}
+
+impl Writer<'_> {
+    /// Render `value` for a position whose line is indented by `indent`
+    fn render(&self, value: &Value, indent: &str) -> Result<String> {
+        let inner = format!("{indent}{}", self.indent);
+        Ok(match value {
+            Value::Null => "null".to_string(),
+            Value::Bool(b) => b.to_string(),
+            Value::Integer(i) => i.to_string(),
+            Value::Float(f) => self.float(*f)?,
+            Value::String(s) => quote_string(s, '"'),
+            Value::Array(items) if items.is_empty() => "[]".to_string(),
+            Value::Array(items) => {
+                let items = items
+                    .iter()
+                    .map(|item| Ok(format!("{inner}{}", self.render(item, &inner)?)))
+                    .collect::<Result<Vec<_>>>()?;
+                let newline = self.newline;
+                let items = items.join(&format!(",{newline}"));
+                format!("[{newline}{items}{newline}{indent}]")
+            }
+            Value::Table(table) if table.is_empty() => "{}".to_string(),
+            Value::Table(table) => {
+                let members = table
+                    .iter()
+                    .map(|(key, value)| {
+                        let key = quote_key(key, self.key_quote);
+                        Ok(format!("{inner}{key}: {}", self.render(value, &inner)?))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                let newline = self.newline;
+                let members = members.join(&format!(",{newline}"));
+                format!("{{{newline}{members}{newline}{indent}}}")
+            }
+            #[cfg(feature = "chrono")]
+            Value::DateTime(dt) => quote_string(&dt.to_rfc3339(), '"'),
+        })
+    }
+
+    /// Render `value` on a single line
+    fn inline(&self, value: &Value) -> Result<String> {
+        Ok(match value {
+            Value::Array(items) => {
+                let items = items
+                    .iter()
+                    .map(|item| self.inline(item))
+                    .collect::<Result<Vec<_>>>()?;
+                format!("[{}]", items.join(", "))
+            }
+            Value::Table(table) => {
+                let members = table
+                    .iter()
+                    .map(|(key, value)| {
+                        let key = quote_key(key, self.key_quote);
+                        Ok(format!("{key}: {}", self.inline(value)?))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                format!("{{{}}}", members.join(", "))
+            }
+            _ => self.render(value, "")?,
+        })
+    }
+
+    fn float(&self, f: f64) -> Result<String> {
+        if f.is_finite() {
+            return Ok(format!("{f:?}"));
+        }
+        if self.dialect < Dialect::Json5 {
+            return Err(Error::serialize(format!(
+                "{} cannot represent the float {f}",
+                self.dialect
+            )));
+        }
+        Ok(if f.is_nan() {
+            "NaN".to_string()
+        } else if f > 0.0 {
+            "Infinity".to_string()
+        } else {
+            "-Infinity".to_string()
+        })
+    }
+}
+
+/// Write a changed number in the notation of the one it replaces:
+/// exponent notation stays exponent notation and hexadecimal stays
+/// hexadecimal. `None` for other values and plain notation.
+fn renumber(original: &str, new: &Value) -> Option<String> {
+    let unsigned = original.trim_start_matches(['+', '-']);
+    match new {
+        Value::Integer(i) if unsigned.starts_with("0x") || unsigned.starts_with("0X") => {
+            let digits = if unsigned[2..].bytes().any(|b| b.is_ascii_lowercase()) {
+                format!("{:x}", i.unsigned_abs())
+            } else {
+                format!("{:X}", i.unsigned_abs())
+            };
+            let sign = if *i < 0 { "-" } else { "" };
+            Some(format!("{sign}{}{digits}", &unsigned[..2]))
+        }
+        Value::Float(f) if f.is_finite() && unsigned.contains(['e', 'E']) => {
+            let text = format!("{f:e}");
+            Some(if unsigned.contains('E') {
+                text.replace('e', "E")
+            } else {
+                text
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Quote `s` with `quote` (`"` or `'`), escaping what JSON requires
+fn quote_string(s: &str, quote: char) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push(quote);
+    for ch in s.chars() {
+        match ch {
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '\u{8}' => out.push_str("\\b"),
+            '\u{c}' => out.push_str("\\f"),
+            c if c == quote => {
+                out.push('\\');
+                out.push(c);
+            }
+            c if u32::from(c) < 0x20 => out.push_str(&format!("\\u{:04x}", u32::from(c))),
+            c => out.push(c),
+        }
+    }
+    out.push(quote);
+    out
+}
+
+/// Write an object key in the style of its neighbours: `quote` is `"`,
+/// `'`, or `'\0'` for unquoted keys (used when the key allows it)
+fn quote_key(key: &str, quote: char) -> String {
+    if quote != '\0' {
+        return quote_string(key, quote);
+    }
+    let mut chars = key.chars();
+    let identifier = chars
+        .next()
+        .is_some_and(|c| c.is_alphabetic() || c == '$' || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || c == '$' || c == '_');
+    if identifier {
+        key.to_string()
+    } else {
+        quote_string(key, '"')
+    }
+}
+
+/// Quote style of an object member's key, as for [`quote_key`]
+fn quote_style(source: &str, entry: &Entry) -> char {
+    match source[entry.start..].chars().next() {
+        Some('\'') => '\'',
+        Some('"') => '"',
+        _ => '\0',
+    }
+}
+
+/// Quote style of the first object key in the document, so new objects
+/// match it
+fn key_quote(source: &str, node: &Node) -> char {
+    match &node.kind {
+        Kind::Object(container) if !container.entries.is_empty() => {
+            quote_style(source, &container.entries[0])
+        }
+        Kind::Array(container) | Kind::Object(container) => container
+            .entries
+            .iter()
+            .map(|entry| key_quote(source, &entry.value))
+            .find(|&quote| quote != '"')
+            .unwrap_or('"'),
+        Kind::Scalar => '"',
+    }
+}
+
+/// Leading whitespace of the line containing byte `pos`
+fn line_indent(source: &str, pos: usize) -> &str {
+    let start = source[..pos].rfind('\n').map_or(0, |i| i + 1);
+    let line = &source[start..];
+    &line[..line.len() - line.trim_start_matches([' ', '\t']).len()]
+}
+
+/// One level of indentation: the first step from a multi-line container's
+/// line to its first entry's line
+fn indent_unit(source: &str, root: &Node) -> String {
+    fn find(source: &str, node: &Node) -> Option<String> {
+        let (Kind::Array(container) | Kind::Object(container)) = &node.kind else {
+            return None;
+        };
+        let first = container.entries.first()?;
+        if source[node.start..first.start].contains('\n') {
+            let outer = line_indent(source, node.start);
+            if let Some(step) = line_indent(source, first.start).strip_prefix(outer) {
+                if !step.is_empty() {
+                    return Some(step.to_string());
+                }
+            }
+        }
+        container
+            .entries
+            .iter()
+            .find_map(|entry| find(source, &entry.value))
+    }
+
+    find(source, root).unwrap_or_else(|| DEFAULT_INDENT.to_string())
+}
+
+/// Recursive-descent parser recording the span of every value
+struct Parser<'a> {
+    source: &'a str,
+    pos: usize,
+    dialect: Dialect,
+    /// The most relaxed dialect whose syntax has been seen
+    used: Dialect,
+}
+
+impl<'a> Parser<'a> {
+    fn new(source: &'a str, dialect: Dialect) -> Self {
+        Self {
+            source,
+            pos: 0,
+            dialect,
+            used: Dialect::Json,
+        }
+    }
+
+    fn parse(&mut self) -> Result<(Node, Value)> {
+        // A byte order mark is tolerated in every dialect
+        if self.source.starts_with('\u{feff}') {
+            self.pos = '\u{feff}'.len_utf8();
+        }
+        self.skip_trivia()?;
+        let parsed = self.value(0)?;
+        self.skip_trivia()?;
+        if self.pos < self.source.len() {
+            return Err(self.unexpected("end of input"));
+        }
+        Ok(parsed)
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.source[self.pos..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    /// Accept syntax that needs at least `needed`, described by `what`
+    fn allow(&mut self, pos: usize, needed: Dialect, what: &str) -> Result<()> {
+        if self.dialect < needed {
+            return Err(self.error_at(pos, format!("{what} are not allowed in {}", self.dialect)));
+        }
+        self.used = self.used.max(needed);
+        Ok(())
+    }
+
+    fn skip_trivia(&mut self) -> Result<()> {
+        loop {
+            let rest = self.rest();
+            match self.peek() {
+                Some(' ' | '\t' | '\n' | '\r') => self.pos += 1,
+                Some('/') if rest.starts_with("//") => {
+                    self.allow(self.pos, Dialect::Jsonc, "comments")?;
+                    self.pos += rest.find('\n').unwrap_or(rest.len());
+                }
+                Some('/') if rest.starts_with("/*") => {
+                    self.allow(self.pos, Dialect::Jsonc, "comments")?;
+                    let end = rest[2..]
+                        .find("*/")
+                        .ok_or_else(|| self.error("unterminated block comment"))?;
+                    self.pos += end + 4;
+                }
+                Some(c) if c.is_whitespace() || c == '\u{feff}' => {
+                    self.allow(self.pos, Dialect::Json5, "Unicode whitespace characters")?;
+                    self.pos += c.len_utf8();
+                }
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    /// End of the line that `pos` is on, if only whitespace and comments
+    /// follow it there; otherwise `pos` itself.
+    fn line_end(&self, pos: usize) -> usize {
+        let mut at = pos;
+        loop {
+            let rest = &self.source[at..];
+            if rest.starts_with("\r\n") {
+                return at + 2;
+            }
+            match rest.chars().next() {
+                Some('\n') => return at + 1,
+                Some(' ' | '\t') => at += 1,
+                Some('/') if rest.starts_with("//") => at += rest.find('\n').unwrap_or(rest.len()),
+                Some('/') if rest.starts_with("/*") => match rest[2..].find("*/") {
+                    Some(end) if !rest[..end + 2].contains('\n') => at += end + 4,
+                    _ => return pos,
+                },
+                _ => return pos,
+            }
+        }
+    }
+
+    fn value(&mut self, depth: usize) -> Result<(Node, Value)> {
+        if depth > MAX_DEPTH {
+            return Err(self.error(format!("nesting deeper than {MAX_DEPTH} levels")));
+        }
+        let start = self.pos;
+        let value = match self.peek() {
+            Some('{') => return self.object(depth),
+            Some('[') => return self.array(depth),
+            Some('"') => Value::String(self.string()?),
+            Some('\'') => {
+                self.allow(start, Dialect::Json5, "single-quoted strings")?;
+                Value::String(self.string()?)
+            }
+            Some('-' | '+' | '.' | '0'..='9') => self.number()?,
+            Some(c) if c.is_alphabetic() => {
+                let word = self.identifier();
+                match word {
+                    "true" => Value::Bool(true),
+                    "false" => Value::Bool(false),
+                    "null" => Value::Null,
+                    "Infinity" | "NaN" => {
+                        self.pos = start;
+                        self.number()?
+                    }
+                    _ => return Err(self.error_at(start, format!("unexpected `{word}`"))),
+                }
+            }
+            _ => return Err(self.unexpected("a value")),
+        };
+        let node = Node {
+            start,
+            end: self.pos,
+            kind: Kind::Scalar,
+        };
+        Ok((node, value))
+    }
+
+    fn object(&mut self, depth: usize) -> Result<(Node, Value)> {
+        let start = self.pos;
+        self.pos += 1;
+        let mut table = BTreeMap::new();
+        let container = self.entries('}', |parser| {
+            let key = parser.key()?;
+            let key_end = parser.pos;
+            parser.skip_trivia()?;
+            if parser.peek() != Some(':') {
+                return Err(parser.unexpected("`:`"));
+            }
+            parser.pos += 1;
+            parser.skip_trivia()?;
+            let (node, value) = parser.value(depth + 1)?;
+            table.insert(key.clone(), value);
+            Ok((Some(key), key_end, node))
+        })?;
+        let node = Node {
+            start,
+            end: self.pos,
+            kind: Kind::Object(container),
+        };
+        Ok((node, Value::Table(table)))
+    }
+
+    fn array(&mut self, depth: usize) -> Result<(Node, Value)> {
+        let start = self.pos;
+        self.pos += 1;
+        let mut items = Vec::new();
+        let container = self.entries(']', |parser| {
+            let key_end = parser.pos;
+            let (node, value) = parser.value(depth + 1)?;
+            items.push(value);
+            Ok((None, key_end, node))
+        })?;
+        let node = Node {
+            start,
+            end: self.pos,
+            kind: Kind::Array(container),
+        };
+        Ok((node, Value::Array(items)))
+    }
+
+    /// Parse comma-separated entries up to `close`, after the opening
+    /// bracket
+    fn entries(
+        &mut self,
+        close: char,
+        mut entry: impl FnMut(&mut Self) -> Result<(Option<String>, usize, Node)>,
+    ) -> Result<Container> {
+        let mut entries = Vec::new();
+        let mut lead = self.line_end(self.pos);
+        let expected = format!("`,` or `{close}`");
+        loop {
+            self.skip_trivia()?;
+            if self.peek() == Some(close) {
+                if !entries.is_empty() {
+                    self.allow(self.pos, Dialect::Jsonc, "trailing commas")?;
+                }
+                self.pos += 1;
+                return Ok(Container {
+                    trailing_comma: !entries.is_empty(),
+                    entries,
+                });
+            }
+            let start = self.pos;
+            let (key, key_end, value) = entry(self)?;
+            let value_end = value.end;
+            self.skip_trivia()?;
+            match self.peek() {
+                Some(',') => {
+                    let comma = self.pos;
+                    self.pos += 1;
+                    let end = self.line_end(self.pos);
+                    entries.push(Entry {
+                        lead,
+                        start,
+                        key,
+                        key_end,
+                        value,
+                        comma: Some(comma),
+                        end,
+                    });
+                    lead = end;
+                }
+                Some(c) if c == close => {
+                    let end = self.line_end(value_end);
+                    entries.push(Entry {
+                        lead,
+                        start,
+                        key,
+                        key_end,
+                        value,
+                        comma: None,
+                        end,
+                    });
+                    self.pos += 1;
+                    return Ok(Container {
+                        entries,
+                        trailing_comma: false,
+                    });
+                }
+                _ => return Err(self.unexpected(&expected)),
+            }
+        }
+    }
+
+    fn key(&mut self) -> Result<String> {
+        let start = self.pos;
+        match self.peek() {
+            Some('"') => self.string(),
+            Some('\'') => {
+                self.allow(start, Dialect::Json5, "single-quoted strings")?;
+                self.string()
+            }
+            Some(c) if c.is_alphabetic() || c == '$' || c == '_' => {
+                self.allow(start, Dialect::Json5, "unquoted keys")?;
+                Ok(self.identifier().to_string())
+            }
+            _ => Err(self.unexpected("an object key")),
+        }
+    }
+
+    fn identifier(&mut self) -> &'a str {
+        let rest = self.rest();
+        let len = rest
+            .find(|c: char| {
+                !(c.is_alphanumeric() || matches!(c, '$' | '_' | '\u{200c}' | '\u{200d}'))
+            })
+            .unwrap_or(rest.len());
+        self.pos += len;
+        &rest[..len]
+    }
+
+    fn string(&mut self) -> Result<String> {
+        let start = self.pos;
+        let Some(quote) = self.peek() else {
+            return Err(self.unexpected("a string"));
+        };
+        self.pos += 1;
+        let mut out = String::new();
+        loop {
+            let Some(ch) = self.peek() else {
+                return Err(self.error_at(start, "unterminated string"));
+            };
+            match ch {
+                c if c == quote => {
+                    self.pos += 1;
+                    return Ok(out);
+                }
+                '\\' => {
+                    self.pos += 1;
+                    self.escape(&mut out)?;
+                }
+                '\n' | '\r' => return Err(self.error("line break in string")),
+                c if u32::from(c) < 0x20 => {
+                    self.allow(self.pos, Dialect::Json5, "unescaped control characters")?;
+                    out.push(c);
+                    self.pos += 1;
+                }
+                c => {
+                    out.push(c);
+                    self.pos += c.len_utf8();
+                }
+            }
+        }
+    }
+
+    /// Decode the escape sequence after a backslash
+    fn escape(&mut self, out: &mut String) -> Result<()> {
+        let start = self.pos - 1;
+        let Some(ch) = self.peek() else {
+            return Err(self.error("unterminated string"));
+        };
+        self.pos += ch.len_utf8();
+        let decoded = match ch {
+            '"' => '"',
+            '\\' => '\\',
+            '/' => '/',
+            'b' => '\u{8}',
+            'f' => '\u{c}',
+            'n' => '\n',
+            'r' => '\r',
+            't' => '\t',
+            'u' => return self.unicode_escape(start, out),
+            '1'..='9' => return Err(self.error_at(start, format!("invalid escape `\\{ch}`"))),
+            _ => {
+                self.allow(start, Dialect::Json5, "JSON5 escape sequences")?;
+                match ch {
+                    'v' => '\u{b}',
+                    '0' if !self.peek().is_some_and(|c| c.is_ascii_digit()) => '\0',
+                    '0' => return Err(self.error_at(start, "invalid escape `\\0`")),
+                    'x' => char::from_u32(self.hex_digits(start, 2)?).unwrap_or_default(),
+                    // Escaped line break: a line continuation
+                    '\r' => {
+                        if self.peek() == Some('\n') {
+                            self.pos += 1;
+                        }
+                        return Ok(());
+                    }
+                    '\n' | '\u{2028}' | '\u{2029}' => return Ok(()),
+                    other => other,
+                }
+            }
+        };
+        out.push(decoded);
+        Ok(())
+    }
+
+    fn unicode_escape(&mut self, start: usize, out: &mut String) -> Result<()> {
+        let code = self.hex_digits(start, 4)?;
+        let code = if (0xD800..0xDC00).contains(&code) {
+            // High surrogate: a low surrogate escape must follow
+            if !self.rest().starts_with("\\u") {
+                return Err(self.error_at(start, "unpaired surrogate in `\\u` escape"));
+            }
+            self.pos += 2;
+            let low = self.hex_digits(start, 4)?;
+            if !(0xDC00..0xE000).contains(&low) {
+                return Err(self.error_at(start, "unpaired surrogate in `\\u` escape"));
+            }
+            0x10000 + ((code - 0xD800) << 10) + (low - 0xDC00)
+        } else {
+            code
+        };
+        let ch = char::from_u32(code)
+            .ok_or_else(|| self.error_at(start, "unpaired surrogate in `\\u` escape"))?;
+        out.push(ch);
+        Ok(())
+    }
+
+    fn hex_digits(&mut self, start: usize, count: usize) -> Result<u32> {
+        let digits = self.rest().get(..count).unwrap_or_default();
+        if digits.len() != count || !digits.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(self.error_at(start, format!("expected {count} hex digits in escape")));
+        }
+        self.pos += count;
+        Ok(digits
+            .chars()
+            .filter_map(|c| c.to_digit(16))
+            .fold(0, |code, digit| code * 16 + digit))
+    }
+
+    fn number(&mut self) -> Result<Value> {
+        let start = self.pos;
+        let negative = match self.peek() {
+            Some('-') => {
+                self.pos += 1;
+                true
+            }
+            Some('+') => {
+                self.allow(start, Dialect::Json5, "leading `+` signs")?;
+                self.pos += 1;
+                false
+            }
+            _ => false,
+        };
+        let sign = if negative { -1.0 } else { 1.0 };
+
+        let rest = self.rest();
+        for (word, value) in [("Infinity", f64::INFINITY), ("NaN", f64::NAN)] {
+            if rest.starts_with(word) {
+                self.allow(start, Dialect::Json5, "`Infinity` and `NaN`")?;
+                self.pos += word.len();
+                return Ok(Value::Float(sign * value));
+            }
+        }
+        if rest.starts_with("0x") || rest.starts_with("0X") {
+            self.allow(start, Dialect::Json5, "hexadecimal numbers")?;
+            self.pos += 2;
+            let digits = self.take_while(|c| c.is_ascii_hexdigit());
+            if digits.is_empty() {
+                return Err(self.error_at(start, "expected hex digits after `0x`"));
+            }
+            let magnitude = u64::from_str_radix(digits, 16)
+                .map_err(|_| self.error_at(start, "hexadecimal number out of range"))?;
+            let signed = if negative {
+                0i64.checked_sub_unsigned(magnitude)
+            } else {
+                i64::try_from(magnitude).ok()
+            };
+            #[allow(clippy::cast_precision_loss)]
+            let float = sign * magnitude as f64;
+            return Ok(signed.map_or(Value::Float(float), Value::Integer));
+        }
+
+        let int_digits = self.take_while(|c| c.is_ascii_digit());
+        if int_digits.len() > 1 && int_digits.starts_with('0') {
+            return Err(self.error_at(start, "leading zeros are not allowed"));
+        }
+        let mut float = false;
+        if self.peek() == Some('.') {
+            let dot = self.pos;
+            self.pos += 1;
+            float = true;
+            let frac_digits = self.take_while(|c| c.is_ascii_digit());
+            match (int_digits.is_empty(), frac_digits.is_empty()) {
+                (true, true) => return Err(self.error_at(start, "invalid number")),
+                (true, false) => self.allow(start, Dialect::Json5, "leading decimal points")?,
+                (false, true) => self.allow(dot, Dialect::Json5, "trailing decimal points")?,
+                (false, false) => {}
+            }
+        } else if int_digits.is_empty() {
+            return Err(self.error_at(start, "invalid number"));
+        }
+        if matches!(self.peek(), Some('e' | 'E')) {
+            self.pos += 1;
+            float = true;
+            if matches!(self.peek(), Some('+' | '-')) {
+                self.pos += 1;
+            }
+            if self.take_while(|c| c.is_ascii_digit()).is_empty() {
+                return Err(self.error_at(start, "expected digits in exponent"));
+            }
+        }
+
+        // As in `serde_json`: `-0` is a float, and integers outside `i64`
+        // become floats
+        let text = self.source[start..self.pos].trim_start_matches('+');
+        if !float && text != "-0" {
+            if let Ok(i) = text.parse::<i64>() {
+                return Ok(Value::Integer(i));
+            }
+        }
+        match text.parse::<f64>() {
+            Ok(f) if f.is_finite() => Ok(Value::Float(f)),
+            _ => Err(self.error_at(start, "number out of range")),
+        }
+    }
+
+    fn take_while(&mut self, f: impl Fn(char) -> bool) -> &'a str {
+        let rest = self.rest();
+        let len = rest.find(|c: char| !f(c)).unwrap_or(rest.len());
+        self.pos += len;
+        &rest[..len]
+    }
+
+    fn error(&self, message: impl Into<String>) -> Error {
+        self.error_at(self.pos, message)
+    }
+
+    fn error_at(&self, pos: usize, message: impl Into<String>) -> Error {
+        let before = &self.source[..pos];
+        let line = before.matches('\n').count() + 1;
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        let column = before[line_start..].chars().count() + 1;
+        Error::parse(message, line, column)
+    }
+
+    fn unexpected(&self, expected: &str) -> Error {
+        match self.peek() {
+            None => self.error(format!("expected {expected}, found end of input")),
+            Some(ch) => self.error(format!("expected {expected}, found {ch:?}")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_jsonc_comments_and_trailing_commas() -> crate::Result<()> {
+        let source = r#"// VS Code settings
+{
+    "editor.fontSize": 14, // points
+    /* block */ "files.exclude": {
+        "**/.git": true,
+    },
+}
+"#;
+        let value = parse_jsonc(source)?;
+        assert_eq!(value.get("\"editor.fontSize\"").unwrap().as_integer()?, 14);
+        assert!(value
+            .get("\"files.exclude\".\"**/.git\"")
+            .unwrap()
+            .as_bool()?);
+
+        let error = parse_with(source, Dialect::Json).unwrap_err().to_string();
+        assert!(
+            error.contains("comments are not allowed in JSON"),
+            "{error}"
+        );
+        assert!(error.contains("line 1"), "{error}");
+        Ok(())
+    }
+
+    #[test]
+    fn test_json5_syntax() -> crate::Result<()> {
+        let source = r"{
+  // JSON5 extras
+  unquoted: 'single \'quoted\'',
+  hex: 0xFF,
+  negative_hex: -0x10,
+  leading: .5,
+  trailing: 5.,
+  positive: +1,
+  infinity: -Infinity,
+  continued: 'a\
+b',
+  escapes: '\x41\v',
+  list: [1, 2,],
+}";
+        let value = parse(source)?;
+        assert_eq!(
+            value.get("unquoted").unwrap().as_string()?,
+            "single 'quoted'"
+        );
+        assert_eq!(value.get("hex").unwrap().as_integer()?, 255);
+        assert_eq!(value.get("negative_hex").unwrap().as_integer()?, -16);
+        assert_eq!(value.get("leading").unwrap().as_float()?, 0.5);
+        assert_eq!(value.get("trailing").unwrap().as_float()?, 5.0);
+        assert_eq!(value.get("positive").unwrap().as_integer()?, 1);
+        assert_eq!(
+            value.get("infinity").unwrap().as_float()?,
+            f64::NEG_INFINITY
+        );
+        assert_eq!(value.get("continued").unwrap().as_string()?, "ab");
+        assert_eq!(value.get("escapes").unwrap().as_string()?, "A\u{b}");
+        assert_eq!(value.get("list").unwrap().as_array()?.len(), 2);
+
+        let error = parse_jsonc("{unquoted: 1}").unwrap_err().to_string();
+        assert!(
+            error.contains("unquoted keys are not allowed in JSONC"),
+            "{error}"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_strict_json_matches_serde() -> crate::Result<()> {
+        let source = r#"{"a": [1, -2.5e3, "xé😀"], "b": null, "big": 18446744073709551615}"#;
+        let ours = parse_with(source, Dialect::Json)?;
+        let serde = crate::parsers::json_parser::parse(source)?;
+        assert_eq!(ours, serde);
+
+        for bad in [
+            "{\"a\": 01}",
+            "[1,]",
+            "{'a': 1}",
+            "[.5]",
+            "[\"\t\"]",
+            "{\"a\" 1}",
+            "",
+        ] {
+            assert!(
+                parse_with(bad, Dialect::Json).is_err(),
+                "{bad:?} should be rejected"
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_detect_dialect() {
+        assert_eq!(detect_dialect(r#"{"a": 1}"#), Dialect::Json);
+        assert_eq!(detect_dialect("{\"a\": 1, // c\n}"), Dialect::Jsonc);
+        assert_eq!(detect_dialect("{\"a\": [1,],}"), Dialect::Jsonc);
+        assert_eq!(detect_dialect("{a: 1}"), Dialect::Json5);
+        // Syntax before an error still counts
+        assert_eq!(detect_dialect("/* c */ {\"a\": }"), Dialect::Jsonc);
+    }
+
+    #[test]
+    fn test_edit_preserves_comments() -> crate::Result<()> {
+        let source = r#"{
+    // Editor
+    "editor.fontSize": 14, // points
+    "editor.tabSize": 4,
+
+    /* Files */
+    "files.exclude": {
+        "**/.git": true
+    },
+}
+"#;
+        let mut document = parse_document(source, Dialect::Jsonc)?;
+        document.set("\"editor.fontSize\"", Value::integer(16))?;
+        document.set("\"files.exclude\".\"**/node_modules\"", Value::bool(true))?;
+        document.set("theme", Value::string("dark"))?;
+        document.remove("\"editor.tabSize\"")?;
+
+        let expected = r#"{
+    // Editor
+    "editor.fontSize": 16, // points
+
+    /* Files */
+    "files.exclude": {
+        "**/.git": true,
+        "**/node_modules": true
+    },
+    "theme": "dark",
+}
+"#;
+        assert_eq!(document.to_string(), expected);
+        assert_eq!(document.value(), &parse_jsonc(expected)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_edit_json5_and_arrays() -> crate::Result<()> {
+        let source = "{\n\tname: 'app',\n\tports: [80, 443], // public\n\tempty: {},\n}\n";
+        let mut document = parse_document(source, Dialect::Json5)?;
+        document.set("name", Value::string("it's"))?;
+        document.set("ports[1]", Value::integer(8443))?;
+        document.set("empty.nested", Value::table(BTreeMap::new()))?;
+        document.set(
+            "limits",
+            Value::table(BTreeMap::from([("cpu".to_string(), Value::float(0.5))])),
+        )?;
+
+        let expected = "{\n\tname: 'it\\'s',\n\tports: [80, 8443], // public\n\tempty: {\n\t\tnested: {}\n\t},\n\tlimits: {\n\t\tcpu: 0.5\n\t},\n}\n";
+        assert_eq!(document.to_string(), expected);
+
+        let mut target = document.value().clone();
+        if let Value::Table(table) = &mut target {
+            let ports = table.get_mut("ports").unwrap();
+            *ports = Value::array(vec![
+                Value::integer(22),
+                Value::integer(80),
+                Value::integer(8443),
+            ]);
+        }
+        document.sync(&target)?;
+        assert!(document
+            .to_string()
+            .contains("ports: [22, 80, 8443], // public\n"));
+        document.remove("ports[0]")?;
+        assert!(document
+            .to_string()
+            .contains("ports: [80, 8443], // public\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_edit_one_line_document() -> crate::Result<()> {
+        let source = "{\"a\": {\"b\": 1}, \"e\": {}}\n";
+        let mut document = parse_document(source, Dialect::Json)?;
+        document.set("a.c.d", Value::integer(2))?;
+        document.set("e.f", Value::array(vec![Value::integer(3)]))?;
+        document.set("g", Value::integer(4))?;
+
+        let expected = "{\"a\": {\"b\": 1, \"c\": {\"d\": 2}}, \"e\": {\"f\": [3]}, \"g\": 4}\n";
+        assert_eq!(document.to_string(), expected);
+        assert_eq!(document.value(), &parse(expected)?);
+
+        // A one-line object inside a multi-line document grows on its line
+        let source = "{\n  \"a\": {\"b\": 1}\n}\n";
+        let mut document = parse_document(source, Dialect::Json)?;
+        document.set("a.c.d", Value::integer(2))?;
+        assert_eq!(
+            document.to_string(),
+            "{\n  \"a\": {\"b\": 1, \"c\": {\"d\": 2}}\n}\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_serialize_round_trips() -> crate::Result<()> {
+        let source =
+            r#"{"name": "a\"b\n", "list": [1, 2.5, {"x": null}], "empty": [], "nan": NaN}"#;
+        let value = parse(source)?;
+        let json5 = serialize(&value)?;
+        let reparsed = parse(&json5)?;
+        assert_eq!(reparsed.get("name"), value.get("name"));
+        assert_eq!(reparsed.get("list"), value.get("list"));
+        assert!(reparsed.get("nan").unwrap().as_float()?.is_nan());
+        assert!(serialize_with(&value, Dialect::Jsonc).is_err());
+        Ok(())
+    }
+}
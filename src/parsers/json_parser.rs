@@ -1,7 +1,12 @@
 //! # JSON Format Parser
 //!
-//! JSON parser with potential for edit capabilities through structured preservation.
+//! JSON parser and serializer backed by `serde_json`. [`parse_document`]
+//! keeps the original text so that edits are saved as minimal changes,
+//! preserving key order, indentation and number formatting.
+//!
+//! [`parse_document`]: crate::parsers::json_parser::parse_document
 
+use super::json5_parser::{self, Dialect, JsonDocument};
 use crate::error::{Error, Result};
 use crate::value::Value;
 use std::collections::BTreeMap;
@@ -14,6 +19,14 @@ pub fn parse(source: &str) -> Result<Value> {
     convert_json_value(json_value)
 }
 
+/// Parse strict JSON, keeping its layout for in-place edits
+///
+/// Values match [`parse`]; [`JsonDocument::set`], [`JsonDocument::remove`]
+/// and [`JsonDocument::sync`] then rewrite only what changed.
+pub fn parse_document(source: &str) -> Result<JsonDocument> {
+    json5_parser::parse_document(source, Dialect::Json)
+}
+
 /// Convert serde_json::Value to config-lib Value
 fn convert_json_value(json_value: serde_json::Value) -> Result<Value> {
     match json_value {
@@ -228,4 +241,56 @@ mod tests {
         assert!(json.contains("\"port\": 8080"));
         Ok(())
     }
+
+    #[test]
+    fn test_document_minimal_edits() -> crate::Result<()> {
+        let source = r#"{
+    "zeta": 1.0,
+    "alpha": {
+        "timeout": 1e3,
+        "retries": 3
+    },
+    "big": 18446744073709551615,
+    "list": [1, 2]
+}
+"#;
+        let mut document = parse_document(source)?;
+        assert_eq!(document.value(), &parse(source)?);
+
+        document.set("alpha.retries", Value::Integer(5))?;
+        document.set("alpha.timeout", Value::Float(2500.0))?;
+        document.set("list[2]", Value::Integer(3))?;
+        document.set("mode", Value::String("fast".to_string()))?;
+        document.remove("zeta")?;
+
+        let expected = r#"{
+    "alpha": {
+        "timeout": 2.5e3,
+        "retries": 5
+    },
+    "big": 18446744073709551615,
+    "list": [1, 2, 3],
+    "mode": "fast"
+}
+"#;
+        assert_eq!(document.to_string(), expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_document_keeps_crlf_line_endings() -> crate::Result<()> {
+        let mut document = parse_document("{\r\n  \"b\": 1,\r\n  \"a\": 2\r\n}\r\n")?;
+        document.set(
+            "c",
+            Value::table(BTreeMap::from([("d".to_string(), Value::Null)])),
+        )?;
+        assert_eq!(
+            document.to_string(),
+            "{\r\n  \"b\": 1,\r\n  \"a\": 2,\r\n  \"c\": {\r\n    \"d\": null\r\n  }\r\n}\r\n"
+        );
+
+        let error = parse_document("{\"a\": 1e400}").unwrap_err().to_string();
+        assert!(error.contains("out of range"), "{error}");
+        Ok(())
+    }
 }
@@ -0,0 +1,271 @@
+//! Where keys are written in a source file.
+//!
+//! The parsers build plain [`Value`] trees without position data. Most can
+//! also outline a document: a [`Mark`] for every section header, key and
+//! array element, in source order, with its byte offset and canonical path
+//! (see [`crate::path`]). [`outline`] picks the outline for a format; the
+//! linter reports positions from it and [`key_lines`] turns it into the
+//! lines that provenance tracking records.
+
+use crate::error::Result;
+use crate::path;
+#[cfg(any(feature = "hcl", feature = "xml"))]
+use crate::value::Value;
+use std::collections::BTreeMap;
+
+/// A section header, key or array element as written in the source,
+/// from a parser's outline of a document.
+#[derive(Debug, Clone)]
+#[cfg_attr(not(feature = "lint"), allow(dead_code))] // scope and key are for the linter
+pub(crate) struct Mark {
+    /// Byte offset of the key, of the header's `[`, or of the element.
+    pub(crate) offset: usize,
+    /// Canonical path of the table the key belongs to.
+    pub(crate) scope: String,
+    /// The key or section name as parsed; `None` for array elements.
+    pub(crate) key: Option<String>,
+    /// Canonical path of the value, or of the section's table.
+    pub(crate) path: String,
+    /// The `=` or `:` between key and value, in formats that allow both.
+    pub(crate) separator: Option<char>,
+    /// Whether this is a `[section]` header.
+    pub(crate) section: bool,
+}
+
+impl Mark {
+    /// A `[name]` header at `offset`.
+    pub(crate) fn section(offset: usize, name: &str) -> Self {
+        Self {
+            offset,
+            scope: String::new(),
+            key: Some(name.to_string()),
+            path: path::join_key("", name),
+            separator: None,
+            section: true,
+        }
+    }
+
+    /// `key` at `offset`, in the table at `scope`, holding the value at
+    /// `path`.
+    pub(crate) fn entry(offset: usize, scope: &str, key: &str, path: String) -> Self {
+        Self {
+            offset,
+            scope: scope.to_string(),
+            key: Some(key.to_string()),
+            path,
+            separator: None,
+            section: false,
+        }
+    }
+
+    /// An array element at `offset`, holding the value at `path`.
+    #[cfg(any(feature = "json", feature = "yaml", feature = "hcl", feature = "xml"))]
+    pub(crate) fn element(offset: usize, scope: &str, path: String) -> Self {
+        Self {
+            key: None,
+            ..Self::entry(offset, scope, "", path)
+        }
+    }
+
+    /// The same mark, with `separator` between its key and value.
+    pub(crate) fn separated(mut self, separator: char) -> Self {
+        self.separator = Some(separator);
+        self
+    }
+
+    /// The same mark as a `[section]` header.
+    #[cfg(feature = "toml")]
+    pub(crate) fn header(mut self) -> Self {
+        self.section = true;
+        self
+    }
+}
+
+/// The outline of `source` from its format's parser, if it has one.
+///
+/// # Errors
+///
+/// Returns the parser's error if `source` is not valid `format`.
+pub(crate) fn outline(source: &str, format: &str) -> Result<Option<Vec<Mark>>> {
+    #[cfg(feature = "json")]
+    use super::json5_parser::{self, Dialect};
+
+    Ok(match format {
+        "conf" => Some(super::conf::parse_document(source)?.marks()),
+        "ini" => Some(super::ini_parser::parse_document(source)?.marks()),
+        "properties" => Some(super::properties_parser::marks(source)?),
+        "dotenv" => Some(super::dotenv_parser::parse_document(source)?.marks()),
+        #[cfg(feature = "json")]
+        "json" => json5_parser::marks(source, Dialect::Json),
+        #[cfg(feature = "json")]
+        "jsonc" => json5_parser::marks(source, Dialect::Jsonc),
+        #[cfg(feature = "json")]
+        "json5" => json5_parser::marks(source, Dialect::Json5),
+        #[cfg(feature = "toml")]
+        "toml" => Some(super::toml_parser::marks(source)?),
+        #[cfg(feature = "yaml")]
+        "yaml" => Some(super::yaml_parser::marks(source)?),
+        #[cfg(feature = "hcl")]
+        "hcl" => Some(super::hcl_parser::marks(source)?),
+        #[cfg(feature = "xml")]
+        "xml" => Some(super::xml_parser::marks(source)?),
+        _ => None,
+    })
+}
+
+/// Map canonical key paths to the 1-indexed line on which each key is
+/// defined, from the format's [`outline`]. Formats without one, and
+/// sources that do not parse, yield an empty map. When a key is defined
+/// more than once the last definition wins, matching the parsers.
+pub(crate) fn key_lines(source: &str, format: &str) -> BTreeMap<String, usize> {
+    let Ok(Some(marks)) = outline(source, format) else {
+        return BTreeMap::new();
+    };
+    let starts = line_starts(source);
+    marks
+        .into_iter()
+        .map(|mark| (mark.path, line_of(&starts, mark.offset)))
+        .collect()
+}
+
+/// Byte offset of every line start in `source`.
+pub(crate) fn line_starts(source: &str) -> Vec<usize> {
+    std::iter::once(0)
+        .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+        .collect()
+}
+
+/// The 1-indexed line holding `offset`, given [`line_starts`].
+pub(crate) fn line_of(starts: &[usize], offset: usize) -> usize {
+    starts.partition_point(|&start| start <= offset)
+}
+
+/// One step of a path whose array indices are only known once the whole
+/// document is parsed: XML elements and HCL blocks become arrays when a
+/// later sibling repeats their name.
+#[cfg(any(feature = "hcl", feature = "xml"))]
+#[derive(Debug, Clone)]
+pub(crate) struct Step {
+    pub(crate) key: String,
+    /// For a repeatable element, its position among the siblings with the
+    /// same name; `None` for keys that are never collected into arrays.
+    pub(crate) occurrence: Option<usize>,
+}
+
+/// Turn a mark recorded as [`Step`]s at `offset` into a [`Mark`] against
+/// the parsed `root`. A repeated element becomes an array element; `None`
+/// if the steps do not lead anywhere in `root`.
+#[cfg(any(feature = "hcl", feature = "xml"))]
+pub(crate) fn resolve(root: &Value, offset: usize, steps: &[Step]) -> Option<Mark> {
+    let mut scope = String::new();
+    let mut node = root;
+    let mut mark = None;
+    for step in steps {
+        let mut path = path::join_key(&scope, &step.key);
+        node = node.as_table().ok()?.get(&step.key)?;
+        mark = Some(match (step.occurrence, node) {
+            (Some(occurrence), Value::Array(items)) => {
+                path = path::join_index(&path, occurrence);
+                node = items.get(occurrence)?;
+                Mark::element(offset, &scope, path.clone())
+            }
+            _ => Mark::entry(offset, &scope, &step.key, path.clone()),
+        });
+        scope = path;
+    }
+    mark
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conf_lines() {
+        let source = "name = app\n\n[database]\n# comment\nport = 5432\nhosts = [\"a\", \"b = c\"]\n\nuser = admin\nhost.name = db\n";
+        let lines = key_lines(source, "conf");
+        assert_eq!(lines["name"], 1);
+        assert_eq!(lines["database"], 3);
+        assert_eq!(lines["database.port"], 5);
+        assert_eq!(lines["database.hosts"], 6);
+        assert_eq!(lines["database.user"], 8);
+        assert_eq!(lines["database.\"host.name\""], 9);
+        assert_eq!(lines.len(), 6);
+    }
+
+    #[test]
+    fn test_ini_and_properties_lines() {
+        let ini = key_lines("[server]\nhost = x\nport: 80\n", "ini");
+        assert_eq!(ini["\"server.host\""], 2);
+        assert_eq!(ini["\"server.port\""], 3);
+
+        let props = key_lines("! c\na.b = 1\nlong = x \\\n  y = z\nc: 2\n", "properties");
+        assert_eq!(props["\"a.b\""], 2);
+        assert_eq!(props["c"], 5);
+        assert_eq!(props.len(), 3);
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_json_lines() {
+        let source = "{\n  \"db\": {\n    \"port\": 5432,\n    \"hosts\": [\n      \"a\",\n      {\"name\": \"b\"}\n    ]\n  },\n  \"a.b\": true\n}";
+        let lines = key_lines(source, "json");
+        assert_eq!(lines["db"], 2);
+        assert_eq!(lines["db.port"], 3);
+        assert_eq!(lines["db.hosts"], 4);
+        assert_eq!(lines["db.hosts[0]"], 5);
+        assert_eq!(lines["db.hosts[1].name"], 6);
+        assert_eq!(lines["\"a.b\""], 9);
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn test_toml_lines() {
+        let source = "title = \"app\"\n\n[server]\nhost = \"x\"\ntls.cert = \"c\"\n\n[[server.routes]]\npath = \"/\"\n\n[[server.routes]]\npath = \"/api\"\n";
+        let lines = key_lines(source, "toml");
+        assert_eq!(lines["title"], 1);
+        assert_eq!(lines["server"], 3);
+        assert_eq!(lines["server.host"], 4);
+        assert_eq!(lines["server.tls.cert"], 5);
+        assert_eq!(lines["server.routes[0]"], 7);
+        assert_eq!(lines["server.routes[1].path"], 11);
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn test_yaml_lines() {
+        let source = "server:\n  host: x\n  ports:\n    - 80\n    - name: tls\n      port: 443\n";
+        let lines = key_lines(source, "yaml");
+        assert_eq!(lines["server"], 1);
+        assert_eq!(lines["server.host"], 2);
+        assert_eq!(lines["server.ports[0]"], 4);
+        assert_eq!(lines["server.ports[1].port"], 6);
+
+        let stream = key_lines("a: 1\n---\nb: 2\n", "yaml");
+        assert_eq!(stream["[0].a"], 1);
+        assert_eq!(stream["[1].b"], 3);
+    }
+
+    #[cfg(feature = "hcl")]
+    #[test]
+    fn test_hcl_lines() {
+        let source = "region = \"eu\"\n\njob \"web\" {\n  group \"api\" {\n    count = 2\n  }\n  task {\n    driver = \"docker\"\n  }\n  task {\n    driver = \"exec\"\n  }\n}\n";
+        let lines = key_lines(source, "hcl");
+        assert_eq!(lines["region"], 1);
+        assert_eq!(lines["job.web"], 3);
+        assert_eq!(lines["job.web.group.api.count"], 5);
+        assert_eq!(lines["job.web.task[0]"], 7);
+        assert_eq!(lines["job.web.task[1].driver"], 11);
+    }
+
+    #[cfg(feature = "xml")]
+    #[test]
+    fn test_xml_lines() {
+        let source = "<config>\n  <name>app</name>\n  <server port=\"80\">\n    <host>a</host>\n  </server>\n  <server>\n    <host>b</host>\n  </server>\n</config>\n";
+        let lines = key_lines(source, "xml");
+        assert_eq!(lines["config"], 1);
+        assert_eq!(lines["config.name"], 2);
+        assert_eq!(lines["config.server[0]"], 3);
+        assert_eq!(lines["config.server[1].host"], 7);
+    }
+}
@@ -5,15 +5,25 @@
 
 pub mod conf;
 
+/// Document outlines: where each key is written
+pub(crate) mod locate;
+
 /// Java Properties format parser
 pub mod properties_parser;
 
 /// INI format parser
 pub mod ini_parser;
 
+/// Dotenv (.env) format parser
+pub mod dotenv_parser;
+
 #[cfg(feature = "json")]
 pub mod json_parser;
 
+/// JSON5 and JSONC parser with comment-preserving edits
+#[cfg(feature = "json")]
+pub mod json5_parser;
+
 /// XML format parser (enterprise feature)
 #[cfg(feature = "xml")]
 pub mod xml_parser;
@@ -22,6 +32,10 @@ pub mod xml_parser;
 #[cfg(feature = "hcl")]
 pub mod hcl_parser;
 
+/// YAML format parser and serializer
+#[cfg(feature = "yaml")]
+pub mod yaml_parser;
+
 // TOML and NOML parsers now enabled with published crate
 #[cfg(feature = "toml")]
 pub mod toml_parser;
@@ -42,19 +56,26 @@ pub fn parse_string(source: &str, format: Option<&str>) -> Result<Value> {
         "conf" => conf::parse(source),
         "properties" => properties_parser::parse(source),
         "ini" => ini_parser::parse(source),
+        "dotenv" => dotenv_parser::parse(source),
         #[cfg(feature = "json")]
         "json" => json_parser::parse(source),
+        #[cfg(feature = "json")]
+        "jsonc" => json5_parser::parse_jsonc(source),
+        #[cfg(feature = "json")]
+        "json5" => json5_parser::parse(source),
         #[cfg(feature = "xml")]
         "xml" => xml_parser::parse(source),
         #[cfg(feature = "hcl")]
         "hcl" => hcl_parser::parse(source),
+        #[cfg(feature = "yaml")]
+        "yaml" => yaml_parser::parse(source),
         #[cfg(feature = "noml")]
         "noml" => noml_parser::parse(source),
         #[cfg(feature = "toml")]
         "toml" => toml_parser::parse(source),
         _ => {
             #[cfg(not(feature = "json"))]
-            if detected_format == "json" {
+            if matches!(detected_format, "json" | "jsonc" | "json5") {
                 return Err(Error::feature_not_enabled("json"));
             }
 
@@ -68,6 +89,11 @@ pub fn parse_string(source: &str, format: Option<&str>) -> Result<Value> {
                 return Err(Error::feature_not_enabled("hcl"));
             }
 
+            #[cfg(not(feature = "yaml"))]
+            if detected_format == "yaml" {
+                return Err(Error::feature_not_enabled("yaml"));
+            }
+
             #[cfg(not(feature = "noml"))]
             if detected_format == "noml" {
                 return Err(Error::feature_not_enabled("noml"));
@@ -90,9 +116,26 @@ pub fn parse_file<P: AsRef<Path>>(path: P) -> Result<Value> {
     let content =
         std::fs::read_to_string(path).map_err(|e| Error::io(path.display().to_string(), e))?;
 
-    let format = detect_format_from_path(path).or_else(|| Some(detect_format(&content)));
+    parse_string(&content, Some(detect_file_format(path, &content)))
+}
 
-    parse_string(&content, format)
+/// Parse configuration from a file, refusing to guess its format.
+///
+/// Like [`parse_file`], except that files without a known extension
+/// (including unknown extensions, which [`parse_file`] reads as CONF)
+/// are detected with [`detect_file_format_strict`].
+///
+/// # Errors
+///
+/// Returns [`Error::UnknownFormat`] if the format is ambiguous or no
+/// enabled format parses the file, and otherwise what [`parse_file`]
+/// returns.
+pub fn parse_file_strict<P: AsRef<Path>>(path: P) -> Result<Value> {
+    let path = path.as_ref();
+    let content =
+        std::fs::read_to_string(path).map_err(|e| Error::io(path.display().to_string(), e))?;
+
+    parse_string(&content, Some(detect_file_format_strict(path, &content)?))
 }
 
 /// Async version of parse_file
@@ -103,26 +146,57 @@ pub async fn parse_file_async<P: AsRef<Path>>(path: P) -> Result<Value> {
         .await
         .map_err(|e| Error::io(path.display().to_string(), e))?;
 
-    let format = detect_format_from_path(path).or_else(|| Some(detect_format(&content)));
-
-    parse_string(&content, format)
+    parse_string(&content, Some(detect_file_format(path, &content)))
 }
 
 /// Detect configuration format from file path
+///
+/// `.env` and `.env.*` files (`.env.local`, `.env.production`) are dotenv.
 pub fn detect_format_from_path(path: &Path) -> Option<&'static str> {
-    path.extension()
-        .and_then(|ext| ext.to_str())
-        .map(|ext| match ext.to_lowercase().as_str() {
-            "conf" | "config" | "cfg" => "conf",
-            "properties" => "properties",
-            "ini" => "ini",
-            "toml" => "toml",
-            "json" => "json",
-            "noml" => "noml",
-            "xml" => "xml",
-            "hcl" | "tf" => "hcl", // .tf files are Terraform HCL
-            _ => "conf",           // Default to conf for unknown extensions
-        })
+    known_format(path).or_else(|| {
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .map(|_| "conf") // Default to conf for unknown extensions
+    })
+}
+
+/// The format named by `path`'s file name or extension; `None` for
+/// unknown extensions and paths without one.
+fn known_format(path: &Path) -> Option<&'static str> {
+    let name = path.file_name().and_then(|name| name.to_str());
+    if name.is_some_and(|name| name == ".env" || name.starts_with(".env.")) {
+        return Some("dotenv");
+    }
+    let ext = path.extension()?.to_str()?.to_lowercase();
+    Some(match ext.as_str() {
+        "conf" | "config" | "cfg" => "conf",
+        "properties" => "properties",
+        "ini" => "ini",
+        "env" => "dotenv",
+        "toml" => "toml",
+        "json" => "json",
+        "jsonc" => "jsonc",
+        "json5" => "json5",
+        "noml" => "noml",
+        "xml" => "xml",
+        "hcl" | "tf" | "nomad" => "hcl", // .tf files are Terraform HCL
+        "yaml" | "yml" => "yaml",
+        _ => return None,
+    })
+}
+
+/// Detect the format of a file's `content`: by extension, falling back
+/// to the content for paths without one.
+///
+/// `.json` files that use comments, trailing commas or other JSON5 syntax
+/// (`tsconfig.json`, VS Code's `settings.json`) are read as `jsonc` or
+/// `json5`.
+pub fn detect_file_format(path: &Path, content: &str) -> &'static str {
+    match detect_format_from_path(path) {
+        Some("json") => json_dialect(content),
+        Some(format) => format,
+        None => detect_format(content),
+    }
 }
 
 /// Detect configuration format from content
@@ -134,9 +208,17 @@ pub fn detect_format(content: &str) -> &'static str {
         return "xml";
     }
 
-    // JSON detection - starts with { or [
-    if trimmed.starts_with('{') || trimmed.starts_with('[') {
-        return "json";
+    // JSON detection - starts with { or [, possibly after comments
+    let mut body = trimmed;
+    while body.starts_with("//") || body.starts_with("/*") {
+        body = match body.strip_prefix("//") {
+            Some(comment) => comment.split_once('\n').map_or("", |(_, rest)| rest),
+            None => body.split_once("*/").map_or("", |(_, rest)| rest),
+        }
+        .trim_start();
+    }
+    if body.starts_with('{') || body.starts_with('[') {
+        return json_dialect(content);
     }
 
     // HCL detection - look for HCL-specific features
@@ -149,6 +231,12 @@ pub fn detect_format(content: &str) -> &'static str {
         return "noml";
     }
 
+    // YAML detection - document markers or indented block structure
+    // (before INI and properties, whose `key: value` lines YAML shares)
+    if contains_yaml_features(content) {
+        return "yaml";
+    }
+
     // INI detection - look for section headers (before properties since INI can use colons)
     if contains_ini_features(content) {
         return "ini";
@@ -168,6 +256,257 @@ pub fn detect_format(content: &str) -> &'static str {
     "conf"
 }
 
+/// Formats [`detect_format_scored`] tries, most specific first; candidates
+/// with equal confidence keep this order.
+const CANDIDATES: &[&str] = &[
+    "json",
+    "jsonc",
+    "json5",
+    "xml",
+    "toml",
+    "noml",
+    "hcl",
+    "yaml",
+    "ini",
+    "properties",
+    "dotenv",
+    "conf",
+];
+
+/// Confidence gap, in hundredths, within which two candidates that parse
+/// to different values make strict detection ambiguous.
+const AMBIGUITY_MARGIN: u32 = 10;
+
+/// Confidence, in hundredths, below which strict detection finds no format.
+const MIN_CONFIDENCE: u32 = 25;
+
+/// A format that parses the content, with its confidence in hundredths.
+struct Candidate {
+    format: &'static str,
+    points: u32,
+    value: Value,
+}
+
+impl Candidate {
+    fn confidence(&self) -> f64 {
+        f64::from(self.points) / 100.0
+    }
+}
+
+/// Rank the formats `content` could be in by trial-parsing it as each
+/// enabled format.
+///
+/// Every format whose parser accepts `content` is returned with a
+/// confidence in `(0, 1]`, best first; an empty vector means none does.
+/// The confidence starts from how strict the format's syntax is (almost
+/// any text is valid YAML, little is valid JSON) and moves with the
+/// evidence: section headers (INI, CONF), quoted values, arrays and
+/// `[[tables]]` (TOML), blocks (HCL), indented mappings (YAML), `!`
+/// comments and line continuations (Properties), `export` and upper-case
+/// names (dotenv). Parses whose root is not a table rank low.
+///
+/// Probes never read the process environment or included files: dotenv
+/// and NOML variables expand as if unset.
+///
+/// ```rust
+/// use config_lib::parsers::detect_format_scored;
+///
+/// let scores = detect_format_scored("[server]\nhost = localhost\n");
+/// assert_eq!(scores[0].0, "ini");
+/// // Bare strings are not TOML, and section headers are not Properties
+/// assert!(scores.iter().all(|(format, _)| *format != "toml" && *format != "properties"));
+/// ```
+pub fn detect_format_scored(content: &str) -> Vec<(&'static str, f64)> {
+    candidates(content)
+        .iter()
+        .map(|candidate| (candidate.format, candidate.confidence()))
+        .collect()
+}
+
+/// Detect the format of `content` without guessing: the best format of
+/// [`detect_format_scored`], provided its confidence is at least 0.25 and
+/// no other format within 0.1 of it reads `content` as different values.
+///
+/// # Errors
+///
+/// Returns [`Error::UnknownFormat`], listing the close candidates, if the
+/// detection is ambiguous or finds no format.
+pub fn detect_format_strict(content: &str) -> Result<&'static str> {
+    strict_format("the content", content)
+}
+
+/// Detect the format of a file without guessing: by its name or a known
+/// extension like [`detect_file_format`], and otherwise (no extension, or
+/// one [`detect_format_from_path`] would read as CONF) with
+/// [`detect_format_strict`].
+///
+/// # Errors
+///
+/// Returns [`Error::UnknownFormat`] naming the file if its content has to
+/// be detected and the detection is ambiguous or finds no format.
+pub fn detect_file_format_strict(path: &Path, content: &str) -> Result<&'static str> {
+    match known_format(path) {
+        Some("json") => Ok(json_dialect(content)),
+        Some(format) => Ok(format),
+        None => strict_format(&path.display().to_string(), content),
+    }
+}
+
+fn strict_format(name: &str, content: &str) -> Result<&'static str> {
+    let found = candidates(content);
+    let Some(best) = found.first().filter(|best| best.points >= MIN_CONFIDENCE) else {
+        return Err(Error::unknown_format(format!(
+            "{name} does not look like any supported format"
+        )));
+    };
+    let rivals: Vec<String> = found[1..]
+        .iter()
+        .filter(|other| best.points - other.points < AMBIGUITY_MARGIN && other.value != best.value)
+        .map(|other| format!("{} ({:.2})", other.format, other.confidence()))
+        .collect();
+    if rivals.is_empty() {
+        return Ok(best.format);
+    }
+    Err(Error::unknown_format(format!(
+        "{name} could be {} ({:.2}) or {}; name the format or use a known file extension",
+        best.format,
+        best.confidence(),
+        rivals.join(" or ")
+    )))
+}
+
+/// Every candidate format that parses `content`, best first.
+fn candidates(content: &str) -> Vec<Candidate> {
+    let mut found: Vec<Candidate> = CANDIDATES
+        .iter()
+        .filter_map(|&format| {
+            // Disabled formats fail with `FeatureNotEnabled`
+            let value = probe(content, format).ok()?;
+            Some(Candidate {
+                format,
+                points: confidence(format, content, &value),
+                value,
+            })
+        })
+        .collect();
+    found.sort_by_key(|candidate| std::cmp::Reverse(candidate.points));
+    found
+}
+
+/// Parse `content` as `format` for detection only. Dotenv and NOML are
+/// parsed without the process environment or included files, so a probe
+/// whose result is thrown away cannot read them.
+fn probe(content: &str, format: &str) -> Result<Value> {
+    match format {
+        "dotenv" => dotenv_parser::parse_with(content, &|_| None),
+        #[cfg(feature = "noml")]
+        "noml" => noml_parser::parse_isolated(content),
+        _ => parse_string(content, Some(format)),
+    }
+}
+
+/// How likely `content`, which parses as `format` to `value`, is meant
+/// as `format`, in hundredths.
+fn confidence(format: &str, content: &str, value: &Value) -> u32 {
+    let lines: Vec<&str> = content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect();
+    // The text after each line's first `=` or `:`
+    let values = || {
+        lines
+            .iter()
+            .filter_map(|line| line.split_once(['=', ':']).map(|(_, value)| value.trim()))
+    };
+    let quoted = values().any(|value| value.starts_with(['"', '\'']));
+    let sections = lines.iter().any(|line| {
+        line.starts_with('[')
+            && line.ends_with(']')
+            && !line.starts_with("[[")
+            && !line.contains('=')
+    });
+
+    let prior: i32 = match format {
+        "json" | "xml" => 95,
+        "jsonc" => 90,
+        "json5" => 85,
+        "toml" => 80,
+        "noml" => 75,
+        "hcl" => 70,
+        "yaml" | "ini" => 60,
+        "properties" => 55,
+        "dotenv" => 50,
+        _ => 45,
+    };
+    let evidence: i32 = match format {
+        "toml" | "noml" => {
+            let structured = lines.iter().any(|line| line.starts_with("[["))
+                || values().any(|value| value.starts_with(['"', '\'', '[', '{']));
+            if structured {
+                15
+            } else {
+                0
+            }
+        }
+        // Both parsers take plain text: as an XML text node and as HCL
+        // variable references
+        "xml" if !content.trim_start().starts_with('<') => -100,
+        "hcl" => {
+            let blocks = lines
+                .iter()
+                .any(|line| line.ends_with('{') && !line.contains('='));
+            if blocks {
+                20
+            } else if quoted {
+                0
+            } else {
+                -30
+            }
+        }
+        "yaml" if contains_yaml_features(content) => 30,
+        "ini" => {
+            let comments = lines.iter().any(|line| line.starts_with(';'));
+            (if sections { 15 } else { -15 }) + if comments { 10 } else { 0 }
+        }
+        "conf" if sections => 10,
+        "properties" => {
+            let syntax = lines
+                .iter()
+                .any(|line| line.starts_with('!') || line.ends_with('\\'));
+            (if syntax { 15 } else { 0 }) - if quoted { 30 } else { 0 }
+        }
+        "dotenv" => {
+            let exported = lines.iter().any(|line| line.starts_with("export "));
+            let upper = value.as_table().is_ok_and(|table| {
+                table.keys().all(|key| {
+                    key.chars()
+                        .all(|ch| ch.is_ascii_uppercase() || ch.is_ascii_digit() || ch == '_')
+                })
+            });
+            (if exported { 30 } else { 0 }) + if upper { 15 } else { -10 }
+        }
+        _ => 0,
+    };
+
+    let mut points = prior + evidence;
+    if !matches!(value, Value::Table(_)) {
+        points /= 5;
+    }
+    u32::try_from(points.clamp(1, 100)).unwrap_or(1)
+}
+
+/// `json`, `jsonc` or `json5`: the strictest JSON dialect `content` needs
+fn json_dialect(content: &str) -> &'static str {
+    #[cfg(feature = "json")]
+    return json5_parser::detect_dialect(content).name();
+    #[cfg(not(feature = "json"))]
+    {
+        let _ = content;
+        "json"
+    }
+}
+
 /// Check if content contains NOML-specific features
 fn contains_noml_features(content: &str) -> bool {
     // Look for NOML-specific syntax
@@ -180,6 +519,40 @@ fn contains_noml_features(content: &str) -> bool {
         || content.contains("@ip(")
 }
 
+/// Check if content contains YAML-specific features
+fn contains_yaml_features(content: &str) -> bool {
+    let mut lines = content
+        .lines()
+        .filter(|line| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .peekable();
+
+    while let Some(line) = lines.next() {
+        let trimmed = line.trim_end();
+        // Document start marker or %YAML directive
+        if trimmed == "---" || trimmed.starts_with("--- ") || trimmed.starts_with("%YAML") {
+            return true;
+        }
+
+        // `key:` opening a nested block: more-indented lines or `- ` items
+        let Some(key) = trimmed.trim_start().strip_suffix(':') else {
+            continue;
+        };
+        if key.is_empty() || key.contains(['=', ' ', '\t', '[', '{']) {
+            continue;
+        }
+        if let Some(next) = lines.peek() {
+            let indent = |l: &str| l.len() - l.trim_start().len();
+            let next_trimmed = next.trim_start();
+            if indent(next) > indent(line)
+                || (indent(next) == indent(line) && next_trimmed.starts_with("- "))
+            {
+                return true;
+            }
+        }
+    }
+    false
+}
+
 /// Check if content contains Properties-specific features
 fn contains_properties_features(content: &str) -> bool {
     content.lines().any(|line| {
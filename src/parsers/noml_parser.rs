@@ -9,16 +9,19 @@
 //! - Native types (@size, @duration, etc.)
 //! - Format preservation for round-trip editing
 
-#[cfg(not(feature = "noml"))]
-use crate::error::Error;
-use crate::error::Result;
+use crate::error::{Error, Result};
 use crate::value::Value;
 use std::collections::BTreeMap;
 
+/// Deepest nesting of arrays, tables and calls accepted
+#[cfg(feature = "noml")]
+const MAX_DEPTH: usize = 128;
+
 /// Parse NOML format configuration using the noml library
 #[cfg(feature = "noml")]
 pub fn parse(source: &str) -> Result<Value> {
     // Parse the document
+    check_depth(source)?;
     let document = noml::parse_string(source, None)?;
 
     // Resolve dynamic features (env vars, includes, etc.)
@@ -28,6 +31,69 @@ pub fn parse(source: &str) -> Result<Value> {
     convert_noml_value(resolved)
 }
 
+/// Parse NOML without reading the process environment or included
+/// files: `env()` falls back to its default and includes fail. Used to
+/// probe content whose format is still unknown.
+#[cfg(feature = "noml")]
+pub(crate) fn parse_isolated(source: &str) -> Result<Value> {
+    check_depth(source)?;
+    let document = noml::parse_string(source, None)?;
+    let config = noml::resolver::ResolverConfig {
+        env_vars: Some(std::collections::HashMap::new()),
+        max_include_depth: 0,
+        allow_missing_env: true,
+        ..noml::resolver::ResolverConfig::default()
+    };
+    let resolved = noml::Resolver::with_config(config).resolve(&document)?;
+    convert_noml_value(resolved)
+}
+
+/// Reject nesting deeper than [`MAX_DEPTH`] before `source` reaches the
+/// `noml` parser, which recurses without a limit of its own
+#[cfg(feature = "noml")]
+fn check_depth(source: &str) -> Result<()> {
+    let (mut line, mut column, mut depth) = (1, 0, 0);
+    let mut quote = None;
+    let (mut escaped, mut comment) = (false, false);
+    for ch in source.chars() {
+        if ch == '\n' {
+            (line, column, comment) = (line + 1, 0, false);
+            continue;
+        }
+        column += 1;
+        if comment {
+            continue;
+        }
+        if let Some(open) = quote {
+            if escaped {
+                escaped = false;
+            } else if ch == '\\' && open == '"' {
+                escaped = true;
+            } else if ch == open {
+                quote = None;
+            }
+            continue;
+        }
+        match ch {
+            '#' => comment = true,
+            '"' | '\'' => quote = Some(ch),
+            '[' | '{' | '(' => {
+                depth += 1;
+                if depth > MAX_DEPTH {
+                    return Err(Error::parse(
+                        format!("nesting deeper than {MAX_DEPTH} levels"),
+                        line,
+                        column,
+                    ));
+                }
+            }
+            ']' | '}' | ')' => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
 /// Parse NOML format configuration (fallback when NOML is not available)
 #[cfg(not(feature = "noml"))]
 pub fn parse(_source: &str) -> Result<Value> {
@@ -80,6 +146,7 @@ fn convert_noml_value(noml_value: noml::Value) -> Result<Value> {
 /// Parse NOML with format preservation for round-trip editing
 pub fn parse_with_preservation(source: &str) -> Result<(Value, noml::Document)> {
     // Parse to get the AST document for format preservation
+    check_depth(source)?;
     let document = noml::parse_string(source, None)?;
 
     // Resolve to get the actual values
@@ -134,4 +201,16 @@ mod tests {
         // Duration converted to seconds
         assert_eq!(config.get("timeout").unwrap().as_float().unwrap(), 30.0);
     }
+
+    #[test]
+    fn test_depth_and_isolation() {
+        assert!(parse(&format!("x = {}", "{".repeat(5000))).is_err());
+        // Brackets in strings and comments do not count
+        let quoted = format!("x = \"{0}\" # {0}\n", "[".repeat(500));
+        assert!(parse(&quoted).is_ok());
+
+        std::env::set_var("NOML_ISOLATION_TEST", "leaked");
+        let probe = parse_isolated(r#"v = env("NOML_ISOLATION_TEST", "default")"#).unwrap();
+        assert_eq!(probe.get("v").unwrap().as_string().unwrap(), "default");
+    }
 }
@@ -1,6 +1,8 @@
 use crate::error::{Error, Result};
+use crate::formatter::{Comments, Line};
 use crate::value::Value;
 use std::collections::BTreeMap;
+use std::fmt::Write as _;
 
 /// Parse Properties format configuration
 pub fn parse(source: &str) -> Result<Value> {
@@ -8,6 +10,158 @@ pub fn parse(source: &str) -> Result<Value> {
     parser.parse()
 }
 
+/// Serialize a value tree as Java Properties.
+///
+/// Nested tables are flattened to dotted keys and arrays to Spring-style
+/// indexed keys (`servers[0].host`), sorted by key. Escaping follows
+/// `java.util.Properties::store`: `\`, `=`, `:`, `#` and `!` are
+/// backslash-escaped, spaces in keys and leading or trailing spaces in
+/// values become `\ `, control characters use `\t` / `\n` / `\r` / `\f`,
+/// and anything outside printable ASCII is written as `\uXXXX` (surrogate
+/// pairs above U+FFFF). Parsing the output yields the same flat table the
+/// parser produced; `Null` is written as an empty value.
+///
+/// # Errors
+///
+/// Returns [`Error::General`] if the root is not a table or two paths
+/// flatten to the same key.
+pub fn serialize(value: &Value) -> Result<String> {
+    let mut flat = BTreeMap::new();
+    flatten(value.as_table()?, "", &mut flat)?;
+
+    let mut out = String::new();
+    for (key, value) in &flat {
+        escape(key, true, true, &mut out);
+        out.push('=');
+        escape(&value_text(value), false, true, &mut out);
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+/// The file as [`crate::formatter`] lines: `#` comments, keys and values
+/// escaped as [`serialize`] does, but with non-ASCII text left as written.
+pub(crate) fn lines(source: &str) -> Result<Vec<Line>> {
+    let comments = Comments {
+        markers: &['#', '!'],
+        canonical: '#',
+    };
+    let mut parser = PropertiesParser::new(source.to_string());
+    let mut lines = Vec::new();
+    loop {
+        let mark = parser.position;
+        parser.skip_whitespace_and_comments();
+        comments.push_lines(&parser.input[mark..parser.position], &mut lines);
+        if parser.at_end() {
+            return Ok(lines);
+        }
+
+        let (key, _, value) = parser.parse_property()?;
+        parser.skip_newline();
+        let mut escaped_key = String::new();
+        escape(&key, true, false, &mut escaped_key);
+        let mut escaped_value = String::new();
+        escape(&value_text(&value), false, false, &mut escaped_value);
+        lines.push(Line::Entry {
+            key: escaped_key,
+            value: escaped_value,
+            comment: None,
+        });
+    }
+}
+
+/// Where each key starts, for the document outline.
+pub(crate) fn marks(source: &str) -> Result<Vec<super::locate::Mark>> {
+    let mut parser = PropertiesParser::new(source.to_string());
+    let mut marks = Vec::new();
+    loop {
+        parser.skip_whitespace_and_comments();
+        if parser.at_end() {
+            return Ok(marks);
+        }
+        let offset = parser.position;
+        let (key, separator, _) = parser.parse_property()?;
+        let path = crate::path::join_key("", &key);
+        marks.push(super::locate::Mark::entry(offset, "", &key, path).separated(separator));
+    }
+}
+
+/// A parsed value as the text that parses back to it.
+fn value_text(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::Float(f) if f.fract() == 0.0 && f.is_finite() => format!("{f:.1}"),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn flatten<'v>(
+    table: &'v BTreeMap<String, Value>,
+    prefix: &str,
+    out: &mut BTreeMap<String, &'v Value>,
+) -> Result<()> {
+    for (key, value) in table {
+        let key = if prefix.is_empty() {
+            key.clone()
+        } else {
+            format!("{prefix}.{key}")
+        };
+        flatten_value(value, key, out)?;
+    }
+    Ok(())
+}
+
+fn flatten_value<'v>(
+    value: &'v Value,
+    key: String,
+    out: &mut BTreeMap<String, &'v Value>,
+) -> Result<()> {
+    match value {
+        Value::Table(table) => flatten(table, &key, out),
+        Value::Array(items) => {
+            for (index, item) in items.iter().enumerate() {
+                flatten_value(item, format!("{key}[{index}]"), out)?;
+            }
+            Ok(())
+        }
+        _ => match out.insert(key.clone(), value) {
+            Some(_) => Err(Error::serialize(format!(
+                "Properties key '{key}' is produced by more than one path"
+            ))),
+            None => Ok(()),
+        },
+    }
+}
+
+/// Append `text` escaped the way `Properties::store` does; with
+/// `ascii_only` unset, printable non-ASCII characters are kept as they are.
+fn escape(text: &str, is_key: bool, ascii_only: bool, out: &mut String) {
+    // The parser trims unescaped trailing whitespace, so protect it too.
+    let trailing = text.trim_end().len();
+    for (index, ch) in text.char_indices() {
+        match ch {
+            ' ' if is_key || index == 0 || index >= trailing => out.push_str("\\ "),
+            '\\' | '=' | ':' | '#' | '!' => {
+                out.push('\\');
+                out.push(ch);
+            }
+            '\t' => out.push_str("\\t"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\u{c}' => out.push_str("\\f"),
+            ' '..='~' => out.push(ch),
+            _ if !ascii_only && !ch.is_control() => out.push(ch),
+            _ => {
+                let mut units = [0u16; 2];
+                for unit in ch.encode_utf16(&mut units) {
+                    let _ = write!(out, "\\u{unit:04X}");
+                }
+            }
+        }
+    }
+}
+
 /// High-performance Java Properties format parser
 ///
 /// Properties format specification:
@@ -25,6 +179,7 @@ pub fn parse(source: &str) -> Result<Value> {
 /// - Zero-copy where possible
 pub struct PropertiesParser {
     input: String,
+    /// Byte offset into `input`
     position: usize,
     line: usize,
     column: usize,
@@ -52,14 +207,15 @@ impl PropertiesParser {
                 break;
             }
 
-            let (key, value) = self.parse_property()?;
+            let (key, _, value) = self.parse_property()?;
             properties.insert(key, value);
         }
 
         Ok(Value::table(properties))
     }
 
-    fn parse_property(&mut self) -> Result<(String, Value)> {
+    /// Parse one entry: its key, separator and value.
+    fn parse_property(&mut self) -> Result<(String, char, Value)> {
         let key = self.parse_key()?;
         self.skip_whitespace();
 
@@ -73,16 +229,19 @@ impl PropertiesParser {
             });
         }
 
-        self.advance(); // Skip separator
+        let separator = self.current_char();
+        self.advance();
         self.skip_whitespace();
 
         let value = self.parse_value()?;
 
-        Ok((key, value))
+        Ok((key, separator, value))
     }
 
     fn parse_key(&mut self) -> Result<String> {
         let mut key = String::new();
+        // Escaped characters (e.g. `\ `) survive trimming.
+        let mut protected = 0;
 
         while !self.at_end() {
             let ch = self.current_char();
@@ -102,6 +261,7 @@ impl PropertiesParser {
 
                     let escaped = self.parse_escape()?;
                     key.push_str(&escaped);
+                    protected = key.len();
                 }
                 '\n' | '\r' => {
                     return Err(Error::Parse {
@@ -118,7 +278,8 @@ impl PropertiesParser {
             }
         }
 
-        if key.trim().is_empty() {
+        key.truncate(key.trim_end().len().max(protected));
+        if key.is_empty() {
             return Err(Error::Parse {
                 message: "Empty key name".to_string(),
                 line: self.line,
@@ -127,11 +288,12 @@ impl PropertiesParser {
             });
         }
 
-        Ok(key.trim().to_string())
+        Ok(key)
     }
 
     fn parse_value(&mut self) -> Result<Value> {
         let mut value = String::new();
+        let mut protected = 0;
 
         while !self.at_end() {
             let ch = self.current_char();
@@ -152,6 +314,7 @@ impl PropertiesParser {
 
                     let escaped = self.parse_escape()?;
                     value.push_str(&escaped);
+                    protected = value.len();
                 }
                 '\n' | '\r' => break,
                 _ => {
@@ -161,8 +324,8 @@ impl PropertiesParser {
             }
         }
 
-        let trimmed = value.trim();
-        Ok(self.infer_value_type(trimmed))
+        let end = value.trim_end().len().max(protected);
+        Ok(self.infer_value_type(&value[..end]))
     }
 
     fn parse_escape(&mut self) -> Result<String> {
@@ -173,6 +336,7 @@ impl PropertiesParser {
             'n' => Ok("\n".to_string()),
             't' => Ok("\t".to_string()),
             'r' => Ok("\r".to_string()),
+            'f' => Ok("\u{c}".to_string()),
             '\\' => Ok("\\".to_string()),
             '=' => Ok("=".to_string()),
             ':' => Ok(":".to_string()),
@@ -183,6 +347,39 @@ impl PropertiesParser {
     }
 
     fn parse_unicode_escape(&mut self) -> Result<String> {
+        let high = self.parse_code_unit()?;
+        let code_point =
+            if (0xD800..0xDC00).contains(&high) && self.input[self.position..].starts_with("\\u") {
+                // UTF-16 surrogate pair, as written for characters above U+FFFF
+                self.advance();
+                self.advance();
+                let low = self.parse_code_unit()?;
+                if !(0xDC00..0xE000).contains(&low) {
+                    return Err(Error::Parse {
+                        message: format!("Unpaired surrogate in unicode escape: {high:04X}"),
+                        line: self.line,
+                        column: self.column,
+                        file: None,
+                    });
+                }
+                0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+            } else {
+                high
+            };
+        if let Some(unicode_char) = char::from_u32(code_point) {
+            Ok(unicode_char.to_string())
+        } else {
+            Err(Error::Parse {
+                message: format!("Invalid unicode code point: {code_point}"),
+                line: self.line,
+                column: self.column,
+                file: None,
+            })
+        }
+    }
+
+    /// Read the four hex digits of a `\uXXXX` escape.
+    fn parse_code_unit(&mut self) -> Result<u32> {
         let mut hex_digits = String::new();
 
         for _ in 0..4 {
@@ -209,27 +406,12 @@ impl PropertiesParser {
             }
         }
 
-        let code_point = match u32::from_str_radix(&hex_digits, 16) {
-            Ok(cp) => cp,
-            Err(_) => {
-                return Err(Error::Parse {
-                    message: format!("Invalid hex digits in unicode escape: {hex_digits}"),
-                    line: self.line,
-                    column: self.column,
-                    file: None,
-                });
-            }
-        };
-        if let Some(unicode_char) = char::from_u32(code_point) {
-            Ok(unicode_char.to_string())
-        } else {
-            Err(Error::Parse {
-                message: format!("Invalid unicode code point: {code_point}"),
-                line: self.line,
-                column: self.column,
-                file: None,
-            })
-        }
+        u32::from_str_radix(&hex_digits, 16).map_err(|_| Error::Parse {
+            message: format!("Invalid hex digits in unicode escape: {hex_digits}"),
+            line: self.line,
+            column: self.column,
+            file: None,
+        })
     }
 
     fn infer_value_type(&self, value: &str) -> Value {
@@ -291,7 +473,7 @@ impl PropertiesParser {
     }
 
     fn current_char(&self) -> char {
-        self.input.chars().nth(self.position).unwrap_or('\0')
+        self.input[self.position..].chars().next().unwrap_or('\0')
     }
 
     fn at_end(&self) -> bool {
@@ -306,7 +488,7 @@ impl PropertiesParser {
             } else {
                 self.column += 1;
             }
-            self.position += 1;
+            self.position += self.current_char().len_utf8();
         }
     }
 }
@@ -365,4 +547,62 @@ mod tests {
             assert_eq!(table.get("key2").unwrap().as_string().unwrap(), "value2");
         }
     }
+
+    #[test]
+    fn test_serialize_escaping() {
+        let mut value = Value::table(BTreeMap::new());
+        value
+            .set_nested("server.url", Value::string("http://host:80/?a=b#top"))
+            .unwrap();
+        value
+            .set_nested(r#""key with space""#, Value::string("  padded "))
+            .unwrap();
+        value.set_nested("name", Value::string("Zoë 🚀")).unwrap();
+        value
+            .set_nested(
+                "hosts",
+                Value::array(vec![Value::string("a"), Value::integer(2)]),
+            )
+            .unwrap();
+        value.set_nested("ratio", Value::float(2.0)).unwrap();
+
+        let text = serialize(&value).unwrap();
+        assert_eq!(
+            text,
+            "hosts[0]=a\nhosts[1]=2\nkey\\ with\\ space=\\  padded\\ \nname=Zo\\u00EB \\uD83D\\uDE80\nratio=2.0\nserver.url=http\\://host\\:80/?a\\=b\\#top\n"
+        );
+
+        let reparsed = parse(&text).unwrap();
+        assert_eq!(
+            reparsed
+                .get("\"key with space\"")
+                .unwrap()
+                .as_string()
+                .unwrap(),
+            "  padded "
+        );
+        assert_eq!(reparsed.get("name").unwrap().as_string().unwrap(), "Zoë 🚀");
+        assert_eq!(
+            reparsed.get("\"server.url\"").unwrap().as_string().unwrap(),
+            "http://host:80/?a=b#top"
+        );
+    }
+
+    #[test]
+    fn test_round_trip_identity() {
+        let input = "# Spring overrides\nspring.datasource.url = jdbc:h2:mem:test\nmsg=caf\\u00e9 \\\n    continued\nutf8=naïve\nempty=\ntrailing=x\\ \n\\#hash\\:key=1\ntabbed=a\\tb\n";
+        let parsed = parse(input).unwrap();
+        assert_eq!(
+            parsed.get("msg").unwrap().as_string().unwrap(),
+            "café continued"
+        );
+        assert_eq!(parsed.get("trailing").unwrap().as_string().unwrap(), "x ");
+        assert_eq!(
+            parsed.get("\"#hash:key\"").unwrap().as_integer().unwrap(),
+            1
+        );
+
+        let written = serialize(&parsed).unwrap();
+        assert_eq!(parse(&written).unwrap(), parsed);
+    }
 }
@@ -1,26 +1,66 @@
 //! # TOML Format Parser
 //!
-//! TOML parser with format preservation capabilities.
-//! Uses the NOML library's TOML compatibility for round-trip editing.
+//! First-party TOML 1.0 parser and writer, independent of the `noml`
+//! crate. The parser implements the full specification — dotted keys,
+//! `[table]` and `[[array of tables]]` headers with the spec's
+//! redefinition rules, inline tables, basic / literal / multi-line
+//! strings, all integer and float forms, and every datetime form — and
+//! reports errors as [`Error::Parse`] with the line and column of the
+//! offending character. It is checked against the `toml-test` corpus in
+//! `tests/parser_corpus.rs`.
+//!
+//! Offset datetimes become [`Value::DateTime`] (converted to UTC) when the
+//! `chrono` feature is enabled. Local datetimes, local dates and local
+//! times have no zone to anchor them, so they are kept as strings in
+//! RFC 3339 form (`1979-05-27T07:32:00`), as are offset datetimes without
+//! `chrono`. [`serialize`] writes such strings back bare, so they stay
+//! datetimes across a round trip.
+//!
+//! [`parse_document`] returns a [`TomlDocument`] that edits the source in
+//! place, keeping comments, key order and formatting; [`Config`] saves
+//! TOML files through it. With the `noml` feature,
+//! [`parse_with_preservation`] additionally keeps a NOML document.
+//!
+//! ```rust
+//! # fn main() -> config_lib::Result<()> {
+//! let value = config_lib::parsers::toml_parser::parse(
+//!     "title = \"demo\"\n\n[[servers]]\nhost = \"alpha\"\nports = [80, 443]\n",
+//! )?;
+//! assert_eq!(value.get("servers[0].host").unwrap().as_string()?, "alpha");
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`Config`]: crate::Config
 
-use crate::error::Result;
+use super::locate::Mark;
+use crate::error::{Error, Result};
+use crate::path;
 use crate::value::Value;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::{self, Write as _};
+use std::ops::Range;
+
+/// Deepest nesting of arrays and inline tables accepted
+const MAX_DEPTH: usize = 128;
 
 /// Parse TOML format configuration
-#[cfg(feature = "noml")]
+///
+/// # Errors
+///
+/// Returns [`Error::Parse`] with the line and column of the first
+/// character that is not valid TOML 1.0.
 pub fn parse(source: &str) -> Result<Value> {
-    // Use NOML's TOML parsing capability for format preservation
-    let noml_value = noml::parse(source)?;
-    convert_noml_value(noml_value)
+    TomlParser::new(source).parse()
 }
 
-/// Parse TOML format configuration (fallback when NOML is not available)
-#[cfg(not(feature = "noml"))]
-pub fn parse(_source: &str) -> Result<Value> {
-    Err(Error::general(
-        "TOML parsing requires either the 'noml' feature or a dedicated TOML parser",
-    ))
+/// Where each table header and key starts, for the document outline.
+/// Keys inside inline tables are left to the key holding the table.
+pub(crate) fn marks(source: &str) -> Result<Vec<Mark>> {
+    let mut parser = TomlParser::new(source);
+    parser.marks = Some(Vec::new());
+    parser.document()?;
+    Ok(parser.marks.unwrap_or_default())
 }
 
 /// Parse TOML with format preservation for round-trip editing
@@ -47,6 +87,1624 @@ pub fn parse_with_preservation(_source: &str) -> Result<(Value, ())> {
     ))
 }
 
+/// One step of a path into the document: a key, or an element of an
+/// array of tables.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+/// How a table came into existence, which decides how it may be
+/// extended later.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TableKind {
+    /// Parent of a header, e.g. `a` for `[a.b]`; may be defined once later
+    Implicit,
+    /// Defined by its own `[header]` or `[[header]]` element
+    Header,
+    /// Created by a dotted key, e.g. `a` for `a.b = 1`
+    Dotted,
+    /// Inline table value; frozen
+    Inline,
+    /// Array created by `[[header]]`; further headers append to it
+    ArrayOfTables,
+}
+
+type Kinds = HashMap<Vec<Segment>, TableKind>;
+
+struct TomlParser<'a> {
+    source: &'a str,
+    pos: usize,
+    root: Value,
+    kinds: Kinds,
+    /// Table the following key/value pairs belong to
+    current: Vec<Segment>,
+    /// Arrays and inline tables currently open
+    depth: usize,
+    /// The document outline, when asked for
+    marks: Option<Vec<Mark>>,
+    /// Where each header and key/value line sits, for [`TomlDocument`]
+    layout: Option<Vec<Span>>,
+}
+
+impl<'a> TomlParser<'a> {
+    fn new(source: &'a str) -> Self {
+        Self {
+            source,
+            pos: 0,
+            root: Value::table(BTreeMap::new()),
+            kinds: Kinds::new(),
+            current: Vec::new(),
+            depth: 0,
+            marks: None,
+            layout: None,
+        }
+    }
+
+    fn parse(mut self) -> Result<Value> {
+        self.document()?;
+        Ok(self.root)
+    }
+
+    fn document(&mut self) -> Result<()> {
+        if self.source.starts_with('\u{feff}') {
+            self.pos = '\u{feff}'.len_utf8();
+        }
+        loop {
+            self.skip_whitespace();
+            let start = self.pos;
+            let kind = match self.peek() {
+                None => break,
+                Some('#' | '\n' | '\r') => None,
+                Some('[') => {
+                    self.header()?;
+                    Some(SpanKind::Header {
+                        path: self.current.clone(),
+                    })
+                }
+                Some(_) => {
+                    let (keys, value, value_span) = self.key_value()?;
+                    let mut path = self.current.clone();
+                    path.extend(keys.iter().cloned().map(Segment::Key));
+                    if let Some(marks) = &mut self.marks {
+                        let scope = canonical(&path[..path.len() - 1]);
+                        let key = &keys[keys.len() - 1];
+                        marks.push(Mark::entry(start, &scope, key, path::join_key(&scope, key)));
+                    }
+                    insert(&mut self.root, &mut self.kinds, &self.current, keys, value)
+                        .map_err(|message| self.error_at(start, message))?;
+                    let key_end = start
+                        + self.source[start..value_span.start]
+                            .trim_end_matches([' ', '\t', '='])
+                            .len();
+                    Some(SpanKind::Entry {
+                        path,
+                        key: start..key_end,
+                        value: value_span,
+                    })
+                }
+            };
+            self.line_end()?;
+            if let (Some(layout), Some(kind)) = (&mut self.layout, kind) {
+                layout.push(Span {
+                    line: self.source[..start].rfind('\n').map_or(0, |i| i + 1),
+                    end: self.pos,
+                    kind,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    // ---------------------------------------------------------------
+    // Cursor helpers
+    // ---------------------------------------------------------------
+
+    fn rest(&self) -> &'a str {
+        &self.source[self.pos..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let ch = self.peek()?;
+        self.pos += ch.len_utf8();
+        Some(ch)
+    }
+
+    fn eat(&mut self, ch: char) -> bool {
+        if self.peek() == Some(ch) {
+            self.pos += ch.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, ch: char, what: &str) -> Result<()> {
+        if self.eat(ch) {
+            Ok(())
+        } else {
+            Err(self.unexpected(what))
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t')) {
+            self.pos += 1;
+        }
+    }
+
+    /// Skip whitespace, comments and newlines (inside arrays).
+    fn skip_trivia(&mut self) -> Result<()> {
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                Some('#') => self.comment()?,
+                Some('\n' | '\r') => self.newline()?,
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    fn comment(&mut self) -> Result<()> {
+        self.pos += 1;
+        while let Some(ch) = self.peek() {
+            match ch {
+                '\n' => break,
+                '\r' if self.rest().starts_with("\r\n") => break,
+                '\t' => {}
+                c if c.is_ascii_control() => {
+                    return Err(self.error("control characters are not allowed in comments"))
+                }
+                _ => {}
+            }
+            self.pos += ch.len_utf8();
+        }
+        Ok(())
+    }
+
+    fn newline(&mut self) -> Result<()> {
+        if self.eat('\n') || self.rest().starts_with("\r\n") && self.eat('\r') && self.eat('\n') {
+            Ok(())
+        } else {
+            Err(self.error("bare carriage return; expected a newline"))
+        }
+    }
+
+    /// Whitespace and an optional comment, then a newline or the end.
+    fn line_end(&mut self) -> Result<()> {
+        self.skip_whitespace();
+        if self.peek() == Some('#') {
+            self.comment()?;
+        }
+        match self.peek() {
+            None => Ok(()),
+            Some(_) => self.newline().map_err(|_| self.unexpected("a newline")),
+        }
+    }
+
+    fn error(&self, message: impl Into<String>) -> Error {
+        self.error_at(self.pos, message)
+    }
+
+    fn error_at(&self, pos: usize, message: impl Into<String>) -> Error {
+        let before = &self.source[..pos];
+        let line = before.matches('\n').count() + 1;
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        let column = before[line_start..].chars().count() + 1;
+        Error::parse(message, line, column)
+    }
+
+    fn unexpected(&self, expected: &str) -> Error {
+        match self.peek() {
+            None => self.error(format!("expected {expected}, found end of input")),
+            Some(ch) => self.error(format!("expected {expected}, found {ch:?}")),
+        }
+    }
+
+    // ---------------------------------------------------------------
+    // Keys and tables
+    // ---------------------------------------------------------------
+
+    fn key(&mut self) -> Result<Vec<String>> {
+        let mut keys = vec![self.simple_key()?];
+        loop {
+            self.skip_whitespace();
+            if !self.eat('.') {
+                return Ok(keys);
+            }
+            self.skip_whitespace();
+            keys.push(self.simple_key()?);
+        }
+    }
+
+    fn simple_key(&mut self) -> Result<String> {
+        match self.peek() {
+            Some('"') if self.rest().starts_with("\"\"\"") => {
+                Err(self.error("multi-line strings cannot be keys"))
+            }
+            Some('\'') if self.rest().starts_with("'''") => {
+                Err(self.error("multi-line strings cannot be keys"))
+            }
+            Some('"') => self.basic_string(),
+            Some('\'') => self.literal_string(),
+            Some(c) if is_bare_key_char(c) => {
+                let start = self.pos;
+                while self.peek().is_some_and(is_bare_key_char) {
+                    self.pos += 1;
+                }
+                Ok(self.source[start..self.pos].to_string())
+            }
+            _ => Err(self.unexpected("a key")),
+        }
+    }
+
+    /// A key, `=` and value; also returns where the value is written.
+    fn key_value(&mut self) -> Result<(Vec<String>, Value, Range<usize>)> {
+        let keys = self.key()?;
+        self.skip_whitespace();
+        self.expect('=', "'=' after key")?;
+        self.skip_whitespace();
+        let start = self.pos;
+        let value = self.value()?;
+        Ok((keys, value, start..self.pos))
+    }
+
+    /// `[table]` or `[[array of tables]]`
+    fn header(&mut self) -> Result<()> {
+        let start = self.pos;
+        let array = self.rest().starts_with("[[");
+        self.pos += if array { 2 } else { 1 };
+        self.skip_whitespace();
+        let keys = self.key()?;
+        self.skip_whitespace();
+        if array {
+            if !self.rest().starts_with("]]") {
+                return Err(self.unexpected("']]'"));
+            }
+            self.pos += 2;
+        } else {
+            self.expect(']', "']'")?;
+        }
+        let key = keys[keys.len() - 1].clone();
+        self.current = self
+            .open_table(keys, array)
+            .map_err(|message| self.error_at(start, message))?;
+        if let Some(marks) = &mut self.marks {
+            let parent = &self.current[..self.current.len() - 1 - usize::from(array)];
+            let scope = canonical(parent);
+            marks.push(Mark::entry(start, &scope, &key, canonical(&self.current)).header());
+        }
+        Ok(())
+    }
+
+    fn open_table(
+        &mut self,
+        keys: Vec<String>,
+        array: bool,
+    ) -> std::result::Result<Vec<Segment>, String> {
+        let last = keys.len() - 1;
+        let mut path = Vec::new();
+        for (index, key) in keys.into_iter().enumerate() {
+            let table = table_mut(&mut self.root, &path).ok_or("internal: lost table")?;
+            path.push(Segment::Key(key.clone()));
+            let kind = self.kinds.get(&path).copied();
+            let name = display_path(&path);
+
+            if index < last {
+                match table.get_mut(&key) {
+                    None => {
+                        table.insert(key, Value::table(BTreeMap::new()));
+                        self.kinds.insert(path.clone(), TableKind::Implicit);
+                    }
+                    Some(Value::Table(_)) if kind.is_some_and(|k| k != TableKind::Inline) => {}
+                    Some(Value::Array(items)) if kind == Some(TableKind::ArrayOfTables) => {
+                        path.push(Segment::Index(items.len() - 1));
+                    }
+                    Some(_) => {
+                        return Err(format!("cannot extend `{name}`: it is already a value"))
+                    }
+                }
+                continue;
+            }
+
+            match table.get_mut(&key) {
+                None if array => {
+                    table.insert(key, Value::array(vec![Value::table(BTreeMap::new())]));
+                    self.kinds.insert(path.clone(), TableKind::ArrayOfTables);
+                    path.push(Segment::Index(0));
+                }
+                None => {
+                    table.insert(key, Value::table(BTreeMap::new()));
+                }
+                Some(Value::Array(items)) if array && kind == Some(TableKind::ArrayOfTables) => {
+                    items.push(Value::table(BTreeMap::new()));
+                    path.push(Segment::Index(items.len() - 1));
+                }
+                Some(Value::Table(_)) if !array && kind == Some(TableKind::Implicit) => {}
+                Some(_) if array => {
+                    return Err(format!(
+                        "cannot append to `{name}`: it is not an array of tables"
+                    ))
+                }
+                Some(_) => return Err(format!("table `{name}` is defined more than once")),
+            }
+            self.kinds.insert(path.clone(), TableKind::Header);
+        }
+        Ok(path)
+    }
+
+    // ---------------------------------------------------------------
+    // Values
+    // ---------------------------------------------------------------
+
+    fn value(&mut self) -> Result<Value> {
+        match self.peek() {
+            Some('"') if self.rest().starts_with("\"\"\"") => {
+                self.multiline_basic_string().map(Value::String)
+            }
+            Some('\'') if self.rest().starts_with("'''") => {
+                self.multiline_literal_string().map(Value::String)
+            }
+            Some('"') => self.basic_string().map(Value::String),
+            Some('\'') => self.literal_string().map(Value::String),
+            Some('[') => self.nested(Self::array),
+            Some('{') => self.nested(Self::inline_table),
+            Some(c) if c.is_ascii_alphanumeric() || matches!(c, '+' | '-') => self.scalar(),
+            _ => Err(self.unexpected("a value")),
+        }
+    }
+
+    /// Parse an array or inline table one level deeper, refusing to go
+    /// past [`MAX_DEPTH`]
+    fn nested(&mut self, parse: fn(&mut Self) -> Result<Value>) -> Result<Value> {
+        if self.depth == MAX_DEPTH {
+            return Err(self.error(format!("nesting deeper than {MAX_DEPTH} levels")));
+        }
+        self.depth += 1;
+        let value = parse(self);
+        self.depth -= 1;
+        value
+    }
+
+    fn array(&mut self) -> Result<Value> {
+        self.pos += 1;
+        let mut items = Vec::new();
+        loop {
+            self.skip_trivia()?;
+            if self.eat(']') {
+                return Ok(Value::array(items));
+            }
+            items.push(self.value()?);
+            self.skip_trivia()?;
+            if self.eat(']') {
+                return Ok(Value::array(items));
+            }
+            self.expect(',', "',' or ']' in array")?;
+        }
+    }
+
+    fn inline_table(&mut self) -> Result<Value> {
+        self.pos += 1;
+        let mut table = Value::table(BTreeMap::new());
+        let mut kinds = Kinds::new();
+        self.skip_whitespace();
+        if self.eat('}') {
+            return Ok(table);
+        }
+        loop {
+            let start = self.pos;
+            let (keys, value, _) = self.key_value()?;
+            insert(&mut table, &mut kinds, &[], keys, value)
+                .map_err(|message| self.error_at(start, message))?;
+            self.skip_whitespace();
+            if self.eat('}') {
+                return Ok(table);
+            }
+            self.expect(',', "',' or '}' in inline table")?;
+            self.skip_whitespace();
+        }
+    }
+
+    /// Booleans, numbers and datetimes
+    fn scalar(&mut self) -> Result<Value> {
+        let start = self.pos;
+        let is_token =
+            |c: char| c.is_ascii_alphanumeric() || matches!(c, '_' | '+' | '-' | '.' | ':');
+        while self.peek().is_some_and(is_token) {
+            self.pos += 1;
+        }
+        // A space may separate the date and time of a datetime.
+        if is_date(&self.source[start..self.pos]) {
+            let after = self.rest().as_bytes();
+            if after.len() > 3
+                && after[0] == b' '
+                && after[1].is_ascii_digit()
+                && after[2].is_ascii_digit()
+                && after[3] == b':'
+            {
+                self.pos += 1;
+                while self.peek().is_some_and(is_token) {
+                    self.pos += 1;
+                }
+            }
+        }
+        let token = &self.source[start..self.pos];
+        let value = match token {
+            "true" => Some(Value::bool(true)),
+            "false" => Some(Value::bool(false)),
+            _ if is_date(token) || token.get(2..3) == Some(":") => datetime(token),
+            _ => number(token).map_err(|message| self.error_at(start, message))?,
+        };
+        value.ok_or_else(|| self.error_at(start, format!("invalid value `{token}`")))
+    }
+
+    // ---------------------------------------------------------------
+    // Strings
+    // ---------------------------------------------------------------
+
+    fn basic_string(&mut self) -> Result<String> {
+        self.pos += 1;
+        let mut out = String::new();
+        loop {
+            match self.peek() {
+                Some('"') => {
+                    self.pos += 1;
+                    return Ok(out);
+                }
+                Some('\\') => out.push(self.escape()?),
+                Some('\n' | '\r') | None => return Err(self.error("unterminated string")),
+                Some(ch) => {
+                    self.check_string_char(ch)?;
+                    out.push(ch);
+                    self.pos += ch.len_utf8();
+                }
+            }
+        }
+    }
+
+    fn multiline_basic_string(&mut self) -> Result<String> {
+        self.pos += 3;
+        self.skip_opening_newline();
+        let mut out = String::new();
+        loop {
+            match self.peek() {
+                Some('"') => {
+                    if self.closing_quotes('"', &mut out)? {
+                        return Ok(out);
+                    }
+                }
+                Some('\\') => {
+                    if self.line_ending_backslash()? {
+                        continue;
+                    }
+                    out.push(self.escape()?);
+                }
+                Some('\n' | '\r') => {
+                    self.newline()?;
+                    out.push('\n');
+                }
+                None => return Err(self.error("unterminated multi-line string")),
+                Some(ch) => {
+                    self.check_string_char(ch)?;
+                    out.push(ch);
+                    self.pos += ch.len_utf8();
+                }
+            }
+        }
+    }
+
+    fn literal_string(&mut self) -> Result<String> {
+        self.pos += 1;
+        let start = self.pos;
+        loop {
+            match self.peek() {
+                Some('\'') => {
+                    let text = self.source[start..self.pos].to_string();
+                    self.pos += 1;
+                    return Ok(text);
+                }
+                Some('\n' | '\r') | None => return Err(self.error("unterminated string")),
+                Some(ch) => {
+                    self.check_string_char(ch)?;
+                    self.pos += ch.len_utf8();
+                }
+            }
+        }
+    }
+
+    fn multiline_literal_string(&mut self) -> Result<String> {
+        self.pos += 3;
+        self.skip_opening_newline();
+        let mut out = String::new();
+        loop {
+            match self.peek() {
+                Some('\'') => {
+                    if self.closing_quotes('\'', &mut out)? {
+                        return Ok(out);
+                    }
+                }
+                Some('\n' | '\r') => {
+                    self.newline()?;
+                    out.push('\n');
+                }
+                None => return Err(self.error("unterminated multi-line string")),
+                Some(ch) => {
+                    self.check_string_char(ch)?;
+                    out.push(ch);
+                    self.pos += ch.len_utf8();
+                }
+            }
+        }
+    }
+
+    /// A newline right after the opening delimiter is trimmed.
+    fn skip_opening_newline(&mut self) {
+        if self.rest().starts_with('\n') {
+            self.pos += 1;
+        } else if self.rest().starts_with("\r\n") {
+            self.pos += 2;
+        }
+    }
+
+    /// Consume a run of quotes inside a multi-line string. Three end the
+    /// string; up to two more before them belong to the content.
+    fn closing_quotes(&mut self, quote: char, out: &mut String) -> Result<bool> {
+        let run = self.rest().chars().take_while(|&c| c == quote).count();
+        if run < 3 {
+            out.extend(std::iter::repeat(quote).take(run));
+            self.pos += run;
+            return Ok(false);
+        }
+        if run > 5 {
+            return Err(self.error_at(self.pos + 5, "too many quotes in multi-line string"));
+        }
+        out.extend(std::iter::repeat(quote).take(run - 3));
+        self.pos += run;
+        Ok(true)
+    }
+
+    /// `\` followed by optional whitespace and a newline trims all
+    /// whitespace up to the next non-whitespace character.
+    fn line_ending_backslash(&mut self) -> Result<bool> {
+        let after = self.rest()[1..].trim_start_matches([' ', '\t']);
+        if !(after.starts_with('\n') || after.starts_with("\r\n")) {
+            return Ok(false);
+        }
+        self.pos = self.source.len() - after.len();
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                Some('\n' | '\r') => self.newline()?,
+                _ => return Ok(true),
+            }
+        }
+    }
+
+    fn escape(&mut self) -> Result<char> {
+        let start = self.pos;
+        self.pos += 1;
+        let ch = match self.bump() {
+            Some('b') => '\u{8}',
+            Some('t') => '\t',
+            Some('n') => '\n',
+            Some('f') => '\u{c}',
+            Some('r') => '\r',
+            Some('"') => '"',
+            Some('\\') => '\\',
+            Some('u') => return self.unicode_escape(start, 4),
+            Some('U') => return self.unicode_escape(start, 8),
+            _ => return Err(self.error_at(start, "invalid escape sequence")),
+        };
+        Ok(ch)
+    }
+
+    fn unicode_escape(&mut self, start: usize, digits: usize) -> Result<char> {
+        let hex = self
+            .rest()
+            .get(..digits)
+            .filter(|hex| hex.bytes().all(|b| b.is_ascii_hexdigit()))
+            .ok_or_else(|| self.error_at(start, "invalid unicode escape"))?;
+        self.pos += digits;
+        u32::from_str_radix(hex, 16)
+            .ok()
+            .and_then(char::from_u32)
+            .ok_or_else(|| self.error_at(start, "unicode escape is not a scalar value"))
+    }
+
+    fn check_string_char(&self, ch: char) -> Result<()> {
+        if ch != '\t' && ch.is_ascii_control() {
+            Err(self.error("control characters must be escaped in strings"))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Insert a key/value pair under `base`, creating dotted-key tables.
+fn insert(
+    root: &mut Value,
+    kinds: &mut Kinds,
+    base: &[Segment],
+    keys: Vec<String>,
+    value: Value,
+) -> std::result::Result<(), String> {
+    let last = keys.len() - 1;
+    let mut path = base.to_vec();
+    for (index, key) in keys.into_iter().enumerate() {
+        let table = table_mut(root, &path).ok_or("internal: lost table")?;
+        path.push(Segment::Key(key.clone()));
+        if index == last {
+            if table.contains_key(&key) {
+                return Err(format!("duplicate key `{}`", display_path(&path)));
+            }
+            if value.is_table() {
+                kinds.insert(path, TableKind::Inline);
+            }
+            table.insert(key, value);
+            return Ok(());
+        }
+        match table.get(&key) {
+            None => {
+                table.insert(key, Value::table(BTreeMap::new()));
+                kinds.insert(path.clone(), TableKind::Dotted);
+            }
+            Some(Value::Table(_)) if kinds.get(&path) == Some(&TableKind::Dotted) => {}
+            Some(_) => {
+                return Err(format!(
+                    "cannot add keys to `{}` here: it is already defined",
+                    display_path(&path)
+                ))
+            }
+        }
+    }
+    Ok(())
+}
+
+fn table_mut<'v>(root: &'v mut Value, path: &[Segment]) -> Option<&'v mut BTreeMap<String, Value>> {
+    let mut cursor = root;
+    for segment in path {
+        cursor = match (segment, cursor) {
+            (Segment::Key(key), Value::Table(table)) => table.get_mut(key)?,
+            (Segment::Index(index), Value::Array(items)) => items.get_mut(*index)?,
+            _ => return None,
+        };
+    }
+    match cursor {
+        Value::Table(table) => Some(table),
+        _ => None,
+    }
+}
+
+fn display_path(path: &[Segment]) -> String {
+    let mut out = String::new();
+    for segment in path {
+        match segment {
+            Segment::Key(key) => {
+                if !out.is_empty() {
+                    out.push('.');
+                }
+                out.push_str(key);
+            }
+            Segment::Index(index) => {
+                let _ = write!(out, "[{index}]");
+            }
+        }
+    }
+    out
+}
+
+/// A path in the [`crate::path`] grammar.
+fn canonical(path: &[Segment]) -> String {
+    path.iter()
+        .fold(String::new(), |out, segment| match segment {
+            Segment::Key(key) => path::join_key(&out, key),
+            Segment::Index(index) => path::join_index(&out, *index),
+        })
+}
+
+fn is_bare_key_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '-'
+}
+
+// -------------------------------------------------------------------
+// Numbers
+// -------------------------------------------------------------------
+
+/// Parse an integer or float token; `Ok(None)` when it is neither.
+fn number(token: &str) -> std::result::Result<Option<Value>, String> {
+    let (sign, unsigned) = match token.as_bytes().first() {
+        Some(b'+' | b'-') => (&token[..1], &token[1..]),
+        _ => ("", token),
+    };
+    match unsigned {
+        "inf" => {
+            return Ok(Some(Value::float(if sign == "-" {
+                f64::NEG_INFINITY
+            } else {
+                f64::INFINITY
+            })))
+        }
+        "nan" => return Ok(Some(Value::float(f64::NAN))),
+        _ => {}
+    }
+
+    for (prefix, radix) in [("0x", 16), ("0o", 8), ("0b", 2)] {
+        if let Some(digits) = unsigned.strip_prefix(prefix) {
+            if !sign.is_empty() || !valid_digits(digits, radix) {
+                return Ok(None);
+            }
+            return i64::from_str_radix(&digits.replace('_', ""), radix)
+                .map(|i| Some(Value::integer(i)))
+                .map_err(|_| format!("integer `{token}` is out of range"));
+        }
+    }
+
+    let (mantissa, exponent) = match unsigned.find(['e', 'E']) {
+        Some(at) => (&unsigned[..at], Some(&unsigned[at + 1..])),
+        None => (unsigned, None),
+    };
+    let (integral, fraction) = match mantissa.split_once('.') {
+        Some((integral, fraction)) => (integral, Some(fraction)),
+        None => (mantissa, None),
+    };
+    let leading_zero = integral.len() > 1 && integral.starts_with('0');
+    if !valid_digits(integral, 10) || leading_zero {
+        return Ok(None);
+    }
+
+    if fraction.is_none() && exponent.is_none() {
+        return format!("{sign}{}", integral.replace('_', ""))
+            .parse::<i64>()
+            .map(|i| Some(Value::integer(i)))
+            .map_err(|_| format!("integer `{token}` is out of range"));
+    }
+    if fraction.is_some_and(|f| !valid_digits(f, 10)) {
+        return Ok(None);
+    }
+    if let Some(exponent) = exponent {
+        let digits = exponent.strip_prefix(['+', '-']).unwrap_or(exponent);
+        if !valid_digits(digits, 10) {
+            return Ok(None);
+        }
+    }
+    Ok(token.replace('_', "").parse::<f64>().ok().map(Value::float))
+}
+
+/// Digits of `radix`, with single underscores only between digits
+fn valid_digits(digits: &str, radix: u32) -> bool {
+    !digits.is_empty()
+        && !digits.starts_with('_')
+        && !digits.ends_with('_')
+        && !digits.contains("__")
+        && digits.chars().all(|c| c == '_' || c.is_digit(radix))
+}
+
+// -------------------------------------------------------------------
+// Datetimes
+// -------------------------------------------------------------------
+
+fn is_date(token: &str) -> bool {
+    let bytes = token.as_bytes();
+    bytes.len() >= 10
+        && bytes[..4].iter().all(u8::is_ascii_digit)
+        && bytes[4] == b'-'
+        && bytes[5..7].iter().all(u8::is_ascii_digit)
+        && bytes[7] == b'-'
+        && bytes[8..10].iter().all(u8::is_ascii_digit)
+}
+
+/// Parse any of the four datetime forms; `None` if malformed.
+fn datetime(token: &str) -> Option<Value> {
+    if token.len() == 10 {
+        return valid_date(token).then(|| Value::string(token));
+    }
+    if !is_date(token) {
+        return valid_time(token).then(|| Value::string(token));
+    }
+
+    let (date, rest) = token.split_at(10);
+    let rest = rest.strip_prefix(['T', 't', ' '])?;
+    if !valid_date(date) {
+        return None;
+    }
+    let (time, offset) = match rest.find(['Z', 'z', '+', '-']) {
+        Some(at) => rest.split_at(at),
+        None => (rest, ""),
+    };
+    if !valid_time(time) {
+        return None;
+    }
+    if offset.is_empty() {
+        return Some(Value::string(format!("{date}T{time}")));
+    }
+    let offset = if offset.eq_ignore_ascii_case("z") {
+        "Z"
+    } else {
+        let bytes = offset.as_bytes();
+        let valid = bytes.len() == 6
+            && bytes[3] == b':'
+            && two_digits(&offset[1..3]).is_some_and(|h| h < 24)
+            && two_digits(&offset[4..6]).is_some_and(|m| m < 60);
+        if !valid {
+            return None;
+        }
+        offset
+    };
+    offset_datetime(&format!("{date}T{time}{offset}"))
+}
+
+#[cfg(feature = "chrono")]
+fn offset_datetime(text: &str) -> Option<Value> {
+    chrono::DateTime::parse_from_rfc3339(text)
+        .ok()
+        .map(|dt| Value::datetime(dt.with_timezone(&chrono::Utc)))
+}
+
+#[cfg(not(feature = "chrono"))]
+fn offset_datetime(text: &str) -> Option<Value> {
+    Some(Value::string(text))
+}
+
+fn two_digits(text: &str) -> Option<u32> {
+    (text.len() == 2 && text.bytes().all(|b| b.is_ascii_digit()))
+        .then(|| text.parse().ok())
+        .flatten()
+}
+
+fn valid_date(date: &str) -> bool {
+    if !is_date(date) || date.len() != 10 {
+        return false;
+    }
+    let year: u32 = date[..4].parse().unwrap_or(0);
+    let (Some(month), Some(day)) = (two_digits(&date[5..7]), two_digits(&date[8..10])) else {
+        return false;
+    };
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let days = match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if leap => 29,
+        2 => 28,
+        _ => return false,
+    };
+    (1..=days).contains(&day)
+}
+
+/// `HH:MM:SS` with an optional fraction
+fn valid_time(time: &str) -> bool {
+    let (clock, fraction) = match time.split_once('.') {
+        Some((clock, fraction)) => (clock, Some(fraction)),
+        None => (time, None),
+    };
+    if fraction.is_some_and(|f| f.is_empty() || !f.bytes().all(|b| b.is_ascii_digit())) {
+        return false;
+    }
+    let bytes = clock.as_bytes();
+    bytes.len() == 8
+        && bytes[2] == b':'
+        && bytes[5] == b':'
+        && two_digits(&clock[..2]).is_some_and(|h| h < 24)
+        && two_digits(&clock[3..5]).is_some_and(|m| m < 60)
+        && two_digits(&clock[6..8]).is_some_and(|s| s <= 60)
+}
+
+// -------------------------------------------------------------------
+// Documents
+// -------------------------------------------------------------------
+
+/// Parse TOML source into an editable [`TomlDocument`] that remembers its
+/// layout.
+///
+/// # Errors
+///
+/// Returns [`Error::Parse`] like [`parse`].
+pub fn parse_document(source: &str) -> Result<TomlDocument> {
+    let mut parser = TomlParser::new(source);
+    parser.layout = Some(Vec::new());
+    parser.document()?;
+    Ok(TomlDocument {
+        source: source.to_string(),
+        spans: parser.layout.unwrap_or_default(),
+        value: parser.root,
+    })
+}
+
+/// A parsed TOML file that remembers its layout.
+///
+/// [`sync`](Self::sync), [`set`](Self::set) and [`remove`](Self::remove)
+/// edit the source text in place. Changed values are rewritten where they
+/// stand (literal strings stay literal where possible); removed keys and
+/// tables take the comments directly above them; new keys join the table
+/// they belong to, reusing a sibling's indentation and `=` spacing; new
+/// tables and `[[array]]` elements follow their parent's last section.
+/// Comments, key order and untouched values, such as local dates, stay
+/// byte-identical.
+#[derive(Debug, Clone)]
+pub struct TomlDocument {
+    source: String,
+    spans: Vec<Span>,
+    value: Value,
+}
+
+/// Where a header or key/value line sits in a [`TomlDocument`]'s source.
+#[derive(Debug, Clone)]
+struct Span {
+    /// Start of the line holding the header or key
+    line: usize,
+    /// Just past the line ending after the header or value
+    end: usize,
+    kind: SpanKind,
+}
+
+#[derive(Debug, Clone)]
+enum SpanKind {
+    /// `[table]` or `[[array]]` header opening the table at `path`
+    Header { path: Vec<Segment> },
+    /// `key = value` holding the value at `path`
+    Entry {
+        path: Vec<Segment>,
+        key: Range<usize>,
+        value: Range<usize>,
+    },
+}
+
+impl TomlDocument {
+    /// The parsed values.
+    pub fn value(&self) -> &Value {
+        &self.value
+    }
+
+    /// Consume the document, keeping only the values.
+    pub fn into_value(self) -> Value {
+        self.value
+    }
+
+    /// Set the value at `path` (see [`crate::path`]), editing the owning
+    /// entry in place or adding it to its table.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the path is invalid or the result cannot be
+    /// written as TOML (e.g. `Null`); the document is unchanged in that
+    /// case.
+    pub fn set(&mut self, path: &str, value: Value) -> Result<()> {
+        let mut target = self.value.clone();
+        target.set_nested(path, value)?;
+        self.sync(&target)
+    }
+
+    /// Remove the value at `path`, dropping its entry or table with the
+    /// comments directly above it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the path is invalid or the result cannot be
+    /// written as TOML.
+    pub fn remove(&mut self, path: &str) -> Result<Option<Value>> {
+        let mut target = self.value.clone();
+        let removed = target.remove(path)?;
+        if removed.is_some() {
+            self.sync(&target)?;
+        }
+        Ok(removed)
+    }
+
+    /// Reconcile the document with `target`, editing only what differs.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::General`] if `target` is not a table, holds values
+    /// TOML cannot represent (`Null`), or cannot be reached by editing
+    /// the file without rewriting it; the document is unchanged in that
+    /// case.
+    pub fn sync(&mut self, target: &Value) -> Result<()> {
+        let table = target.as_table()?;
+        let mut edit = Edit::new(self, target);
+        edit.walk(table, &mut Vec::new())?;
+        let text = edit.apply()?;
+        let document = parse_document(&text).map_err(|_| layout_error())?;
+        if !same(&document.value, target) {
+            return Err(layout_error());
+        }
+        *self = document;
+        Ok(())
+    }
+}
+
+impl fmt::Display for TomlDocument {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+fn layout_error() -> Error {
+    Error::serialize("Cannot write these values as TOML without rewriting the file")
+}
+
+/// Equal values, counting NaN as equal to itself
+fn same(a: &Value, b: &Value) -> bool {
+    crate::diff::DiffOptions::new()
+        .strict_numbers(true)
+        .equal(a, b)
+}
+
+fn lookup<'v>(root: &'v Value, path: &[Segment]) -> Option<&'v Value> {
+    path.iter()
+        .try_fold(root, |node, segment| match (segment, node) {
+            (Segment::Key(key), Value::Table(table)) => table.get(key),
+            (Segment::Index(index), Value::Array(items)) => items.get(*index),
+            _ => None,
+        })
+}
+
+/// The keys of `path`, as written in a header
+fn header_keys(path: &[Segment]) -> Vec<String> {
+    path.iter()
+        .filter_map(|segment| match segment {
+            Segment::Key(key) => Some(key.clone()),
+            Segment::Index(_) => None,
+        })
+        .collect()
+}
+
+/// Start of the comment lines directly above the line at `line`.
+fn block_start(source: &str, line: usize) -> usize {
+    let mut start = line;
+    while start > 0 {
+        let previous = source[..start - 1].rfind('\n').map_or(0, |i| i + 1);
+        if !source[previous..start].trim_start().starts_with('#') {
+            break;
+        }
+        start = previous;
+    }
+    start
+}
+
+/// `value` written in place of `original`, in the same style if it reads
+/// back unchanged.
+fn render(value: &Value, original: &str, path: &[Segment]) -> Result<String> {
+    let mut candidates = Vec::new();
+    if let Value::String(s) = value {
+        if original.starts_with('\'') && !original.starts_with("'''") {
+            candidates.push(format!("'{s}'"));
+        }
+    }
+    let mut inline = String::new();
+    write_inline(&mut inline, value, &[], &display_path(path))?;
+    candidates.push(inline);
+    candidates
+        .into_iter()
+        .find(|raw| reparse(raw).is_some_and(|parsed| same(&parsed, value)))
+        .ok_or_else(|| {
+            Error::serialize(format!(
+                "Cannot write {} value at '{}' as TOML",
+                value.type_name(),
+                display_path(path)
+            ))
+        })
+}
+
+/// Parse a raw value, requiring it to be consumed entirely.
+fn reparse(raw: &str) -> Option<Value> {
+    let mut parser = TomlParser::new(raw);
+    let value = parser.value().ok()?;
+    (parser.pos == raw.len()).then_some(value)
+}
+
+/// A table's lines: the root before the first header, or a header and
+/// the entries after it.
+struct Section {
+    path: Vec<Segment>,
+    /// Start of the comments directly above the header
+    block: usize,
+    /// Just past the header line
+    body: usize,
+    /// Start of the next section's block, or the end of the source
+    end: usize,
+    /// Whether the target still has a table here
+    kept: bool,
+}
+
+/// A `key = value` line of a [`Section`].
+struct Entry<'d> {
+    path: &'d [Segment],
+    section: usize,
+    line: usize,
+    block: usize,
+    end: usize,
+    key: Range<usize>,
+    value: Range<usize>,
+    kept: bool,
+}
+
+/// Text edits turning a [`TomlDocument`] into one holding a target.
+struct Edit<'d> {
+    source: &'d str,
+    sections: Vec<Section>,
+    lines: Vec<Entry<'d>>,
+    /// Paths of kept headers and entries, and every prefix of them
+    present: HashSet<&'d [Segment]>,
+    /// Replacements, deletions and insertions
+    edits: Vec<(Range<usize>, String)>,
+    newline: &'static str,
+}
+
+impl<'d> Edit<'d> {
+    /// Drop the entries and tables `target` no longer has.
+    fn new(document: &'d TomlDocument, target: &Value) -> Self {
+        let source = document.source.as_str();
+        let mut sections = vec![Section {
+            path: Vec::new(),
+            block: 0,
+            body: 0,
+            end: source.len(),
+            kept: true,
+        }];
+        let mut lines = Vec::new();
+        for span in &document.spans {
+            let block = block_start(source, span.line);
+            match &span.kind {
+                SpanKind::Header { path } => {
+                    if let Some(last) = sections.last_mut() {
+                        last.end = block;
+                    }
+                    sections.push(Section {
+                        path: path.clone(),
+                        block,
+                        body: span.end,
+                        end: source.len(),
+                        kept: matches!(lookup(target, path), Some(Value::Table(_))),
+                    });
+                }
+                SpanKind::Entry { path, key, value } => {
+                    let section = sections.len() - 1;
+                    lines.push(Entry {
+                        path,
+                        section,
+                        line: span.line,
+                        block,
+                        end: span.end,
+                        key: key.clone(),
+                        value: value.clone(),
+                        kept: sections[section].kept && lookup(target, path).is_some(),
+                    });
+                }
+            }
+        }
+
+        let mut present = HashSet::new();
+        let mut edits = Vec::new();
+        for section in sections.iter().skip(1) {
+            if !section.kept {
+                edits.push((section.block..section.end, String::new()));
+            }
+        }
+        for line in &lines {
+            if line.kept {
+                present.extend((1..=line.path.len()).map(|len| &line.path[..len]));
+            } else if sections[line.section].kept {
+                edits.push((line.block..line.end, String::new()));
+            }
+        }
+        for span in &document.spans {
+            if let SpanKind::Header { path } = &span.kind {
+                if matches!(lookup(target, path), Some(Value::Table(_))) {
+                    present.extend((1..=path.len()).map(|len| &path[..len]));
+                }
+            }
+        }
+
+        Self {
+            source,
+            sections,
+            lines,
+            present,
+            edits,
+            newline: if source.contains("\r\n") {
+                "\r\n"
+            } else {
+                "\n"
+            },
+        }
+    }
+
+    /// Rewrite the changed entries below `table` at `path` and add the
+    /// missing ones.
+    fn walk(&mut self, table: &BTreeMap<String, Value>, path: &mut Vec<Segment>) -> Result<()> {
+        let mut plain = BTreeMap::new();
+        let mut tables = Vec::new();
+        for (key, value) in table {
+            path.push(Segment::Key(key.clone()));
+            let line = self
+                .lines
+                .iter()
+                .find(|line| line.kept && line.path == path.as_slice())
+                .map(|line| line.value.clone());
+            if let Some(range) = line {
+                let original = &self.source[range.clone()];
+                if reparse(original).map_or(true, |old| !same(&old, value)) {
+                    let raw = render(value, original, path)?;
+                    self.edits.push((range, raw));
+                }
+            } else if self.present.contains(path.as_slice()) {
+                match value {
+                    Value::Table(child) => self.walk(child, path)?,
+                    Value::Array(items) if is_array_of_tables(value) => {
+                        for (index, item) in items.iter().enumerate() {
+                            path.push(Segment::Index(index));
+                            if self.present.contains(path.as_slice()) {
+                                self.walk(item.as_table()?, path)?;
+                            } else {
+                                let keys = header_keys(path);
+                                let mut out = String::new();
+                                write_header(&mut out, &keys, "[[", "]]");
+                                write_table(&mut out, &mut keys.clone(), item.as_table()?)?;
+                                let at = self.group_end(&path[..path.len() - 1]);
+                                self.insert_block(at, &out);
+                            }
+                            path.pop();
+                        }
+                    }
+                    _ => return Err(layout_error()),
+                }
+            } else if value.is_table() || is_array_of_tables(value) {
+                tables.push((key, value));
+            } else {
+                plain.insert(key.clone(), value.clone());
+            }
+            path.pop();
+        }
+
+        if !plain.is_empty() {
+            self.add_entries(path, &plain)?;
+        }
+        for (key, value) in tables {
+            let mut keys = header_keys(path);
+            keys.push(key.clone());
+            let mut out = String::new();
+            write_section(&mut out, &mut keys, value)?;
+            let at = self.group_end(path);
+            self.insert_block(at, &out);
+        }
+        Ok(())
+    }
+
+    /// Add new `key = value` lines to the table at `path`: after the
+    /// entries of its section, as dotted keys next to the ones that made
+    /// it, or under a new header for a table only implied by sub-tables.
+    fn add_entries(&mut self, path: &[Segment], plain: &BTreeMap<String, Value>) -> Result<()> {
+        let Some((section, prefix)) = self.home(path) else {
+            let keys = header_keys(path);
+            let mut out = String::new();
+            write_header(&mut out, &keys, "[", "]");
+            write_table(&mut out, &mut keys.clone(), plain)?;
+            let at = self.group_end(path);
+            self.insert_block(at, &out);
+            return Ok(());
+        };
+
+        let (indent, separator) = self
+            .lines
+            .iter()
+            .rev()
+            .find(|line| line.section == section)
+            .map_or(("", " = "), |line| {
+                (
+                    &self.source[line.line..line.key.start],
+                    &self.source[line.key.end..line.value.start],
+                )
+            });
+        let last = self
+            .lines
+            .iter()
+            .rev()
+            .find(|line| line.section == section && line.kept)
+            .map(|line| line.end);
+        let at = match last {
+            Some(end) => end,
+            None if section == 0 => self.sections[0].end,
+            None => self.sections[section].body,
+        };
+
+        let mut text = String::new();
+        if at > 0 && !self.source[..at].ends_with('\n') {
+            text.push_str(self.newline);
+        }
+        for (key, value) in plain {
+            let mut full = path.to_vec();
+            full.push(Segment::Key(key.clone()));
+            text.push_str(indent);
+            for part in prefix.iter().chain(std::iter::once(key)) {
+                write_key(&mut text, part);
+                text.push('.');
+            }
+            text.pop();
+            text.push_str(separator);
+            text.push_str(&render(value, "", &full)?);
+            text.push_str(self.newline);
+        }
+        if last.is_none() && section == 0 && at < self.source.len() {
+            text.push_str(self.newline);
+        }
+        self.edits.push((at..at, text));
+        Ok(())
+    }
+
+    /// The kept section whose entries define the table at `path`, with the
+    /// dotted keys leading from that section's table to it.
+    fn home(&self, path: &[Segment]) -> Option<(usize, Vec<String>)> {
+        if let Some(index) = self
+            .sections
+            .iter()
+            .position(|section| section.kept && section.path == path)
+        {
+            return Some((index, Vec::new()));
+        }
+        self.lines
+            .iter()
+            .filter(|line| line.kept && line.path.len() > path.len() && line.path.starts_with(path))
+            .find_map(|line| {
+                let rest = path.strip_prefix(self.sections[line.section].path.as_slice())?;
+                rest.iter()
+                    .map(|segment| match segment {
+                        Segment::Key(key) => Some(key.clone()),
+                        Segment::Index(_) => None,
+                    })
+                    .collect::<Option<Vec<_>>>()
+                    .map(|prefix| (line.section, prefix))
+            })
+    }
+
+    /// Where new sections below the table at `path` go: after the last
+    /// section inside it, or after its home section's.
+    fn group_end(&self, path: &[Segment]) -> usize {
+        let end = self
+            .sections
+            .iter()
+            .skip(1)
+            .filter(|section| section.kept && section.path.starts_with(path))
+            .map(|section| section.end)
+            .max();
+        match (end, self.home(path)) {
+            (Some(end), _) => end,
+            (None, Some((section, _))) if section > 0 => {
+                self.group_end(&self.sections[section].path)
+            }
+            _ => self.source.len(),
+        }
+    }
+
+    /// Insert serialized sections at `at`, set off by blank lines.
+    fn insert_block(&mut self, at: usize, text: &str) {
+        let before = &self.source[..at];
+        let mut block = String::new();
+        if !before.is_empty() {
+            if !before.ends_with('\n') {
+                block.push_str(self.newline);
+            }
+            if !(before.ends_with("\n\n") || before.ends_with("\n\r\n")) {
+                block.push_str(self.newline);
+            }
+        }
+        block.push_str(&text.replace('\n', self.newline));
+        if at < self.source.len() {
+            block.push_str(self.newline);
+        }
+        self.edits.push((at..at, block));
+    }
+
+    /// The source with every edit applied.
+    fn apply(mut self) -> Result<String> {
+        // Stable, so insertions at one place keep the order they were made
+        self.edits
+            .sort_by_key(|(range, _)| (range.start, range.end));
+        let mut out = String::with_capacity(self.source.len());
+        let mut cursor = 0;
+        for (range, text) in &self.edits {
+            if range.start < cursor {
+                return Err(layout_error());
+            }
+            out.push_str(&self.source[cursor..range.start]);
+            out.push_str(text);
+            cursor = range.end;
+        }
+        out.push_str(&self.source[cursor..]);
+        Ok(out)
+    }
+}
+
+/// Serialize a value tree as TOML.
+///
+/// Scalars and arrays of scalars are written as `key = value` before any
+/// table of the same level; nested tables become `[section]` headers (a
+/// header is skipped when the table only holds sub-tables), non-empty
+/// arrays whose elements are all tables become `[[array]]` blocks, and
+/// tables inside other arrays are written inline. Keys that are not bare
+/// (`A-Za-z0-9_-`) are quoted, strings use basic-string escapes, floats
+/// always carry a decimal point or exponent (`nan`, `inf` and `-inf` for
+/// the special values), and datetimes are written as RFC 3339 offset
+/// datetimes. Does not depend on the `noml` crate.
+///
+/// # Errors
+///
+/// Returns [`Error::General`] if the root is not a table or the tree
+/// contains `Null`, which TOML cannot represent.
+pub fn serialize(value: &Value) -> Result<String> {
+    let mut out = String::new();
+    write_table(&mut out, &mut Vec::new(), value.as_table()?)?;
+    Ok(out)
+}
+
+/// Write the plain entries of `table`, then its sections.
+fn write_table(
+    out: &mut String,
+    path: &mut Vec<String>,
+    table: &BTreeMap<String, Value>,
+) -> Result<()> {
+    for (key, value) in table {
+        if !value.is_table() && !is_array_of_tables(value) {
+            write_key(out, key);
+            out.push_str(" = ");
+            write_inline(out, value, path, key)?;
+            out.push('\n');
+        }
+    }
+
+    for (key, value) in table {
+        path.push(key.clone());
+        write_section(out, path, value)?;
+        path.pop();
+    }
+    Ok(())
+}
+
+/// Write the table or array of tables at `path` as sections; other values
+/// are [`write_table`]'s plain entries.
+fn write_section(out: &mut String, path: &mut Vec<String>, value: &Value) -> Result<()> {
+    match value {
+        Value::Table(child) => {
+            let has_plain = child
+                .values()
+                .any(|v| !v.is_table() && !is_array_of_tables(v));
+            if has_plain || child.is_empty() {
+                write_header(out, path, "[", "]");
+            }
+            write_table(out, path, child)?;
+        }
+        Value::Array(items) if is_array_of_tables(value) => {
+            for item in items {
+                write_header(out, path, "[[", "]]");
+                write_table(out, path, item.as_table()?)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn is_array_of_tables(value: &Value) -> bool {
+    matches!(value, Value::Array(items) if !items.is_empty() && items.iter().all(Value::is_table))
+}
+
+fn write_header(out: &mut String, path: &[String], open: &str, close: &str) {
+    if !out.is_empty() {
+        out.push('\n');
+    }
+    out.push_str(open);
+    for (index, key) in path.iter().enumerate() {
+        if index > 0 {
+            out.push('.');
+        }
+        write_key(out, key);
+    }
+    out.push_str(close);
+    out.push('\n');
+}
+
+fn write_key(out: &mut String, key: &str) {
+    let bare = !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if bare {
+        out.push_str(key);
+    } else {
+        write_string(out, key);
+    }
+}
+
+/// Write a value in inline position; `path` and `key` only name the
+/// offending entry in errors.
+fn write_inline(out: &mut String, value: &Value, path: &[String], key: &str) -> Result<()> {
+    match value {
+        Value::Null => {
+            let mut full = path.join(".");
+            if !full.is_empty() {
+                full.push('.');
+            }
+            full.push_str(key);
+            return Err(Error::serialize(format!(
+                "TOML cannot represent null (at '{full}')"
+            )));
+        }
+        Value::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        Value::Integer(i) => {
+            let _ = write!(out, "{i}");
+        }
+        Value::Float(f) => write_float(out, *f),
+        // Local dates and times read back as these strings; keep them
+        // datetimes rather than quoting them
+        Value::String(s) if datetime(s).as_ref() == Some(value) => out.push_str(s),
+        Value::String(s) => write_string(out, s),
+        Value::Array(items) => {
+            out.push('[');
+            for (index, item) in items.iter().enumerate() {
+                if index > 0 {
+                    out.push_str(", ");
+                }
+                write_inline(out, item, path, key)?;
+            }
+            out.push(']');
+        }
+        Value::Table(table) => {
+            if table.is_empty() {
+                out.push_str("{}");
+                return Ok(());
+            }
+            out.push_str("{ ");
+            for (index, (child_key, child)) in table.iter().enumerate() {
+                if index > 0 {
+                    out.push_str(", ");
+                }
+                write_key(out, child_key);
+                out.push_str(" = ");
+                write_inline(out, child, path, key)?;
+            }
+            out.push_str(" }");
+        }
+        #[cfg(feature = "chrono")]
+        Value::DateTime(dt) => out.push_str(&dt.to_rfc3339()),
+    }
+    Ok(())
+}
+
+fn write_float(out: &mut String, f: f64) {
+    if f.is_nan() {
+        out.push_str("nan");
+    } else if f.is_infinite() {
+        out.push_str(if f > 0.0 { "inf" } else { "-inf" });
+    } else if f.fract() == 0.0 {
+        let _ = write!(out, "{f:.1}");
+    } else {
+        let _ = write!(out, "{f}");
+    }
+}
+
+fn write_string(out: &mut String, s: &str) {
+    out.push('"');
+    for ch in s.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\u{8}' => out.push_str("\\b"),
+            '\t' => out.push_str("\\t"),
+            '\n' => out.push_str("\\n"),
+            '\u{c}' => out.push_str("\\f"),
+            '\r' => out.push_str("\\r"),
+            c if c.is_control() => {
+                let _ = write!(out, "\\u{:04X}", u32::from(c));
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
 /// Convert NOML Value to config-lib Value
 #[cfg(feature = "noml")]
 fn convert_noml_value(noml_value: noml::Value) -> Result<Value> {
@@ -120,4 +1778,284 @@ mod tests {
         let ports = config.get("ports").unwrap().as_array().unwrap();
         assert_eq!(ports[0].as_integer().unwrap(), 8001);
     }
+
+    #[test]
+    fn test_serialize_layout() {
+        let value = parse(
+            r#"
+            title = "demo"
+            ratio = 2.0
+
+            [server]
+            host = "localhost"
+            ports = [80, 443]
+
+            [server.tls]
+            enabled = true
+
+            [[plugins]]
+            name = "auth"
+
+            [[plugins]]
+            name = "cache"
+            options = { ttl = 60 }
+        "#,
+        )
+        .unwrap();
+
+        let written = serialize(&value).unwrap();
+        assert_eq!(
+            written,
+            "ratio = 2.0\ntitle = \"demo\"\n\n[[plugins]]\nname = \"auth\"\n\n\
+             [[plugins]]\nname = \"cache\"\n\n[plugins.options]\nttl = 60\n\n\
+             [server]\nhost = \"localhost\"\nports = [80, 443]\n\n[server.tls]\nenabled = true\n"
+        );
+        assert_eq!(parse(&written).unwrap(), value);
+    }
+
+    #[test]
+    fn test_serialize_escapes_and_special_values() {
+        let mut nested = BTreeMap::new();
+        nested.insert(
+            "a b".to_string(),
+            Value::string("tab\there \"quoted\" \\ \u{1}"),
+        );
+        nested.insert(
+            "mixed".to_string(),
+            Value::array(vec![
+                Value::integer(1),
+                Value::table(BTreeMap::from([("x".to_string(), Value::float(0.5))])),
+            ]),
+        );
+        nested.insert("big".to_string(), Value::float(1e20));
+        nested.insert("inf".to_string(), Value::float(f64::NEG_INFINITY));
+        nested.insert("only".to_string(), Value::table(BTreeMap::new()));
+        let value = Value::table(BTreeMap::from([(
+            "outer".to_string(),
+            Value::table(nested),
+        )]));
+
+        let written = serialize(&value).unwrap();
+        assert_eq!(
+            written,
+            "[outer]\n\"a b\" = \"tab\\there \\\"quoted\\\" \\\\ \\u0001\"\n\
+             big = 100000000000000000000.0\ninf = -inf\nmixed = [1, { x = 0.5 }]\n\n[outer.only]\n"
+        );
+        assert_eq!(parse(&written).unwrap(), value);
+    }
+
+    #[test]
+    fn test_serialize_rejects_null() {
+        let value = Value::table(BTreeMap::from([("missing".to_string(), Value::Null)]));
+        assert!(serialize(&value).is_err());
+        assert!(serialize(&Value::integer(1)).is_err());
+    }
+
+    #[test]
+    fn test_serialize_keeps_local_datetimes_bare() {
+        let value = parse("d = 1979-05-27\nt = 07:32:00\nlocal = 1979-05-27T07:32:00\n").unwrap();
+        let written = serialize(&value).unwrap();
+        assert_eq!(
+            written,
+            "d = 1979-05-27\nlocal = 1979-05-27T07:32:00\nt = 07:32:00\n"
+        );
+
+        let text = Value::table(BTreeMap::from([(
+            "s".to_string(),
+            Value::string("1979-05-27 07:32:00"),
+        )]));
+        assert_eq!(serialize(&text).unwrap(), "s = \"1979-05-27 07:32:00\"\n");
+    }
+
+    const DOCUMENT: &str = "\
+# Deployment
+name = 'demo' # shown in logs
+released = 1979-05-27
+
+# Listener
+[server]
+host   =   \"localhost\"
+port   =   8080
+
+[server.tls]
+cert = \"a.pem\"
+
+[[plugins]]
+name = \"auth\"
+
+[owner]
+name.first = \"Tom\"
+";
+
+    #[test]
+    fn test_document_edits_in_place() {
+        let mut document = parse_document(DOCUMENT).unwrap();
+        let unchanged = document.value().clone();
+        document.sync(&unchanged).unwrap();
+        assert_eq!(document.to_string(), DOCUMENT);
+
+        document.set("name", Value::string("prod")).unwrap();
+        document
+            .set("released", Value::string("2024-01-02"))
+            .unwrap();
+        document.set("server.port", Value::integer(9090)).unwrap();
+        assert_eq!(
+            document.to_string(),
+            DOCUMENT
+                .replace("'demo'", "'prod'")
+                .replace("1979-05-27", "2024-01-02")
+                .replace("8080", "9090")
+        );
+        assert_eq!(parse(&document.to_string()).unwrap(), *document.value());
+    }
+
+    #[test]
+    fn test_document_adds_and_removes() {
+        let mut document = parse_document(DOCUMENT).unwrap();
+        document.set("server.timeout", Value::integer(30)).unwrap();
+        document.set("owner.name.last", Value::string("P")).unwrap();
+        document
+            .set("server.limits.max", Value::integer(5))
+            .unwrap();
+        let mut target = document.value().clone();
+        let plugin = Value::table(BTreeMap::from([(
+            "name".to_string(),
+            Value::string("cache"),
+        )]));
+        if let Value::Table(root) = &mut target {
+            if let Some(Value::Array(plugins)) = root.get_mut("plugins") {
+                plugins.push(plugin);
+            }
+        }
+        document.sync(&target).unwrap();
+        document.remove("name").unwrap();
+        document.remove("server.tls").unwrap();
+
+        assert_eq!(
+            document.to_string(),
+            "released = 1979-05-27
+
+# Listener
+[server]
+host   =   \"localhost\"
+port   =   8080
+timeout   =   30
+
+[server.limits]
+max = 5
+
+[[plugins]]
+name = \"auth\"
+
+[[plugins]]
+name = \"cache\"
+
+[owner]
+name.first = \"Tom\"
+name.last = \"P\"
+"
+        );
+    }
+
+    #[test]
+    fn test_document_rejects_null() {
+        let mut document = parse_document(DOCUMENT).unwrap();
+        assert!(document.set("name", Value::Null).is_err());
+        assert_eq!(document.to_string(), DOCUMENT);
+    }
+
+    #[test]
+    fn test_tables_and_dotted_keys() {
+        let value = parse(
+            "a.b.c = 1\na.d = 2\n\n[x.y]\nz = 3\n\n[x]\nw = 4\n\n\
+             [[fruits]]\nname = \"apple\"\n[fruits.physical]\ncolor = \"red\"\n\
+             [[fruits.varieties]]\nname = \"gala\"\n\n[[fruits]]\nname = \"banana\"\n",
+        )
+        .unwrap();
+        assert_eq!(value.get("a.b.c"), Some(&Value::integer(1)));
+        assert_eq!(value.get("x.y.z"), Some(&Value::integer(3)));
+        assert_eq!(value.get("x.w"), Some(&Value::integer(4)));
+        assert_eq!(
+            value.get("fruits[0].physical.color"),
+            Some(&Value::string("red"))
+        );
+        assert_eq!(
+            value.get("fruits[0].varieties[0].name"),
+            Some(&Value::string("gala"))
+        );
+        assert_eq!(value.get("fruits[1].name"), Some(&Value::string("banana")));
+    }
+
+    #[test]
+    fn test_scalars_and_strings() {
+        let value = parse(concat!(
+            "hex = 0xDEAD_beef\noct = 0o755\nbin = 0b1101\n",
+            "big = 9_223_372_036_854_775_807\nexp = -1.5e+3\nnan = nan\n",
+            "basic = \"tab\\t\\u00E9\\U0001F600\"\nliteral = 'C:\\path'\n",
+            "multi = \"\"\"\nline one \\\n    continued \"\"quoted\"\"\"\"\"\n",
+            "raw = '''\nkeep \\n as is'''\n",
+            "odt = 1979-05-27 07:32:00-07:00\nldt = 1979-05-27T07:32:00.5\n",
+            "ld = 1979-05-27\nlt = 07:32:00\n",
+        ))
+        .unwrap();
+        assert_eq!(value.get("hex"), Some(&Value::integer(0xDEAD_BEEF)));
+        assert_eq!(value.get("oct"), Some(&Value::integer(0o755)));
+        assert_eq!(value.get("bin"), Some(&Value::integer(13)));
+        assert_eq!(value.get("big"), Some(&Value::integer(i64::MAX)));
+        assert_eq!(value.get("exp"), Some(&Value::float(-1500.0)));
+        assert!(value.get("nan").unwrap().as_float().unwrap().is_nan());
+        assert_eq!(
+            value.get("basic"),
+            Some(&Value::string("tab\t\u{e9}\u{1F600}"))
+        );
+        assert_eq!(value.get("literal"), Some(&Value::string("C:\\path")));
+        assert_eq!(
+            value.get("multi"),
+            Some(&Value::string("line one continued \"\"quoted\"\""))
+        );
+        assert_eq!(value.get("raw"), Some(&Value::string("keep \\n as is")));
+        #[cfg(feature = "chrono")]
+        assert_eq!(
+            value.get("odt").unwrap().to_string(),
+            "1979-05-27T14:32:00+00:00"
+        );
+        #[cfg(not(feature = "chrono"))]
+        assert_eq!(
+            value.get("odt"),
+            Some(&Value::string("1979-05-27T07:32:00-07:00"))
+        );
+        assert_eq!(
+            value.get("ldt"),
+            Some(&Value::string("1979-05-27T07:32:00.5"))
+        );
+        assert_eq!(value.get("ld"), Some(&Value::string("1979-05-27")));
+        assert_eq!(value.get("lt"), Some(&Value::string("07:32:00")));
+    }
+
+    #[test]
+    fn test_errors_report_position() {
+        let cases = [
+            ("a = 1\na = 2\n", 2, 1),
+            ("[t]\nx = 1\n[t]\n", 3, 1),
+            ("a.b = 1\n[a.b]\n", 2, 1),
+            ("t = { x = 1 }\n[t.y]\n", 2, 1),
+            ("arr = [1]\n[[arr]]\n", 2, 1),
+            ("n = 012\n", 1, 5),
+            ("n = 9_223_372_036_854_775_808\n", 1, 5),
+            ("d = 2021-02-29\n", 1, 5),
+            ("s = \"bad \\q\"\n", 1, 10),
+            ("k = \"é\" x\n", 1, 9),
+            ("t = { a = 1, }\n", 1, 14),
+            (&format!("a = {}\n", "[".repeat(5000)), 1, 133),
+            (&format!("a = {}\n", "{a=".repeat(5000)), 1, 389),
+        ];
+        for (source, line, column) in cases {
+            match parse(source) {
+                Err(Error::Parse {
+                    line: l, column: c, ..
+                }) => assert_eq!((l, c), (line, column), "{source:?}"),
+                other => panic!("{source:?} parsed as {other:?}"),
+            }
+        }
+    }
 }
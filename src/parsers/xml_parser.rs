@@ -13,26 +13,202 @@
 //! - ASP.NET Core appsettings.xml
 //! - Maven/Gradle configuration XML
 //! - Generic key-value XML structures
+//!
+//! ## Mapping
+//!
+//! | XML | [`Value`] |
+//! |-----|-----------|
+//! | element with only text | typed scalar (`true`, `42`, `1.5`, string) |
+//! | element with attributes / children | table |
+//! | attribute | string entry (`{prefix}name` with [`XmlOptions::attribute_prefix`]) |
+//! | repeated sibling elements | array, in document order |
+//! | text next to attributes / children | `text` entry |
+//! | empty element | empty table |
+//!
+//! [`serialize`] reverses the mapping so that parsing its output gives back
+//! the same value. Tables, arrays, numbers and booleans become elements and
+//! `text` becomes text content. With an attribute prefix, prefixed keys
+//! are written as attributes; without one, strings that would not read back
+//! unchanged as element text (empty, padded, or looking like a number or
+//! boolean) become attributes and all other strings become elements.
+//! Values XML cannot hold exactly are lossy: `Null` is written as an empty
+//! element, a table holding only `text` reads back as that scalar, strings
+//! inside arrays are retyped and trimmed, and nested arrays are rejected.
+//!
+//! [`XmlOptions::attribute_prefix`]: crate::parsers::xml_parser::XmlOptions::attribute_prefix
+//! [`serialize`]: crate::parsers::xml_parser::serialize
 
+#[cfg(feature = "xml")]
+use super::locate::{self, Mark, Step};
 use crate::{error::Error, Result, Value};
 #[cfg(feature = "xml")]
-use quick_xml::{events::Event, Reader};
-use std::collections::BTreeMap;
+use quick_xml::{
+    events::{BytesDecl, BytesEnd, BytesStart, BytesText, Event},
+    Reader, Writer,
+};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// Options shared by the XML parser and [`serialize_with`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct XmlOptions {
+    /// Wrap the value in a document element with this name. Without it the
+    /// value must have exactly one top-level key (as parsed documents do).
+    pub root: Option<String>,
+    /// Spaces per nesting level; `0` writes everything on one line.
+    pub indent: usize,
+    /// Emit `<?xml version="1.0" encoding="UTF-8"?>`.
+    pub declaration: bool,
+    /// Store attributes as `{prefix}name` when parsing and write such keys
+    /// as attributes when serializing (e.g. `"@"`).
+    pub attribute_prefix: Option<String>,
+}
+
+impl Default for XmlOptions {
+    fn default() -> Self {
+        Self {
+            root: None,
+            indent: 2,
+            declaration: true,
+            attribute_prefix: None,
+        }
+    }
+}
+
+impl XmlOptions {
+    /// Default options: two-space indent, declaration, no attribute prefix.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the document element name. See [`XmlOptions::root`].
+    pub fn root(mut self, name: impl Into<String>) -> Self {
+        self.root = Some(name.into());
+        self
+    }
+
+    /// Set the indent width. See [`XmlOptions::indent`].
+    pub fn indent(mut self, spaces: usize) -> Self {
+        self.indent = spaces;
+        self
+    }
+
+    /// Toggle the XML declaration. See [`XmlOptions::declaration`].
+    pub fn declaration(mut self, declaration: bool) -> Self {
+        self.declaration = declaration;
+        self
+    }
+
+    /// Set the attribute prefix. See [`XmlOptions::attribute_prefix`].
+    pub fn attribute_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.attribute_prefix = Some(prefix.into());
+        self
+    }
+}
 
 /// XML configuration parser with zero-copy optimizations
 #[cfg(feature = "xml")]
 pub struct XmlParser<'a> {
+    source: &'a str,
     reader: Reader<&'a [u8]>,
+    attribute_prefix: Option<String>,
+    /// Dotted element paths of every attribute seen, e.g. `config.server.port`
+    attribute_paths: BTreeSet<String>,
+    /// Attribute and child element names of each element path, in source
+    /// order
+    order: HashMap<String, Vec<String>>,
+    declaration: bool,
+    /// Whether the source had comments, processing instructions or a
+    /// DOCTYPE, none of which are kept
+    markup: bool,
+    /// Where each element starts, when asked for; array indices are
+    /// resolved once the whole file is read
+    marks: Option<Vec<(usize, Vec<Step>)>>,
 }
 
 #[cfg(feature = "xml")]
 impl<'a> XmlParser<'a> {
     /// Create a new XML parser for the given content
     pub fn new(content: &'a str) -> Self {
+        Self::with_options(content, &XmlOptions::default())
+    }
+
+    /// Create a parser honouring [`XmlOptions::attribute_prefix`]
+    pub fn with_options(content: &'a str, options: &XmlOptions) -> Self {
         let mut reader = Reader::from_str(content);
         reader.trim_text(true); // Trim whitespace for cleaner parsing
 
-        Self { reader }
+        Self {
+            source: content,
+            reader,
+            attribute_prefix: options.attribute_prefix.clone(),
+            attribute_paths: BTreeSet::new(),
+            order: HashMap::new(),
+            declaration: false,
+            markup: false,
+            marks: None,
+        }
+    }
+
+    /// Read an element's attributes into a fresh table
+    fn attributes(
+        &mut self,
+        stack: &[(String, BTreeMap<String, Value>)],
+        start: &BytesStart<'_>,
+    ) -> BTreeMap<String, Value> {
+        let prefix = self.attribute_prefix.clone().unwrap_or_default();
+        let mut element_map = BTreeMap::new();
+        for attr in start.attributes().flatten() {
+            let key = String::from_utf8_lossy(attr.key.as_ref());
+            let value = match attr.unescape_value() {
+                Ok(value) => value.into_owned(),
+                Err(_) => String::from_utf8_lossy(&attr.value).into_owned(),
+            };
+            let mut path: Vec<&str> = stack.iter().map(|(name, _)| name.as_str()).collect();
+            let element = String::from_utf8_lossy(start.name().as_ref()).into_owned();
+            path.push(&element);
+            self.record(path.join("."), &format!("{prefix}{key}"));
+            path.push(&key);
+            self.attribute_paths.insert(path.join("."));
+            element_map.insert(format!("{prefix}{key}"), Value::string(value));
+        }
+        element_map
+    }
+
+    /// Note `name` among the entries of the element at `path`, in the
+    /// order first seen.
+    fn record(&mut self, path: String, name: &str) {
+        let names = self.order.entry(path).or_default();
+        if !names.iter().any(|known| known == name) {
+            names.push(name.to_string());
+        }
+    }
+
+    /// Note a child element `name` of the innermost open element.
+    fn record_child(&mut self, stack: &[(String, BTreeMap<String, Value>)], name: &str) {
+        let path: Vec<&str> = stack.iter().map(|(name, _)| name.as_str()).collect();
+        self.record(path.join("."), name);
+    }
+
+    /// Record the element just read, `name` below `steps`, counting it
+    /// among its same-name siblings in `seen`.
+    fn mark(&mut self, steps: &[Step], seen: &mut HashMap<String, usize>, name: &str) -> Step {
+        let count = seen.entry(name.to_string()).or_insert(0);
+        let step = Step {
+            key: name.to_string(),
+            occurrence: Some(*count),
+        };
+        *count += 1;
+        if let Some(marks) = &mut self.marks {
+            let end = self.reader.buffer_position();
+            let offset = self.source[..end.min(self.source.len())]
+                .rfind('<')
+                .unwrap_or(0);
+            let mut path = steps.to_vec();
+            path.push(step.clone());
+            marks.push((offset, path));
+        }
+        step
     }
 
     /// Parse XML content into a Value tree
@@ -40,24 +216,27 @@ impl<'a> XmlParser<'a> {
         let mut stack: Vec<(String, BTreeMap<String, Value>)> = Vec::new();
         let mut root = BTreeMap::new();
         let mut buf = Vec::new();
+        // Steps to the open elements, and the names seen under each
+        let mut steps = Vec::new();
+        let mut seen = vec![HashMap::new()];
 
         loop {
             match self.reader.read_event_into(&mut buf) {
                 Ok(Event::Start(e)) => {
                     let name = String::from_utf8_lossy(e.name().as_ref()).into_owned();
-                    let mut element_map = BTreeMap::new();
-
-                    // Handle attributes
-                    for attr in e.attributes().flatten() {
-                        let key = String::from_utf8_lossy(attr.key.as_ref()).into_owned();
-                        let value = String::from_utf8_lossy(&attr.value).into_owned();
-                        element_map.insert(key, Value::string(value));
+                    self.record_child(&stack, &name);
+                    let element_map = self.attributes(&stack, &e);
+                    if let Some(siblings) = seen.last_mut() {
+                        let step = self.mark(&steps, siblings, &name);
+                        steps.push(step);
                     }
-
+                    seen.push(HashMap::new());
                     stack.push((name, element_map));
                 }
 
                 Ok(Event::End(_)) => {
+                    steps.pop();
+                    seen.pop();
                     if let Some((tag_name, element_map)) = stack.pop() {
                         // If element only contains text, unwrap it
                         let value = if element_map.len() == 1 && element_map.contains_key("text") {
@@ -70,10 +249,9 @@ impl<'a> XmlParser<'a> {
                             Value::table(element_map)
                         };
 
-                        if let Some((_, ref mut parent)) = stack.last_mut() {
-                            parent.insert(tag_name, value);
-                        } else {
-                            root.insert(tag_name, value);
+                        match stack.last_mut() {
+                            Some((_, parent)) => insert_child(parent, tag_name, value),
+                            None => insert_child(&mut root, tag_name, value),
                         }
                     }
                 }
@@ -97,24 +275,22 @@ impl<'a> XmlParser<'a> {
 
                 Ok(Event::Empty(e)) => {
                     let name = String::from_utf8_lossy(e.name().as_ref()).into_owned();
-                    let mut element_map = BTreeMap::new();
-
-                    // Handle attributes
-                    for attr in e.attributes().flatten() {
-                        let key = String::from_utf8_lossy(attr.key.as_ref()).into_owned();
-                        let value = String::from_utf8_lossy(&attr.value).into_owned();
-                        element_map.insert(key, Value::string(value));
+                    self.record_child(&stack, &name);
+                    let value = Value::table(self.attributes(&stack, &e));
+                    if let Some(siblings) = seen.last_mut() {
+                        self.mark(&steps, siblings, &name);
                     }
 
-                    let value = Value::table(element_map);
-
-                    if let Some((_, ref mut parent)) = stack.last_mut() {
-                        parent.insert(name, value);
-                    } else {
-                        root.insert(name, value);
+                    match stack.last_mut() {
+                        Some((_, parent)) => insert_child(parent, name, value),
+                        None => insert_child(&mut root, name, value),
                     }
                 }
 
+                Ok(Event::Decl(_)) => self.declaration = true,
+
+                Ok(Event::Comment(_) | Event::PI(_) | Event::DocType(_)) => self.markup = true,
+
                 Ok(Event::Eof) => break,
 
                 Err(e) => {
@@ -137,17 +313,373 @@ impl<'a> XmlParser<'a> {
 
     /// Parse a text value into appropriate type
     fn parse_value(&self, text: &str) -> Value {
-        // Try parsing as different types
-        if let Ok(bool_val) = text.parse::<bool>() {
-            Value::bool(bool_val)
-        } else if let Ok(int_val) = text.parse::<i64>() {
-            Value::integer(int_val)
-        } else if let Ok(float_val) = text.parse::<f64>() {
-            Value::float(float_val)
-        } else {
-            Value::string(text)
+        typed_text(text)
+    }
+}
+
+/// Type element text: booleans, integers, floats, else a string
+#[cfg(feature = "xml")]
+fn typed_text(text: &str) -> Value {
+    // Try parsing as different types
+    if let Ok(bool_val) = text.parse::<bool>() {
+        Value::bool(bool_val)
+    } else if let Ok(int_val) = text.parse::<i64>() {
+        Value::integer(int_val)
+    } else if let Ok(float_val) = text.parse::<f64>() {
+        Value::float(float_val)
+    } else {
+        Value::string(text)
+    }
+}
+
+/// Add a child element, collecting repeated names into an array
+#[cfg(feature = "xml")]
+fn insert_child(parent: &mut BTreeMap<String, Value>, name: String, value: Value) {
+    match parent.get_mut(&name) {
+        Some(Value::Array(items)) => items.push(value),
+        Some(existing) => {
+            let first = std::mem::replace(existing, Value::Null);
+            *existing = Value::array(vec![first, value]);
+        }
+        None => {
+            parent.insert(name, value);
+        }
+    }
+}
+
+/// A parsed XML file that remembers which keys were attributes.
+///
+/// Plain parsing cannot tell `<server port="80"/>` from
+/// `<server><port>80</port></server>`; the document keeps that distinction,
+/// the order of attributes and child elements, and whether the file had
+/// an XML declaration, so [`XmlDocument::sync`] followed by
+/// [`XmlDocument::render`] writes edited values back in the file's own
+/// shape. New keys follow the [`serialize`] convention and come after the
+/// known ones, in key order; repeated elements are written together at
+/// the place of the first one.
+///
+/// Comments, processing instructions and DOCTYPE declarations are not
+/// kept, so documents that had any refuse to render rather than silently
+/// drop them.
+#[cfg(feature = "xml")]
+#[derive(Debug, Clone, PartialEq)]
+pub struct XmlDocument {
+    value: Value,
+    attributes: BTreeSet<String>,
+    /// Attribute and child element names of each element path, in source
+    /// order
+    order: HashMap<String, Vec<String>>,
+    options: XmlOptions,
+    /// Whether the source had markup that is not kept
+    markup: bool,
+}
+
+#[cfg(feature = "xml")]
+impl XmlDocument {
+    /// The parsed values
+    pub fn value(&self) -> &Value {
+        &self.value
+    }
+
+    /// Consume the document, returning the parsed values
+    pub fn into_value(self) -> Value {
+        self.value
+    }
+
+    /// Replace the document's values; the next render reflects them.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::General`] if `value` cannot be written as XML.
+    pub fn sync(&mut self, value: &Value) -> Result<()> {
+        self.render_value(value)?;
+        self.value = value.clone();
+        Ok(())
+    }
+
+    /// Write the document back as XML.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::General`] if the source had comments, processing
+    /// instructions or a DOCTYPE, since writing it would remove them, or if
+    /// the values do not have a single document element, e.g. for
+    /// `<a>1</a><b>2</b>` or an empty file.
+    pub fn render(&self) -> Result<String> {
+        self.check_markup()?;
+        self.render_value(&self.value)
+    }
+
+    fn render_value(&self, value: &Value) -> Result<String> {
+        write_document(value, &self.options, Some(self.shape()))
+    }
+
+    fn shape(&self) -> Shape<'_> {
+        Shape {
+            attributes: &self.attributes,
+            order: &self.order,
+        }
+    }
+
+    fn check_markup(&self) -> Result<()> {
+        if self.markup {
+            return Err(Error::serialize(
+                "XML comments, processing instructions and DOCTYPE declarations are not kept, \
+                 so writing the file would remove them",
+            ));
+        }
+        Ok(())
+    }
+
+    /// The document in [`crate::formatter`] layout: two-space indentation,
+    /// attributes kept as attributes, in source order unless `sort_keys` is
+    /// set. Refused like [`XmlDocument::render`].
+    pub(crate) fn formatted(&self, sort_keys: bool) -> Result<String> {
+        self.check_markup()?;
+        let options = self.options.clone().indent(XmlOptions::default().indent);
+        let unordered = HashMap::new();
+        let shape = Shape {
+            order: if sort_keys { &unordered } else { &self.order },
+            ..self.shape()
+        };
+        write_document(&self.value, &options, Some(shape))
+    }
+}
+
+/// Parse XML into an [`XmlDocument`] that can be written back in the same
+/// shape
+#[cfg(feature = "xml")]
+pub fn parse_document(content: &str) -> Result<XmlDocument> {
+    let mut parser = XmlParser::new(content);
+    let value = parser.parse()?;
+    let options = XmlOptions::new()
+        .declaration(parser.declaration)
+        .indent(detect_indent(content));
+    Ok(XmlDocument {
+        value,
+        attributes: parser.attribute_paths,
+        order: parser.order,
+        options,
+        markup: parser.markup,
+    })
+}
+
+/// Width of the first indented element line, defaulting to two spaces
+#[cfg(feature = "xml")]
+fn detect_indent(content: &str) -> usize {
+    content
+        .lines()
+        .find_map(|line| {
+            let trimmed = line.trim_start_matches(' ');
+            (trimmed.starts_with('<') && trimmed.len() < line.len())
+                .then(|| line.len() - trimmed.len())
+        })
+        .unwrap_or(2)
+}
+
+/// Serialize a value tree as XML with default [`XmlOptions`].
+///
+/// # Errors
+///
+/// See [`serialize_with`].
+#[cfg(feature = "xml")]
+pub fn serialize(value: &Value) -> Result<String> {
+    serialize_with(value, &XmlOptions::default())
+}
+
+/// Serialize a value tree as XML following the mapping in the module docs.
+///
+/// # Errors
+///
+/// Returns [`Error::General`] if the root is not a table, has more than
+/// one key and no [`XmlOptions::root`] is set, or a key is not a valid XML
+/// name.
+#[cfg(feature = "xml")]
+pub fn serialize_with(value: &Value, options: &XmlOptions) -> Result<String> {
+    write_document(value, options, None)
+}
+
+#[cfg(feature = "xml")]
+fn write_document(value: &Value, options: &XmlOptions, shape: Option<Shape<'_>>) -> Result<String> {
+    let table = value.as_table()?;
+    let mut writer = if options.indent > 0 {
+        Writer::new_with_indent(Vec::new(), b' ', options.indent)
+    } else {
+        Writer::new(Vec::new())
+    };
+    if options.declaration {
+        writer.write_event(Event::Decl(BytesDecl::new("1.0", Some("UTF-8"), None)))?;
+    }
+
+    let serializer = XmlSerializer { options, shape };
+    match &options.root {
+        Some(root) => serializer.write_element(&mut writer, root, root, value)?,
+        None => {
+            let mut entries = table.iter();
+            match (entries.next(), entries.next()) {
+                (Some((name, element)), None) => {
+                    serializer.write_element(&mut writer, name, name, element)?;
+                }
+                _ => {
+                    return Err(Error::serialize(
+                        "XML needs a single document element; set XmlOptions::root",
+                    ))
+                }
+            }
         }
     }
+
+    let mut out = String::from_utf8(writer.into_inner())
+        .map_err(|e| Error::serialize(format!("XML output is not UTF-8: {e}")))?;
+    if options.indent > 0 {
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+/// What an [`XmlDocument`] remembers about the source's shape
+#[cfg(feature = "xml")]
+#[derive(Clone, Copy)]
+struct Shape<'d> {
+    /// Dotted paths of the keys that were attributes
+    attributes: &'d BTreeSet<String>,
+    /// Attribute and child element names of each element path, in source
+    /// order
+    order: &'d HashMap<String, Vec<String>>,
+}
+
+#[cfg(feature = "xml")]
+struct XmlSerializer<'o> {
+    options: &'o XmlOptions,
+    shape: Option<Shape<'o>>,
+}
+
+#[cfg(feature = "xml")]
+impl XmlSerializer<'_> {
+    fn write_element(
+        &self,
+        writer: &mut Writer<Vec<u8>>,
+        path: &str,
+        name: &str,
+        value: &Value,
+    ) -> Result<()> {
+        check_name(name)?;
+        match value {
+            Value::Array(items) => {
+                for item in items {
+                    if item.is_array() {
+                        return Err(Error::serialize(format!(
+                            "XML cannot represent nested arrays at <{name}>"
+                        )));
+                    }
+                    self.write_element(writer, path, name, item)?;
+                }
+            }
+            Value::Table(table) => {
+                let mut start = BytesStart::new(name);
+                let mut children = Vec::new();
+                let mut text = None;
+                for (key, child) in self.ordered(path, table) {
+                    if let Some(attribute) = self.attribute_name(path, key, child) {
+                        check_name(attribute)?;
+                        start.push_attribute((attribute, scalar_text(child).as_str()));
+                    } else if key == "text" && !child.is_table() && !child.is_array() {
+                        text = Some(scalar_text(child));
+                    } else {
+                        children.push((key, child));
+                    }
+                }
+                if children.is_empty() && text.is_none() {
+                    writer.write_event(Event::Empty(start))?;
+                } else {
+                    writer.write_event(Event::Start(start))?;
+                    if let Some(text) = text {
+                        writer.write_event(Event::Text(BytesText::new(&text)))?;
+                    }
+                    for (key, child) in children {
+                        self.write_element(writer, &format!("{path}.{key}"), key, child)?;
+                    }
+                    writer.write_event(Event::End(BytesEnd::new(name)))?;
+                }
+            }
+            Value::Null => writer.write_event(Event::Empty(BytesStart::new(name)))?,
+            scalar => {
+                writer.write_event(Event::Start(BytesStart::new(name)))?;
+                writer.write_event(Event::Text(BytesText::new(&scalar_text(scalar))))?;
+                writer.write_event(Event::End(BytesEnd::new(name)))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The entries of the table at `path`: the ones the source had in its
+    /// order, then new ones in key order.
+    fn ordered<'t>(
+        &self,
+        path: &str,
+        table: &'t BTreeMap<String, Value>,
+    ) -> Vec<(&'t String, &'t Value)> {
+        let mut entries: Vec<_> = table.iter().collect();
+        if let Some(names) = self.shape.and_then(|shape| shape.order.get(path)) {
+            entries.sort_by_key(|(key, _)| {
+                names
+                    .iter()
+                    .position(|name| name == *key)
+                    .unwrap_or(names.len())
+            });
+        }
+        entries
+    }
+
+    /// The attribute name for a table entry, or `None` to write an element.
+    fn attribute_name<'k>(&self, path: &str, key: &'k str, value: &Value) -> Option<&'k str> {
+        if let Some(prefix) = &self.options.attribute_prefix {
+            return key.strip_prefix(prefix.as_str());
+        }
+        let scalar = !value.is_table() && !value.is_array() && !value.is_null();
+        if scalar
+            && self
+                .shape
+                .is_some_and(|shape| shape.attributes.contains(&format!("{path}.{key}")))
+        {
+            return Some(key);
+        }
+        match value {
+            Value::String(s) if key != "text" => {
+                let survives = !s.is_empty() && s.trim() == s && typed_text(s) == *value;
+                (!survives).then_some(key)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Text for a scalar; integral floats keep a `.0` so they read back as
+/// floats.
+#[cfg(feature = "xml")]
+fn scalar_text(value: &Value) -> String {
+    match value {
+        Value::Float(f) if f.fract() == 0.0 && f.is_finite() => format!("{f:.1}"),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+/// Element and attribute names must be XML names.
+#[cfg(feature = "xml")]
+fn check_name(name: &str) -> Result<()> {
+    let mut chars = name.chars();
+    let valid = chars
+        .next()
+        .is_some_and(|c| c.is_alphabetic() || c == '_' || c == ':')
+        && chars.all(|c| c.is_alphanumeric() || matches!(c, '_' | ':' | '-' | '.'));
+    if valid {
+        Ok(())
+    } else {
+        Err(Error::serialize(format!(
+            "'{name}' is not a valid XML name"
+        )))
+    }
 }
 
 #[cfg(feature = "xml")]
@@ -179,6 +711,26 @@ pub fn parse_xml(content: &str) -> Result<Value> {
     parser.parse()
 }
 
+/// Where each element starts, for the document outline.
+#[cfg(feature = "xml")]
+pub(crate) fn marks(source: &str) -> Result<Vec<Mark>> {
+    let mut parser = XmlParser::new(source);
+    parser.marks = Some(Vec::new());
+    let value = parser.parse()?;
+    Ok(parser
+        .marks
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|(offset, steps)| locate::resolve(&value, *offset, steps))
+        .collect())
+}
+
+/// Parse XML configuration honouring [`XmlOptions::attribute_prefix`]
+#[cfg(feature = "xml")]
+pub fn parse_with_options(content: &str, options: &XmlOptions) -> Result<Value> {
+    XmlParser::with_options(content, options).parse()
+}
+
 /// Placeholder when XML feature is disabled
 #[cfg(not(feature = "xml"))]
 pub fn parse_xml(_content: &str) -> Result<Value> {
@@ -298,4 +850,141 @@ mod tests {
         assert!(matches!(result, Value::Table(_)));
         Ok(())
     }
+
+    #[test]
+    fn test_repeated_elements_become_arrays() -> crate::Result<()> {
+        let xml = r#"<config><feature name="auth"/><feature name="cache"/><port>1</port></config>"#;
+        let value = parse_xml(xml)?;
+
+        let features = value.get("config.feature").unwrap().as_array()?;
+        assert_eq!(features.len(), 2);
+        assert_eq!(features[0].get("name").unwrap().as_string()?, "auth");
+        assert_eq!(features[1].get("name").unwrap().as_string()?, "cache");
+        assert_eq!(value.get("config.port").unwrap().as_integer()?, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_serialize_round_trip() -> crate::Result<()> {
+        let xml = r#"<config><server host="localhost" port="8080"><name>Main &amp; only</name><ratio>1.0</ratio></server></config>"#;
+        let value = parse_xml(xml)?;
+        let written = serialize(&value)?;
+        assert_eq!(parse_xml(&written)?, value);
+        assert!(written.contains("<host>localhost</host>"));
+        assert!(written.contains(r#"<server port="8080">"#));
+        assert!(written.contains("<name>Main &amp; only</name>"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_document_keeps_attributes() -> crate::Result<()> {
+        let xml = r#"<configuration>
+    <appSettings>
+        <add key="Mode" value="production"/>
+        <add key="Retries" value="3"/>
+    </appSettings>
+    <server host="localhost" port="8080">
+        <empty/>
+        <ssl>true</ssl>
+    </server>
+</configuration>
+"#;
+        let mut document = parse_document(xml)?;
+        assert_eq!(document.render()?, xml);
+
+        let mut edited = document.value().clone();
+        edited.set_nested("configuration.server.port", Value::integer(9090))?;
+        edited.set_nested("configuration.server.timeout", Value::integer(30))?;
+        document.sync(&edited)?;
+        let expected = xml
+            .replace("8080", "9090")
+            .replace("</ssl>", "</ssl>\n        <timeout>30</timeout>");
+        assert_eq!(document.render()?, expected);
+
+        // Accepted by the parser, but there is no single element to write
+        let document = parse_document("<a>1</a><b>2</b>")?;
+        assert!(document.render().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_document_keeps_order_and_refuses_comments() -> crate::Result<()> {
+        let xml = r#"<config>
+  <zone value="eu" name="primary"/>
+  <retries>3</retries>
+  <alpha>a</alpha>
+</config>
+"#;
+        let mut document = parse_document(xml)?;
+        assert_eq!(document.render()?, xml);
+        let mut edited = document.value().clone();
+        edited.set_nested("config.beta", Value::string("b"))?;
+        edited.set_nested("config.retries", Value::integer(5))?;
+        document.sync(&edited)?;
+        assert_eq!(
+            document.render()?,
+            xml.replace(">3<", ">5<")
+                .replace("</config>", "  <beta>b</beta>\n</config>")
+        );
+
+        let commented = parse_document("<config><!-- port --><port>1</port></config>")?;
+        let error = commented.render().unwrap_err().to_string();
+        assert!(error.contains("comments"), "{error}");
+        Ok(())
+    }
+
+    #[test]
+    fn test_serialize_scalars_pick_a_reversible_form() -> crate::Result<()> {
+        let mut server = BTreeMap::new();
+        server.insert("name".to_string(), Value::string("web"));
+        server.insert("port".to_string(), Value::integer(80));
+        server.insert("code".to_string(), Value::string("007"));
+        server.insert("padded".to_string(), Value::string(" x "));
+        server.insert("text".to_string(), Value::string("body"));
+        let mut root = BTreeMap::new();
+        root.insert("server".to_string(), Value::table(server));
+        let value = Value::table(root);
+
+        let written = serialize_with(&value, &XmlOptions::new().declaration(false).indent(0))?;
+        assert_eq!(
+            written,
+            r#"<server code="007" padded=" x ">body<name>web</name><port>80</port></server>"#
+        );
+        assert_eq!(parse_xml(&written)?, value);
+        Ok(())
+    }
+
+    #[test]
+    fn test_serialize_options() -> crate::Result<()> {
+        let options = XmlOptions::new()
+            .root("settings")
+            .indent(4)
+            .declaration(false)
+            .attribute_prefix("@");
+        let xml = "<settings version=\"2\">\n    <debug>false</debug>\n</settings>\n";
+        let value = parse_with_options(xml, &options)?;
+        assert_eq!(value.get("settings.@version").unwrap().as_string()?, "2");
+
+        let inner = value.get("settings").unwrap().clone();
+        assert_eq!(serialize_with(&inner, &options)?, xml);
+        Ok(())
+    }
+
+    #[test]
+    fn test_serialize_rejects_unrepresentable_values() {
+        let mut two_roots = BTreeMap::new();
+        two_roots.insert("a".to_string(), Value::integer(1));
+        two_roots.insert("b".to_string(), Value::integer(2));
+        assert!(serialize(&Value::table(two_roots.clone())).is_err());
+        assert!(serialize_with(&Value::table(two_roots), &XmlOptions::new().root("r")).is_ok());
+
+        let mut bad_name = BTreeMap::new();
+        bad_name.insert("1st".to_string(), Value::integer(1));
+        assert!(serialize_with(&Value::table(bad_name), &XmlOptions::new().root("r")).is_err());
+
+        let nested = Value::array(vec![Value::array(vec![Value::integer(1)])]);
+        let mut root = BTreeMap::new();
+        root.insert("list".to_string(), nested);
+        assert!(serialize_with(&Value::table(root), &XmlOptions::new().root("r")).is_err());
+    }
 }
@@ -0,0 +1,850 @@
+//! # YAML Format Parser
+//!
+//! YAML 1.2 support built on the `yaml-rust2` event parser.
+//!
+//! - Anchors and aliases are resolved (aliases are copies of the anchored
+//!   node), including `<<` merge keys, where explicit keys win over merged
+//!   ones and earlier merge sources win over later ones.
+//! - Plain scalars resolve with the YAML 1.2 core schema: `null` / `~` /
+//!   empty, `true` / `false`, decimal, `0o` and `0x` integers, floats and
+//!   `.inf` / `.nan`. `yes`, `no`, `on` and `off` stay strings. Quoted
+//!   scalars are always strings.
+//! - Tagged scalars follow their tag: `!!str`, `!!int`, `!!float`,
+//!   `!!bool`, `!!null`, and `!!timestamp` (a [`Value::DateTime`] with the
+//!   `chrono` feature). Application tags such as `!Ref` keep the scalar
+//!   text as a string; collection tags are ignored.
+//! - Mapping keys must be scalars and are kept as written.
+//! - A stream with several documents parses to an array of documents; use
+//!   [`parse_documents`] to always get one value per document.
+//!
+//! ```rust,ignore
+//! let value = config_lib::parsers::yaml_parser::parse("server:\n  port: 8080\n")?;
+//! assert_eq!(value.get("server.port").unwrap().as_integer()?, 8080);
+//! ```
+//!
+//! [`parse_documents`]: crate::parsers::yaml_parser::parse_documents
+
+use super::locate::Mark;
+use crate::error::{Error, Result};
+use crate::path;
+use crate::value::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write as _;
+use yaml_rust2::parser::{Event, Parser, Tag};
+use yaml_rust2::scanner::{Marker, TScalarStyle};
+
+/// Deepest nesting accepted, matching `serde_json`'s recursion limit
+const MAX_DEPTH: usize = 128;
+
+/// Most nodes alias expansion may copy, so a small "billion laughs"
+/// document cannot exhaust memory
+const MAX_ALIAS_NODES: usize = 1_000_000;
+
+/// The core-schema tag prefix `!!` expands to
+const CORE_TAG: &str = "tag:yaml.org,2002:";
+
+/// Parse YAML format configuration.
+///
+/// An empty stream (or a single empty document) yields an empty table, a
+/// single document yields its root node, and several documents yield an
+/// array with one element per document.
+///
+/// # Errors
+///
+/// Returns [`Error::Parse`] for malformed YAML, duplicate keys, non-scalar
+/// keys, scalars that do not match their tag, and documents nested deeper
+/// than 128 levels or expanding aliases beyond a million nodes.
+pub fn parse(source: &str) -> Result<Value> {
+    let mut documents = parse_documents(source)?;
+    Ok(match documents.len() {
+        0 => Value::table(BTreeMap::new()),
+        1 => match documents.remove(0) {
+            Value::Null => Value::table(BTreeMap::new()),
+            document => document,
+        },
+        _ => Value::array(documents),
+    })
+}
+
+/// Parse a YAML stream into one value per document.
+///
+/// # Errors
+///
+/// See [`parse`].
+pub fn parse_documents(source: &str) -> Result<Vec<Value>> {
+    Ok(build(source, Builder::default())?.documents)
+}
+
+/// Where each key and sequence element starts, for the document outline.
+/// Keys brought in by `<<` merge keys have no place of their own.
+pub(crate) fn marks(source: &str) -> Result<Vec<Mark>> {
+    let builder = build(
+        source,
+        Builder {
+            marks: Some(Vec::new()),
+            ..Builder::default()
+        },
+    )?;
+    let several = builder.documents.len() > 1;
+    // Markers count characters; marks hold byte offsets
+    let mut chars = source
+        .char_indices()
+        .map(|(offset, _)| offset)
+        .enumerate()
+        .peekable();
+    let mut marks = Vec::new();
+    for (document, mark) in builder.marks.unwrap_or_default() {
+        while chars.peek().is_some_and(|&(index, _)| index < mark.offset) {
+            chars.next();
+        }
+        let offset = chars.peek().map_or(source.len(), |&(_, offset)| offset);
+        let within = |path: &str| {
+            if !several {
+                path.to_string()
+            } else if path.is_empty() || path.starts_with('[') {
+                format!("[{document}]{path}")
+            } else {
+                format!("[{document}].{path}")
+            }
+        };
+        marks.push(Mark {
+            offset,
+            scope: within(&mark.scope),
+            path: within(&mark.path),
+            ..mark
+        });
+    }
+    Ok(marks)
+}
+
+/// Feed the events of `source` to `builder`.
+fn build(source: &str, mut builder: Builder) -> Result<Builder> {
+    let mut parser = Parser::new_from_str(source);
+    // Pull events one at a time rather than through `Parser::load`, which
+    // recurses once per nesting level before the depth limit can stop it
+    loop {
+        let (event, mark) = parser
+            .next_token()
+            .map_err(|e| position_error(e.info(), *e.marker()))?;
+        if event == Event::StreamEnd {
+            return Ok(builder);
+        }
+        builder
+            .handle(event, mark.index())
+            .map_err(|message| position_error(message, mark))?;
+    }
+}
+
+fn position_error(message: impl Into<String>, mark: Marker) -> Error {
+    Error::parse(message, mark.line(), mark.col() + 1)
+}
+
+/// A collection being built
+enum Frame {
+    Sequence {
+        items: Vec<Value>,
+        anchor: usize,
+    },
+    Mapping {
+        entries: BTreeMap<String, Value>,
+        /// The key awaiting its value; `None` while reading a key
+        key: Option<Key>,
+        merges: Vec<Value>,
+        anchor: usize,
+    },
+}
+
+enum Key {
+    Name(String),
+    /// A plain `<<`
+    Merge,
+}
+
+/// Turns parser events into [`Value`]s
+#[derive(Default)]
+struct Builder {
+    documents: Vec<Value>,
+    stack: Vec<Frame>,
+    root: Option<Value>,
+    anchors: HashMap<usize, Value>,
+    alias_nodes: usize,
+    /// The outline, when asked for: each mark with its document, at a
+    /// character offset and with paths inside the document
+    marks: Option<Vec<(usize, Mark)>>,
+    /// Path of each open collection; `None` below a `<<` merge key
+    paths: Vec<Option<String>>,
+}
+
+impl Builder {
+    /// `offset` is where the event starts, in characters.
+    fn handle(&mut self, event: Event, offset: usize) -> std::result::Result<(), String> {
+        if self.marks.is_some() {
+            self.mark(&event, offset);
+        }
+        match event {
+            Event::DocumentEnd => {
+                let root = self.root.take().unwrap_or(Value::Null);
+                self.documents.push(root);
+                self.anchors.clear();
+            }
+            Event::SequenceStart(anchor, _) => {
+                self.open(Frame::Sequence {
+                    items: Vec::new(),
+                    anchor,
+                })?;
+            }
+            Event::MappingStart(anchor, _) => {
+                self.open(Frame::Mapping {
+                    entries: BTreeMap::new(),
+                    key: None,
+                    merges: Vec::new(),
+                    anchor,
+                })?;
+            }
+            Event::SequenceEnd | Event::MappingEnd => {
+                let (value, anchor) = match self.stack.pop() {
+                    Some(Frame::Sequence { items, anchor }) => (Value::array(items), anchor),
+                    Some(Frame::Mapping {
+                        mut entries,
+                        merges,
+                        anchor,
+                        ..
+                    }) => {
+                        for source in merges {
+                            merge_into(&mut entries, source)?;
+                        }
+                        (Value::table(entries), anchor)
+                    }
+                    None => return Err("unbalanced collection end".to_string()),
+                };
+                self.node(value, anchor)?;
+            }
+            Event::Scalar(text, style, anchor, tag) => {
+                if let Some(Frame::Mapping {
+                    key: key @ None, ..
+                }) = self.stack.last_mut()
+                {
+                    *key = Some(
+                        if style == TScalarStyle::Plain && tag.is_none() && text == "<<" {
+                            Key::Merge
+                        } else {
+                            Key::Name(text.clone())
+                        },
+                    );
+                    if anchor > 0 {
+                        self.anchors.insert(anchor, Value::string(text));
+                    }
+                    return Ok(());
+                }
+                let value = scalar(&text, style, tag.as_ref())?;
+                self.node(value, anchor)?;
+            }
+            Event::Alias(id) => {
+                let value = self
+                    .anchors
+                    .get(&id)
+                    .cloned()
+                    .ok_or("alias refers to an unknown anchor")?;
+                self.alias_nodes += node_count(&value);
+                if self.alias_nodes > MAX_ALIAS_NODES {
+                    return Err("aliases expand to too many nodes".to_string());
+                }
+                if let Some(Frame::Mapping {
+                    key: key @ None, ..
+                }) = self.stack.last_mut()
+                {
+                    *key = Some(Key::Name(scalar_key(&value)?));
+                    return Ok(());
+                }
+                self.node(value, 0)?;
+            }
+            Event::Nothing | Event::StreamStart | Event::StreamEnd | Event::DocumentStart => {}
+        }
+        Ok(())
+    }
+
+    fn open(&mut self, frame: Frame) -> std::result::Result<(), String> {
+        if let Some(Frame::Mapping { key: None, .. }) = self.stack.last() {
+            return Err("mapping keys must be scalars".to_string());
+        }
+        if self.stack.len() >= MAX_DEPTH {
+            return Err(format!("nesting deeper than {MAX_DEPTH} levels"));
+        }
+        if self.marks.is_some() {
+            let path = self.child_path();
+            self.paths.push(path);
+        }
+        self.stack.push(frame);
+        Ok(())
+    }
+
+    /// Path of the node about to be attached to the open collection.
+    fn child_path(&self) -> Option<String> {
+        let parent = match self.paths.last() {
+            None => return Some(String::new()),
+            Some(parent) => parent.as_deref()?,
+        };
+        match self.stack.last()? {
+            Frame::Sequence { items, .. } => Some(path::join_index(parent, items.len())),
+            Frame::Mapping {
+                key: Some(Key::Name(name)),
+                ..
+            } => Some(path::join_key(parent, name)),
+            Frame::Mapping { .. } => None,
+        }
+    }
+
+    /// Record the key or sequence element `event` starts, if any.
+    fn mark(&mut self, event: &Event, offset: usize) {
+        let document = self.documents.len();
+        let key = match event {
+            Event::Scalar(text, style, _, tag) => {
+                let merge = *style == TScalarStyle::Plain && tag.is_none() && text == "<<";
+                (!merge).then(|| text.clone())
+            }
+            Event::Alias(id) => self.anchors.get(id).and_then(|v| scalar_key(v).ok()),
+            Event::SequenceStart(..) | Event::MappingStart(..) => None,
+            Event::SequenceEnd | Event::MappingEnd => {
+                self.paths.pop();
+                return;
+            }
+            _ => return,
+        };
+        let Some(Some(scope)) = self.paths.last().cloned() else {
+            return;
+        };
+        let mark = match self.stack.last() {
+            Some(Frame::Sequence { items, .. }) => {
+                Mark::element(offset, &scope, path::join_index(&scope, items.len()))
+            }
+            Some(Frame::Mapping { key: None, .. }) => {
+                let Some(key) = key else { return };
+                let path = path::join_key(&scope, &key);
+                Mark::entry(offset, &scope, &key, path)
+            }
+            _ => return,
+        };
+        if let Some(marks) = &mut self.marks {
+            marks.push((document, mark));
+        }
+    }
+
+    /// Attach a finished node to its parent (or make it the root).
+    fn node(&mut self, value: Value, anchor: usize) -> std::result::Result<(), String> {
+        if anchor > 0 {
+            self.anchors.insert(anchor, value.clone());
+        }
+        match self.stack.last_mut() {
+            None => self.root = Some(value),
+            Some(Frame::Sequence { items, .. }) => items.push(value),
+            Some(Frame::Mapping {
+                entries,
+                key,
+                merges,
+                ..
+            }) => match key.take() {
+                Some(Key::Merge) => match value {
+                    Value::Array(sources) => merges.extend(sources),
+                    source => merges.push(source),
+                },
+                Some(Key::Name(name)) => {
+                    if entries.contains_key(&name) {
+                        return Err(format!("duplicate key `{name}`"));
+                    }
+                    entries.insert(name, value);
+                }
+                None => return Err("mapping keys must be scalars".to_string()),
+            },
+        }
+        Ok(())
+    }
+}
+
+/// Add the entries of a `<<` source that the mapping does not define.
+fn merge_into(
+    entries: &mut BTreeMap<String, Value>,
+    source: Value,
+) -> std::result::Result<(), String> {
+    let Value::Table(source) = source else {
+        return Err("merge key `<<` needs a mapping or a list of mappings".to_string());
+    };
+    for (key, value) in source {
+        entries.entry(key).or_insert(value);
+    }
+    Ok(())
+}
+
+fn node_count(value: &Value) -> usize {
+    match value {
+        Value::Array(items) => 1 + items.iter().map(node_count).sum::<usize>(),
+        Value::Table(table) => 1 + table.values().map(node_count).sum::<usize>(),
+        _ => 1,
+    }
+}
+
+fn scalar_key(value: &Value) -> std::result::Result<String, String> {
+    match value {
+        Value::String(s) => Ok(s.clone()),
+        Value::Array(_) | Value::Table(_) => Err("mapping keys must be scalars".to_string()),
+        Value::Null => Ok(String::new()),
+        other => Ok(other.to_string()),
+    }
+}
+
+/// Resolve a scalar from its text, style and tag.
+fn scalar(
+    text: &str,
+    style: TScalarStyle,
+    tag: Option<&Tag>,
+) -> std::result::Result<Value, String> {
+    let Some(tag) = tag else {
+        return Ok(if style == TScalarStyle::Plain {
+            resolve_plain(text)
+        } else {
+            Value::string(text)
+        });
+    };
+    if tag.handle != CORE_TAG {
+        return Ok(Value::string(text));
+    }
+    let resolved = resolve_plain(text);
+    let mismatch = || format!("`{text}` is not a valid !!{}", tag.suffix);
+    match tag.suffix.as_str() {
+        "int" => match resolved {
+            Value::Integer(_) => Ok(resolved),
+            _ => Err(mismatch()),
+        },
+        "float" => match resolved {
+            Value::Float(_) => Ok(resolved),
+            #[allow(clippy::cast_precision_loss)]
+            Value::Integer(i) => Ok(Value::float(i as f64)),
+            _ => Err(mismatch()),
+        },
+        "bool" => match resolved {
+            Value::Bool(_) => Ok(resolved),
+            _ => Err(mismatch()),
+        },
+        "null" => match resolved {
+            Value::Null => Ok(resolved),
+            _ => Err(mismatch()),
+        },
+        "timestamp" => timestamp(text).ok_or_else(mismatch),
+        _ => Ok(Value::string(text)),
+    }
+}
+
+/// YAML 1.2 core schema resolution for untagged plain scalars
+fn resolve_plain(text: &str) -> Value {
+    match text {
+        "" | "~" | "null" | "Null" | "NULL" => return Value::Null,
+        "true" | "True" | "TRUE" => return Value::bool(true),
+        "false" | "False" | "FALSE" => return Value::bool(false),
+        ".inf" | ".Inf" | ".INF" | "+.inf" | "+.Inf" | "+.INF" => {
+            return Value::float(f64::INFINITY)
+        }
+        "-.inf" | "-.Inf" | "-.INF" => return Value::float(f64::NEG_INFINITY),
+        ".nan" | ".NaN" | ".NAN" => return Value::float(f64::NAN),
+        _ => {}
+    }
+
+    let radix = |prefix: &str, radix: u32| {
+        text.strip_prefix(prefix)
+            .filter(|digits| !digits.is_empty() && digits.chars().all(|c| c.is_digit(radix)))
+            .and_then(|digits| i64::from_str_radix(digits, radix).ok())
+    };
+    if let Some(i) = radix("0o", 8).or_else(|| radix("0x", 16)) {
+        return Value::integer(i);
+    }
+
+    let unsigned = text.strip_prefix(['-', '+']).unwrap_or(text);
+    let (mantissa, exponent) = match unsigned.find(['e', 'E']) {
+        Some(at) => (&unsigned[..at], Some(&unsigned[at + 1..])),
+        None => (unsigned, None),
+    };
+    let digits = |s: &str| s.bytes().all(|b| b.is_ascii_digit());
+    let (integral, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let numeric = digits(integral)
+        && digits(fraction)
+        && (!integral.is_empty() || !fraction.is_empty())
+        && exponent.map_or(true, |e| {
+            let e = e.strip_prefix(['-', '+']).unwrap_or(e);
+            !e.is_empty() && digits(e)
+        });
+    if numeric {
+        if !mantissa.contains('.') && exponent.is_none() {
+            if let Ok(i) = text.parse::<i64>() {
+                return Value::integer(i);
+            }
+        }
+        if let Ok(f) = text.parse::<f64>() {
+            return Value::float(f);
+        }
+    }
+    Value::string(text)
+}
+
+#[cfg(feature = "chrono")]
+fn timestamp(text: &str) -> Option<Value> {
+    use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+
+    let normalized = text.trim().replacen([' ', 't'], "T", 1);
+    if let Ok(dt) = DateTime::parse_from_rfc3339(&normalized) {
+        return Some(Value::datetime(dt.with_timezone(&Utc)));
+    }
+    if let Ok(naive) = NaiveDateTime::parse_from_str(&normalized, "%Y-%m-%dT%H:%M:%S%.f") {
+        return Some(Value::datetime(naive.and_utc()));
+    }
+    NaiveDate::parse_from_str(text.trim(), "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .map(|naive| Value::datetime(naive.and_utc()))
+}
+
+#[cfg(not(feature = "chrono"))]
+fn timestamp(text: &str) -> Option<Value> {
+    let bytes = text.as_bytes();
+    let date_like = bytes.len() >= 10 && bytes[4] == b'-' && bytes[7] == b'-';
+    date_like.then(|| Value::string(text))
+}
+
+/// Serialize a value tree as block-style YAML.
+///
+/// Tables become mappings and arrays sequences (empty ones are written as
+/// `{}` / `[]`). Strings are written plain when they would read back as the
+/// same string, as `|` literal blocks when they span lines, and double
+/// quoted otherwise. Floats always read back as floats (`.inf`, `.nan`
+/// for the special values) and datetimes are written as `!!timestamp`.
+/// Parsing the output with [`parse`] yields the input value.
+///
+/// # Errors
+///
+/// Currently infallible; returns [`Result`] for parity with the other
+/// serializers.
+pub fn serialize(value: &Value) -> Result<String> {
+    let mut out = String::new();
+    match value {
+        Value::Table(table) if !table.is_empty() => write_mapping(&mut out, table, 0, false),
+        Value::Array(items) if !items.is_empty() => write_sequence(&mut out, items, 0, false),
+        scalar => {
+            write_scalar(&mut out, scalar, 0);
+        }
+    }
+    Ok(out)
+}
+
+/// Serialize one value per document, separated by `---`.
+///
+/// # Errors
+///
+/// See [`serialize`].
+pub fn serialize_documents(documents: &[Value]) -> Result<String> {
+    let mut out = String::new();
+    for document in documents {
+        out.push_str("---\n");
+        out.push_str(&serialize(document)?);
+    }
+    Ok(out)
+}
+
+fn pad(out: &mut String, indent: usize) {
+    out.extend(std::iter::repeat(' ').take(indent));
+}
+
+/// Write mapping entries at `indent`; with `inline_first` the first entry
+/// continues the current line (after `- `).
+fn write_mapping(
+    out: &mut String,
+    table: &BTreeMap<String, Value>,
+    indent: usize,
+    inline_first: bool,
+) {
+    for (index, (key, value)) in table.iter().enumerate() {
+        if index > 0 || !inline_first {
+            pad(out, indent);
+        }
+        write_string(out, key, None);
+        out.push(':');
+        match value {
+            Value::Table(child) if !child.is_empty() => {
+                out.push('\n');
+                write_mapping(out, child, indent + 2, false);
+            }
+            Value::Array(items) if !items.is_empty() => {
+                out.push('\n');
+                write_sequence(out, items, indent + 2, false);
+            }
+            scalar => {
+                out.push(' ');
+                write_scalar(out, scalar, indent + 2);
+            }
+        }
+    }
+}
+
+fn write_sequence(out: &mut String, items: &[Value], indent: usize, inline_first: bool) {
+    for (index, item) in items.iter().enumerate() {
+        if index > 0 || !inline_first {
+            pad(out, indent);
+        }
+        out.push_str("- ");
+        match item {
+            Value::Table(table) if !table.is_empty() => {
+                write_mapping(out, table, indent + 2, true);
+            }
+            Value::Array(nested) if !nested.is_empty() => {
+                write_sequence(out, nested, indent + 2, true);
+            }
+            scalar => write_scalar(out, scalar, indent + 2),
+        }
+    }
+}
+
+/// Write a scalar (or empty collection) and end the line; block strings
+/// indent their content by `indent`.
+fn write_scalar(out: &mut String, value: &Value, indent: usize) {
+    match value {
+        Value::Null => out.push_str("null"),
+        Value::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        Value::Integer(i) => {
+            let _ = write!(out, "{i}");
+        }
+        Value::Float(f) if f.is_nan() => out.push_str(".nan"),
+        Value::Float(f) if f.is_infinite() => {
+            out.push_str(if *f > 0.0 { ".inf" } else { "-.inf" });
+        }
+        Value::Float(f) if f.fract() == 0.0 => {
+            let _ = write!(out, "{f:.1}");
+        }
+        Value::Float(f) => {
+            let _ = write!(out, "{f}");
+        }
+        Value::String(s) => write_string(out, s, Some(indent)),
+        Value::Array(_) => out.push_str("[]"),
+        Value::Table(_) => out.push_str("{}"),
+        #[cfg(feature = "chrono")]
+        Value::DateTime(dt) => {
+            let _ = write!(out, "!!timestamp {}", dt.to_rfc3339());
+        }
+    }
+    if !out.ends_with('\n') {
+        out.push('\n');
+    }
+}
+
+/// Write a string plain, as a literal block (when `block_indent` is given
+/// and the text spans lines), or double quoted.
+fn write_string(out: &mut String, s: &str, block_indent: Option<usize>) {
+    if is_plain_safe(s) {
+        out.push_str(s);
+    } else if let Some(indent) = block_indent.filter(|_| is_block_safe(s)) {
+        let body = s.trim_end_matches('\n');
+        let trailing = s.len() - body.len();
+        out.push_str(match trailing {
+            0 => "|-\n",
+            1 => "|\n",
+            _ => "|+\n",
+        });
+        for line in body.split('\n') {
+            if !line.is_empty() {
+                pad(out, indent);
+                out.push_str(line);
+            }
+            out.push('\n');
+        }
+        for _ in 1..trailing {
+            out.push('\n');
+        }
+    } else {
+        out.push('"');
+        for ch in s.chars() {
+            match ch {
+                '"' => out.push_str("\\\""),
+                '\\' => out.push_str("\\\\"),
+                '\n' => out.push_str("\\n"),
+                '\t' => out.push_str("\\t"),
+                '\r' => out.push_str("\\r"),
+                '\0' => out.push_str("\\0"),
+                c if needs_escape(c) && u32::from(c) < 0x100 => {
+                    let _ = write!(out, "\\x{:02X}", u32::from(c));
+                }
+                c if needs_escape(c) => {
+                    let _ = write!(out, "\\u{:04X}", u32::from(c));
+                }
+                c => out.push(c),
+            }
+        }
+        out.push('"');
+    }
+}
+
+fn needs_escape(c: char) -> bool {
+    c.is_control() || matches!(c, '\u{feff}' | '\u{2028}' | '\u{2029}')
+}
+
+/// Whether `s` reads back unchanged as an untagged plain scalar.
+fn is_plain_safe(s: &str) -> bool {
+    let (Some(first), Some(last)) = (s.chars().next(), s.chars().last()) else {
+        return false;
+    };
+    matches!(resolve_plain(s), Value::String(_))
+        && !"-?:,[]{}#&*!|>'\"%@`".contains(first)
+        && !first.is_whitespace()
+        && !last.is_whitespace()
+        && last != ':'
+        && !s.starts_with("...")
+        && !s.contains(": ")
+        && !s.contains(":\t")
+        && !s.contains(" #")
+        && !s.contains("\t#")
+        && !s.chars().any(|c| needs_escape(c) || c == '\t')
+}
+
+/// Whether a multi-line `s` can be written as a `|` literal block.
+fn is_block_safe(s: &str) -> bool {
+    let first_line = s.trim_start_matches('\n');
+    s.contains('\n')
+        && !first_line.starts_with([' ', '\t'])
+        && !s.chars().any(|c| c != '\n' && c != '\t' && needs_escape(c))
+        && s.split('\n')
+            .all(|line| line.is_empty() || !line.trim().is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kubernetes_style_document() {
+        let value = parse(
+            "apiVersion: apps/v1\n\
+             kind: Deployment\n\
+             metadata:\n  name: web\n  labels: {app: web, tier: \"1\"}\n\
+             spec:\n  replicas: 3\n  ports:\n  - containerPort: 80\n    protocol: TCP\n  - 443\n\
+             enabled: yes\n",
+        )
+        .unwrap();
+        assert_eq!(value.get("kind"), Some(&Value::string("Deployment")));
+        assert_eq!(value.get("metadata.labels.tier"), Some(&Value::string("1")));
+        assert_eq!(value.get("spec.replicas"), Some(&Value::integer(3)));
+        assert_eq!(
+            value.get("spec.ports[0].containerPort"),
+            Some(&Value::integer(80))
+        );
+        assert_eq!(value.get("spec.ports[1]"), Some(&Value::integer(443)));
+        assert_eq!(value.get("enabled"), Some(&Value::string("yes")));
+    }
+
+    #[test]
+    fn test_anchors_aliases_and_merge_keys() {
+        let value = parse(
+            "defaults: &defaults\n  adapter: postgres\n  pool: 5\n\
+             development:\n  <<: *defaults\n  pool: 10\n\
+             hosts: &hosts [a, b]\n\
+             mirror: *hosts\n",
+        )
+        .unwrap();
+        assert_eq!(
+            value.get("development.adapter"),
+            Some(&Value::string("postgres"))
+        );
+        assert_eq!(value.get("development.pool"), Some(&Value::integer(10)));
+        assert_eq!(value.get("mirror"), value.get("hosts"));
+    }
+
+    #[test]
+    fn test_tags_and_core_schema() {
+        let value = parse(
+            "a: !!str 42\nb: !!float 1\nc: \"true\"\nd: 0x1F\ne: .inf\nf: ~\ng: !Ref Bucket\n\
+             h: 1e3\ni: 1_000\n",
+        )
+        .unwrap();
+        assert_eq!(value.get("a"), Some(&Value::string("42")));
+        assert_eq!(value.get("b"), Some(&Value::float(1.0)));
+        assert_eq!(value.get("c"), Some(&Value::string("true")));
+        assert_eq!(value.get("d"), Some(&Value::integer(31)));
+        assert_eq!(value.get("e"), Some(&Value::float(f64::INFINITY)));
+        assert_eq!(value.get("f"), Some(&Value::Null));
+        assert_eq!(value.get("g"), Some(&Value::string("Bucket")));
+        assert_eq!(value.get("h"), Some(&Value::float(1000.0)));
+        assert_eq!(value.get("i"), Some(&Value::string("1_000")));
+        assert!(parse("a: !!int nope\n").is_err());
+
+        #[cfg(feature = "chrono")]
+        for stamp in [
+            "2001-12-14 21:59:43.10-05:00",
+            "2001-12-14t21:59:43Z",
+            "2002-12-14",
+        ] {
+            let value = parse(&format!("at: !!timestamp {stamp}\n")).unwrap();
+            assert!(
+                matches!(value.get("at"), Some(Value::DateTime(_))),
+                "{stamp}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_multi_document_streams() {
+        let stream = "---\nname: a\n---\nname: b\n";
+        let documents = parse_documents(stream).unwrap();
+        assert_eq!(documents.len(), 2);
+        assert_eq!(documents[1].get("name"), Some(&Value::string("b")));
+        assert_eq!(parse(stream).unwrap(), Value::array(documents.clone()));
+        assert_eq!(
+            parse_documents(&serialize_documents(&documents).unwrap()).unwrap(),
+            documents
+        );
+        assert_eq!(
+            parse("# only a comment\n").unwrap(),
+            Value::table(BTreeMap::new())
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let cases = [
+            ("a: 1\na: 2\n", 2),
+            ("a: [1, 2\n", 2),
+            ("? [x]\n: 1\n", 1),
+            ("a: *missing\n", 1),
+        ];
+        for (source, line) in cases {
+            match parse(source) {
+                Err(Error::Parse { line: l, .. }) => assert_eq!(l, line, "{source:?}"),
+                other => panic!("{source:?} parsed as {other:?}"),
+            }
+        }
+
+        let laughs = "a: &a [x, x, x, x, x, x, x, x, x, x]\n\
+                      b: &b [*a, *a, *a, *a, *a, *a, *a, *a, *a, *a]\n\
+                      c: &c [*b, *b, *b, *b, *b, *b, *b, *b, *b, *b]\n\
+                      d: &d [*c, *c, *c, *c, *c, *c, *c, *c, *c, *c]\n\
+                      e: &e [*d, *d, *d, *d, *d, *d, *d, *d, *d, *d]\n\
+                      f: [*e, *e, *e, *e, *e, *e, *e, *e, *e, *e]\n";
+        assert!(parse(laughs).is_err());
+        assert!(parse(&"[".repeat(200)).is_err());
+        assert!(parse(&"- ".repeat(5000)).is_err());
+    }
+
+    #[test]
+    fn test_serialize_round_trip() {
+        let value = parse(
+            "name: demo\nempty: ''\nnumeric: '8080'\nflag: 'yes'\nratio: 2.0\nnothing: null\n\
+             spaced: ' padded '\ncolon: 'a: b'\nquote: \"it's \\\"quoted\\\"\"\n\
+             script: |\n  echo one\n    indented\n\n  echo two\n\
+             control: \"bell\\a\"\n\
+             nested:\n  list:\n  - a: 1\n    b: [x, y]\n  - - 1\n    - 2\n  - {}\n  - []\n\
+             'key with: colon': 1\n",
+        )
+        .unwrap();
+        let written = serialize(&value).unwrap();
+        assert_eq!(parse(&written).unwrap(), value, "{written}");
+        assert!(written.contains("name: demo\n"), "{written}");
+        assert!(
+            written.contains("script: |\n  echo one\n    indented\n\n  echo two\n"),
+            "{written}"
+        );
+        assert!(written.contains("numeric: \"8080\"\n"), "{written}");
+        assert!(written.contains("\"key with: colon\": 1\n"), "{written}");
+    }
+}
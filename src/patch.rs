@@ -0,0 +1,634 @@
+//! # JSON Patch and Merge Patch
+//!
+//! [RFC 6902] JSON Patch and [RFC 7396] JSON Merge Patch over [`Value`]
+//! trees, with [RFC 6901] JSON Pointer addressing.
+//!
+//! [`PatchOp`] implements serde, so a patch document can be read with any
+//! serde format or straight from a parsed [`Value`] with [`parse`].
+//! Patches apply atomically: if any operation fails, the target is left
+//! unchanged. [`Config::apply_patch`](crate::Config::apply_patch) and
+//! [`Config::apply_merge_patch`](crate::Config::apply_merge_patch) add the
+//! read-only check and cache invalidation on top.
+//!
+//! ```rust
+//! use config_lib::patch::{self, PatchOp};
+//! use config_lib::Value;
+//!
+//! # fn main() -> Result<(), config_lib::Error> {
+//! let mut config = Value::table(Default::default());
+//! config.set_nested("server.port", Value::integer(80))?;
+//!
+//! config.apply_patch(&[
+//!     PatchOp::Test { path: "/server/port".into(), value: Value::integer(80) },
+//!     PatchOp::Replace { path: "/server/port".into(), value: Value::integer(8080) },
+//!     PatchOp::Add { path: "/server/hosts".into(), value: Value::array(vec![]) },
+//!     PatchOp::Add { path: "/server/hosts/-".into(), value: Value::string("a") },
+//! ])?;
+//! assert_eq!(config.pointer("/server/hosts/0"), Some(&Value::string("a")));
+//!
+//! // Generate the patch that turns one tree into another.
+//! let target = Value::table(Default::default());
+//! let ops = patch::diff_patch(&config, &target)?;
+//! config.apply_patch(&ops)?;
+//! assert_eq!(config, target);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [RFC 6902]: https://www.rfc-editor.org/rfc/rfc6902
+//! [RFC 7396]: https://www.rfc-editor.org/rfc/rfc7396
+//! [RFC 6901]: https://www.rfc-editor.org/rfc/rfc6901
+//! [`PatchOp`]: crate::patch::PatchOp
+
+use crate::diff::{Change, ChangeKind};
+use crate::error::{Error, Result};
+use crate::merge::MergeOptions;
+use crate::path::{self, PathSegment};
+use crate::value::Value;
+use serde::{Deserialize, Serialize};
+
+/// One JSON Patch operation. Paths are JSON Pointers (`/a/0/b`).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+#[non_exhaustive]
+pub enum PatchOp {
+    /// Insert `value` at `path` (array index, `-` to append, or table key).
+    Add {
+        /// Target pointer
+        path: String,
+        /// Value to insert
+        value: Value,
+    },
+    /// Remove the value at `path`.
+    Remove {
+        /// Target pointer
+        path: String,
+    },
+    /// Replace the existing value at `path`.
+    Replace {
+        /// Target pointer
+        path: String,
+        /// Replacement value
+        value: Value,
+    },
+    /// Remove the value at `from` and add it at `path`.
+    Move {
+        /// Source pointer
+        from: String,
+        /// Target pointer
+        path: String,
+    },
+    /// Add a copy of the value at `from` at `path`.
+    Copy {
+        /// Source pointer
+        from: String,
+        /// Target pointer
+        path: String,
+    },
+    /// Fail the patch unless the value at `path` equals `value`
+    /// (integers and floats compare numerically).
+    Test {
+        /// Target pointer
+        path: String,
+        /// Expected value
+        value: Value,
+    },
+}
+
+impl PatchOp {
+    /// The operation's target pointer.
+    pub fn path(&self) -> &str {
+        match self {
+            PatchOp::Add { path, .. }
+            | PatchOp::Remove { path }
+            | PatchOp::Replace { path, .. }
+            | PatchOp::Move { path, .. }
+            | PatchOp::Copy { path, .. }
+            | PatchOp::Test { path, .. } => path,
+        }
+    }
+}
+
+/// Read a patch document (an array of operation tables).
+///
+/// # Errors
+///
+/// Returns [`Error::Deserialize`] if the document is not a valid patch.
+pub fn parse(document: &Value) -> Result<Vec<PatchOp>> {
+    crate::de::from_value(document)
+}
+
+/// Split a JSON Pointer into unescaped reference tokens.
+///
+/// # Errors
+///
+/// Returns [`Error::Parse`] if the pointer is neither empty nor starts
+/// with `/`, or contains an invalid `~` escape.
+pub fn parse_pointer(pointer: &str) -> Result<Vec<String>> {
+    if pointer.is_empty() {
+        return Ok(Vec::new());
+    }
+    let Some(rest) = pointer.strip_prefix('/') else {
+        return Err(pointer_error(pointer, "must be empty or start with '/'"));
+    };
+    rest.split('/')
+        .map(|token| {
+            let mut out = String::with_capacity(token.len());
+            let mut chars = token.chars();
+            while let Some(ch) = chars.next() {
+                if ch == '~' {
+                    match chars.next() {
+                        Some('0') => out.push('~'),
+                        Some('1') => out.push('/'),
+                        _ => return Err(pointer_error(pointer, "invalid '~' escape")),
+                    }
+                } else {
+                    out.push(ch);
+                }
+            }
+            Ok(out)
+        })
+        .collect()
+}
+
+/// Convert a [`crate::path`] path into a JSON Pointer.
+///
+/// # Errors
+///
+/// Returns [`Error::Parse`] if the path is malformed or uses a negative
+/// index, which JSON Pointer cannot express.
+pub fn path_to_pointer(path: &str) -> Result<String> {
+    let mut pointer = String::new();
+    for segment in path::parse(path)? {
+        pointer.push('/');
+        match segment {
+            PathSegment::Key(key) => pointer.push_str(&key.replace('~', "~0").replace('/', "~1")),
+            PathSegment::Index(index) if index >= 0 => pointer.push_str(&index.to_string()),
+            PathSegment::Index(_) => {
+                return Err(Error::parse(
+                    format!("Path '{path}' uses a negative index"),
+                    1,
+                    1,
+                ))
+            }
+        }
+    }
+    Ok(pointer)
+}
+
+/// Convert a JSON Pointer into the canonical [`crate::path`] path of the
+/// value it addresses in `root`, for provenance tracking after an operation
+/// has been applied (`-` resolves to the last array element).
+pub(crate) fn pointer_to_path(root: &Value, pointer: &str) -> Option<String> {
+    let mut out = String::new();
+    let mut node = root;
+    for token in parse_pointer(pointer).ok()? {
+        match node {
+            Value::Array(items) => {
+                let index = if token == "-" {
+                    items.len().checked_sub(1)?
+                } else {
+                    array_index(&token)?
+                };
+                out = path::join_index(&out, index);
+                node = items.get(index)?;
+            }
+            Value::Table(table) => {
+                out = path::join_key(&out, &token);
+                node = table.get(&token)?;
+            }
+            _ => return None,
+        }
+    }
+    Some(out)
+}
+
+/// Build the patch that turns `from` into `to`.
+///
+/// # Errors
+///
+/// Returns an error only if a generated path cannot be expressed as a
+/// pointer, which does not happen for diffs produced by [`Value::diff`].
+pub fn diff_patch(from: &Value, to: &Value) -> Result<Vec<PatchOp>> {
+    from_changes(&from.diff(to))
+}
+
+/// Convert index-based diff changes (from [`Value::diff`]) into patch
+/// operations. Trailing array removals are emitted highest index first so
+/// earlier removals do not shift later ones.
+///
+/// # Errors
+///
+/// Returns [`Error::Parse`] if a change path cannot be expressed as a
+/// pointer.
+pub fn from_changes(changes: &[Change]) -> Result<Vec<PatchOp>> {
+    let mut ops = Vec::with_capacity(changes.len());
+    let mut removals: Vec<PatchOp> = Vec::new();
+    for change in changes {
+        let path = path_to_pointer(&change.path)?;
+        let op = match (change.kind, &change.new) {
+            (ChangeKind::Removed, _) => PatchOp::Remove { path },
+            (ChangeKind::Added, Some(value)) => PatchOp::Add {
+                path,
+                value: value.clone(),
+            },
+            (_, Some(value)) => PatchOp::Replace {
+                path,
+                value: value.clone(),
+            },
+            (_, None) => PatchOp::Remove { path },
+        };
+        if change.kind == ChangeKind::Removed && change.path.ends_with(']') {
+            removals.push(op);
+        } else {
+            ops.extend(removals.drain(..).rev());
+            ops.push(op);
+        }
+    }
+    ops.extend(removals.into_iter().rev());
+    Ok(ops)
+}
+
+impl Value {
+    /// Look up a value by JSON Pointer.
+    pub fn pointer(&self, pointer: &str) -> Option<&Value> {
+        let mut node = self;
+        for token in parse_pointer(pointer).ok()? {
+            node = child(node, &token)?;
+        }
+        Some(node)
+    }
+
+    /// Apply an RFC 6902 patch. The patch is atomic: on error `self` is
+    /// left unchanged.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Parse`] for malformed pointers,
+    /// [`Error::KeyNotFound`] when a target or parent does not exist, and
+    /// [`Error::Validation`] when a `test` operation fails.
+    pub fn apply_patch(&mut self, ops: &[PatchOp]) -> Result<()> {
+        let mut patched = self.clone();
+        for op in ops {
+            apply_op(&mut patched, op)?;
+        }
+        *self = patched;
+        Ok(())
+    }
+
+    /// Apply an RFC 7396 merge patch: tables merge recursively, `null`
+    /// deletes a key, and anything else (arrays included) replaces and is
+    /// copied unchanged, `null` elements and all.
+    pub fn apply_merge_patch(&mut self, patch: &Value) {
+        self.merge_with(patch, &merge_patch_options());
+    }
+}
+
+/// Merge options implementing RFC 7396 on top of [`crate::merge`].
+pub(crate) fn merge_patch_options() -> MergeOptions {
+    MergeOptions::new().delete_on_null(true)
+}
+
+/// Apply one operation in place.
+pub(crate) fn apply_op(root: &mut Value, op: &PatchOp) -> Result<()> {
+    match op {
+        PatchOp::Add { path, value } => add(root, path, value.clone()),
+        PatchOp::Remove { path } => remove(root, path).map(drop),
+        PatchOp::Replace { path, value } => {
+            let target = resolve_mut(root, path)?;
+            *target = value.clone();
+            Ok(())
+        }
+        PatchOp::Move { from, path } => {
+            if from == path {
+                return resolve_mut(root, from).map(drop);
+            }
+            if path.starts_with(from.as_str()) && path[from.len()..].starts_with('/') {
+                return Err(pointer_error(path, "cannot move a value into itself"));
+            }
+            let value = remove(root, from)?;
+            add(root, path, value)
+        }
+        PatchOp::Copy { from, path } => {
+            let value = root
+                .pointer(from)
+                .cloned()
+                .ok_or_else(|| Error::key_not_found(from.as_str()))?;
+            add(root, path, value)
+        }
+        PatchOp::Test { path, value } => {
+            let actual = root
+                .pointer(path)
+                .ok_or_else(|| Error::key_not_found(path.as_str()))?;
+            if actual.diff(value).is_empty() {
+                Ok(())
+            } else {
+                Err(Error::validation(format!(
+                    "Patch test failed at '{path}': expected {value}, found {actual}"
+                )))
+            }
+        }
+    }
+}
+
+fn add(root: &mut Value, pointer: &str, value: Value) -> Result<()> {
+    let (parent, last) = split_parent(root, pointer)?;
+    let Some(last) = last else {
+        *root = value;
+        return Ok(());
+    };
+    match parent {
+        Value::Table(table) => {
+            table.insert(last, value);
+            Ok(())
+        }
+        Value::Array(items) => {
+            let index = if last == "-" {
+                items.len()
+            } else {
+                array_index(&last).ok_or_else(|| pointer_error(pointer, "invalid array index"))?
+            };
+            if index > items.len() {
+                return Err(Error::key_not_found(pointer));
+            }
+            items.insert(index, value);
+            Ok(())
+        }
+        _ => Err(Error::key_not_found(pointer)),
+    }
+}
+
+fn remove(root: &mut Value, pointer: &str) -> Result<Value> {
+    let (parent, last) = split_parent(root, pointer)?;
+    let Some(last) = last else {
+        return Err(pointer_error(pointer, "cannot remove the root"));
+    };
+    let removed = match parent {
+        Value::Table(table) => table.remove(&last),
+        Value::Array(items) => array_index(&last)
+            .filter(|index| *index < items.len())
+            .map(|index| items.remove(index)),
+        _ => None,
+    };
+    removed.ok_or_else(|| Error::key_not_found(pointer))
+}
+
+/// Resolve every token but the last; returns the parent and the last token
+/// (`None` for the root pointer).
+fn split_parent<'v>(root: &'v mut Value, pointer: &str) -> Result<(&'v mut Value, Option<String>)> {
+    let mut tokens = parse_pointer(pointer)?;
+    let Some(last) = tokens.pop() else {
+        return Ok((root, None));
+    };
+    let mut node = root;
+    for token in &tokens {
+        node = child_mut(node, token).ok_or_else(|| Error::key_not_found(pointer))?;
+    }
+    Ok((node, Some(last)))
+}
+
+fn resolve_mut<'v>(root: &'v mut Value, pointer: &str) -> Result<&'v mut Value> {
+    let mut node = root;
+    for token in parse_pointer(pointer)? {
+        node = child_mut(node, &token).ok_or_else(|| Error::key_not_found(pointer))?;
+    }
+    Ok(node)
+}
+
+fn child<'v>(node: &'v Value, token: &str) -> Option<&'v Value> {
+    match node {
+        Value::Table(table) => table.get(token),
+        Value::Array(items) => items.get(array_index(token)?),
+        _ => None,
+    }
+}
+
+fn child_mut<'v>(node: &'v mut Value, token: &str) -> Option<&'v mut Value> {
+    match node {
+        Value::Table(table) => table.get_mut(token),
+        Value::Array(items) => items.get_mut(array_index(token)?),
+        _ => None,
+    }
+}
+
+/// RFC 6901 array index: `0` or digits without a leading zero.
+fn array_index(token: &str) -> Option<usize> {
+    let valid = !token.is_empty()
+        && token.bytes().all(|b| b.is_ascii_digit())
+        && (token == "0" || !token.starts_with('0'));
+    if valid {
+        token.parse().ok()
+    } else {
+        None
+    }
+}
+
+fn pointer_error(pointer: &str, message: &str) -> Error {
+    Error::parse(format!("Invalid JSON pointer '{pointer}': {message}"), 1, 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn doc() -> Value {
+        let mut root = Value::table(BTreeMap::new());
+        root.set_nested("a.b", Value::integer(1)).unwrap();
+        root.set_nested("list", Value::array(vec![1.into(), 2.into(), 3.into()]))
+            .unwrap();
+        root.set_nested("\"x/y~z\"", Value::bool(true)).unwrap();
+        root
+    }
+
+    #[test]
+    fn test_pointers() {
+        let root = doc();
+        assert_eq!(root.pointer(""), Some(&root));
+        assert_eq!(root.pointer("/a/b"), Some(&Value::integer(1)));
+        assert_eq!(root.pointer("/list/2"), Some(&Value::integer(3)));
+        assert_eq!(root.pointer("/x~1y~0z"), Some(&Value::bool(true)));
+        assert_eq!(root.pointer("/list/01"), None);
+        assert!(parse_pointer("a").is_err());
+        assert!(parse_pointer("/~2").is_err());
+
+        assert_eq!(path_to_pointer("\"x/y~z\"").unwrap(), "/x~1y~0z");
+        assert_eq!(path_to_pointer("list[1]").unwrap(), "/list/1");
+        assert_eq!(
+            pointer_to_path(&root, "/list/-").as_deref(),
+            Some("list[2]")
+        );
+    }
+
+    #[test]
+    fn test_operations() {
+        let mut root = doc();
+        root.apply_patch(&[
+            PatchOp::Add {
+                path: "/list/1".into(),
+                value: Value::integer(9),
+            },
+            PatchOp::Remove {
+                path: "/list/0".into(),
+            },
+            PatchOp::Move {
+                from: "/a/b".into(),
+                path: "/c".into(),
+            },
+            PatchOp::Copy {
+                from: "/c".into(),
+                path: "/a/copy".into(),
+            },
+            PatchOp::Test {
+                path: "/c".into(),
+                value: Value::float(1.0),
+            },
+            PatchOp::Replace {
+                path: "/list/-1".into(),
+                value: Value::Null,
+            },
+        ])
+        .unwrap_err();
+        // Atomic: the failed replace (invalid index) rolled everything back.
+        assert_eq!(root, doc());
+
+        root.apply_patch(&[
+            PatchOp::Add {
+                path: "/list/1".into(),
+                value: Value::integer(9),
+            },
+            PatchOp::Remove {
+                path: "/list/0".into(),
+            },
+            PatchOp::Move {
+                from: "/a/b".into(),
+                path: "/c".into(),
+            },
+            PatchOp::Copy {
+                from: "/c".into(),
+                path: "/a/copy".into(),
+            },
+        ])
+        .unwrap();
+        assert_eq!(
+            root.get("list"),
+            Some(&Value::array(vec![9.into(), 2.into(), 3.into()]))
+        );
+        assert_eq!(root.get("c"), Some(&Value::integer(1)));
+        assert_eq!(root.get("a.copy"), Some(&Value::integer(1)));
+        assert!(root.get("a.b").is_none());
+
+        let err = root
+            .apply_patch(&[PatchOp::Test {
+                path: "/c".into(),
+                value: Value::integer(2),
+            }])
+            .unwrap_err();
+        assert!(matches!(err, Error::Validation { .. }));
+        assert!(root
+            .apply_patch(&[PatchOp::Move {
+                from: "/a".into(),
+                path: "/a/inner".into(),
+            }])
+            .is_err());
+    }
+
+    #[test]
+    fn test_parse_document() {
+        let mut op = BTreeMap::new();
+        op.insert("op".to_string(), Value::string("add"));
+        op.insert("path".to_string(), Value::string("/n"));
+        op.insert("value".to_string(), Value::Null);
+        let ops = parse(&Value::array(vec![Value::table(op)])).unwrap();
+        assert_eq!(
+            ops,
+            [PatchOp::Add {
+                path: "/n".into(),
+                value: Value::Null
+            }]
+        );
+        assert_eq!(
+            crate::ser::to_value(&ops).unwrap().get("[0].op"),
+            Some(&Value::string("add"))
+        );
+    }
+
+    #[test]
+    fn test_merge_patch() {
+        let mut root = doc();
+        let mut patch = BTreeMap::new();
+        patch.insert("a".to_string(), Value::Null);
+        patch.insert("list".to_string(), Value::array(vec![Value::integer(7)]));
+        let mut nested = BTreeMap::new();
+        nested.insert("k".to_string(), Value::Null);
+        nested.insert("v".to_string(), Value::integer(1));
+        patch.insert("new".to_string(), Value::table(nested));
+        root.apply_merge_patch(&Value::table(patch));
+
+        assert!(root.get("a").is_none());
+        assert_eq!(
+            root.get("list"),
+            Some(&Value::array(vec![Value::integer(7)]))
+        );
+        assert_eq!(root.pointer("/new/v"), Some(&Value::integer(1)));
+        assert!(root.pointer("/new/k").is_none());
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_merge_patch_rfc_7396_examples() {
+        let json = |text: &str| crate::parsers::json_parser::parse(text).unwrap();
+        // RFC 7396 appendix A, plus nulls inside arrays.
+        let cases = [
+            (r#"{"a":"b"}"#, r#"{"a":"c"}"#, r#"{"a":"c"}"#),
+            (r#"{"a":"b"}"#, r#"{"b":"c"}"#, r#"{"a":"b","b":"c"}"#),
+            (r#"{"a":"b"}"#, r#"{"a":null}"#, r#"{}"#),
+            (r#"{"a":"b","b":"c"}"#, r#"{"a":null}"#, r#"{"b":"c"}"#),
+            (r#"{"a":["b"]}"#, r#"{"a":"c"}"#, r#"{"a":"c"}"#),
+            (r#"{"a":"c"}"#, r#"{"a":["b"]}"#, r#"{"a":["b"]}"#),
+            (
+                r#"{"a":{"b":"c"}}"#,
+                r#"{"a":{"b":"d","c":null}}"#,
+                r#"{"a":{"b":"d"}}"#,
+            ),
+            (r#"{"a":[{"b":"c"}]}"#, r#"{"a":[1]}"#, r#"{"a":[1]}"#),
+            (r#"["a","b"]"#, r#"["c","d"]"#, r#"["c","d"]"#),
+            (r#"{"a":"b"}"#, r#"["c"]"#, r#"["c"]"#),
+            (r#"{"a":"foo"}"#, "null", "null"),
+            (r#"{"a":"foo"}"#, r#""bar""#, r#""bar""#),
+            (r#"{"e":null}"#, r#"{"a":1}"#, r#"{"e":null,"a":1}"#),
+            (r#"[1,2]"#, r#"{"a":"b","c":null}"#, r#"{"a":"b"}"#),
+            (
+                r#"{}"#,
+                r#"{"a":{"bb":{"ccc":null}}}"#,
+                r#"{"a":{"bb":{}}}"#,
+            ),
+            (r#"{}"#, r#"{"a":[{"b":null}]}"#, r#"{"a":[{"b":null}]}"#),
+            (
+                r#"{"a":[1]}"#,
+                r#"{"a":[null,{"c":null}]}"#,
+                r#"{"a":[null,{"c":null}]}"#,
+            ),
+        ];
+        for (target, patch, expected) in cases {
+            let mut value = json(target);
+            value.apply_merge_patch(&json(patch));
+            assert_eq!(value, json(expected), "{target} + {patch}");
+        }
+    }
+
+    #[test]
+    fn test_diff_patch_round_trip() {
+        let from = doc();
+        let mut to = doc();
+        to.set_nested("list", Value::array(vec![Value::integer(1)]))
+            .unwrap();
+        to.set_nested("a.c", Value::string("new")).unwrap();
+        to.remove("\"x/y~z\"").unwrap();
+
+        let ops = diff_patch(&from, &to).unwrap();
+        let mut patched = from.clone();
+        patched.apply_patch(&ops).unwrap();
+        assert_eq!(patched, to);
+    }
+}
@@ -0,0 +1,313 @@
+//! # Path Syntax
+//!
+//! The dotted path grammar shared by [`Value::get`], [`Value::set_nested`],
+//! [`Value::remove`], [`Config`] accessors, schema error paths and
+//! environment overrides.
+//!
+//! ```text
+//! server.port          nested table keys
+//! servers[2].host      array index
+//! servers[-1]          negative index, counted from the end
+//! "app.example.com".ip quoted key containing dots
+//! [0].name             index into a root array
+//! ```
+//!
+//! Quoted keys use double quotes; `\"` and `\\` are the only escapes.
+//! Paths that fail to parse, or that do not resolve, still fall back to a
+//! literal flat-key lookup in [`Value::get`] so formats that store dotted
+//! keys verbatim (INI) keep working.
+//!
+//! [`Value::get`]: crate::Value::get
+//! [`Value::set_nested`]: crate::Value::set_nested
+//! [`Value::remove`]: crate::Value::remove
+//! [`Config`]: crate::Config
+
+use crate::error::{Error, Result};
+use std::borrow::Cow;
+use std::fmt;
+
+/// One step of a parsed path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PathSegment<'a> {
+    /// A table key. Borrowed from the input unless it was quoted with escapes.
+    Key(Cow<'a, str>),
+    /// An array index; negative values count from the end (`-1` is the last
+    /// element).
+    Index(i64),
+}
+
+impl PathSegment<'_> {
+    /// Resolve an index segment against an array of length `len`.
+    ///
+    /// Returns `None` for key segments and for out-of-range indices.
+    pub fn resolve_index(&self, len: usize) -> Option<usize> {
+        match self {
+            PathSegment::Index(i) => resolve_index(*i, len),
+            PathSegment::Key(_) => None,
+        }
+    }
+
+    /// Convert into a segment that owns its key.
+    pub fn into_owned(self) -> PathSegment<'static> {
+        match self {
+            PathSegment::Key(k) => PathSegment::Key(Cow::Owned(k.into_owned())),
+            PathSegment::Index(i) => PathSegment::Index(i),
+        }
+    }
+}
+
+impl fmt::Display for PathSegment<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PathSegment::Key(k) => f.write_str(&quote_key(k)),
+            PathSegment::Index(i) => write!(f, "[{i}]"),
+        }
+    }
+}
+
+/// Resolve a possibly negative index against a length.
+pub(crate) fn resolve_index(index: i64, len: usize) -> Option<usize> {
+    if index >= 0 {
+        let i = usize::try_from(index).ok()?;
+        (i < len).then_some(i)
+    } else {
+        let back = usize::try_from(index.unsigned_abs()).ok()?;
+        len.checked_sub(back)
+    }
+}
+
+/// Parse a path into its segments.
+///
+/// The empty path parses to no segments (the root).
+///
+/// # Errors
+///
+/// Returns [`Error::Parse`] (line 1, column of the offending character) for
+/// empty segments, unterminated quotes or brackets, and non-integer indices.
+pub fn parse(path: &str) -> Result<Vec<PathSegment<'_>>> {
+    let bytes = path.as_bytes();
+    let mut segments = Vec::new();
+    let mut pos = 0;
+
+    if bytes.is_empty() {
+        return Ok(segments);
+    }
+
+    // A path may open with an index (`[0].name`); otherwise it opens with a key.
+    let mut expect_key = bytes[0] != b'[';
+
+    while pos < bytes.len() {
+        if expect_key {
+            let (key, next) = parse_key(path, pos)?;
+            segments.push(PathSegment::Key(key));
+            pos = next;
+        }
+
+        // Any number of `[n]` suffixes.
+        while pos < bytes.len() && bytes[pos] == b'[' {
+            let (index, next) = parse_index(path, pos)?;
+            segments.push(PathSegment::Index(index));
+            pos = next;
+        }
+
+        if pos >= bytes.len() {
+            break;
+        }
+
+        if bytes[pos] != b'.' {
+            return Err(path_error(path, pos, "expected '.' or '['"));
+        }
+        pos += 1;
+        if pos >= bytes.len() {
+            return Err(path_error(path, pos, "path ends with '.'"));
+        }
+        expect_key = true;
+    }
+
+    Ok(segments)
+}
+
+/// Parse a key starting at byte offset `start`; returns the key and the
+/// offset just past it.
+pub(crate) fn parse_key(path: &str, start: usize) -> Result<(Cow<'_, str>, usize)> {
+    let bytes = path.as_bytes();
+    if bytes[start] == b'"' {
+        let mut owned = String::new();
+        let mut escaped = false;
+        let mut run_start = start + 1;
+        let mut pos = start + 1;
+        while pos < bytes.len() {
+            match bytes[pos] {
+                b'\\' => {
+                    owned.push_str(&path[run_start..pos]);
+                    let Some(&next) = bytes.get(pos + 1) else {
+                        return Err(path_error(path, pos, "unterminated escape in quoted key"));
+                    };
+                    if next != b'"' && next != b'\\' {
+                        return Err(path_error(path, pos, "invalid escape in quoted key"));
+                    }
+                    owned.push(char::from(next));
+                    escaped = true;
+                    pos += 2;
+                    run_start = pos;
+                }
+                b'"' => {
+                    let key = if escaped {
+                        owned.push_str(&path[run_start..pos]);
+                        Cow::Owned(owned)
+                    } else {
+                        Cow::Borrowed(&path[start + 1..pos])
+                    };
+                    return Ok((key, pos + 1));
+                }
+                _ => pos += 1,
+            }
+        }
+        return Err(path_error(path, start, "unterminated quoted key"));
+    }
+
+    let mut pos = start;
+    while pos < bytes.len() && !matches!(bytes[pos], b'.' | b'[' | b']' | b'"') {
+        pos += 1;
+    }
+    if pos == start {
+        return Err(path_error(path, start, "empty key"));
+    }
+    if pos < bytes.len() && matches!(bytes[pos], b']' | b'"') {
+        return Err(path_error(path, pos, "unexpected character in key"));
+    }
+    Ok((Cow::Borrowed(&path[start..pos]), pos))
+}
+
+/// Parse `[n]` starting at the `[`; returns the index and the offset past `]`.
+pub(crate) fn parse_index(path: &str, start: usize) -> Result<(i64, usize)> {
+    let close = path[start..]
+        .find(']')
+        .map(|offset| start + offset)
+        .ok_or_else(|| path_error(path, start, "unterminated '['"))?;
+    let digits = path[start + 1..close].trim();
+    let index = digits
+        .parse::<i64>()
+        .map_err(|_| path_error(path, start + 1, "array index must be an integer"))?;
+    Ok((index, close + 1))
+}
+
+pub(crate) fn path_error(path: &str, byte_offset: usize, message: &str) -> Error {
+    let column = path[..byte_offset.min(path.len())].chars().count() + 1;
+    Error::parse(format!("Invalid path '{path}': {message}"), 1, column)
+}
+
+/// Render a key as a path segment, quoting it when it contains characters
+/// that are part of the path syntax.
+pub fn quote_key(key: &str) -> Cow<'_, str> {
+    if !key.is_empty() && !key.contains(['.', '[', ']', '"', '\\']) {
+        return Cow::Borrowed(key);
+    }
+    let mut quoted = String::with_capacity(key.len() + 2);
+    quoted.push('"');
+    for ch in key.chars() {
+        if ch == '"' || ch == '\\' {
+            quoted.push('\\');
+        }
+        quoted.push(ch);
+    }
+    quoted.push('"');
+    Cow::Owned(quoted)
+}
+
+/// Append a table key to a parent path (`parent.key`, quoting as needed).
+pub fn join_key(parent: &str, key: &str) -> String {
+    if parent.is_empty() {
+        quote_key(key).into_owned()
+    } else {
+        format!("{parent}.{}", quote_key(key))
+    }
+}
+
+/// Append an array index to a parent path (`parent[index]`).
+pub fn join_index(parent: &str, index: usize) -> String {
+    format!("{parent}[{index}]")
+}
+
+/// Render segments back into canonical path syntax.
+pub fn to_path_string(segments: &[PathSegment<'_>]) -> String {
+    let mut out = String::new();
+    for segment in segments {
+        match segment {
+            PathSegment::Key(k) => {
+                if !out.is_empty() {
+                    out.push('.');
+                }
+                out.push_str(&quote_key(k));
+            }
+            PathSegment::Index(i) => {
+                out.push_str(&format!("[{i}]"));
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(k: &str) -> PathSegment<'_> {
+        PathSegment::Key(Cow::Borrowed(k))
+    }
+
+    #[test]
+    fn test_parse_segments() {
+        assert_eq!(parse("").unwrap(), vec![]);
+        assert_eq!(parse("a.b").unwrap(), vec![key("a"), key("b")]);
+        assert_eq!(
+            parse("servers[2].host").unwrap(),
+            vec![key("servers"), PathSegment::Index(2), key("host")]
+        );
+        assert_eq!(
+            parse("m[0][-1]").unwrap(),
+            vec![key("m"), PathSegment::Index(0), PathSegment::Index(-1)]
+        );
+        assert_eq!(parse(r#""a.b".c"#).unwrap(), vec![key("a.b"), key("c")]);
+        assert_eq!(
+            parse(r#""say \"hi\"""#).unwrap(),
+            vec![PathSegment::Key(Cow::Owned("say \"hi\"".to_string()))]
+        );
+        assert_eq!(
+            parse("[0].x").unwrap(),
+            vec![PathSegment::Index(0), key("x")]
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        for bad in [
+            "a..b", "a.", ".a", "a[x]", "a[1", r#""open"#, "a]b", "a[0]b",
+        ] {
+            assert!(parse(bad).is_err(), "{bad} should not parse");
+        }
+    }
+
+    #[test]
+    fn test_round_trip_rendering() {
+        for path in [
+            "a.b",
+            "servers[2].host",
+            r#""a.b".c"#,
+            "[0][-1]",
+            r#""q\"k""#,
+        ] {
+            assert_eq!(to_path_string(&parse(path).unwrap()), path);
+        }
+        assert_eq!(join_key("", "x.y"), r#""x.y""#);
+        assert_eq!(join_index("list", 3), "list[3]");
+    }
+
+    #[test]
+    fn test_resolve_index() {
+        assert_eq!(resolve_index(0, 3), Some(0));
+        assert_eq!(resolve_index(3, 3), None);
+        assert_eq!(resolve_index(-1, 3), Some(2));
+        assert_eq!(resolve_index(-4, 3), None);
+    }
+}
@@ -0,0 +1,664 @@
+//! # Query API
+//!
+//! Pattern matching over a [`Value`] tree, built on the [`path`](crate::path)
+//! grammar. A query is a path whose segments may also be:
+//!
+//! | Segment        | Matches                                                   |
+//! |----------------|-----------------------------------------------------------|
+//! | `*`            | every value of a table / every element of an array        |
+//! | `**`           | the current node and all of its descendants               |
+//! | `[*]`          | every element of an array                                 |
+//! | `[start:end]`  | an array slice (end-exclusive, negative bounds allowed)   |
+//! | `[?expr]`      | children for which a predicate holds                      |
+//!
+//! Predicates are `[?path]` (the child has a non-null value at `path`) or
+//! `[?path op literal]` with `op` one of `==`, `!=`, `<`, `<=`, `>`, `>=`.
+//! `@` refers to the child itself, e.g. `ports[?@ >= 8000]`, and quoted
+//! keys in `path` may hold operator characters (`[?"a=b" == 1]`).
+//! Literals are numbers, `true` / `false` / `null`, or double-quoted
+//! strings; integers and floats compare numerically.
+//!
+//! Every match carries the canonical path of the value, which can be fed
+//! straight back into [`Value::get`] or [`Config::set`](crate::Config::set).
+//!
+//! ```rust
+//! use config_lib::Config;
+//!
+//! # fn main() -> Result<(), config_lib::Error> {
+//! let mut config = Config::new();
+//! config.set("services.api.port", 8080)?;
+//! config.set("services.web.port", 80)?;
+//! config.set("services.web.timeout", 5)?;
+//! let ports: Vec<String> = config
+//!     .query("services.*.port")?
+//!     .into_iter()
+//!     .map(|m| m.path)
+//!     .collect();
+//! assert_eq!(ports, ["services.api.port", "services.web.port"]);
+//! assert_eq!(config.query("**.timeout")?.len(), 1);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::{Error, Result};
+use crate::path::{self, PathSegment};
+use crate::value::Value;
+use std::cmp::Ordering;
+use std::collections::HashSet;
+
+/// A single query result: the value's canonical path and a borrow of it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryMatch<'v> {
+    /// Canonical path of the matched value (see [`crate::path`]).
+    pub path: String,
+    /// The matched value.
+    pub value: &'v Value,
+}
+
+/// A compiled query. Parse once with [`Query::parse`] and run it against
+/// any number of values with [`Query::apply`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query {
+    segments: Vec<QuerySegment>,
+}
+
+/// One step of a compiled query.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum QuerySegment {
+    /// A literal table key.
+    Key(String),
+    /// A literal array index (negative counts from the end).
+    Index(i64),
+    /// `*` / `[*]`: every child.
+    Wildcard,
+    /// `**`: the current node and every descendant.
+    Recursive,
+    /// `[start:end]`: an end-exclusive array slice.
+    Slice {
+        /// Inclusive start; `None` means the beginning.
+        start: Option<i64>,
+        /// Exclusive end; `None` means the end.
+        end: Option<i64>,
+    },
+    /// `[?...]`: children satisfying a predicate.
+    Filter(Predicate),
+}
+
+/// A `[?...]` predicate evaluated against each child.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Predicate {
+    subject: Vec<PathSegment<'static>>,
+    comparison: Option<(CompareOp, Value)>,
+}
+
+/// Comparison operator inside a predicate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CompareOp {
+    /// `==`
+    Eq,
+    /// `!=`
+    Ne,
+    /// `<`
+    Lt,
+    /// `<=`
+    Le,
+    /// `>`
+    Gt,
+    /// `>=`
+    Ge,
+}
+
+impl Query {
+    /// Compile a query pattern.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Parse`] describing the offending column when the
+    /// pattern is malformed.
+    pub fn parse(pattern: &str) -> Result<Self> {
+        let bytes = pattern.as_bytes();
+        let mut segments = Vec::new();
+        let mut pos = 0;
+        let mut expect_key = !bytes.is_empty() && bytes[0] != b'[';
+
+        while pos < bytes.len() {
+            if expect_key {
+                let quoted = bytes[pos] == b'"';
+                let (key, next) = path::parse_key(pattern, pos)?;
+                segments.push(match key.as_ref() {
+                    "*" if !quoted => QuerySegment::Wildcard,
+                    "**" if !quoted => QuerySegment::Recursive,
+                    _ => QuerySegment::Key(key.into_owned()),
+                });
+                pos = next;
+            }
+
+            while pos < bytes.len() && bytes[pos] == b'[' {
+                let (segment, next) = parse_bracket(pattern, pos)?;
+                segments.push(segment);
+                pos = next;
+            }
+
+            if pos >= bytes.len() {
+                break;
+            }
+            if bytes[pos] != b'.' {
+                return Err(path::path_error(pattern, pos, "expected '.' or '['"));
+            }
+            pos += 1;
+            if pos >= bytes.len() {
+                return Err(path::path_error(pattern, pos, "query ends with '.'"));
+            }
+            expect_key = true;
+        }
+
+        Ok(Self { segments })
+    }
+
+    /// The compiled segments.
+    pub fn segments(&self) -> &[QuerySegment] {
+        &self.segments
+    }
+
+    /// Whether a concrete path matches this pattern, without a value to
+    /// evaluate against. Predicates never match, and slices only match
+    /// with non-negative bounds.
+    pub fn matches_path(&self, path: &str) -> bool {
+        path::parse(path).is_ok_and(|segments| self.matches_segments(&segments))
+    }
+
+    pub(crate) fn matches_segments(&self, path: &[PathSegment<'_>]) -> bool {
+        matches_from(&self.segments, path)
+    }
+
+    /// Run the query against `root`, returning matches in pre-order: a
+    /// node before its descendants, table members in key order and array
+    /// elements by index. (Tables are sorted maps, so this is not the
+    /// order keys were written in the source.)
+    ///
+    /// Each distinct path is reported once, even when several branches of
+    /// a `**` query reach it.
+    pub fn apply<'v>(&self, root: &'v Value) -> Vec<QueryMatch<'v>> {
+        let mut current = vec![QueryMatch {
+            path: String::new(),
+            value: root,
+        }];
+
+        for segment in &self.segments {
+            let mut next = Vec::new();
+            for node in &current {
+                step(segment, node, &mut next);
+            }
+            current = dedup(next);
+            if current.is_empty() {
+                break;
+            }
+        }
+
+        current
+    }
+}
+
+fn matches_from(query: &[QuerySegment], path: &[PathSegment<'_>]) -> bool {
+    match query.split_first() {
+        None => path.is_empty(),
+        Some((QuerySegment::Recursive, rest)) => {
+            (0..=path.len()).any(|skip| matches_from(rest, &path[skip..]))
+        }
+        Some((segment, rest)) => path
+            .split_first()
+            .is_some_and(|(head, tail)| segment_matches(segment, head) && matches_from(rest, tail)),
+    }
+}
+
+fn segment_matches(segment: &QuerySegment, path: &PathSegment<'_>) -> bool {
+    match (segment, path) {
+        (QuerySegment::Key(key), PathSegment::Key(actual)) => key == actual,
+        (QuerySegment::Index(index), PathSegment::Index(actual)) => index == actual,
+        (QuerySegment::Wildcard, _) => true,
+        (QuerySegment::Slice { start, end }, PathSegment::Index(actual)) => {
+            let start = start.unwrap_or(0);
+            start >= 0 && *actual >= start && end.map_or(true, |end| end >= 0 && *actual < end)
+        }
+        _ => false,
+    }
+}
+
+/// Expand one node by one segment.
+fn step<'v>(segment: &QuerySegment, node: &QueryMatch<'v>, out: &mut Vec<QueryMatch<'v>>) {
+    match segment {
+        QuerySegment::Key(key) => {
+            if let Value::Table(table) = node.value {
+                if let Some(value) = table.get(key) {
+                    out.push(QueryMatch {
+                        path: path::join_key(&node.path, key),
+                        value,
+                    });
+                }
+            }
+        }
+        QuerySegment::Index(index) => {
+            if let Value::Array(items) = node.value {
+                if let Some(i) = path::resolve_index(*index, items.len()) {
+                    out.push(QueryMatch {
+                        path: path::join_index(&node.path, i),
+                        value: &items[i],
+                    });
+                }
+            }
+        }
+        QuerySegment::Wildcard => children(node, out),
+        QuerySegment::Recursive => descendants(node, out),
+        QuerySegment::Slice { start, end } => {
+            if let Value::Array(items) = node.value {
+                let len = items.len();
+                let from = slice_bound(*start, len, 0);
+                let to = slice_bound(*end, len, len);
+                for (i, value) in items.iter().enumerate().take(to).skip(from) {
+                    out.push(QueryMatch {
+                        path: path::join_index(&node.path, i),
+                        value,
+                    });
+                }
+            }
+        }
+        QuerySegment::Filter(predicate) => {
+            let mut all = Vec::new();
+            children(node, &mut all);
+            out.extend(all.into_iter().filter(|m| predicate.matches(m.value)));
+        }
+    }
+}
+
+/// Push every direct child of `node`.
+fn children<'v>(node: &QueryMatch<'v>, out: &mut Vec<QueryMatch<'v>>) {
+    match node.value {
+        Value::Table(table) => {
+            for (key, value) in table {
+                out.push(QueryMatch {
+                    path: path::join_key(&node.path, key),
+                    value,
+                });
+            }
+        }
+        Value::Array(items) => {
+            for (i, value) in items.iter().enumerate() {
+                out.push(QueryMatch {
+                    path: path::join_index(&node.path, i),
+                    value,
+                });
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Push `node` and all of its descendants in pre-order. Walks with an
+/// explicit stack, so deeply nested values cannot overflow the call stack.
+fn descendants<'v>(node: &QueryMatch<'v>, out: &mut Vec<QueryMatch<'v>>) {
+    let mut stack = vec![node.clone()];
+    let mut direct = Vec::new();
+    while let Some(node) = stack.pop() {
+        children(&node, &mut direct);
+        out.push(node);
+        stack.extend(direct.drain(..).rev());
+    }
+}
+
+fn dedup(matches: Vec<QueryMatch<'_>>) -> Vec<QueryMatch<'_>> {
+    let mut seen = HashSet::new();
+    matches
+        .into_iter()
+        .filter(|m| seen.insert(m.path.clone()))
+        .collect()
+}
+
+/// Clamp a Python-style slice bound into `0..=len`.
+fn slice_bound(bound: Option<i64>, len: usize, default: usize) -> usize {
+    match bound {
+        None => default,
+        Some(b) if b >= 0 => usize::try_from(b).map_or(len, |b| b.min(len)),
+        Some(b) => usize::try_from(b.unsigned_abs()).map_or(0, |back| len.saturating_sub(back)),
+    }
+}
+
+/// Parse a bracketed segment starting at `[`.
+fn parse_bracket(pattern: &str, start: usize) -> Result<(QuerySegment, usize)> {
+    let close = find_close_bracket(pattern, start)
+        .ok_or_else(|| path::path_error(pattern, start, "unterminated '['"))?;
+    let inner = pattern[start + 1..close].trim();
+
+    let segment = if inner == "*" {
+        QuerySegment::Wildcard
+    } else if let Some(expr) = inner.strip_prefix('?') {
+        QuerySegment::Filter(Predicate::parse(pattern, start + 1, expr.trim())?)
+    } else if let Some((from, to)) = inner.split_once(':') {
+        let bound = |text: &str| -> Result<Option<i64>> {
+            let text = text.trim();
+            if text.is_empty() {
+                Ok(None)
+            } else {
+                text.parse::<i64>().map(Some).map_err(|_| {
+                    path::path_error(pattern, start + 1, "slice bounds must be integers")
+                })
+            }
+        };
+        QuerySegment::Slice {
+            start: bound(from)?,
+            end: bound(to)?,
+        }
+    } else {
+        let (index, _) = path::parse_index(pattern, start)?;
+        QuerySegment::Index(index)
+    };
+
+    Ok((segment, close + 1))
+}
+
+/// Find the `]` closing the bracket at `start`, skipping quoted strings
+/// and nested brackets.
+fn find_close_bracket(pattern: &str, start: usize) -> Option<usize> {
+    let bytes = pattern.as_bytes();
+    let mut in_string = false;
+    let mut depth = 0;
+    let mut pos = start + 1;
+    while pos < bytes.len() {
+        match bytes[pos] {
+            b'\\' if in_string => pos += 1,
+            b'"' => in_string = !in_string,
+            b'[' if !in_string => depth += 1,
+            b']' if !in_string && depth == 0 => return Some(pos),
+            b']' if !in_string => depth -= 1,
+            _ => {}
+        }
+        pos += 1;
+    }
+    None
+}
+
+/// Byte offset of the comparison operator in a predicate: the first `=`,
+/// `!`, `<` or `>` outside the subject path's quoted keys and brackets.
+fn operator_at(expr: &str) -> Option<usize> {
+    let bytes = expr.as_bytes();
+    let mut pos = 0;
+    while pos < bytes.len() {
+        match bytes[pos] {
+            b'"' => pos = path::parse_key(expr, pos).map_or(bytes.len(), |(_, next)| next),
+            b'[' => pos = find_close_bracket(expr, pos).map_or(bytes.len(), |close| close + 1),
+            b'=' | b'!' | b'<' | b'>' => return Some(pos),
+            _ => pos += 1,
+        }
+    }
+    None
+}
+
+impl Predicate {
+    fn parse(pattern: &str, offset: usize, expr: &str) -> Result<Self> {
+        let error = |message: &str| path::path_error(pattern, offset, message);
+
+        let op_at = operator_at(expr);
+        let (subject_text, comparison) = match op_at {
+            None => (expr, None),
+            Some(at) => {
+                let rest = &expr[at..];
+                let (op, width) = match rest.get(..2) {
+                    Some("==") => (CompareOp::Eq, 2),
+                    Some("!=") => (CompareOp::Ne, 2),
+                    Some("<=") => (CompareOp::Le, 2),
+                    Some(">=") => (CompareOp::Ge, 2),
+                    _ if rest.starts_with('<') => (CompareOp::Lt, 1),
+                    _ if rest.starts_with('>') => (CompareOp::Gt, 1),
+                    _ => return Err(error("unknown comparison operator")),
+                };
+                let literal = parse_literal(rest[width..].trim()).ok_or_else(|| {
+                    error("predicate literal must be a number, bool, null or quoted string")
+                })?;
+                (&expr[..at], Some((op, literal)))
+            }
+        };
+
+        let subject_text = subject_text.trim();
+        let subject_text = subject_text
+            .strip_prefix("@.")
+            .or_else(|| subject_text.strip_prefix('@'))
+            .unwrap_or(subject_text);
+        if !subject_text.starts_with('"') && subject_text.contains(char::is_whitespace) {
+            return Err(error("expected a comparison operator"));
+        }
+        let subject = path::parse(subject_text)
+            .map_err(|_| error("invalid predicate path"))?
+            .into_iter()
+            .map(PathSegment::into_owned)
+            .collect();
+
+        Ok(Self {
+            subject,
+            comparison,
+        })
+    }
+
+    /// Evaluate the predicate against one child value.
+    pub fn matches(&self, value: &Value) -> bool {
+        let Some(subject) = value.get_segments(&self.subject) else {
+            return false;
+        };
+        match &self.comparison {
+            None => !subject.is_null(),
+            Some((op, literal)) => {
+                let ordering = compare(subject, literal);
+                match op {
+                    CompareOp::Eq => ordering == Some(Ordering::Equal),
+                    CompareOp::Ne => ordering != Some(Ordering::Equal),
+                    CompareOp::Lt => ordering == Some(Ordering::Less),
+                    CompareOp::Le => {
+                        matches!(ordering, Some(Ordering::Less | Ordering::Equal))
+                    }
+                    CompareOp::Gt => ordering == Some(Ordering::Greater),
+                    CompareOp::Ge => {
+                        matches!(ordering, Some(Ordering::Greater | Ordering::Equal))
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Order two values of compatible types; integers and floats compare
+/// numerically. Incomparable pairs return `None`.
+fn compare(a: &Value, b: &Value) -> Option<Ordering> {
+    match (a, b) {
+        (Value::Integer(x), Value::Integer(y)) => Some(x.cmp(y)),
+        (Value::Integer(_) | Value::Float(_), Value::Integer(_) | Value::Float(_)) => {
+            a.as_float().ok()?.partial_cmp(&b.as_float().ok()?)
+        }
+        (Value::String(x), Value::String(y)) => Some(x.cmp(y)),
+        (Value::Bool(x), Value::Bool(y)) => Some(x.cmp(y)),
+        (Value::Null, Value::Null) => Some(Ordering::Equal),
+        _ => (a == b).then_some(Ordering::Equal),
+    }
+}
+
+fn parse_literal(text: &str) -> Option<Value> {
+    match text {
+        "true" => return Some(Value::Bool(true)),
+        "false" => return Some(Value::Bool(false)),
+        "null" => return Some(Value::Null),
+        _ => {}
+    }
+    if let Some(body) = text.strip_prefix('"').and_then(|t| t.strip_suffix('"')) {
+        let mut out = String::with_capacity(body.len());
+        let mut chars = body.chars();
+        while let Some(c) = chars.next() {
+            if c == '\\' {
+                out.push(chars.next()?);
+            } else {
+                out.push(c);
+            }
+        }
+        return Some(Value::String(out));
+    }
+    if let Ok(i) = text.parse::<i64>() {
+        return Some(Value::Integer(i));
+    }
+    text.parse::<f64>().ok().map(Value::Float)
+}
+
+impl Value {
+    /// Run a query (see [`crate::query`]) against this value.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Parse`] if the pattern is malformed.
+    pub fn query(&self, pattern: &str) -> Result<Vec<QueryMatch<'_>>> {
+        Ok(Query::parse(pattern)?.apply(self))
+    }
+}
+
+impl std::str::FromStr for Query {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::parse(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Value {
+        let mut root = Value::table(std::collections::BTreeMap::new());
+        root.set_nested("services.api.port", Value::integer(8080))
+            .unwrap();
+        root.set_nested("services.api.timeout", Value::integer(30))
+            .unwrap();
+        root.set_nested("services.web.port", Value::integer(80))
+            .unwrap();
+        root.set_nested("services.web.name", Value::string("web"))
+            .unwrap();
+        root.set_nested("timeout", Value::float(2.5)).unwrap();
+        root.set_nested(
+            "ports",
+            Value::array((1..=5).map(|p| Value::integer(p * 1000)).collect()),
+        )
+        .unwrap();
+        root
+    }
+
+    fn paths(value: &Value, pattern: &str) -> Vec<String> {
+        value
+            .query(pattern)
+            .unwrap()
+            .into_iter()
+            .map(|m| m.path)
+            .collect()
+    }
+
+    #[test]
+    fn test_wildcards() {
+        let value = sample();
+        assert_eq!(
+            paths(&value, "services.*.port"),
+            ["services.api.port", "services.web.port"]
+        );
+        assert_eq!(
+            paths(&value, "**.timeout"),
+            ["timeout", "services.api.timeout"]
+        );
+        assert_eq!(paths(&value, "ports[*]").len(), 5);
+        assert_eq!(paths(&value, "services.**.**.port").len(), 2);
+    }
+
+    #[test]
+    fn test_slices_and_indices() {
+        let value = sample();
+        assert_eq!(paths(&value, "ports[1:3]"), ["ports[1]", "ports[2]"]);
+        assert_eq!(paths(&value, "ports[-2:]"), ["ports[3]", "ports[4]"]);
+        assert_eq!(paths(&value, "ports[:1]"), ["ports[0]"]);
+        assert_eq!(paths(&value, "ports[-1]"), ["ports[4]"]);
+    }
+
+    #[test]
+    fn test_predicates() {
+        let value = sample();
+        assert_eq!(paths(&value, "ports[?@ >= 4000]"), ["ports[3]", "ports[4]"]);
+        assert_eq!(paths(&value, "services[?port > 100]"), ["services.api"]);
+        assert_eq!(
+            paths(&value, r#"services[?name == "web"]"#),
+            ["services.web"]
+        );
+        assert_eq!(paths(&value, "services[?timeout]"), ["services.api"]);
+        assert_eq!(paths(&value, "ports[?@ == 1000.0]"), ["ports[0]"]);
+
+        let mut value = Value::table(std::collections::BTreeMap::new());
+        value
+            .set_nested(r#"rules[0]."a=b""#, Value::integer(1))
+            .unwrap();
+        value
+            .set_nested(
+                r#"rules[1]."x<y".list"#,
+                Value::array(vec![Value::integer(2)]),
+            )
+            .unwrap();
+        assert_eq!(paths(&value, r#"rules[?"a=b" == 1]"#), ["rules[0]"]);
+        assert_eq!(
+            paths(&value, r#"rules[?@."x<y".list[0] >= 2]"#),
+            ["rules[1]"]
+        );
+        assert_eq!(paths(&value, r#"rules[?"a=b"]"#), ["rules[0]"]);
+    }
+
+    #[test]
+    fn test_paths_feed_back_into_get() {
+        let value = sample();
+        for m in value.query("**").unwrap() {
+            assert_eq!(value.get(&m.path), Some(m.value), "{}", m.path);
+        }
+    }
+
+    #[test]
+    fn test_matches_path() {
+        let query = Query::parse("services.*.port").unwrap();
+        assert!(query.matches_path("services.api.port"));
+        assert!(!query.matches_path("services.port"));
+        assert!(Query::parse("**.tls").unwrap().matches_path("a.b[2].tls"));
+        assert!(Query::parse("**.tls").unwrap().matches_path("tls"));
+        assert!(Query::parse("list[1:3]").unwrap().matches_path("list[2]"));
+        assert!(!Query::parse("list[1:3]").unwrap().matches_path("list[3]"));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        for bad in [
+            "a[",
+            "a[?x ~ 1]",
+            "a[1:x]",
+            "a..b",
+            "a[?x == nope]",
+            r#"a[?"x == 1]"#,
+        ] {
+            assert!(Query::parse(bad).is_err(), "{bad} should not parse");
+        }
+    }
+
+    #[test]
+    fn test_recursive_descent_on_deep_values() {
+        let mut value = Value::integer(1);
+        for _ in 0..2000 {
+            value = Value::array(vec![value]);
+        }
+        let query = Query::parse("**").unwrap();
+        // A small stack that recursing once per level would overflow
+        let count = std::thread::scope(|scope| {
+            std::thread::Builder::new()
+                .stack_size(128 * 1024)
+                .spawn_scoped(scope, || query.apply(&value).len())
+                .unwrap()
+                .join()
+                .unwrap()
+        });
+        assert_eq!(count, 2001);
+    }
+}
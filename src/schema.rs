@@ -211,31 +211,196 @@ impl Schema {
         SchemaBuilder::new()
     }
 
+    /// Build a schema from a JSON Schema style document, read from any
+    /// supported format.
+    ///
+    /// Understands the `type` (`"string"`, `"integer"`, `"number"`,
+    /// `"boolean"`, `"null"`, `"array"`, `"object"`, or a list of these for a
+    /// union), `properties`, `required`, `items`, `default` and
+    /// `description` keywords; other keywords are ignored. A node without a
+    /// `type` accepts any value unless it has `properties` (an object) or
+    /// `items` (an array).
+    ///
+    /// ```rust
+    /// # #[cfg(feature = "toml")]
+    /// # {
+    /// use config_lib::{parse, Schema};
+    ///
+    /// let document = parse(
+    ///     "type = 'object'\nrequired = ['port']\n\n[properties.port]\ntype = 'integer'",
+    ///     Some("toml"),
+    /// )?;
+    /// let schema = Schema::from_value(&document)?;
+    /// assert!(schema.validate(&parse("port = 8080", Some("conf"))?).is_ok());
+    /// assert!(schema.validate(&parse("port = http", Some("conf"))?).is_err());
+    /// # }
+    /// # Ok::<(), config_lib::Error>(())
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Schema`] naming the offending schema node if the
+    /// root is not an object schema or a keyword has the wrong shape.
+    pub fn from_value(document: &Value) -> Result<Schema> {
+        match Self::field_type_from(document, "")? {
+            FieldType::Table(fields) => Ok(Schema { fields }),
+            _ => Err(Error::schema(
+                "",
+                "Schema root must describe an object (`type = object` or `properties`)",
+            )),
+        }
+    }
+
+    /// Read the type described by schema node `node` at `path`.
+    fn field_type_from(node: &Value, path: &str) -> Result<FieldType> {
+        let Value::Table(table) = node else {
+            return Err(Error::schema(path, "Schema node must be a table"));
+        };
+        let names: Vec<&str> = match table.get("type") {
+            None if table.contains_key("properties") => vec!["object"],
+            None if table.contains_key("items") => vec!["array"],
+            None => return Ok(FieldType::Any),
+            Some(Value::String(name)) => vec![name.as_str()],
+            Some(Value::Array(names)) => names
+                .iter()
+                .map(|name| name.as_string())
+                .collect::<Result<_>>()
+                .map_err(|_| Error::schema(path, "`type` must list type names"))?,
+            Some(_) => {
+                return Err(Error::schema(
+                    path,
+                    "`type` must be a type name or a list of them",
+                ))
+            }
+        };
+
+        let mut types = Vec::with_capacity(names.len());
+        for name in names {
+            types.push(match name {
+                "null" => FieldType::Null,
+                "boolean" => FieldType::Bool,
+                "integer" => FieldType::Integer,
+                "number" => FieldType::Float,
+                "string" => FieldType::String,
+                "array" => {
+                    let items = match table.get("items") {
+                        Some(items) => {
+                            Self::field_type_from(items, &crate::path::join_key(path, "items"))?
+                        }
+                        None => FieldType::Any,
+                    };
+                    FieldType::Array(Box::new(items))
+                }
+                "object" => FieldType::Table(Self::fields_from(table, path)?),
+                other => {
+                    return Err(Error::schema(
+                        path,
+                        format!("Unknown schema type '{other}'"),
+                    ))
+                }
+            });
+        }
+        Ok(match types.len() {
+            1 => types.remove(0),
+            _ => FieldType::Union(types),
+        })
+    }
+
+    /// Read the `properties` and `required` keywords of an object node.
+    fn fields_from(
+        table: &BTreeMap<String, Value>,
+        path: &str,
+    ) -> Result<HashMap<String, FieldSchema>> {
+        let required: Vec<&str> = match table.get("required") {
+            None => Vec::new(),
+            Some(Value::String(name)) => vec![name.as_str()],
+            Some(Value::Array(names)) => names
+                .iter()
+                .map(|name| name.as_string())
+                .collect::<Result<_>>()
+                .map_err(|_| Error::schema(path, "`required` must list field names"))?,
+            Some(_) => {
+                return Err(Error::schema(path, "`required` must list field names"));
+            }
+        };
+
+        let properties_path = crate::path::join_key(path, "properties");
+        let properties = match table.get("properties") {
+            None => return Ok(HashMap::new()),
+            Some(Value::Table(properties)) => properties,
+            Some(_) => {
+                return Err(Error::schema(
+                    properties_path,
+                    "`properties` must be a table",
+                ))
+            }
+        };
+
+        let mut fields = HashMap::with_capacity(properties.len());
+        for (name, node) in properties {
+            let node_path = crate::path::join_key(&properties_path, name);
+            let field_type = Self::field_type_from(node, &node_path)?;
+            let description = match node.get("description") {
+                Some(Value::String(text)) => Some(text.clone()),
+                _ => None,
+            };
+            fields.insert(
+                name.clone(),
+                FieldSchema {
+                    field_type,
+                    required: required.contains(&name.as_str()),
+                    default: node.get("default").cloned(),
+                    description,
+                },
+            );
+        }
+        Ok(fields)
+    }
+
     /// Validate a value against this schema
+    ///
+    /// Returns the first error; [`Schema::validate_all`] reports every one.
     pub fn validate(&self, value: &Value) -> Result<()> {
+        match self.validate_all(value).into_iter().next() {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+
+    /// Validate a value against this schema, collecting every error
+    ///
+    /// Fields are checked in name order, so the errors come out in path
+    /// order; an empty list means the value is valid.
+    pub fn validate_all(&self, value: &Value) -> Vec<Error> {
+        let mut errors = Vec::new();
         match value {
-            Value::Table(table) => self.validate_table(table, ""),
-            _ => Err(Error::schema("", "Root value must be a table")),
+            Value::Table(table) => Self::validate_table(&self.fields, table, "", &mut errors),
+            _ => errors.push(Error::schema("", "Root value must be a table")),
         }
+        errors
     }
 
-    /// Validate a table against the schema
-    fn validate_table(&self, table: &BTreeMap<String, Value>, path: &str) -> Result<()> {
-        // Check required fields
-        for (field_name, field_schema) in &self.fields {
-            let field_path = if path.is_empty() {
-                field_name.clone()
-            } else {
-                format!("{path}.{field_name}")
-            };
+    /// Validate a table against `fields`
+    fn validate_table(
+        fields: &HashMap<String, FieldSchema>,
+        table: &BTreeMap<String, Value>,
+        path: &str,
+        errors: &mut Vec<Error>,
+    ) {
+        let mut fields: Vec<_> = fields.iter().collect();
+        fields.sort_by_key(|(name, _)| *name);
+
+        // Check required fields; unknown fields are allowed
+        for (field_name, field_schema) in fields {
+            let field_path = crate::path::join_key(path, field_name);
 
             match table.get(field_name) {
                 Some(value) => {
-                    self.validate_field(value, field_schema, &field_path)?;
+                    Self::validate_type(value, &field_schema.field_type, &field_path, errors);
                 }
                 None => {
                     if field_schema.required {
-                        return Err(Error::schema(
+                        errors.push(Error::schema(
                             field_path,
                             format!("Required field '{field_name}' is missing"),
                         ));
@@ -243,76 +408,49 @@ impl Schema {
                 }
             }
         }
-
-        // Check for unknown fields (optional - could be configurable)
-        for field_name in table.keys() {
-            if !self.fields.contains_key(field_name) {
-                // For now, we allow unknown fields
-                // Could add strict mode later
-            }
-        }
-
-        Ok(())
-    }
-
-    /// Validate a single field
-    fn validate_field(&self, value: &Value, schema: &FieldSchema, path: &str) -> Result<()> {
-        self.validate_type(value, &schema.field_type, path)
     }
 
     /// Validate a value against a type
-    fn validate_type(&self, value: &Value, field_type: &FieldType, path: &str) -> Result<()> {
-        Self::validate_type_impl(value, field_type, path)
-    }
-
-    /// Implementation of type validation (static to avoid clippy warning)
-    fn validate_type_impl(value: &Value, field_type: &FieldType, path: &str) -> Result<()> {
+    fn validate_type(value: &Value, field_type: &FieldType, path: &str, errors: &mut Vec<Error>) {
         match (value, field_type) {
-            (Value::Null, FieldType::Null) => Ok(()),
-            (Value::Bool(_), FieldType::Bool) => Ok(()),
-            (Value::Integer(_), FieldType::Integer) => Ok(()),
-            (Value::Float(_), FieldType::Float) => Ok(()),
-            (Value::String(_), FieldType::String) => Ok(()),
-
-            // Allow integer to float conversion
-            (Value::Integer(_), FieldType::Float) => Ok(()),
+            // Integers are also accepted as floats, and anything as `Any`
+            (Value::Null, FieldType::Null)
+            | (Value::Bool(_), FieldType::Bool)
+            | (Value::Integer(_), FieldType::Integer | FieldType::Float)
+            | (Value::Float(_), FieldType::Float)
+            | (Value::String(_), FieldType::String)
+            | (_, FieldType::Any) => {}
 
             // Array validation
             (Value::Array(arr), FieldType::Array(element_type)) => {
                 for (i, element) in arr.iter().enumerate() {
-                    let element_path = format!("{path}[{i}]");
-                    Self::validate_type_impl(element, element_type, &element_path)?;
+                    let element_path = crate::path::join_index(path, i);
+                    Self::validate_type(element, element_type, &element_path, errors);
                 }
-                Ok(())
             }
 
             // Table validation
             (Value::Table(table), FieldType::Table(table_schema)) => {
-                // Create a temporary schema for nested validation
-                let nested_schema = Schema {
-                    fields: table_schema.clone(),
-                };
-                nested_schema.validate_table(table, path)
+                Self::validate_table(table_schema, table, path, errors);
             }
 
             // Union type validation
             (value, FieldType::Union(types)) => {
-                for union_type in types {
-                    if Self::validate_type_impl(value, union_type, path).is_ok() {
-                        return Ok(());
-                    }
+                let matches = |union_type: &FieldType| {
+                    let mut scratch = Vec::new();
+                    Self::validate_type(value, union_type, path, &mut scratch);
+                    scratch.is_empty()
+                };
+                if !types.iter().any(matches) {
+                    errors.push(Error::schema(
+                        path.to_string(),
+                        format!("Value does not match any of the union types: {types:?}"),
+                    ));
                 }
-                Err(Error::schema(
-                    path.to_string(),
-                    format!("Value does not match any of the union types: {types:?}"),
-                ))
             }
 
-            // Any type always validates
-            (_, FieldType::Any) => Ok(()),
-
             // Type mismatch
-            _ => Err(Error::schema(
+            _ => errors.push(Error::schema(
                 path.to_string(),
                 format!("Expected {:?}, found {}", field_type, value.type_name()),
             )),
@@ -363,6 +501,80 @@ mod tests {
         assert!(schema.validate(&config).is_err());
     }
 
+    #[test]
+    fn test_validate_all_collects_every_error() {
+        let schema = SchemaBuilder::new()
+            .require_string("name")
+            .require_integer("port")
+            .optional_bool("debug")
+            .build();
+
+        let mut config = BTreeMap::new();
+        config.insert("debug".to_string(), Value::string("yes"));
+        config.insert("port".to_string(), Value::string("high"));
+        let errors = schema.validate_all(&Value::table(config));
+
+        let paths: Vec<&str> = errors
+            .iter()
+            .filter_map(|error| match error {
+                Error::Schema { path, .. } => Some(path.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(paths, ["debug", "name", "port"]);
+        assert_eq!(schema.validate_all(&Value::integer(1)).len(), 1);
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn test_schema_from_document() -> Result<()> {
+        let document = crate::parse(
+            r#"
+type = "object"
+required = ["name", "servers"]
+
+[properties.name]
+type = "string"
+
+[properties.debug]
+type = ["boolean", "null"]
+
+[properties.servers]
+type = "array"
+
+[properties.servers.items.properties.port]
+type = "integer"
+"#,
+            Some("toml"),
+        )?;
+        let schema = Schema::from_value(&document)?;
+
+        let valid = crate::parse(
+            "name = 'api'\ndebug = true\n[[servers]]\nport = 80",
+            Some("toml"),
+        )?;
+        assert!(schema.validate(&valid).is_ok());
+
+        let mut config = BTreeMap::new();
+        config.insert("name".to_string(), Value::string("api"));
+        let error = schema.validate(&Value::table(config.clone())).unwrap_err();
+        assert!(matches!(error, Error::Schema { ref path, .. } if path == "servers"));
+
+        let mut server = BTreeMap::new();
+        server.insert("port".to_string(), Value::string("eighty"));
+        config.insert(
+            "servers".to_string(),
+            Value::array(vec![Value::table(server)]),
+        );
+        let error = schema.validate(&Value::table(config)).unwrap_err();
+        assert!(matches!(error, Error::Schema { ref path, .. } if path == "servers[0].port"));
+
+        let bad = crate::parse("[properties.port]\ntype = 'int'", Some("toml"))?;
+        let error = Schema::from_value(&bad).unwrap_err();
+        assert!(matches!(error, Error::Schema { ref path, .. } if path == "properties.port"));
+        Ok(())
+    }
+
     #[test]
     fn test_array_schema() {
         let schema = SchemaBuilder::new()
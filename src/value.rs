@@ -4,6 +4,7 @@
 //! commonly found in configuration files.
 
 use crate::error::{Error, Result};
+use crate::path::{self, PathSegment};
 use std::collections::BTreeMap;
 use std::fmt;
 
@@ -71,6 +72,14 @@ impl Value {
         Value::DateTime(dt)
     }
 
+    /// Build a value from any `Serialize` type (see [`crate::ser`]).
+    ///
+    /// Structs and maps become tables, sequences become arrays, and `None`
+    /// becomes [`Value::Null`].
+    pub fn from_serialize<T: serde::Serialize + ?Sized>(value: &T) -> Result<Self> {
+        crate::ser::to_value(value)
+    }
+
     /// Get the type name of this value
     pub fn type_name(&self) -> &'static str {
         match self {
@@ -249,39 +258,24 @@ impl Value {
         }
     }
 
-    /// Get a value by path (dot-separated)
+    /// Get a value by path
+    ///
+    /// Paths use the grammar documented in [`crate::path`]: dotted keys,
+    /// `[n]` / `[-n]` array indices and `"quoted.keys"`. If the structured
+    /// lookup fails, the whole path is tried as a literal key of this table
+    /// (formats like INI store dotted keys verbatim).
     pub fn get(&self, path: &str) -> Option<&Value> {
         if path.is_empty() {
             return Some(self);
         }
 
-        // First try nested table access
-        let parts: Vec<&str> = path.split('.').collect();
-        let mut current = self;
-        let mut found_nested = true;
-
-        for part in parts {
-            match current {
-                Value::Table(table) => {
-                    if let Some(next) = table.get(part) {
-                        current = next;
-                    } else {
-                        found_nested = false;
-                        break;
-                    }
-                }
-                _ => {
-                    found_nested = false;
-                    break;
-                }
+        // First try structured access
+        if let Ok(segments) = path::parse(path) {
+            if let Some(found) = self.get_segments(&segments) {
+                return Some(found);
             }
         }
 
-        // If nested access succeeds, return it
-        if found_nested {
-            return Some(current);
-        }
-
         // Fallback: try flat key access for formats like INI that use dotted keys
         if let Value::Table(table) = self {
             table.get(path)
@@ -290,155 +284,229 @@ impl Value {
         }
     }
 
+    /// Get a value by pre-parsed path segments (no flat-key fallback)
+    pub fn get_segments(&self, segments: &[PathSegment<'_>]) -> Option<&Value> {
+        let mut current = self;
+        for segment in segments {
+            current = match (current, segment) {
+                (Value::Table(table), PathSegment::Key(key)) => table.get(key.as_ref())?,
+                (Value::Array(items), PathSegment::Index(_)) => {
+                    &items[segment.resolve_index(items.len())?]
+                }
+                _ => return None,
+            };
+        }
+        Some(current)
+    }
+
     /// Get a mutable reference to a value by path (ENTERPRISE ERROR HANDLING)
     pub fn get_mut_nested(&mut self, path: &str) -> Result<&mut Value> {
         if path.is_empty() {
             return Ok(self);
         }
 
-        let parts: Vec<&str> = path.split('.').collect();
-        if parts.is_empty() {
-            return Err(Error::key_not_found(path));
-        }
-
-        let (last_key, parent_path) = parts
-            .split_last()
-            .ok_or_else(|| Error::key_not_found(path))?;
-
-        // Navigate to parent
+        let segments = path::parse(path)?;
         let mut current = self;
-        for part in parent_path {
-            match current {
-                Value::Table(table) => {
-                    current = table
-                        .get_mut(*part)
-                        .ok_or_else(|| Error::key_not_found(*part))?;
-                }
-                _ => {
+        for segment in &segments {
+            current = match current {
+                Value::Table(table) => match segment {
+                    PathSegment::Key(key) => table
+                        .get_mut(key.as_ref())
+                        .ok_or_else(|| Error::key_not_found(key.as_ref()))?,
+                    PathSegment::Index(_) => {
+                        return Err(Error::type_error(
+                            format!("Cannot index table with {segment}"),
+                            "array",
+                            "table",
+                        ))
+                    }
+                },
+                Value::Array(items) => match segment.resolve_index(items.len()) {
+                    Some(index) => &mut items[index],
+                    None => return Err(Error::key_not_found(segment.to_string())),
+                },
+                other => {
                     return Err(Error::type_error(
                         format!(
-                            "Cannot navigate into {} when looking for key '{}'",
-                            current.type_name(),
-                            part
+                            "Cannot navigate into {} when looking for '{}'",
+                            other.type_name(),
+                            segment
                         ),
                         "table",
-                        current.type_name(),
+                        other.type_name(),
                     ))
                 }
-            }
-        }
-
-        // Get the final value
-        match current {
-            Value::Table(table) => table
-                .get_mut(*last_key)
-                .ok_or_else(|| Error::key_not_found(*last_key)),
-            _ => Err(Error::type_error(
-                format!("Cannot get key '{}' from {}", last_key, current.type_name()),
-                "table",
-                current.type_name(),
-            )),
+            };
         }
+        Ok(current)
     }
 
     /// Set a value by path, creating intermediate tables as needed (ZERO-COPY optimized)
+    ///
+    /// Missing intermediate keys become tables, or empty arrays when the
+    /// next segment is an index. An index equal to the array length
+    /// appends; any other out-of-range index is an error. Like [`get`],
+    /// a path that only exists as a literal dotted key of this table (INI
+    /// `section.key`) updates that key instead of creating a nested copy.
+    ///
+    /// [`get`]: Value::get
     pub fn set_nested(&mut self, path: &str, value: Value) -> Result<()> {
         if path.is_empty() {
             return Err(Error::key_not_found(""));
         }
 
-        let parts: Vec<&str> = path.split('.').collect();
-        if parts.is_empty() {
-            return Err(Error::key_not_found(path));
+        let segments = path::parse(path)?;
+        if let Some(table) = self.flat_key_table(path, &segments) {
+            table.insert(path.to_string(), value);
+            return Ok(());
         }
-
-        let (last_key, parent_path) = parts
+        let (last, parents) = segments
             .split_last()
             .ok_or_else(|| Error::key_not_found(path))?;
 
-        // Navigate to parent, creating tables as needed
+        // Navigate to parent, creating containers as needed
         let mut current = self;
-        for part in parent_path {
-            if let Value::Table(table) = current {
-                // ZERO-COPY: Use entry API to avoid string allocation when possible
-                let entry = table
-                    .entry((*part).to_string())
-                    .or_insert_with(|| Value::table(BTreeMap::new()));
-                current = entry;
-            } else {
-                return Err(Error::type_error(
-                    format!("Cannot navigate into {}", current.type_name()),
-                    "table",
-                    current.type_name(),
-                ));
-            }
+        for (i, segment) in parents.iter().enumerate() {
+            let next_is_index = matches!(segments[i + 1], PathSegment::Index(_));
+            current = current.child_or_insert(segment, || {
+                if next_is_index {
+                    Value::array(Vec::new())
+                } else {
+                    Value::table(BTreeMap::new())
+                }
+            })?;
         }
 
         // Set the final value
-        if let Value::Table(table) = current {
-            table.insert((*last_key).to_string(), value);
-            Ok(())
-        } else {
-            Err(Error::type_error(
-                format!("Cannot set key in {}", current.type_name()),
+        match (current, last) {
+            (Value::Table(table), PathSegment::Key(key)) => {
+                table.insert(key.to_string(), value);
+                Ok(())
+            }
+            (Value::Array(items), PathSegment::Index(raw)) => {
+                match last.resolve_index(items.len()) {
+                    Some(index) => items[index] = value,
+                    None if usize::try_from(*raw).ok() == Some(items.len()) => items.push(value),
+                    None => return Err(Error::key_not_found(path)),
+                }
+                Ok(())
+            }
+            (other, _) => Err(Error::type_error(
+                format!("Cannot set {last} in {}", other.type_name()),
+                if matches!(last, PathSegment::Index(_)) {
+                    "array"
+                } else {
+                    "table"
+                },
+                other.type_name(),
+            )),
+        }
+    }
+
+    /// This table, when `path` has several segments, does not resolve
+    /// structurally, and is stored verbatim as a key (the `get` fallback).
+    fn flat_key_table(
+        &mut self,
+        path: &str,
+        segments: &[PathSegment<'_>],
+    ) -> Option<&mut BTreeMap<String, Value>> {
+        if segments.len() < 2 || self.get_segments(segments).is_some() {
+            return None;
+        }
+        match self {
+            Value::Table(table) if table.contains_key(path) => Some(table),
+            _ => None,
+        }
+    }
+
+    /// Step into the child named by `segment`, inserting `make()` when a
+    /// table key is missing or an index equals the array length.
+    fn child_or_insert(
+        &mut self,
+        segment: &PathSegment<'_>,
+        make: impl FnOnce() -> Value,
+    ) -> Result<&mut Value> {
+        match (self, segment) {
+            (Value::Table(table), PathSegment::Key(key)) => {
+                Ok(table.entry(key.to_string()).or_insert_with(make))
+            }
+            (Value::Array(items), PathSegment::Index(raw)) => {
+                if let Some(index) = segment.resolve_index(items.len()) {
+                    Ok(&mut items[index])
+                } else if usize::try_from(*raw).ok() == Some(items.len()) {
+                    items.push(make());
+                    let last = items.len() - 1;
+                    Ok(&mut items[last])
+                } else {
+                    Err(Error::key_not_found(segment.to_string()))
+                }
+            }
+            (other, _) => Err(Error::type_error(
+                format!("Cannot navigate into {}", other.type_name()),
                 "table",
-                current.type_name(),
-            ))
+                other.type_name(),
+            )),
         }
     }
 
     /// Remove a value by path (ENTERPRISE ERROR HANDLING)
+    ///
+    /// Removing an array element shifts the following elements down.
+    /// A missing final key or out-of-range final index yields `Ok(None)`.
+    /// Literal dotted keys are removed as [`set_nested`](Value::set_nested)
+    /// updates them.
     pub fn remove(&mut self, path: &str) -> Result<Option<Value>> {
         if path.is_empty() {
             let old = std::mem::replace(self, Value::Null);
             return Ok(Some(old));
         }
 
-        let parts: Vec<&str> = path.split('.').collect();
-        if parts.is_empty() {
-            return Err(Error::key_not_found(path));
+        let segments = path::parse(path)?;
+        if let Some(table) = self.flat_key_table(path, &segments) {
+            return Ok(table.remove(path));
         }
-
-        let (last_key, parent_path) = parts
+        let (last, parents) = segments
             .split_last()
             .ok_or_else(|| Error::key_not_found(path))?;
 
         // Navigate to parent
         let mut current = self;
-        for part in parent_path {
-            match current {
-                Value::Table(table) => {
-                    current = table
-                        .get_mut(*part)
-                        .ok_or_else(|| Error::key_not_found(*part))?;
+        for segment in parents {
+            current = match (current, segment) {
+                (Value::Table(table), PathSegment::Key(key)) => table
+                    .get_mut(key.as_ref())
+                    .ok_or_else(|| Error::key_not_found(key.as_ref()))?,
+                (Value::Array(items), PathSegment::Index(_)) => {
+                    match segment.resolve_index(items.len()) {
+                        Some(index) => &mut items[index],
+                        None => return Err(Error::key_not_found(segment.to_string())),
+                    }
                 }
-                _ => {
+                (other, _) => {
                     return Err(Error::type_error(
                         format!(
-                            "Cannot navigate into {} when removing key '{}'",
-                            current.type_name(),
-                            part
+                            "Cannot navigate into {} when removing '{}'",
+                            other.type_name(),
+                            segment
                         ),
                         "table",
-                        current.type_name(),
+                        other.type_name(),
                     ))
                 }
-            }
+            };
         }
 
         // Remove from parent
-        if let Value::Table(table) = current {
-            Ok(table.remove(*last_key))
-        } else {
-            Err(Error::type_error(
-                format!(
-                    "Cannot remove key '{}' from {}",
-                    last_key,
-                    current.type_name()
-                ),
+        match (current, last) {
+            (Value::Table(table), PathSegment::Key(key)) => Ok(table.remove(key.as_ref())),
+            (Value::Array(items), PathSegment::Index(_)) => Ok(last
+                .resolve_index(items.len())
+                .map(|index| items.remove(index))),
+            (other, _) => Err(Error::type_error(
+                format!("Cannot remove '{last}' from {}", other.type_name()),
                 "table",
-                current.type_name(),
-            ))
+                other.type_name(),
+            )),
         }
     }
 
@@ -665,4 +733,83 @@ mod tests {
         assert!(value.set_nested("test.key", Value::string("value")).is_ok());
         assert!(value.get("test.key").is_some());
     }
+
+    #[test]
+    fn test_array_index_paths() {
+        let mut value = Value::table(BTreeMap::new());
+        value
+            .set_nested(
+                "list",
+                Value::array(vec![
+                    Value::integer(1),
+                    Value::integer(2),
+                    Value::integer(3),
+                ]),
+            )
+            .unwrap();
+
+        assert_eq!(value.get("list[0]"), Some(&Value::integer(1)));
+        assert_eq!(value.get("list[-1]"), Some(&Value::integer(3)));
+        assert_eq!(value.get("list[3]"), None);
+        assert_eq!(value.get("list[-4]"), None);
+
+        value.set_nested("list[1]", Value::integer(20)).unwrap();
+        value.set_nested("list[3]", Value::integer(4)).unwrap();
+        assert!(value.set_nested("list[9]", Value::integer(0)).is_err());
+        assert_eq!(value.get("list").unwrap().len(), 4);
+
+        *value.get_mut_nested("list[-1]").unwrap() = Value::integer(40);
+        assert_eq!(value.remove("list[0]").unwrap(), Some(Value::integer(1)));
+        assert_eq!(value.remove("list[10]").unwrap(), None);
+        assert_eq!(
+            value.get("list").unwrap(),
+            &Value::array(vec![
+                Value::integer(20),
+                Value::integer(3),
+                Value::integer(40)
+            ])
+        );
+
+        value
+            .set_nested("nodes[0].name", Value::string("n0"))
+            .unwrap();
+        assert_eq!(value.get("nodes[0].name"), Some(&Value::string("n0")));
+    }
+
+    #[test]
+    fn test_quoted_keys_and_flat_fallback() {
+        let mut value = Value::table(BTreeMap::new());
+        value
+            .set_nested(r#"hosts."db.internal".port"#, Value::integer(5432))
+            .unwrap();
+        assert_eq!(
+            value.get(r#"hosts."db.internal".port"#),
+            Some(&Value::integer(5432))
+        );
+        assert!(value.get("hosts.db.internal.port").is_none());
+
+        // INI-style flat dotted keys still resolve
+        let mut table = BTreeMap::new();
+        table.insert("section.key".to_string(), Value::string("flat"));
+        let mut flat = Value::table(table);
+        assert_eq!(flat.get("section.key"), Some(&Value::string("flat")));
+
+        // ...and are updated and removed in place
+        flat.set_nested("section.key", Value::string("edited"))
+            .unwrap();
+        assert_eq!(flat.as_table().unwrap().len(), 1);
+        assert_eq!(flat.get("section.key"), Some(&Value::string("edited")));
+        assert_eq!(
+            flat.remove("section.key").unwrap(),
+            Some(Value::string("edited"))
+        );
+        flat.set_nested("section.key", Value::integer(1)).unwrap();
+        assert_eq!(
+            flat.get("section"),
+            Some(&Value::table(BTreeMap::from([(
+                "key".to_string(),
+                Value::integer(1)
+            )])))
+        );
+    }
 }
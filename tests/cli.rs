@@ -0,0 +1,335 @@
+//! Integration test: drive the `config-lib` binary end-to-end.
+//!
+//! Each test runs the built binary against files in a temporary directory
+//! and checks stdout, stderr and the exit status the way a shell script
+//! would see them.
+
+#![cfg(feature = "cli")]
+#![allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Output, Stdio};
+use tempfile::TempDir;
+
+const APP: &str = r#"[server]
+host = "localhost"
+port = 8080
+tags = ["web", "api"]
+
+[database]
+url = "postgres://db"
+"#;
+
+fn config_lib(args: &[&str], dir: &Path) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_config-lib"))
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8(output.stderr.clone()).unwrap()
+}
+
+fn workspace() -> TempDir {
+    let dir = TempDir::new().unwrap();
+    std::fs::write(dir.path().join("app.toml"), APP).unwrap();
+    dir
+}
+
+#[test]
+fn get_prints_values_in_each_output_mode() {
+    let dir = workspace();
+    let path = dir.path();
+
+    let scalar = config_lib(&["get", "app.toml", "server.host"], path);
+    assert!(scalar.status.success());
+    assert_eq!(stdout(&scalar), "localhost\n");
+
+    let table = config_lib(&["get", "app.toml", "server"], path);
+    assert_eq!(
+        stdout(&table),
+        "server.host = localhost\nserver.port = 8080\nserver.tags[0] = web\nserver.tags[1] = api\n"
+    );
+
+    let json = config_lib(&["get", "app.toml", "server.tags", "--json"], path);
+    let parsed = config_lib::parse(&stdout(&json), Some("json")).unwrap();
+    assert_eq!(parsed.as_array().unwrap().len(), 2);
+
+    let export = config_lib(
+        &[
+            "get", "app.toml", "database", "-o", "export", "--prefix", "APP_",
+        ],
+        path,
+    );
+    assert_eq!(stdout(&export), "export APP_DATABASE_URL='postgres://db'\n");
+}
+
+#[test]
+fn get_missing_key_fails() {
+    let dir = workspace();
+    let output = config_lib(&["get", "app.toml", "server.missing"], dir.path());
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).contains("server.missing"));
+    assert!(stdout(&output).is_empty());
+}
+
+#[test]
+fn keys_lists_leaf_paths() {
+    let dir = workspace();
+    let output = config_lib(&["keys", "app.toml"], dir.path());
+    assert_eq!(
+        stdout(&output),
+        "database.url\nserver.host\nserver.port\nserver.tags[0]\nserver.tags[1]\n"
+    );
+
+    let json = config_lib(&["keys", "app.toml", "database", "--json"], dir.path());
+    assert_eq!(stdout(&json), "[\n  \"database.url\"\n]\n");
+}
+
+#[test]
+fn set_types_values_and_saves_in_place() {
+    let dir = workspace();
+    let path = dir.path();
+
+    assert!(
+        config_lib(&["set", "app.toml", "server.port", "9090"], path)
+            .status
+            .success()
+    );
+    assert!(
+        config_lib(&["set", "app.toml", "server.build", "42", "--string"], path)
+            .status
+            .success()
+    );
+
+    let saved = std::fs::read_to_string(path.join("app.toml")).unwrap();
+    assert!(saved.contains("port = 9090"));
+    assert!(saved.contains("build = \"42\""));
+    // Untouched entries keep their formatting
+    assert!(saved.contains("tags = [\"web\", \"api\"]"));
+}
+
+#[test]
+fn convert_reports_lossy_steps() {
+    let dir = workspace();
+    let path = dir.path();
+
+    let json = config_lib(&["convert", "app.toml", "--to", "json"], path);
+    assert!(json.status.success());
+    let parsed = config_lib::parse(&stdout(&json), Some("json")).unwrap();
+    assert_eq!(
+        parsed.get("server.port").unwrap().as_integer().unwrap(),
+        8080
+    );
+    assert!(stderr(&json).is_empty());
+
+    std::fs::write(path.join("nested.json"), r#"{"a": {"b": {"c": 1}}}"#).unwrap();
+    let lossy = config_lib(&["convert", "nested.json", "--to", "dotenv"], path);
+    assert!(lossy.status.success());
+    assert!(stderr(&lossy).contains("lossy"));
+
+    let strict = config_lib(
+        &["convert", "nested.json", "--to", "dotenv", "--strict"],
+        path,
+    );
+    assert_eq!(strict.status.code(), Some(1));
+    assert!(stdout(&strict).is_empty());
+}
+
+#[test]
+fn validate_against_json_schema() {
+    let dir = workspace();
+    let path = dir.path();
+    std::fs::write(
+        path.join("schema.json"),
+        r#"{
+            "type": "object",
+            "required": ["server"],
+            "properties": {
+                "server": {
+                    "type": "object",
+                    "properties": {
+                        "host": {"type": "string"},
+                        "port": {"type": "integer"}
+                    }
+                }
+            }
+        }"#,
+    )
+    .unwrap();
+
+    let valid = config_lib(&["validate", "app.toml", "--schema", "schema.json"], path);
+    assert!(valid.status.success());
+    assert_eq!(stdout(&valid), "app.toml: valid\n");
+
+    std::fs::write(
+        path.join("bad.toml"),
+        "[server]\nport = \"high\"\nhost = 1\n",
+    )
+    .unwrap();
+    let invalid = config_lib(
+        &["validate", "bad.toml", "--schema", "schema.json", "--json"],
+        path,
+    );
+    assert_eq!(invalid.status.code(), Some(1));
+    let report = config_lib::parse(&stdout(&invalid), Some("json")).unwrap();
+    assert!(!report.get("valid").unwrap().as_bool().unwrap());
+    let errors: Vec<&str> = report
+        .get("errors")
+        .unwrap()
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|error| error.get("path").unwrap().as_string().unwrap())
+        .collect();
+    assert_eq!(errors, ["server.host", "server.port"]);
+}
+
+#[test]
+fn detect_reads_files_and_stdin() {
+    let dir = workspace();
+    let output = config_lib(&["detect", "app.toml"], dir.path());
+    assert_eq!(stdout(&output), "toml\n");
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_config-lib"))
+        .args(["detect", "--json"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(br#"{"name": "app"}"#)
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert_eq!(stdout(&output), "{\n  \"format\": \"json\"\n}\n");
+
+    // Unknown extensions are read as CONF unless `--strict` sniffs them
+    std::fs::write(dir.path().join("app.txt"), "[a]\nb = 1\n").unwrap();
+    let lenient = config_lib(&["detect", "app.txt"], dir.path());
+    assert_eq!(stdout(&lenient), "conf\n");
+    let strict = config_lib(&["detect", "app.txt", "--strict"], dir.path());
+    assert_eq!(strict.status.code(), Some(1));
+    assert!(stderr(&strict).contains("could be toml (0.80) or ini (0.75)"));
+
+    std::fs::write(dir.path().join("settings"), "[a]\nb = \"x\"\n").unwrap();
+    let sniffed = config_lib(&["detect", "settings", "--strict"], dir.path());
+    assert_eq!(stdout(&sniffed), "toml\n");
+}
+
+#[test]
+fn usage_errors_exit_with_status_two() {
+    let dir = workspace();
+    for args in [
+        &["frobnicate"][..],
+        &["get", "app.toml"],
+        &["convert", "app.toml"],
+        &["get", "app.toml", "a", "--output", "yaml"],
+        &["get", "app.toml", "a", "--sarif"],
+        &["lint", "app.toml", "--disable", "no-such-rule"],
+    ] {
+        let output = config_lib(args, dir.path());
+        assert_eq!(output.status.code(), Some(2), "{args:?}");
+        assert!(stderr(&output).contains("Usage: config-lib"));
+    }
+    assert!(config_lib(&["--help"], dir.path()).status.success());
+}
+
+#[test]
+fn fmt_check_and_rewrite() {
+    let dir = workspace();
+    let path = dir.path();
+    std::fs::write(
+        path.join("messy.ini"),
+        "# Listener\nport:8080\nhost_name='localhost'\n[db]\n\n\nurl = x\n",
+    )
+    .unwrap();
+    std::fs::write(path.join("clean.conf"), "name = app\n").unwrap();
+
+    let check = config_lib(&["fmt", "--check", "messy.ini", "clean.conf"], path);
+    assert_eq!(check.status.code(), Some(1));
+    assert_eq!(stdout(&check), "messy.ini\n");
+    // `--check` never writes
+    assert!(std::fs::read_to_string(path.join("messy.ini"))
+        .unwrap()
+        .contains("port:8080"));
+
+    let write = config_lib(&["fmt", "messy.ini", "clean.conf"], path);
+    assert!(write.status.success());
+    assert_eq!(
+        std::fs::read_to_string(path.join("messy.ini")).unwrap(),
+        "; Listener\nport      = 8080\nhost_name = localhost\n\n[db]\nurl = x\n"
+    );
+    assert!(config_lib(&["fmt", "--check", "messy.ini"], path)
+        .status
+        .success());
+
+    std::fs::write(path.join("notes.xml"), "<a><!-- keep --><b>1</b></a>").unwrap();
+    let refused = config_lib(&["fmt", "notes.xml"], path);
+    assert_eq!(refused.status.code(), Some(1));
+    assert!(stderr(&refused).contains("notes.xml"));
+    assert_eq!(
+        std::fs::read_to_string(path.join("notes.xml")).unwrap(),
+        "<a><!-- keep --><b>1</b></a>"
+    );
+}
+
+#[test]
+fn lint_reports_findings_as_text_json_and_sarif() {
+    let dir = workspace();
+    let path = dir.path();
+    std::fs::write(
+        path.join("app.ini"),
+        "[server]\nhost = a\nport = 80800\nhost = b\n",
+    )
+    .unwrap();
+    std::fs::write(path.join("quiet.conf"), "name = \"\"\n").unwrap();
+
+    let text = config_lib(&["lint", "app.ini", "app.toml"], path);
+    assert_eq!(text.status.code(), Some(1));
+    assert_eq!(
+        stdout(&text),
+        "app.ini:3:1: error[port-range] \"server.port\": 80800 is outside the port range 0-65535\n\
+         app.ini:4:1: error[duplicate-key] \"server.host\": already defined on line 2; this value replaces it\n"
+    );
+
+    // Info findings are reported without failing the run
+    let info = config_lib(&["lint", "quiet.conf"], path);
+    assert!(info.status.success());
+    assert!(stdout(&info).contains("info[empty-string]"));
+
+    let json = config_lib(
+        &["lint", "app.ini", "--json", "--disable", "port-range"],
+        path,
+    );
+    let reports = config_lib::parse(&stdout(&json), Some("json")).unwrap();
+    assert_eq!(
+        reports
+            .get("[0].findings[0].rule")
+            .unwrap()
+            .as_string()
+            .unwrap(),
+        "duplicate-key"
+    );
+    assert!(reports.get("[0].findings[1]").is_none());
+
+    let sarif = config_lib(&["lint", "app.ini", "--sarif"], path);
+    let log = config_lib::parse(&stdout(&sarif), Some("json")).unwrap();
+    assert_eq!(log.get("version").unwrap().as_string().unwrap(), "2.1.0");
+    assert_eq!(
+        log.get("runs[0].results[1].locations[0].physicalLocation.region.startLine")
+            .unwrap()
+            .as_integer()
+            .unwrap(),
+        4
+    );
+}
@@ -167,6 +167,85 @@ fn test_format_detection() {
     assert_eq!(config.format(), "conf"); // Will be conf since that's what we specified
 }
 
+/// Test content sniffing by trial parse
+#[test]
+fn test_scored_format_detection() {
+    use config_lib::parsers::{detect_format_scored, detect_format_strict};
+
+    // Bare values rule out TOML; the section rules out Properties
+    let ini = "; listener\n[server]\nhost = localhost\n";
+    let scores = detect_format_scored(ini);
+    assert_eq!(scores[0].0, "ini");
+    assert!(scores.windows(2).all(|pair| pair[0].1 >= pair[1].1));
+    assert!(scores.iter().all(|(format, _)| *format != "properties"));
+    assert_eq!(detect_format_strict(ini).unwrap(), "ini");
+
+    // `detect_format` reads any line with a `T` and a `Z` as TOML
+    let properties = "Zone=Tokyo\n! continued below\nnames=a,\\\n  b\n";
+    assert_eq!(detect_format_scored(properties)[0].0, "properties");
+    assert_eq!(detect_format_strict(properties).unwrap(), "properties");
+
+    // Deep nesting rules nested formats out instead of overflowing the stack
+    for (open, nested) in [
+        ("[", &["json", "json5", "yaml"][..]),
+        ("- ", &["yaml"]),
+        ("x = [", &["toml", "noml", "hcl"]),
+        ("x = {", &["toml", "noml", "hcl"]),
+    ] {
+        let scores = detect_format_scored(&open.repeat(5000));
+        assert!(
+            scores.iter().all(|(format, _)| !nested.contains(format)),
+            "{open:?}: {scores:?}"
+        );
+    }
+
+    #[cfg(feature = "toml")]
+    {
+        let toml = "[server]\nhost = \"localhost\"\nports = [80, 443]\n";
+        assert_eq!(detect_format_strict(toml).unwrap(), "toml");
+
+        // TOML and INI both parse it, to different values
+        let either = "[a]\nb = 1\n";
+        let error = detect_format_strict(either).unwrap_err();
+        assert!(matches!(error, config_lib::Error::UnknownFormat { .. }));
+        assert!(error.to_string().contains("toml (0.80) or ini (0.75)"));
+    }
+}
+
+/// Test that strict file parsing refuses to guess
+#[test]
+fn test_parse_file_strict() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+
+    // No extension: detected from the content
+    let plain = dir.path().join("settings");
+    std::fs::write(&plain, "[server]\nhost = localhost\nport = 8080\n")?;
+    let value = config_lib::parse_file_strict(&plain)?;
+    assert_eq!(value.get("server.port").unwrap().as_integer()?, 8080);
+
+    // A known extension decides without sniffing
+    let conf = dir.path().join("app.conf");
+    std::fs::write(&conf, "name = app\n")?;
+    assert_eq!(
+        config_lib::parse_file_strict(&conf)?
+            .get("name")
+            .unwrap()
+            .as_string()?,
+        "app"
+    );
+
+    // Unknown extensions are sniffed rather than read as CONF
+    #[cfg(feature = "toml")]
+    {
+        let unknown = dir.path().join("app.txt");
+        std::fs::write(&unknown, "[a]\nb = 1\n")?;
+        assert!(config_lib::parse_file(&unknown).is_ok());
+        let error = config_lib::parse_file_strict(&unknown).unwrap_err();
+        assert!(error.to_string().contains("app.txt could be toml"));
+    }
+    Ok(())
+}
+
 /// Test configuration modification and change tracking
 #[test]
 fn test_config_modification() {
@@ -301,6 +380,522 @@ fn test_serialization_roundtrip() {
     assert!(reparsed.get("section.nested").unwrap().as_bool().unwrap());
 }
 
+/// Saving an edited CONF file keeps comments, ordering and quoting
+#[test]
+fn test_conf_save_preserves_layout() -> Result<(), Box<dyn std::error::Error>> {
+    let original = "# Operator notes: do not lower the pool size\n\nzeta = 1\nalpha = 'two'  # legacy name\n\n[database]\n# primary\nhost = db1\nport = 5432\n";
+    let mut file = tempfile::Builder::new().suffix(".conf").tempfile()?;
+    write!(file, "{original}")?;
+
+    let mut config = Config::from_file(file.path())?;
+    assert_eq!(config.serialize()?, original);
+
+    config.set("database.port", 6432)?;
+    config.remove("zeta")?;
+    config.set("database.pool", 20)?;
+    config.save()?;
+
+    let saved = std::fs::read_to_string(file.path())?;
+    assert_eq!(
+        saved,
+        "# Operator notes: do not lower the pool size\n\nalpha = 'two'  # legacy name\n\n[database]\n# primary\nhost = db1\nport = 6432\npool = 20\n"
+    );
+    assert_eq!(
+        Config::from_file(file.path())?.as_value(),
+        config.as_value()
+    );
+
+    // Shapes CONF cannot hold are refused instead of regenerating the file
+    let mut nested = Config::from_file(file.path())?;
+    nested.set("database.limits.size", 5)?;
+    assert!(nested.save().is_err());
+    let mut single = Config::from_file(file.path())?;
+    single.set("database.tags", vec![config_lib::Value::integer(1)])?;
+    assert!(single.save().is_err());
+    assert_eq!(std::fs::read_to_string(file.path())?, saved);
+    Ok(())
+}
+
+/// INI configs can be edited and saved without losing their layout
+#[test]
+fn test_ini_save_preserves_layout() -> Result<(), Box<dyn std::error::Error>> {
+    let original = "; Windows service\r\n[Service]\r\nName=Indexer\r\nPort: 8080 ; http\r\n\r\n[Logging]\r\nLevel=info\r\n";
+    let mut file = tempfile::Builder::new().suffix(".ini").tempfile()?;
+    write!(file, "{original}")?;
+
+    let mut config = Config::from_file(file.path())?;
+    assert_eq!(config.format(), "ini");
+    assert_eq!(config.serialize()?, original);
+
+    config.set("Service.Port", 9090)?;
+    config.set("Logging.Path", "C:\\logs\\indexer.log")?;
+    config.remove("Service.Name")?;
+    config.save()?;
+
+    let saved = std::fs::read_to_string(file.path())?;
+    assert_eq!(
+        saved,
+        "; Windows service\r\n[Service]\r\nPort: 9090 ; http\r\n\r\n[Logging]\r\nLevel=info\r\nPath=C:\\\\logs\\\\indexer.log\r\n"
+    );
+    let reloaded = Config::from_file(file.path())?;
+    assert_eq!(reloaded.get("Service.Port").unwrap().as_integer()?, 9090);
+    assert_eq!(
+        reloaded.get("Logging.Path").unwrap().as_string()?,
+        "C:\\logs\\indexer.log"
+    );
+
+    // Configs built in memory serialize as INI too.
+    let mut fresh = Config::from_string("[a]\nb = 1", Some("ini"))?;
+    fresh.set("c.d", "x y")?;
+    assert_eq!(fresh.serialize()?, "[a]\nb = 1\n\n[c]\nd = x y\n");
+    Ok(())
+}
+
+#[cfg(feature = "hcl")]
+#[test]
+fn test_hcl_terraform_module() -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = tempfile::Builder::new().suffix(".tf").tempfile()?;
+    write!(
+        file,
+        r#"
+terraform {{
+  required_providers {{
+    aws = {{ source = "hashicorp/aws", version = "~> 5.0" }}
+  }}
+}}
+
+locals {{
+  zones = ["a", "b", "c"]
+  subnets = {{
+    for index, zone in local.zones : zone => cidrsubnet(var.cidr, 8, index)
+  }}
+  /* evaluated: no variables involved */
+  ports = [for p in [80, 443, 8080] : p if p < 1000]
+}}
+
+resource "aws_security_group" "web" {{
+  name = "web-${{terraform.workspace}}"
+
+  ingress {{
+    from_port = 80
+    to_port   = 80
+  }}
+  ingress {{
+    from_port = 443
+    to_port   = 443
+  }}
+
+  dynamic "egress" {{
+    for_each = local.ports
+    content {{
+      from_port = egress.value
+    }}
+  }}
+}}
+
+resource "aws_instance" "web" {{
+  count     = 2
+  user_data = <<-EOT
+    #!/bin/bash
+    echo "instance ${{count.index}}"
+  EOT
+}}
+"#
+    )?;
+
+    let config = Config::from_file(file.path())?;
+    assert_eq!(config.format(), "hcl");
+    assert_eq!(
+        config
+            .get("terraform.required_providers.aws.version")
+            .unwrap()
+            .as_string()?,
+        "~> 5.0"
+    );
+    assert_eq!(
+        config.get("locals.subnets").unwrap().as_string()?,
+        "${{\n    for index, zone in local.zones : zone => cidrsubnet(var.cidr, 8, index)\n  }}"
+    );
+    assert_eq!(config.get("locals.ports[1]").unwrap().as_integer()?, 443);
+    assert_eq!(
+        config
+            .get("resource.aws_security_group.web.ingress[1].to_port")
+            .unwrap()
+            .as_integer()?,
+        443
+    );
+    assert_eq!(
+        config
+            .get("resource.aws_security_group.web.dynamic.egress.for_each")
+            .unwrap()
+            .as_string()?,
+        "${local.ports}"
+    );
+    assert_eq!(
+        config
+            .get("resource.aws_instance.web.user_data")
+            .unwrap()
+            .as_string()?,
+        "#!/bin/bash\necho \"instance ${count.index}\"\n"
+    );
+    Ok(())
+}
+
+#[cfg(feature = "hcl")]
+#[test]
+fn test_hcl_nomad_job_save() -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = tempfile::Builder::new().suffix(".nomad").tempfile()?;
+    write!(
+        file,
+        r#"job "cache" {{
+  datacenters = ["dc1"]
+  type = "service"
+
+  group "redis" {{
+    count = 1
+
+    task "redis" {{
+      driver = "docker"
+      config {{
+        image = "redis:7"
+      }}
+    }}
+  }}
+}}
+"#
+    )?;
+
+    let mut config = Config::from_file(file.path())?;
+    assert_eq!(config.format(), "hcl");
+    config.set("job.cache.group.redis.count", 3)?;
+    config.set("job.cache.group.redis.task.redis.config.image", "redis:7.2")?;
+    config.save()?;
+
+    let saved = std::fs::read_to_string(file.path())?;
+    assert_eq!(
+        saved,
+        r#"job "cache" {
+  datacenters = ["dc1"]
+  type        = "service"
+
+  group "redis" {
+    count = 3
+
+    task "redis" {
+      driver = "docker"
+
+      config {
+        image = "redis:7.2"
+      }
+    }
+  }
+}
+"#
+    );
+    let reloaded = Config::from_file(file.path())?;
+    assert_eq!(
+        reloaded
+            .get("job.cache.group.redis.count")
+            .unwrap()
+            .as_integer()?,
+        3
+    );
+    Ok(())
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn test_json_save_is_a_minimal_diff() -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = tempfile::Builder::new().suffix(".json").tempfile()?;
+    write!(
+        file,
+        r#"{{
+   "service": "billing",
+   "database": {{
+      "port": 5432,
+      "host": "db.internal",
+      "ratio": 1.0
+   }},
+   "features": ["a", "b"]
+}}
+"#
+    )?;
+
+    let mut config = Config::from_file(file.path())?;
+    assert_eq!(config.format(), "json");
+    config.set("database.port", 6432)?;
+    config.set("database.pool", 10)?;
+    config.remove("features")?;
+    config.save()?;
+
+    assert_eq!(
+        std::fs::read_to_string(file.path())?,
+        r#"{
+   "service": "billing",
+   "database": {
+      "port": 6432,
+      "host": "db.internal",
+      "ratio": 1.0,
+      "pool": 10
+   }
+}
+"#
+    );
+    Ok(())
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn test_jsonc_settings_edit_keeps_comments() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("settings.json");
+    std::fs::write(
+        &path,
+        r#"// User settings
+{
+    "editor.fontSize": 14, // points
+    "editor.tabSize": 4,
+    "files.exclude": {
+        "**/.git": true,
+    },
+}
+"#,
+    )?;
+
+    let mut config = Config::from_file(&path)?;
+    assert_eq!(config.format(), "jsonc");
+    assert_eq!(config.get("\"editor.tabSize\"").unwrap().as_integer()?, 4);
+    config.set("\"editor.fontSize\"", 16)?;
+    config.set("\"files.exclude\".\"**/target\"", true)?;
+    config.remove("\"editor.tabSize\"")?;
+    config.save()?;
+
+    assert_eq!(
+        std::fs::read_to_string(&path)?,
+        r#"// User settings
+{
+    "editor.fontSize": 16, // points
+    "files.exclude": {
+        "**/.git": true,
+        "**/target": true,
+    },
+}
+"#
+    );
+
+    let json5 = Config::from_string("{name: 'app', port: 0x1F90}", None)?;
+    assert_eq!(json5.format(), "json5");
+    assert_eq!(json5.get("port").unwrap().as_integer()?, 8080);
+    Ok(())
+}
+
+#[test]
+fn test_convert_between_formats() -> Result<(), Box<dyn std::error::Error>> {
+    use config_lib::convert::LossKind;
+
+    let dir = tempfile::tempdir()?;
+    let source = dir.path().join("service.conf");
+    std::fs::write(
+        &source,
+        "name = billing\nreplicas = 3\n\n[database]\nhost = db.internal\nport = 5432\n",
+    )?;
+    let config = Config::from_file(&source)?;
+
+    let (properties, report) = config.convert_to("properties")?;
+    assert_eq!(report.from(), "conf");
+    assert_eq!(report.to(), "properties");
+    assert_eq!(report.losses().len(), 1);
+    assert_eq!(report.losses()[0].path, "database");
+    assert_eq!(report.losses()[0].kind, LossKind::Flattened);
+
+    let target = dir.path().join("service.properties");
+    properties.save_to_file(&target)?;
+    let reloaded = Config::from_file(&target)?;
+    assert_eq!(reloaded.get("database.port").unwrap().as_integer()?, 5432);
+
+    #[cfg(feature = "toml")]
+    {
+        let (toml, report) = config.convert_to("toml")?;
+        assert!(report.is_lossless(), "{report}");
+        assert_eq!(toml.as_value(), config.as_value());
+        assert_eq!(
+            config.serialize_as("toml")?,
+            "name = \"billing\"\nreplicas = 3\n\n[database]\nhost = \"db.internal\"\nport = 5432\n"
+        );
+    }
+
+    #[cfg(feature = "xml")]
+    {
+        // Several root keys need a document element to hold them
+        let (xml, report) = config.convert_to("xml")?;
+        assert!(report.is_lossless(), "{report}");
+        assert_eq!(xml.get("config.database.port").unwrap().as_integer()?, 5432);
+        assert!(config
+            .serialize_as("xml")?
+            .contains("<config>\n  <database>\n    <host>db.internal</host>"));
+        assert!(xml.serialize()?.contains("<replicas>3</replicas>"));
+    }
+
+    assert!(config.serialize_as("nope").is_err());
+    Ok(())
+}
+
+#[test]
+fn test_dotenv_load_edit_save() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join(".env");
+    std::fs::write(
+        &path,
+        "# Local overrides\nexport DATABASE_HOST=localhost\nexport DATABASE_PORT=5432 # postgres\nexport DATABASE_URL=\"postgres://${DATABASE_HOST}:${DATABASE_PORT}/app\"\n",
+    )?;
+
+    let mut config = Config::from_file(&path)?;
+    assert_eq!(config.format(), "dotenv");
+    assert_eq!(
+        config.get("DATABASE_URL").unwrap().as_string()?,
+        "postgres://localhost:5432/app"
+    );
+    config.set("DATABASE_PORT", "6543")?;
+    config.set("GREETING", "hello world")?;
+    config.save()?;
+
+    assert_eq!(
+        std::fs::read_to_string(&path)?,
+        "# Local overrides\nexport DATABASE_HOST=localhost\nexport DATABASE_PORT=6543 # postgres\nexport DATABASE_URL=postgres://localhost:5432/app\nexport GREETING=\"hello world\"\n"
+    );
+
+    #[cfg(feature = "env-override")]
+    {
+        use config_lib::env_override::EnvOverrideSystem;
+
+        let defaults = std::collections::BTreeMap::from([(
+            "database".to_string(),
+            config_lib::Value::table(std::collections::BTreeMap::from([(
+                "port".to_string(),
+                config_lib::Value::integer(5432),
+            )])),
+        )]);
+        let config = Config::layered()
+            .with_defaults(config_lib::Value::table(defaults))
+            .with_env(EnvOverrideSystem::with_prefix("").with_dotenv_file(&path)?)
+            .build()?;
+        assert_eq!(config.get("database.port").unwrap().as_integer()?, 6543);
+        assert!(std::env::var("DATABASE_PORT").is_err());
+    }
+    Ok(())
+}
+
+#[cfg(feature = "yaml")]
+#[test]
+fn test_yaml_load_edit_save() -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = tempfile::Builder::new().suffix(".yml").tempfile()?;
+    write!(
+        file,
+        "defaults: &defaults\n  timeout: 30\n  retries: 3\nservice:\n  <<: *defaults\n  name: api\n  hosts:\n  - alpha\n  - beta\n"
+    )?;
+
+    let mut config = Config::from_file(file.path())?;
+    assert_eq!(config.format(), "yaml");
+    assert_eq!(config.get("service.timeout").unwrap().as_integer()?, 30);
+    assert_eq!(config.get("service.hosts[1]").unwrap().as_string()?, "beta");
+
+    config.set("service.name", "gateway: v2")?;
+    config.save()?;
+
+    let reloaded = Config::from_file(file.path())?;
+    assert_eq!(
+        reloaded.get("service.name").unwrap().as_string()?,
+        "gateway: v2"
+    );
+    assert_eq!(reloaded.get("service.retries").unwrap().as_integer()?, 3);
+    assert_eq!(
+        config_lib::parsers::detect_format("---\nname: demo\n"),
+        "yaml"
+    );
+    Ok(())
+}
+
+#[cfg(feature = "xml")]
+#[test]
+fn test_xml_save_keeps_attributes() -> Result<(), Box<dyn std::error::Error>> {
+    let original = r#"<?xml version="1.0" encoding="UTF-8"?>
+<configuration>
+  <appSettings>
+    <add key="Mode" value="production"/>
+    <add key="Retries" value="3"/>
+  </appSettings>
+  <connection server="db01" timeout="30"/>
+</configuration>
+"#;
+    let mut file = tempfile::Builder::new().suffix(".xml").tempfile()?;
+    write!(file, "{original}")?;
+
+    let mut config = Config::from_file(file.path())?;
+    assert_eq!(config.format(), "xml");
+    assert_eq!(config.serialize()?, original);
+
+    config.set("configuration.connection.timeout", "60")?;
+    config.set("configuration.logging.level", "debug")?;
+    config.save()?;
+
+    let saved = std::fs::read_to_string(file.path())?;
+    assert_eq!(
+        saved,
+        original
+            .replace(r#"timeout="30""#, r#"timeout="60""#)
+            .replace(
+                "</configuration>",
+                "  <logging>\n    <level>debug</level>\n  </logging>\n</configuration>"
+            )
+    );
+    let reloaded = Config::from_file(file.path())?;
+    assert_eq!(
+        reloaded
+            .get("configuration.connection.timeout")
+            .unwrap()
+            .as_string()?,
+        "60"
+    );
+    assert_eq!(
+        reloaded
+            .get("configuration.appSettings.add")
+            .unwrap()
+            .as_array()?
+            .len(),
+        2
+    );
+    Ok(())
+}
+
+#[cfg(feature = "toml")]
+#[test]
+fn test_toml_save_writes_edits() -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = tempfile::Builder::new().suffix(".toml").tempfile()?;
+    write!(
+        file,
+        "# Deployed 2024\nreleased = 2024-05-01\n\n[server]\nport = 8080 # public\n"
+    )?;
+
+    let mut config = Config::from_file(file.path())?;
+    config.set("server.host", "example.com")?;
+    config.set("limits.ratio", 1.0)?;
+    config.save()?;
+
+    let saved = std::fs::read_to_string(file.path())?;
+    assert_eq!(
+        saved,
+        "# Deployed 2024\nreleased = 2024-05-01\n\n[server]\nport = 8080 # public\n\
+         host = \"example.com\"\n\n[limits]\nratio = 1.0\n"
+    );
+    let reloaded = Config::from_file(file.path())?;
+    assert_eq!(
+        reloaded.get("server.host").unwrap().as_string()?,
+        "example.com"
+    );
+    assert_eq!(reloaded.get("limits.ratio").unwrap().as_float()?, 1.0);
+    Ok(())
+}
+
 /// Test error handling
 #[test]
 fn test_error_handling() {
@@ -383,3 +978,109 @@ async fn test_async_operations() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+/// Typed deserialization works the same across formats
+#[test]
+fn test_typed_deserialize_across_formats() {
+    #[derive(serde::Deserialize, Debug, PartialEq)]
+    struct Database {
+        host: String,
+        port: u16,
+    }
+
+    let conf = Config::from_string("[database]\nhost = db\nport = 5432", Some("conf")).unwrap();
+    let expected = Database {
+        host: "db".to_string(),
+        port: 5432,
+    };
+    assert_eq!(conf.get_as::<Database>("database").unwrap(), expected);
+
+    #[cfg(feature = "json")]
+    {
+        let json = Config::from_string(
+            r#"{"database": {"host": "db", "port": 5432}}"#,
+            Some("json"),
+        )
+        .unwrap();
+        assert_eq!(json.get_as::<Database>("database").unwrap(), expected);
+    }
+}
+
+/// Typed defaults can be emitted as a config file and read back
+#[test]
+fn test_serialize_defaults_round_trip() {
+    #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+    struct Defaults {
+        name: String,
+        port: u16,
+        debug: bool,
+    }
+
+    let defaults = Defaults {
+        name: "svc".to_string(),
+        port: 8080,
+        debug: false,
+    };
+    let config = Config::from_serialize(&defaults, "conf").unwrap();
+    let text = config.serialize().unwrap();
+    let reparsed = Config::from_string(&text, Some("conf")).unwrap();
+    assert_eq!(reparsed.try_deserialize::<Defaults>().unwrap(), defaults);
+
+    #[cfg(feature = "json")]
+    {
+        let json = Config::from_serialize(&defaults, "json").unwrap();
+        let text = json.serialize().unwrap();
+        let embedded: config_lib::Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(&embedded, json.as_value());
+    }
+}
+
+/// Layered sources record which file and line set each value
+#[test]
+fn test_layered_file_provenance() -> Result<(), Box<dyn std::error::Error>> {
+    let mut system = tempfile::Builder::new().suffix(".conf").tempfile()?;
+    write!(
+        system,
+        "# system\n[database]\nhost = db.internal\nport = 5432\n"
+    )?;
+
+    let mut defaults = config_lib::Value::table(Default::default());
+    defaults.set_nested("database.host", "localhost".into())?;
+    defaults.set_nested("database.pool", 4.into())?;
+
+    let config = Config::layered()
+        .with_defaults(defaults)
+        .with_file(system.path())
+        .with_optional_file(system.path().with_extension("missing"))
+        .with_args(["--database.pool", "16"])
+        .build()?;
+
+    assert_eq!(config.get("database.port").unwrap().as_integer()?, 5432);
+    assert_eq!(config.get("database.pool").unwrap().as_integer()?, 16);
+
+    let port = config.origin("database.port").unwrap();
+    assert_eq!(port.source, "file");
+    assert_eq!(port.file.as_deref(), Some(system.path()));
+    assert_eq!(port.line, Some(4));
+    assert_eq!(config.origin("database.host").unwrap().line, Some(3));
+    assert_eq!(config.origin("database.pool").unwrap().source, "args");
+
+    #[cfg(feature = "env-override")]
+    {
+        use config_lib::env_override::EnvOverrideSystem;
+
+        std::env::set_var("LAYERED_TEST_DATABASE_HOST", "db.env");
+        let config = Config::layered()
+            .with_file(system.path())
+            .with_env(EnvOverrideSystem::with_prefix("LAYERED_TEST_"))
+            .build()?;
+        assert_eq!(config.get("database.host").unwrap().as_string()?, "db.env");
+        let host = config.origin("database.host").unwrap();
+        assert_eq!(host.to_string(), "env LAYERED_TEST_DATABASE_HOST");
+    }
+
+    let missing = Config::layered().with_file(system.path().with_extension("missing"));
+    assert!(missing.build().is_err());
+
+    Ok(())
+}
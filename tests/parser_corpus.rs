@@ -21,6 +21,12 @@
 //! v0.9.9.x / v1.0.x populates `tests/corpus_seeds/` as the
 //! maintainer's clean fuzz runs surface inputs worth keeping
 //! around as permanent regressions.
+//!
+//! The same file also runs third-party conformance suites committed
+//! under `tests/corpus/`. `tests/corpus/toml-test/` is a TOML 1.0
+//! snapshot of [toml-test](https://github.com/BurntSushi/toml-test)
+//! (MIT, see its `LICENSE`): every `valid/*.toml` must parse to the
+//! tagged JSON next to it and every `invalid/*.toml` must be rejected.
 
 #![allow(clippy::unwrap_used, clippy::expect_used)]
 
@@ -48,3 +54,125 @@
 fn corpus_harness_compiles() {
     let _ = config_lib::parsers::conf::parse("sanity=1\n");
 }
+
+// =========================================================================
+// toml-test conformance
+// =========================================================================
+
+#[cfg(feature = "toml")]
+mod toml_test {
+    use config_lib::parsers::toml_parser;
+    use config_lib::Value;
+    use std::path::{Path, PathBuf};
+
+    fn corpus(kind: &str) -> Vec<PathBuf> {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/corpus/toml-test");
+        let mut files = Vec::new();
+        let mut pending = vec![root.join(kind)];
+        while let Some(dir) = pending.pop() {
+            for entry in std::fs::read_dir(&dir).unwrap() {
+                let path = entry.unwrap().path();
+                if path.is_dir() {
+                    pending.push(path);
+                } else if path.extension().is_some_and(|ext| ext == "toml") {
+                    files.push(path);
+                }
+            }
+        }
+        files.sort();
+        assert!(!files.is_empty(), "toml-test corpus missing under {root:?}");
+        files
+    }
+
+    /// toml-test pads fractional seconds (`.6` → `.600000`).
+    fn trim_fraction(text: &str) -> String {
+        let Some((head, tail)) = text.split_once('.') else {
+            return text.to_string();
+        };
+        let digits = tail.bytes().take_while(u8::is_ascii_digit).count();
+        let fraction = tail[..digits].trim_end_matches('0');
+        let dot = if fraction.is_empty() { "" } else { "." };
+        format!("{head}{dot}{fraction}{}", &tail[digits..])
+    }
+
+    /// Compare a parsed value with toml-test's tagged JSON encoding.
+    fn matches(value: &Value, expected: &serde_json::Value) -> bool {
+        use serde_json::Value as Json;
+
+        match expected {
+            Json::Object(map) if map.len() == 2 && map.get("type").is_some_and(Json::is_string) => {
+                let text = map["value"].as_str().unwrap_or_default();
+                match (map["type"].as_str().unwrap(), value) {
+                    ("string", Value::String(s)) => s == text,
+                    ("datetime-local" | "date-local" | "time-local", Value::String(s)) => {
+                        trim_fraction(s) == trim_fraction(text)
+                    }
+                    ("integer", Value::Integer(i)) => text.parse() == Ok(*i),
+                    ("float", Value::Float(f)) => match text {
+                        "nan" | "+nan" | "-nan" => f.is_nan(),
+                        "inf" | "+inf" => *f == f64::INFINITY,
+                        "-inf" => *f == f64::NEG_INFINITY,
+                        _ => text.parse() == Ok(*f),
+                    },
+                    ("bool", Value::Bool(b)) => text.parse() == Ok(*b),
+                    #[cfg(feature = "chrono")]
+                    ("datetime", Value::DateTime(dt)) => {
+                        chrono::DateTime::parse_from_rfc3339(text).is_ok_and(|e| e == *dt)
+                    }
+                    #[cfg(not(feature = "chrono"))]
+                    ("datetime", Value::String(s)) => {
+                        trim_fraction(s).eq_ignore_ascii_case(&trim_fraction(text))
+                    }
+                    _ => false,
+                }
+            }
+            Json::Object(map) => value.as_table().is_ok_and(|table| {
+                table.len() == map.len()
+                    && map
+                        .iter()
+                        .all(|(key, item)| table.get(key).is_some_and(|v| matches(v, item)))
+            }),
+            Json::Array(items) => value.as_array().is_ok_and(|values| {
+                values.len() == items.len()
+                    && values.iter().zip(items).all(|(v, item)| matches(v, item))
+            }),
+            _ => false,
+        }
+    }
+
+    #[test]
+    fn valid_documents_parse_to_expected_values() {
+        let mut failures = Vec::new();
+        for path in corpus("valid") {
+            let source = std::fs::read_to_string(&path).unwrap();
+            let expected: serde_json::Value = serde_json::from_str(
+                &std::fs::read_to_string(path.with_extension("json")).unwrap(),
+            )
+            .unwrap();
+            match toml_parser::parse(&source) {
+                Ok(value) if matches(&value, &expected) => {}
+                Ok(value) => failures.push(format!("{}: got {value:?}", path.display())),
+                Err(err) => failures.push(format!("{}: {err}", path.display())),
+            }
+        }
+        assert!(failures.is_empty(), "{}", failures.join("\n"));
+    }
+
+    #[test]
+    fn invalid_documents_are_rejected() {
+        let mut accepted = Vec::new();
+        for path in corpus("invalid") {
+            let bytes = std::fs::read(&path).unwrap();
+            if let Ok(source) = std::str::from_utf8(&bytes) {
+                if toml_parser::parse(source).is_ok() {
+                    accepted.push(path.display().to_string());
+                }
+            }
+        }
+        assert!(
+            accepted.is_empty(),
+            "accepted invalid TOML:\n{}",
+            accepted.join("\n")
+        );
+    }
+}